@@ -483,6 +483,8 @@ impl ApplyDiff for TreeTracker {
                         self.tree.insert(*index, node);
                     }
                 }
+                // The rejected moves don't change the tree
+                _ => {}
             }
         }
     }
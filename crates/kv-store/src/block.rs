@@ -170,12 +170,11 @@ impl Block{
         }
     }
 
-    pub fn decode(raw_block_and_check: Bytes, is_large: bool, key: Bytes, compression_type: CompressionType)->Self{
-        // we have checked the checksum, so the block should be valid when decompressing
+    pub fn decode(raw_block_and_check: Bytes, is_large: bool, key: Bytes, compression_type: CompressionType)->LoroResult<Self>{
         if is_large{
-            return LargeValueBlock::decode(raw_block_and_check, key, compression_type).map(Block::Large).unwrap()
+            return LargeValueBlock::decode(raw_block_and_check, key, compression_type).map(Block::Large)
         }
-        NormalBlock::decode(raw_block_and_check, key, compression_type).map(Block::Normal).unwrap()
+        NormalBlock::decode(raw_block_and_check, key, compression_type).map(Block::Normal)
     }
 
     pub fn len(&self)->usize{
@@ -3,18 +3,43 @@ use std::io::{self, Write};
 use bytes::Bytes;
 use loro_common::LoroError;
 
+#[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompressionType {
     None,
     LZ4,
+    /// Zstd trades the encoding speed for a better compression ratio than LZ4.
+    ///
+    /// The level ranges from 1 to [CompressionType::ZSTD_MAX_LEVEL], and 0 means the
+    /// default level of zstd. The level is stored in the block meta, so the decoded
+    /// blocks report the level they were compressed with.
+    #[cfg(feature = "zstd")]
+    Zstd {
+        level: i32,
+    },
 }
 
 impl CompressionType {
+    #[cfg(feature = "zstd")]
+    pub const ZSTD_DEFAULT_LEVEL: i32 = 0;
+    #[cfg(feature = "zstd")]
+    pub const ZSTD_MAX_LEVEL: i32 = 22;
+
     pub fn is_none(&self) -> bool {
         matches!(self, CompressionType::None)
     }
+
+    /// Zstd compression with the given level, it's clamped to the valid range
+    #[cfg(feature = "zstd")]
+    pub fn zstd(level: i32) -> Self {
+        CompressionType::Zstd {
+            level: level.clamp(Self::ZSTD_DEFAULT_LEVEL, Self::ZSTD_MAX_LEVEL),
+        }
+    }
 }
 
+/// The zstd level is not included, it's encoded separately in the block meta.
+/// So `2` is decoded as zstd with [CompressionType::ZSTD_DEFAULT_LEVEL].
 impl TryFrom<u8> for CompressionType {
     type Error = LoroError;
 
@@ -22,6 +47,14 @@ impl TryFrom<u8> for CompressionType {
         match value {
             0 => Ok(CompressionType::None),
             1 => Ok(CompressionType::LZ4),
+            #[cfg(feature = "zstd")]
+            2 => Ok(CompressionType::Zstd {
+                level: CompressionType::ZSTD_DEFAULT_LEVEL,
+            }),
+            #[cfg(not(feature = "zstd"))]
+            2 => Err(LoroError::DecodeError(
+                "The data is compressed with zstd, but the `zstd` feature is not enabled".into(),
+            )),
             _ => Err(LoroError::DecodeError(
                 format!("Invalid compression type: {}", value).into(),
             )),
@@ -34,6 +67,8 @@ impl From<CompressionType> for u8 {
         match value {
             CompressionType::None => 0,
             CompressionType::LZ4 => 1,
+            #[cfg(feature = "zstd")]
+            CompressionType::Zstd { .. } => 2,
         }
     }
 }
@@ -48,6 +83,14 @@ pub fn compress(w: &mut Vec<u8>, data: &[u8], compression_type: CompressionType)
             encoder.write_all(data).unwrap();
             let _w = encoder.finish().unwrap();
         }
+        #[cfg(feature = "zstd")]
+        CompressionType::Zstd { level } => {
+            let level = level.clamp(
+                CompressionType::ZSTD_DEFAULT_LEVEL,
+                CompressionType::ZSTD_MAX_LEVEL,
+            );
+            zstd::stream::copy_encode(data, w, level).unwrap();
+        }
     }
 }
 
@@ -67,5 +110,11 @@ pub fn decompress(
                 .map_err(|e| LoroError::DecodeError(e.to_string().into()))?;
             Ok(())
         }
+        #[cfg(feature = "zstd")]
+        CompressionType::Zstd { .. } => {
+            zstd::stream::copy_decode(data.as_ref(), out)
+                .map_err(|e| LoroError::DecodeError(e.to_string().into()))?;
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,435 @@
+//! # FileKvStore
+//!
+//! FileKvStore persists the key-value pairs in a directory. It shares the SSTable format
+//! with [MemKvStore], and only reads the blocks from the disk when they are accessed.
+//! The checksums of the blocks are verified when the store is opened, so a corrupted
+//! file is reported by [FileKvStore::open] instead of being found by a later read.
+//!
+//! ## Directory Layout
+//!
+//! - `<id>.sst`: An SSTable file. The files are immutable once they are written.
+//! - `MANIFEST`: The ids of the live SSTable files, from the oldest to the newest.
+//!
+//! ## Write Path
+//!
+//! 1. The writes are buffered in the mem table.
+//! 2. [FileKvStore::flush] writes the mem table into a new SSTable file. The deleted keys are
+//!    encoded as empty values, so they can shadow the values in the older files.
+//! 3. The `MANIFEST` is replaced by writing a temporary file and renaming it. So a crash leaves
+//!    either the old or the new set of SSTable files, and the files that are not in the
+//!    `MANIFEST` are removed when the store is opened again.
+//! 4. When the number of SSTable files exceeds the limit, they are compacted into one file
+//!    and the deleted keys are dropped.
+//!
+//! ## Manifest Format
+//!
+//! ┌──────────────────────────────────────────────────────────────────────────────────────┐
+//! │ Manifest                                                                             │
+//! │┌ ─ ─ ─ ─ ─ ─ ─┌ ─ ─ ─ ─ ─ ─ ─ ─┌ ─ ─ ─ ─ ─ ─ ─┌ ─ ─ ─ ─ ─ ─┌ ─ ─ ─ ─ ─ ─ ─┌ ─ ─ ─ ─ ─ ─ ┐│
+//! │  Magic Number │ Schema Version │  Next File ID │ File Number│    File ID   │  checksum   │
+//! ││     u32      │       u8       │      u64      │     u32    │   u64 * n    │     u32    ││
+//! │ ─ ─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ─ │
+//! └──────────────────────────────────────────────────────────────────────────────────────┘
+use crate::compress::CompressionType;
+use crate::mem_store::{MemKvConfig, MemKvStore};
+use crate::sstable::{SsTable, SsTableBuilder, SsTableIter, XXH_SEED};
+use crate::MergeIterator;
+use bytes::{Buf, BufMut, Bytes};
+use loro_common::{LoroError, LoroResult};
+use std::fs;
+use std::io::Write;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+const MANIFEST_MAGIC_BYTES: [u8; 4] = *b"LRMF";
+const MANIFEST_SCHEMA_VERSION: u8 = 0;
+const MANIFEST_FILE_NAME: &str = "MANIFEST";
+const SSTABLE_EXTENSION: &str = "sst";
+const TMP_EXTENSION: &str = "tmp";
+
+#[derive(Debug)]
+pub struct FileKvStore {
+    dir: PathBuf,
+    store: MemKvStore,
+    /// The ids of the files of `store.tables()`, from the oldest to the newest
+    table_ids: Vec<u64>,
+    next_table_id: u64,
+    max_table_num: usize,
+}
+
+pub struct FileKvConfig {
+    block_size: usize,
+    compression_type: CompressionType,
+    max_table_num: usize,
+}
+
+impl Default for FileKvConfig {
+    fn default() -> Self {
+        Self {
+            block_size: MemKvStore::DEFAULT_BLOCK_SIZE,
+            compression_type: CompressionType::LZ4,
+            max_table_num: FileKvStore::DEFAULT_MAX_TABLE_NUM,
+        }
+    }
+}
+
+impl FileKvConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    pub fn compression_type(mut self, compression_type: CompressionType) -> Self {
+        self.compression_type = compression_type;
+        self
+    }
+
+    /// The SSTable files are compacted into one when their number exceeds this limit
+    pub fn max_table_num(mut self, max_table_num: usize) -> Self {
+        self.max_table_num = max_table_num.max(1);
+        self
+    }
+
+    pub fn open(self, dir: impl AsRef<Path>) -> LoroResult<FileKvStore> {
+        FileKvStore::open_with_config(dir, self)
+    }
+}
+
+impl FileKvStore {
+    pub const DEFAULT_MAX_TABLE_NUM: usize = 8;
+
+    /// Open the store in the directory. The directory is created if it doesn't exist.
+    pub fn open(dir: impl AsRef<Path>) -> LoroResult<Self> {
+        Self::open_with_config(dir, FileKvConfig::default())
+    }
+
+    fn open_with_config(dir: impl AsRef<Path>, config: FileKvConfig) -> LoroResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(io_err)?;
+        let (next_table_id, table_ids) = match fs::read(dir.join(MANIFEST_FILE_NAME)) {
+            Ok(bytes) => decode_manifest(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (0, Vec::new()),
+            Err(e) => return Err(io_err(e)),
+        };
+
+        let mut store = MemKvStore::new(
+            MemKvConfig::new()
+                .block_size(config.block_size)
+                .compression_type(config.compression_type),
+        );
+        for id in table_ids.iter() {
+            store.push_table(SsTable::open_file(&table_path(&dir, *id))?);
+        }
+
+        let ans = Self {
+            dir,
+            store,
+            table_ids,
+            next_table_id,
+            max_table_num: config.max_table_num,
+        };
+        ans.remove_unused_files();
+        Ok(ans)
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<Bytes> {
+        self.store.get(key)
+    }
+
+    pub fn set(&mut self, key: &[u8], value: Bytes) {
+        self.store.set(key, value)
+    }
+
+    pub fn compare_and_swap(&mut self, key: &[u8], old: Option<Bytes>, new: Bytes) -> bool {
+        self.store.compare_and_swap(key, old, new)
+    }
+
+    pub fn remove(&mut self, key: &[u8]) {
+        self.store.remove(key)
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.store.contains_key(key)
+    }
+
+    pub fn scan(
+        &self,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+    ) -> Box<dyn DoubleEndedIterator<Item = (Bytes, Bytes)> + '_> {
+        self.store.scan(start, end)
+    }
+
+    /// The number of valid keys, it's expensive to call
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    pub fn size(&self) -> usize {
+        self.store.size()
+    }
+
+    /// The directory of the store
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The number of SSTable files
+    pub fn table_num(&self) -> usize {
+        self.table_ids.len()
+    }
+
+    /// Whether there are writes that are not flushed to the disk
+    pub fn has_pending_writes(&self) -> bool {
+        !self.store.mem_table().is_empty()
+    }
+
+    /// Export all the key-value pairs in the SSTable format.
+    ///
+    /// It doesn't change the files on the disk.
+    pub fn export_all(&self) -> Bytes {
+        let mut builder = SsTableBuilder::new(
+            self.store.block_size(),
+            self.store.compression_type(),
+            false,
+        );
+        for (k, v) in self.store.scan(Bound::Unbounded, Bound::Unbounded) {
+            builder.add(k, v);
+        }
+
+        if builder.is_empty() {
+            return Bytes::new();
+        }
+        builder.build().export_all()
+    }
+
+    /// Import the bytes exported by [FileKvStore::export_all] or [MemKvStore::export_all].
+    ///
+    /// The imported pairs are written into a new SSTable file directly. They override the
+    /// pairs in the existing files, but not the pending writes in the mem table.
+    pub fn import_all(&mut self, bytes: Bytes) -> LoroResult<()> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        // Check the bytes before writing them
+        SsTable::import_all(bytes.clone())?;
+        let (id, table) = self.write_table(&bytes)?;
+        self.store.push_table(table);
+        self.table_ids.push(id);
+        self.write_manifest()?;
+        self.compact_if_needed()
+    }
+
+    /// Write the pending writes into a new SSTable file.
+    ///
+    /// # Errors
+    /// - [LoroError::IoError]
+    pub fn flush(&mut self) -> LoroResult<()> {
+        if self.store.mem_table().is_empty() {
+            return Ok(());
+        }
+
+        // The deleted keys only need to be kept when there are older files
+        let mut builder = SsTableBuilder::new(
+            self.store.block_size(),
+            self.store.compression_type(),
+            !self.store.tables().is_empty(),
+        );
+        for (k, v) in self.store.mem_table().iter() {
+            builder.add(k.clone(), v.clone());
+        }
+
+        if !builder.is_empty() {
+            let bytes = builder.build().export_all();
+            let (id, table) = self.write_table(&bytes)?;
+            self.store.push_table(table);
+            self.table_ids.push(id);
+            self.write_manifest()?;
+        }
+
+        self.store.clear_mem_table();
+        self.compact_if_needed()
+    }
+
+    /// Flush the pending writes and merge all the SSTable files into one
+    pub fn compact(&mut self) -> LoroResult<()> {
+        self.flush()?;
+        if self.table_ids.len() <= 1 {
+            return Ok(());
+        }
+
+        let mut builder = SsTableBuilder::new(
+            self.store.block_size(),
+            self.store.compression_type(),
+            false,
+        );
+        let iter = MergeIterator::new(
+            self.store
+                .tables()
+                .iter()
+                .rev()
+                .map(|table| SsTableIter::new_scan(table, Bound::Unbounded, Bound::Unbounded))
+                .collect(),
+        );
+        for (k, v) in iter {
+            builder.add(k, v);
+        }
+
+        let mut tables = Vec::new();
+        let mut table_ids = Vec::new();
+        if !builder.is_empty() {
+            let bytes = builder.build().export_all();
+            let (id, table) = self.write_table(&bytes)?;
+            tables.push(table);
+            table_ids.push(id);
+        }
+
+        let old_ids = std::mem::replace(&mut self.table_ids, table_ids);
+        self.store.set_tables(tables);
+        self.write_manifest()?;
+        for id in old_ids {
+            // It may fail on some platforms if the file is still opened by a cloned store,
+            // in which case it will be removed on the next open
+            let _ = fs::remove_file(table_path(&self.dir, id));
+        }
+
+        Ok(())
+    }
+
+    /// Create an in-memory store with the same content.
+    ///
+    /// The SSTable files are shared with the new store until they are compacted.
+    pub fn to_mem_store(&self) -> MemKvStore {
+        self.store.clone()
+    }
+
+    fn compact_if_needed(&mut self) -> LoroResult<()> {
+        if self.table_ids.len() > self.max_table_num {
+            self.compact()?;
+        }
+
+        Ok(())
+    }
+
+    fn write_table(&mut self, bytes: &[u8]) -> LoroResult<(u64, SsTable)> {
+        let id = self.next_table_id;
+        self.next_table_id += 1;
+        let path = table_path(&self.dir, id);
+        write_file_atomically(&path, bytes)?;
+        let table = SsTable::open_file(&path)?;
+        Ok((id, table))
+    }
+
+    fn write_manifest(&self) -> LoroResult<()> {
+        let bytes = encode_manifest(self.next_table_id, &self.table_ids);
+        write_file_atomically(&self.dir.join(MANIFEST_FILE_NAME), &bytes)
+    }
+
+    /// Remove the temporary files and the SSTable files that are not in the manifest
+    fn remove_unused_files(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_unused = match path.extension().and_then(|x| x.to_str()) {
+                Some(TMP_EXTENSION) => true,
+                Some(SSTABLE_EXTENSION) => path
+                    .file_stem()
+                    .and_then(|x| x.to_str())
+                    .and_then(|x| x.parse::<u64>().ok())
+                    .is_some_and(|id| !self.table_ids.contains(&id)),
+                _ => false,
+            };
+            if is_unused {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+}
+
+fn table_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{:08}.{}", id, SSTABLE_EXTENSION))
+}
+
+fn io_err(e: std::io::Error) -> LoroError {
+    LoroError::IoError(e.to_string().into_boxed_str())
+}
+
+fn write_file_atomically(path: &Path, bytes: &[u8]) -> LoroResult<()> {
+    let tmp_path = path.with_extension(TMP_EXTENSION);
+    let mut file = fs::File::create(&tmp_path).map_err(io_err)?;
+    file.write_all(bytes).map_err(io_err)?;
+    file.sync_all().map_err(io_err)?;
+    drop(file);
+    fs::rename(&tmp_path, path).map_err(io_err)?;
+    sync_parent_dir(path)
+}
+
+/// Persist the rename, which is only recorded in the directory entry
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> LoroResult<()> {
+    let Some(dir) = path.parent() else {
+        return Ok(());
+    };
+    fs::File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .map_err(io_err)
+}
+
+/// Directories cannot be opened as files on other platforms, so the rename is not synced there
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> LoroResult<()> {
+    Ok(())
+}
+
+fn encode_manifest(next_table_id: u64, table_ids: &[u64]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(21 + table_ids.len() * 8);
+    buf.put_u32_le(u32::from_le_bytes(MANIFEST_MAGIC_BYTES));
+    buf.put_u8(MANIFEST_SCHEMA_VERSION);
+    buf.put_u64_le(next_table_id);
+    buf.put_u32_le(table_ids.len() as u32);
+    for id in table_ids {
+        buf.put_u64_le(*id);
+    }
+    let checksum = xxhash_rust::xxh32::xxh32(&buf, XXH_SEED);
+    buf.put_u32_le(checksum);
+    buf
+}
+
+fn decode_manifest(bytes: &[u8]) -> LoroResult<(u64, Vec<u64>)> {
+    // magic number + schema version + next file id + file number + checksum
+    if bytes.len() < 4 + 1 + 8 + 4 + 4 {
+        return Err(LoroError::DecodeError("Invalid manifest".into()));
+    }
+    let (mut body, mut checksum) = bytes.split_at(bytes.len() - 4);
+    if checksum.get_u32_le() != xxhash_rust::xxh32::xxh32(body, XXH_SEED) {
+        return Err(LoroError::DecodeChecksumMismatchError);
+    }
+    if body.get_u32_le() != u32::from_le_bytes(MANIFEST_MAGIC_BYTES) {
+        return Err(LoroError::DecodeError("Invalid magic number".into()));
+    }
+    let schema_version = body.get_u8();
+    if schema_version != MANIFEST_SCHEMA_VERSION {
+        return Err(LoroError::DecodeError(
+            format!("Invalid manifest schema version {}", schema_version).into(),
+        ));
+    }
+    let next_table_id = body.get_u64_le();
+    let table_num = body.get_u32_le() as usize;
+    if body.len() != table_num * 8 {
+        return Err(LoroError::DecodeError("Invalid manifest".into()));
+    }
+    let table_ids = (0..table_num).map(|_| body.get_u64_le()).collect();
+    Ok((next_table_id, table_ids))
+}
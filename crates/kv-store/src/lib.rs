@@ -64,12 +64,13 @@
 //! 2. Write offsets for each key-value pair.
 //! 3. Write the number of key-value pairs.
 //! 4. By default, **Compress** the entire block using LZ4. If you set `compression_type` to `None`, it will not compress the block.
-//!     - For now, there are two compression type: `None` and `LZ4`.
+//!     - There are two compression type: `None` and `LZ4`, and `Zstd` with the `zstd` feature,
+//!       which trades the speed for a better compression ratio.
 //! 5. Calculate and append xxhash_32 checksum.
 //!
 //! Decoding:
 //! 1. Verify the xxhash_32 checksum.
-//! 2. By default, **Decompress** the block using LZ4. If you set `compression_type` to `None`, it will not decompress the block.
+//! 2. **Decompress** the block by the compression type stored in the block meta.
 //! 3. Read the number of key-value pairs.
 //! 4. Read offsets for each key-value pair.
 //! 5. Parse individual key-value chunks.
@@ -130,13 +131,17 @@
 //! 3. Verify the xxhash_32 checksum.
 //!
 //!
+//! [FileKvStore] stores the same SSTables in files, see [file_store] for the layout of its directory.
+//!
 //! Note: In this crate, the empty value is regarded as deleted. **only** [MemStoreIterator] will filter empty value.
 //! Other iterators will still return empty value.
 pub mod block;
 pub mod compress;
+pub mod file_store;
 pub mod iter;
 pub mod mem_store;
 pub mod sstable;
 mod utils;
+pub use file_store::{FileKvConfig, FileKvStore};
 pub use iter::{KvIterator, MergeIterator};
 pub use mem_store::{MemKvStore, MemStoreIterator};
@@ -81,18 +81,8 @@ impl MemKvStore {
         }
 
         for table in self.ss_table.iter().rev() {
-            if table.first_key > key || table.last_key < key {
-                continue;
-            }
-            // table.
-            let idx = table.find_block_idx(key);
-            let block = table.read_block_cached(idx);
-            let block_iter = BlockIter::new_seek_to_key(block, key);
-            if let Some(k) = block_iter.peek_next_curr_key() {
-                let v = block_iter.peek_next_curr_value().unwrap();
-                if k == key {
-                    return if v.is_empty() { None } else { Some(v) };
-                }
+            if let Some(v) = table.get(key) {
+                return if v.is_empty() { None } else { Some(v) };
             }
         }
         None
@@ -196,7 +186,8 @@ impl MemKvStore {
     }
 
     pub fn export_all(&mut self) -> Bytes {
-        if self.mem_table.is_empty() && self.ss_table.len() == 1 {
+        // The bytes of a table stored in a file are read again, so only the blocks are reused
+        if self.mem_table.is_empty() && self.ss_table.len() == 1 && !self.ss_table[0].is_file() {
             return self.ss_table[0].export_all();
         }
 
@@ -238,6 +229,35 @@ impl MemKvStore {
         ans
     }
 
+    pub(crate) fn mem_table(&self) -> &BTreeMap<Bytes, Bytes> {
+        &self.mem_table
+    }
+
+    pub(crate) fn clear_mem_table(&mut self) {
+        self.mem_table.clear();
+    }
+
+    /// The sstables from the oldest to the newest
+    pub(crate) fn tables(&self) -> &[SsTable] {
+        &self.ss_table
+    }
+
+    pub(crate) fn push_table(&mut self, table: SsTable) {
+        self.ss_table.push(table);
+    }
+
+    pub(crate) fn set_tables(&mut self, tables: Vec<SsTable>) {
+        self.ss_table = tables;
+    }
+
+    pub(crate) fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub(crate) fn compression_type(&self) -> CompressionType {
+        self.compression_type
+    }
+
     /// We can import several times, the latter will override the former.
     pub fn import_all(&mut self, bytes: Bytes) -> Result<(), String> {
         if bytes.is_empty() {
@@ -3,12 +3,20 @@ use crate::{
     block::{Block, BlockBuilder},
     compress::CompressionType,
     iter::KvIterator,
+    mem_store::MemKvStore,
     utils::{get_u16_le, get_u32_le, get_u8_le},
 };
 use bytes::{Buf, BufMut, Bytes};
 use ensure_cov::*;
 use loro_common::{LoroError, LoroResult};
-use std::{fmt::Debug, ops::Bound, sync::Arc};
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    ops::{Bound, Range},
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
 pub(crate) const XXH_SEED: u32 = u32::from_le_bytes(*b"LORO");
 const MAGIC_BYTES: [u8; 4] = *b"LORO";
@@ -29,6 +37,9 @@ const MAX_BLOCK_NUM: u32 = 10_000_000;
 /// │ ─ ─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─  │
 /// └──────────────────────────────────────────────────────────────────────────────────────────┘
 /// ```
+///
+/// The block type is made up of the large flag and the compression type. For the zstd
+/// blocks, it's followed by the compression level in a u8.
 #[derive(Debug, Clone)]
 pub(crate) struct BlockMeta {
     offset: usize,
@@ -56,8 +67,13 @@ impl BlockMeta {
             estimated_size += SIZE_OF_U16;
             // first key
             estimated_size += m.first_key.len();
-            // is large
+            // is large and compression type
             estimated_size += SIZE_OF_U8;
+            // zstd level
+            #[cfg(feature = "zstd")]
+            if matches!(m.compression_type, CompressionType::Zstd { .. }) {
+                estimated_size += SIZE_OF_U8;
+            }
             if m.is_large {
                 continue;
             }
@@ -76,8 +92,15 @@ impl BlockMeta {
             buf.put_u32_le(m.offset as u32);
             buf.put_u16_le(m.first_key.len() as u16);
             buf.put_slice(&m.first_key);
-            let large_and_compress = (m.is_large as u8) << 7 | m.compression_type as u8;
+            let large_and_compress = (m.is_large as u8) << 7 | u8::from(m.compression_type);
             buf.put_u8(large_and_compress);
+            #[cfg(feature = "zstd")]
+            if let CompressionType::Zstd { level } = m.compression_type {
+                buf.put_u8(level.clamp(
+                    CompressionType::ZSTD_DEFAULT_LEVEL,
+                    CompressionType::ZSTD_MAX_LEVEL,
+                ) as u8);
+            }
             if m.is_large {
                 continue;
             }
@@ -107,12 +130,13 @@ impl BlockMeta {
             let first_key = buf.copy_to_bytes(first_key_len as usize);
             let (is_large_and_compression_type, buf) = get_u8_le(buf)?;
             let is_large = is_large_and_compression_type & 0b1000_0000 != 0;
-            let compression_type = is_large_and_compression_type & 0b0111_1111;
+            let (compression_type, buf) =
+                Self::decode_compression_type(is_large_and_compression_type & 0b0111_1111, buf)?;
             if is_large {
                 ans.push(BlockMeta {
                     offset: offset as usize,
                     is_large,
-                    compression_type: compression_type.try_into()?,
+                    compression_type,
                     first_key,
                     last_key: None,
                 });
@@ -127,7 +151,7 @@ impl BlockMeta {
             ans.push(BlockMeta {
                 offset: offset as usize,
                 is_large,
-                compression_type: compression_type.try_into()?,
+                compression_type,
                 first_key,
                 last_key: Some(last_key),
             });
@@ -139,6 +163,17 @@ impl BlockMeta {
         }
         Ok(ans)
     }
+
+    /// Decode the compression type, the zstd level follows the type
+    fn decode_compression_type(ty: u8, buf: &[u8]) -> LoroResult<(CompressionType, &[u8])> {
+        let compression_type = CompressionType::try_from(ty)?;
+        #[cfg(feature = "zstd")]
+        if let CompressionType::Zstd { .. } = compression_type {
+            let (level, buf) = get_u8_le(buf)?;
+            return Ok((CompressionType::zstd(level as i32), buf));
+        }
+        Ok((compression_type, buf))
+    }
 }
 
 pub(crate) struct SsTableBuilder {
@@ -284,7 +319,7 @@ impl SsTableBuilder {
             })
             .unwrap_or_default();
         SsTable {
-            data: Bytes::from(buf),
+            data: SsTableData::Memory(Bytes::from(buf)),
             first_key,
             last_key,
             meta: self.meta,
@@ -296,10 +331,59 @@ impl SsTableBuilder {
 
 type BlockCache = quick_cache::sync::Cache<usize, Arc<Block>>;
 
+/// The bytes of a [SsTable].
+///
+/// A table opened from a file only keeps the block meta in memory, the blocks
+/// are read from the file when they are accessed.
+#[derive(Debug, Clone)]
+enum SsTableData {
+    Memory(Bytes),
+    File(Arc<SsTableFile>),
+}
+
+#[derive(Debug)]
+struct SsTableFile {
+    file: Mutex<File>,
+    len: usize,
+}
+
+impl SsTableData {
+    fn len(&self) -> usize {
+        match self {
+            SsTableData::Memory(bytes) => bytes.len(),
+            SsTableData::File(file) => file.len,
+        }
+    }
+
+    fn slice(&self, range: Range<usize>) -> LoroResult<Bytes> {
+        match self {
+            SsTableData::Memory(bytes) => Ok(bytes.slice(range)),
+            SsTableData::File(file) => file.read(range).map_err(io_err),
+        }
+    }
+
+    fn is_file(&self) -> bool {
+        matches!(self, SsTableData::File(_))
+    }
+}
+
+impl SsTableFile {
+    fn read(&self, range: Range<usize>) -> std::io::Result<Bytes> {
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(range.start as u64))?;
+        let mut buf = vec![0; range.len()];
+        file.read_exact(&mut buf)?;
+        Ok(buf.into())
+    }
+}
+
+fn io_err(e: std::io::Error) -> LoroError {
+    LoroError::IoError(e.to_string().into_boxed_str())
+}
+
 #[derive(Debug)]
 pub struct SsTable {
-    // TODO: mmap?
-    data: Bytes,
+    data: SsTableData,
     pub(crate) first_key: Bytes,
     pub(crate) last_key: Bytes,
     meta: Vec<BlockMeta>,
@@ -321,14 +405,37 @@ impl Clone for SsTable {
 }
 
 impl SsTable {
+    /// Export the bytes of the table.
+    ///
+    /// # Panics
+    ///
+    /// If the table is stored in a file that fails to be read.
     pub fn export_all(&self) -> Bytes {
-        self.data.clone()
+        self.data
+            .slice(0..self.data.len())
+            .unwrap_or_else(|e| panic!("Failed to read the sstable file: {}", e))
     }
 
     pub fn iter(&self) -> SsTableIter {
         SsTableIter::new(self)
     }
 
+    /// Export the table with the blocks compressed by the given compression type.
+    ///
+    /// The blocks that are already compressed by the same type are reused.
+    pub fn export_with_compression(&self, compression_type: CompressionType) -> Bytes {
+        let mut builder =
+            SsTableBuilder::new(MemKvStore::DEFAULT_BLOCK_SIZE, compression_type, true);
+        for idx in 0..self.meta.len() {
+            let block = self.read_block_cached(idx);
+            if !block.is_empty() {
+                builder.add_new_block(block);
+            }
+        }
+
+        builder.build().export_all()
+    }
+
     ///
     ///
     /// # Errors
@@ -341,6 +448,75 @@ impl SsTable {
         if bytes.len() < SIZE_OF_U32 + SIZE_OF_U8 + SIZE_OF_U32 {
             return Err(LoroError::DecodeError("Invalid sstable bytes".into()));
         }
+        Self::check_header(&bytes)?;
+        let data_len = bytes.len();
+        let meta_offset = (&bytes[data_len - SIZE_OF_U32..]).get_u32_le() as usize;
+        if meta_offset >= data_len - SIZE_OF_U32 {
+            return Err(LoroError::DecodeError("Invalid bytes".into()));
+        }
+        let raw_meta = &bytes[meta_offset..data_len - SIZE_OF_U32];
+        let meta = BlockMeta::decode_meta(raw_meta)?;
+        Self::check_block_checksum(&meta, &bytes, meta_offset)?;
+        Ok(Self::from_parts(
+            SsTableData::Memory(bytes),
+            meta,
+            meta_offset,
+        ))
+    }
+
+    /// Open the sstable stored in the file.
+    ///
+    /// The checksums of all the blocks are verified here, so a corrupted file fails
+    /// to be opened. Only the block meta is kept in memory, the blocks are read again
+    /// from the file when they are accessed.
+    ///
+    /// # Errors
+    /// - [LoroError::IoError]
+    /// - [LoroError::DecodeChecksumMismatchError]
+    /// - [LoroError::DecodeError]
+    pub fn open_file(path: &Path) -> LoroResult<Self> {
+        let file = File::open(path).map_err(io_err)?;
+        let data_len = file.metadata().map_err(io_err)?.len() as usize;
+        if data_len < SIZE_OF_U32 + SIZE_OF_U8 + SIZE_OF_U32 {
+            return Err(LoroError::DecodeError("Invalid sstable bytes".into()));
+        }
+        let file = SsTableFile {
+            file: Mutex::new(file),
+            len: data_len,
+        };
+        Self::check_header(&file.read(0..SIZE_OF_U32 + SIZE_OF_U8).map_err(io_err)?)?;
+        let meta_offset = file
+            .read(data_len - SIZE_OF_U32..data_len)
+            .map_err(io_err)?
+            .get_u32_le() as usize;
+        if meta_offset >= data_len - SIZE_OF_U32 {
+            return Err(LoroError::DecodeError("Invalid bytes".into()));
+        }
+        let raw_meta = file
+            .read(meta_offset..data_len - SIZE_OF_U32)
+            .map_err(io_err)?;
+        let meta = BlockMeta::decode_meta(&raw_meta)?;
+        if meta.iter().any(|m| m.offset >= meta_offset) {
+            return Err(LoroError::DecodeError("Invalid bytes".into()));
+        }
+        for i in 0..meta.len() {
+            let offset_end = meta.get(i + 1).map_or(meta_offset, |m| m.offset);
+            if offset_end < meta[i].offset {
+                return Err(LoroError::DecodeError("Invalid bytes".into()));
+            }
+            let raw_block_and_check = file.read(meta[i].offset..offset_end).map_err(io_err)?;
+            if !Self::is_block_checksum_valid(&raw_block_and_check) {
+                return Err(LoroError::DecodeChecksumMismatchError);
+            }
+        }
+        Ok(Self::from_parts(
+            SsTableData::File(Arc::new(file)),
+            meta,
+            meta_offset,
+        ))
+    }
+
+    fn check_header(bytes: &[u8]) -> LoroResult<()> {
         let magic_number = u32::from_le_bytes((&bytes[..SIZE_OF_U32]).try_into().unwrap());
         if magic_number != u32::from_le_bytes(MAGIC_BYTES) {
             return Err(LoroError::DecodeError("Invalid magic number".into()));
@@ -359,14 +535,10 @@ impl SsTable {
                 ))
             }
         }
-        let data_len = bytes.len();
-        let meta_offset = (&bytes[data_len - SIZE_OF_U32..]).get_u32_le() as usize;
-        if meta_offset >= data_len - SIZE_OF_U32 {
-            return Err(LoroError::DecodeError("Invalid bytes".into()));
-        }
-        let raw_meta = &bytes[meta_offset..data_len - SIZE_OF_U32];
-        let meta = BlockMeta::decode_meta(raw_meta)?;
-        Self::check_block_checksum(&meta, &bytes, meta_offset)?;
+        Ok(())
+    }
+
+    fn from_parts(data: SsTableData, meta: Vec<BlockMeta>, meta_offset: usize) -> Self {
         let first_key = meta
             .first()
             .map(|m| m.first_key.clone())
@@ -379,15 +551,14 @@ impl SsTable {
                     .unwrap_or(meta.last().map(|m| m.first_key.clone()).unwrap_or_default())
             })
             .unwrap_or_default();
-        let ans = Self {
-            data: bytes,
+        Self {
+            data,
             first_key,
             last_key,
             meta,
             meta_offset,
             block_cache: BlockCache::new(DEFAULT_CACHE_SIZE),
-        };
-        Ok(ans)
+        }
     }
 
     fn check_block_checksum(
@@ -401,22 +572,26 @@ impl SsTable {
             if offset_end > bytes.len() {
                 return Err(LoroError::DecodeError("Invalid bytes".into()));
             }
-            let raw_block_and_check = bytes.slice(offset..offset_end);
-            let checksum = raw_block_and_check
-                .slice(raw_block_and_check.len() - SIZE_OF_U32..)
-                .get_u32_le();
-            if checksum
-                != xxhash_rust::xxh32::xxh32(
-                    &raw_block_and_check[..raw_block_and_check.len() - SIZE_OF_U32],
-                    XXH_SEED,
-                )
-            {
+            if !Self::is_block_checksum_valid(&bytes[offset..offset_end]) {
                 return Err(LoroError::DecodeChecksumMismatchError);
             }
         }
         Ok(())
     }
 
+    fn is_block_checksum_valid(raw_block_and_check: &[u8]) -> bool {
+        if raw_block_and_check.len() < SIZE_OF_U32 {
+            return false;
+        }
+        let checksum =
+            (&raw_block_and_check[raw_block_and_check.len() - SIZE_OF_U32..]).get_u32_le();
+        checksum
+            == xxhash_rust::xxh32::xxh32(
+                &raw_block_and_check[..raw_block_and_check.len() - SIZE_OF_U32],
+                XXH_SEED,
+            )
+    }
+
     pub fn find_block_idx(&self, key: &[u8]) -> usize {
         self.meta
             .partition_point(|meta| meta.first_key <= key)
@@ -429,54 +604,82 @@ impl SsTable {
             .min(self.meta.len() - 1)
     }
 
-    fn read_block(&self, block_idx: usize) -> Arc<Block> {
+    /// # Errors
+    /// - [LoroError::IoError]
+    /// - [LoroError::DecodeError]
+    fn read_block(&self, block_idx: usize) -> LoroResult<Arc<Block>> {
         let offset = self.meta[block_idx].offset;
         let offset_end = self
             .meta
             .get(block_idx + 1)
             .map_or(self.meta_offset, |m| m.offset);
-        let raw_block_and_check = self.data.slice(offset..offset_end);
-        Arc::new(Block::decode(
+        // The checksums are checked when the table is imported or opened
+        let raw_block_and_check = self.data.slice(offset..offset_end)?;
+        Block::decode(
             raw_block_and_check,
             self.meta[block_idx].is_large,
             self.meta[block_idx].first_key.clone(),
             self.meta[block_idx].compression_type,
-        ))
+        )
+        .map(Arc::new)
     }
 
-    pub(crate) fn read_block_cached(&self, block_idx: usize) -> Arc<Block> {
+    fn try_read_block_cached(&self, block_idx: usize) -> LoroResult<Arc<Block>> {
         self.block_cache
-            .get_or_insert_with(&block_idx, || {
-                Ok::<_, LoroError>(self.read_block(block_idx))
-            })
-            .unwrap()
+            .get_or_insert_with(&block_idx, || self.read_block(block_idx))
+    }
+
+    /// # Panics
+    ///
+    /// If the table is stored in a file that fails to be read. The blocks are verified
+    /// when the file is opened, so it only happens on an I/O error or when the file is
+    /// modified by others after that.
+    pub(crate) fn read_block_cached(&self, block_idx: usize) -> Arc<Block> {
+        self.try_read_block_cached(block_idx).unwrap_or_else(|e| {
+            panic!(
+                "Failed to read the block {} of the sstable: {}",
+                block_idx, e
+            )
+        })
     }
 
     pub fn contains_key(&self, key: &[u8]) -> bool {
-        if self.first_key > key || self.last_key < key {
-            return false;
-        }
-        let idx = self.find_block_idx(key);
-        let block = self.read_block_cached(idx);
-        let block_iter = BlockIter::new_seek_to_key(block, key);
-        block_iter.peek_next_curr_key() == Some(Bytes::copy_from_slice(key))
+        self.get(key).is_some()
     }
 
-    #[allow(unused)]
+    /// Get the value of the key, the deleted key has an empty value.
+    ///
+    /// # Panics
+    ///
+    /// If the block fails to be read, see [SsTable::try_get] for the fallible version.
     pub fn get(&self, key: &[u8]) -> Option<Bytes> {
+        self.try_get(key)
+            .unwrap_or_else(|e| panic!("Failed to read the sstable: {}", e))
+    }
+
+    /// Get the value of the key, the deleted key has an empty value.
+    ///
+    /// # Errors
+    /// - [LoroError::IoError]
+    /// - [LoroError::DecodeError]
+    pub fn try_get(&self, key: &[u8]) -> LoroResult<Option<Bytes>> {
         if self.first_key > key || self.last_key < key {
-            return None;
+            return Ok(None);
         }
         let idx = self.find_block_idx(key);
-        let block = self.read_block_cached(idx);
+        let block = self.try_read_block_cached(idx)?;
         let block_iter = BlockIter::new_seek_to_key(block, key);
-        block_iter.peek_next_curr_key().and_then(|k| {
+        Ok(block_iter.peek_next_curr_key().and_then(|k| {
             if k == key {
                 block_iter.peek_next_curr_value()
             } else {
                 None
             }
-        })
+        }))
+    }
+
+    pub(crate) fn is_file(&self) -> bool {
+        self.data.is_file()
     }
 
     pub fn data_size(&self) -> usize {
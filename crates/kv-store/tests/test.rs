@@ -1,5 +1,9 @@
 use bytes::Bytes;
-use loro_kv_store::{mem_store::MemKvConfig, MemKvStore};
+use loro_common::LoroError;
+use loro_kv_store::{
+    compress::CompressionType, mem_store::MemKvConfig, sstable::SsTable, FileKvConfig, FileKvStore,
+    MemKvStore,
+};
 
 #[ctor::ctor]
 fn init() {
@@ -130,3 +134,182 @@ fn export_import_edit_export() {
     assert_eq!(new_new_store.get(b"b99"), Some(Bytes::from_static(b"2")));
     assert_eq!(new_new_store.get(b"a"), Some(Bytes::from_static(b"2")));
 }
+
+#[test]
+fn export_with_another_compression() {
+    let mut store = MemKvStore::new(MemKvConfig::default());
+    for i in 0..3000 {
+        let s = format!("key{}", i);
+        store.set(s.as_bytes(), Bytes::from(format!("value{}", i % 7)));
+    }
+
+    let bytes = store.export_all();
+    let table = SsTable::import_all(bytes.clone()).unwrap();
+    let uncompressed = table.export_with_compression(CompressionType::None);
+    assert!(uncompressed.len() > bytes.len());
+    let mut new_store = MemKvStore::new(MemKvConfig::default());
+    new_store.import_all(uncompressed).unwrap();
+    assert!(new_store
+        .scan(std::ops::Bound::Unbounded, std::ops::Bound::Unbounded)
+        .eq(store.scan(std::ops::Bound::Unbounded, std::ops::Bound::Unbounded)));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_compression() {
+    let mut store = MemKvStore::new(MemKvConfig::new().compression_type(CompressionType::zstd(19)));
+    for i in 0..3000 {
+        let s = format!("key{}", i);
+        store.set(s.as_bytes(), Bytes::from(format!("value{}", i % 7)));
+    }
+    store.set(b"large", Bytes::from(vec![7; 10_000]));
+
+    let bytes = store.export_all();
+    // The blocks are decoded by the type stored in the block meta
+    let mut new_store = MemKvStore::new(MemKvConfig::default());
+    new_store.import_all(bytes.clone()).unwrap();
+    assert_eq!(
+        new_store.get(b"key1001"),
+        Some(Bytes::from_static(b"value0"))
+    );
+    assert_eq!(new_store.get(b"large"), Some(Bytes::from(vec![7; 10_000])));
+
+    let lz4 = SsTable::import_all(bytes)
+        .unwrap()
+        .export_with_compression(CompressionType::LZ4);
+    let zstd = SsTable::import_all(lz4)
+        .unwrap()
+        .export_with_compression(CompressionType::zstd(19));
+    // The level is decoded from the block meta, so the blocks are reused as they are
+    let reexported = SsTable::import_all(zstd.clone())
+        .unwrap()
+        .export_with_compression(CompressionType::zstd(19));
+    assert_eq!(reexported, zstd);
+    let mut new_store = MemKvStore::new(MemKvConfig::default());
+    new_store.import_all(zstd).unwrap();
+    assert!(new_store
+        .scan(std::ops::Bound::Unbounded, std::ops::Bound::Unbounded)
+        .eq(store.scan(std::ops::Bound::Unbounded, std::ops::Bound::Unbounded)));
+}
+
+#[cfg(not(feature = "zstd"))]
+#[test]
+fn zstd_block_without_the_feature() {
+    // The blocks compressed by zstd fail to be decoded instead of being taken as another type
+    assert!(matches!(
+        CompressionType::try_from(2u8),
+        Err(LoroError::DecodeError(_))
+    ));
+    assert_eq!(CompressionType::try_from(1u8), Ok(CompressionType::LZ4));
+}
+
+fn temp_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("loro-kv-store-test-{}", rand::random::<u64>()))
+}
+
+#[test]
+fn file_store_reopen() {
+    let dir = temp_dir();
+    let mut store = FileKvStore::open(&dir).unwrap();
+    store.set(b"a", Bytes::from_static(b"1"));
+    for i in 0..3000 {
+        let s = format!("b{}", i);
+        store.set(s.as_bytes(), Bytes::from_static(b"2"));
+    }
+    store.flush().unwrap();
+    store.set(b"a", Bytes::from_static(b"3"));
+    store.remove(b"b0");
+    store.flush().unwrap();
+    // Not flushed
+    store.set(b"c", Bytes::from_static(b"4"));
+    assert_eq!(store.table_num(), 2);
+    drop(store);
+
+    let store = FileKvStore::open(&dir).unwrap();
+    assert_eq!(store.get(b"a"), Some(Bytes::from_static(b"3")));
+    assert_eq!(store.get(b"b0"), None);
+    assert_eq!(store.get(b"b2999"), Some(Bytes::from_static(b"2")));
+    assert_eq!(store.get(b"c"), None);
+    assert_eq!(store.len(), 3000);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_store_compact() {
+    let dir = temp_dir();
+    let mut store = FileKvConfig::new().max_table_num(2).open(&dir).unwrap();
+    store.set(b"a", Bytes::from_static(b"1"));
+    store.set(b"b", Bytes::from_static(b"1"));
+    store.flush().unwrap();
+    store.remove(b"a");
+    store.flush().unwrap();
+    assert_eq!(store.table_num(), 2);
+    store.set(b"c", Bytes::from_static(b"1"));
+    store.flush().unwrap();
+    assert_eq!(store.table_num(), 1);
+    let files = std::fs::read_dir(&dir).unwrap().count();
+    // One sstable and the manifest
+    assert_eq!(files, 2);
+    drop(store);
+
+    let store = FileKvStore::open(&dir).unwrap();
+    let pairs: Vec<_> = store
+        .scan(std::ops::Bound::Unbounded, std::ops::Bound::Unbounded)
+        .collect();
+    assert_eq!(
+        pairs,
+        vec![
+            (Bytes::from_static(b"b"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"c"), Bytes::from_static(b"1"))
+        ]
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_store_export_import() {
+    let dir = temp_dir();
+    let mut mem_store = MemKvStore::new(MemKvConfig::default());
+    for i in 0..100 {
+        let s = format!("k{}", i);
+        mem_store.set(s.as_bytes(), Bytes::from(s.clone()));
+    }
+
+    let mut store = FileKvStore::open(&dir).unwrap();
+    store.import_all(mem_store.export_all()).unwrap();
+    store.remove(b"k1");
+    drop(store);
+
+    let mut store = FileKvStore::open(&dir).unwrap();
+    assert_eq!(store.get(b"k1"), Some(Bytes::from_static(b"k1")));
+    store.remove(b"k1");
+    let mut new_store = MemKvStore::new(MemKvConfig::default());
+    new_store.import_all(store.export_all()).unwrap();
+    assert_eq!(new_store.len(), 99);
+    assert_eq!(new_store.get(b"k99"), Some(Bytes::from_static(b"k99")));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_store_corrupted_block() {
+    let dir = temp_dir();
+    let mut store = FileKvStore::open(&dir).unwrap();
+    for i in 0..3000 {
+        let s = format!("b{:04}", i);
+        store.set(s.as_bytes(), Bytes::from_static(b"2"));
+    }
+    store.flush().unwrap();
+    drop(store);
+
+    // Corrupt the first block, which is verified on open
+    let path = dir.join("00000000.sst");
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[10] ^= 0xff;
+    std::fs::write(&path, bytes).unwrap();
+
+    assert_eq!(
+        FileKvStore::open(&dir).unwrap_err(),
+        LoroError::DecodeChecksumMismatchError
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -94,6 +94,12 @@ pub enum LoroError {
     ContainerDeleted { container: Box<ContainerID> },
     #[error("You cannot set the `PeerID` with `PeerID::MAX`, which is an internal specific value")]
     InvalidPeerID,
+    #[error("IO error ({0})")]
+    IoError(Box<str>),
+    #[error("Schema violation at {path}: {reason}")]
+    SchemaViolation { path: Box<str>, reason: Box<str> },
+    #[error("The text update timed out")]
+    UpdateTimeout,
 }
 
 #[derive(Error, Debug, PartialEq)]
@@ -123,6 +129,8 @@ pub enum LoroEncodeError {
     ShallowSnapshotIncompatibleWithOldFormat,
     #[error("Cannot export shallow snapshot with unknown container type. Please upgrade the Loro version.")]
     UnknownContainer,
+    #[error("IO error ({0})")]
+    IoError(Box<str>),
 }
 
 #[cfg(feature = "wasm")]
@@ -4,7 +4,7 @@ use loro::{cursor::Side, LoroResult, PeerID, TextDelta, UpdateOptions, UpdateTim
 
 use crate::{ContainerID, LoroValue, LoroValueLike};
 
-use super::Cursor;
+use super::{Cursor, LoroCounter, LoroList, LoroMap, LoroMovableList, LoroTree};
 
 #[derive(Debug, Clone)]
 pub struct LoroText {
@@ -54,6 +54,87 @@ impl LoroText {
         self.text.insert_utf8(pos as usize, s)
     }
 
+    /// Insert an embed, such as an inline image or a mention, at the given unicode position.
+    ///
+    /// The embed has length 1.
+    pub fn insert_embed(&self, pos: u32, value: Arc<dyn LoroValueLike>) -> LoroResult<()> {
+        self.text.insert_embed(pos as usize, value.as_loro_value())
+    }
+
+    /// Insert a list container as an embed at the given unicode position.
+    #[inline]
+    pub fn insert_list_container(
+        &self,
+        pos: u32,
+        child: Arc<LoroList>,
+    ) -> LoroResult<Arc<LoroList>> {
+        let c = self
+            .text
+            .insert_container(pos as usize, child.as_ref().clone().list)?;
+        Ok(Arc::new(LoroList { list: c }))
+    }
+
+    /// Insert a map container as an embed at the given unicode position.
+    #[inline]
+    pub fn insert_map_container(&self, pos: u32, child: Arc<LoroMap>) -> LoroResult<Arc<LoroMap>> {
+        let c = self
+            .text
+            .insert_container(pos as usize, child.as_ref().clone().map)?;
+        Ok(Arc::new(LoroMap { map: c }))
+    }
+
+    /// Insert a text container as an embed at the given unicode position.
+    #[inline]
+    pub fn insert_text_container(
+        &self,
+        pos: u32,
+        child: Arc<LoroText>,
+    ) -> LoroResult<Arc<LoroText>> {
+        let c = self
+            .text
+            .insert_container(pos as usize, child.as_ref().clone().text)?;
+        Ok(Arc::new(LoroText { text: c }))
+    }
+
+    /// Insert a tree container as an embed at the given unicode position.
+    #[inline]
+    pub fn insert_tree_container(
+        &self,
+        pos: u32,
+        child: Arc<LoroTree>,
+    ) -> LoroResult<Arc<LoroTree>> {
+        let c = self
+            .text
+            .insert_container(pos as usize, child.as_ref().clone().tree)?;
+        Ok(Arc::new(LoroTree { tree: c }))
+    }
+
+    /// Insert a movable list container as an embed at the given unicode position.
+    #[inline]
+    pub fn insert_movable_list_container(
+        &self,
+        pos: u32,
+        child: Arc<LoroMovableList>,
+    ) -> LoroResult<Arc<LoroMovableList>> {
+        let c = self
+            .text
+            .insert_container(pos as usize, child.as_ref().clone().list)?;
+        Ok(Arc::new(LoroMovableList { list: c }))
+    }
+
+    /// Insert a counter container as an embed at the given unicode position.
+    #[inline]
+    pub fn insert_counter_container(
+        &self,
+        pos: u32,
+        child: Arc<LoroCounter>,
+    ) -> LoroResult<Arc<LoroCounter>> {
+        let c = self
+            .text
+            .insert_container(pos as usize, child.as_ref().clone().counter)?;
+        Ok(Arc::new(LoroCounter { counter: c }))
+    }
+
     /// Delete a range of text at the given unicode position with unicode length.
     pub fn delete(&self, pos: u32, len: u32) -> LoroResult<()> {
         self.text.delete(pos as usize, len as usize)
@@ -151,11 +232,25 @@ impl LoroText {
     ///
     /// *You should make sure that a key is always associated with the same expand type.*
     ///
-    /// Note: you cannot delete unmergeable annotations like comments by this method.
+    /// Unmarking a non-exclusive key removes the marks of all its values in the range.
+    /// Use [`Self::unmark_value`] to remove a single one.
     pub fn unmark(&self, from: u32, to: u32, key: &str) -> LoroResult<()> {
         self.text.unmark(from as usize..to as usize, key)
     }
 
+    /// Remove the mark of the given value of a non-exclusive style in the range.
+    /// The marks of its other values are kept.
+    pub fn unmark_value(
+        &self,
+        from: u32,
+        to: u32,
+        key: &str,
+        value: Arc<dyn LoroValueLike>,
+    ) -> LoroResult<()> {
+        self.text
+            .unmark_value(from as usize..to as usize, key, value.as_loro_value())
+    }
+
     /// Get the text in [Delta](https://quilljs.com/docs/delta/) format.
     ///
     /// # Example
@@ -231,6 +326,12 @@ impl LoroText {
         self.text.update_by_line(text, options)
     }
 
+    /// Update the current text to the rich text of the given delta, which can only
+    /// contain inserts.
+    pub fn update_with_marks(&self, delta: &[TextDelta], options: UpdateOptions) -> LoroResult<()> {
+        self.text.update_with_marks(delta, options)
+    }
+
     pub fn is_deleted(&self) -> bool {
         self.text.is_deleted()
     }
@@ -800,6 +800,7 @@ pub struct ContainerPath {
 pub struct ImportStatus {
     pub success: HashMap<u64, CounterSpan>,
     pub pending: Option<HashMap<u64, CounterSpan>>,
+    pub rejected: Option<HashMap<u64, CounterSpan>>,
 }
 
 impl From<loro::ImportStatus> for ImportStatus {
@@ -808,6 +809,7 @@ impl From<loro::ImportStatus> for ImportStatus {
         Self {
             success: vr_to_map(a),
             pending: value.pending.as_ref().map(vr_to_map),
+            rejected: value.rejected.as_ref().map(vr_to_map),
         }
     }
 }
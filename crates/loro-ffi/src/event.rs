@@ -1,6 +1,6 @@
 use std::{collections::HashMap, sync::Arc};
 
-use loro::{EventTriggerKind, TreeID};
+use loro::{EventTriggerKind, TreeID, TreeMoveRejectReason, ID};
 
 use crate::{ContainerID, LoroValue, TreeParentId, ValueOrContainer};
 
@@ -78,6 +78,11 @@ pub enum TextDelta {
         insert: String,
         attributes: Option<HashMap<String, LoroValue>>,
     },
+    /// An embed in the text. Its length is 1.
+    Embed {
+        insert: LoroValue,
+        attributes: Option<HashMap<String, LoroValue>>,
+    },
     Delete {
         delete: u32,
     },
@@ -136,6 +141,11 @@ pub enum TreeExternalDiff {
         old_parent: TreeParentId,
         old_index: u32,
     },
+    MoveRejected {
+        id: ID,
+        parent: TreeParentId,
+        reason: TreeMoveRejectReason,
+    },
 }
 
 impl<'a> From<&loro::event::ContainerDiff<'a>> for ContainerDiff {
@@ -235,6 +245,16 @@ impl From<&loro::event::Diff<'_>> for Diff {
                                 }),
                             });
                         }
+                        loro::TextDelta::Embed { insert, attributes } => {
+                            ans.push(TextDelta::Embed {
+                                insert: insert.clone().into(),
+                                attributes: attributes.as_ref().map(|a| {
+                                    a.iter()
+                                        .map(|(k, v)| (k.to_string(), v.clone().into()))
+                                        .collect()
+                                }),
+                            });
+                        }
                         loro::TextDelta::Delete { delete } => {
                             ans.push(TextDelta::Delete {
                                 delete: *delete as u32,
@@ -295,6 +315,15 @@ impl From<&loro::event::Diff<'_>> for Diff {
                                 old_parent: (*old_parent).into(),
                                 old_index: *old_index as u32,
                             },
+                            loro::TreeExternalDiff::MoveRejected { id, parent, reason } => {
+                                TreeExternalDiff::MoveRejected {
+                                    id: *id,
+                                    parent: (*parent).into(),
+                                    reason: *reason,
+                                }
+                            }
+                            // The kinds of the changes that this binding doesn't know yet
+                            _ => continue,
                         },
                     });
                 }
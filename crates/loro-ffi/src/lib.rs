@@ -6,7 +6,8 @@ pub use loro::{
     CounterSpan, EventTriggerKind, ExpandType, FractionalIndex, IdLp, IdSpan, JsonChange,
     JsonFutureOp, JsonFutureOpWrapper, JsonListOp, JsonMapOp, JsonMovableListOp, JsonOp,
     JsonOpContent, JsonPathError, JsonSchema, JsonTextOp, JsonTreeOp, Lamport, LoroEncodeError,
-    LoroError, PeerID, StyleConfig, TreeID, UpdateOptions, UpdateTimeoutError, ID,
+    LoroError, PeerID, StyleConfig, TreeID, TreeMoveRejectReason, UpdateOptions,
+    UpdateTimeoutError, ID,
 };
 pub use std::cmp::Ordering;
 use std::sync::Arc;
@@ -296,6 +296,18 @@ impl SharedArena {
             .expect("InternalError: Parent is not registered")
     }
 
+    /// Get the parent of the container, or `None` if it's a root container or
+    /// its parent is not registered yet.
+    pub(crate) fn get_registered_parent(&self, child: ContainerIdx) -> Option<ContainerIdx> {
+        self.inner
+            .parents
+            .try_lock()
+            .unwrap()
+            .get(&child)
+            .copied()
+            .flatten()
+    }
+
     /// Call `f` on each ancestor of `container`, including `container` itself.
     ///
     /// f(ContainerIdx, is_first)
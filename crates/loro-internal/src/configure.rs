@@ -1,5 +1,8 @@
-pub use crate::container::richtext::config::{StyleConfig, StyleConfigMap};
+pub use crate::container::richtext::config::{StyleConfig, StyleConfigMap, StyleOptions};
+use crate::schema::Schema;
 use crate::LoroDoc;
+use fxhash::FxHashMap;
+use loro_common::ContainerID;
 
 #[derive(Clone, Debug)]
 pub struct Configure {
@@ -7,6 +10,9 @@ pub struct Configure {
     record_timestamp: Arc<AtomicBool>,
     pub(crate) merge_interval: Arc<AtomicI64>,
     pub(crate) editable_detached_mode: Arc<AtomicBool>,
+    pub(crate) schema: Arc<RwLock<Option<Arc<Schema>>>>,
+    /// The thresholds of the automatic rebalancing of the trees
+    pub(crate) tree_rebalance_thresholds: Arc<RwLock<FxHashMap<ContainerID, usize>>>,
 }
 
 impl LoroDoc {
@@ -15,6 +21,9 @@ impl LoroDoc {
         self.set_record_timestamp(config.record_timestamp());
         self.set_change_merge_interval(config.merge_interval());
         self.set_detached_editing(config.detached_editing());
+        *self.config.schema.write().unwrap() = config.schema();
+        *self.config.tree_rebalance_thresholds.write().unwrap() =
+            config.tree_rebalance_thresholds.read().unwrap().clone();
     }
 }
 
@@ -25,6 +34,8 @@ impl Default for Configure {
             record_timestamp: Arc::new(AtomicBool::new(false)),
             editable_detached_mode: Arc::new(AtomicBool::new(false)),
             merge_interval: Arc::new(AtomicI64::new(1000 * 1000)),
+            schema: Arc::new(RwLock::new(None)),
+            tree_rebalance_thresholds: Default::default(),
         }
     }
 }
@@ -47,6 +58,10 @@ impl Configure {
                 self.editable_detached_mode
                     .load(std::sync::atomic::Ordering::Relaxed),
             )),
+            schema: Arc::new(RwLock::new(self.schema())),
+            tree_rebalance_thresholds: Arc::new(RwLock::new(
+                self.tree_rebalance_thresholds.read().unwrap().clone(),
+            )),
         }
     }
 
@@ -74,6 +89,10 @@ impl Configure {
             .store(mode, std::sync::atomic::Ordering::Relaxed);
     }
 
+    pub fn schema(&self) -> Option<Arc<Schema>> {
+        self.schema.read().unwrap().clone()
+    }
+
     pub fn merge_interval(&self) -> i64 {
         self.merge_interval
             .load(std::sync::atomic::Ordering::Relaxed)
@@ -83,6 +102,26 @@ impl Configure {
         self.merge_interval
             .store(interval, std::sync::atomic::Ordering::Relaxed);
     }
+
+    pub(crate) fn tree_rebalance_threshold(&self, tree: &ContainerID) -> Option<usize> {
+        self.tree_rebalance_thresholds
+            .read()
+            .unwrap()
+            .get(tree)
+            .copied()
+    }
+
+    pub(crate) fn set_tree_rebalance_threshold(
+        &self,
+        tree: &ContainerID,
+        threshold: Option<usize>,
+    ) {
+        let mut thresholds = self.tree_rebalance_thresholds.write().unwrap();
+        match threshold {
+            Some(threshold) => thresholds.insert(tree.clone(), threshold),
+            None => thresholds.remove(tree),
+        };
+    }
 }
 
 #[derive(Debug)]
@@ -28,6 +28,39 @@ pub(crate) use richtext_state::RichtextState;
 pub(crate) use style_range_map::Styles;
 pub(crate) use tracker::{CrdtRopeDelta, Tracker as RichtextTracker};
 
+/// The reserved style key of the embeds in the text.
+///
+/// An embed is stored as an [EMBED_CHAR] marked with this key, whose value is the
+/// embedded value. The mark doesn't expand, so it always covers the single char.
+pub const EMBED_STYLE_KEY: &str = "$embed";
+/// The placeholder char of an embed in the text
+pub const EMBED_CHAR: char = '\u{FFFC}';
+
+/// The type of the positions in a text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PosType {
+    /// UTF-8 byte index
+    Utf8,
+    /// Unicode scalar value index
+    Unicode,
+    /// UTF-16 code unit index
+    Utf16,
+    /// UTF-16 index if feature="wasm", Unicode index otherwise
+    Event,
+}
+
+impl From<PosType> for richtext_state::PosType {
+    fn from(value: PosType) -> Self {
+        match value {
+            PosType::Utf8 => Self::Utf8,
+            PosType::Unicode => Self::Unicode,
+            PosType::Utf16 => Self::Utf16,
+            PosType::Event => Self::Event,
+        }
+    }
+}
+
 /// This is the data structure that represents a span of rich text.
 /// It's used to communicate with the frontend.
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -57,12 +90,19 @@ pub struct StyleOp {
 #[derive(Debug, Hash, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub(crate) enum StyleKey {
     Key(InternalString),
+    /// The key of a non-exclusive style, whose marks with different values
+    /// are kept separately
+    KeyWithValue {
+        key: InternalString,
+        value: LoroValue,
+    },
 }
 
 impl StyleKey {
     pub fn key(&self) -> &InternalString {
         match self {
             Self::Key(key) => key,
+            Self::KeyWithValue { key, .. } => key,
         }
     }
 }
@@ -75,12 +115,24 @@ impl StyleOp {
         }
     }
 
+    /// The value of the style. It's `Null` if the op removes the style.
     pub fn to_value(&self) -> LoroValue {
-        self.value.clone()
+        if self.info.is_removed() {
+            LoroValue::Null
+        } else {
+            self.value.clone()
+        }
     }
 
     pub(crate) fn get_style_key(&self) -> StyleKey {
-        StyleKey::Key(self.key.clone())
+        if self.info.is_non_exclusive() {
+            StyleKey::KeyWithValue {
+                key: self.key.clone(),
+                value: self.value.clone(),
+            }
+        } else {
+            StyleKey::Key(self.key.clone())
+        }
     }
 
     #[cfg(test)]
@@ -132,9 +184,9 @@ impl Ord for StyleOp {
 /// - 0              (1st bit)
 /// - Expand Before  (2nd bit): when inserting new text before this style, whether the new text should inherit this style.
 /// - Expand After   (3rd bit): when inserting new text after  this style, whether the new text should inherit this style.
-/// - 0              (4th bit):
-/// - 0              (5th bit):
-/// - 0              (6th bit)
+/// - Non-exclusive  (4th bit): the marks with different values are kept separately.
+/// - No nesting     (5th bit): the marks of a non-exclusive style with different values cannot overlap.
+/// - Removed        (6th bit): the op removes the mark of its value of a non-exclusive style.
 /// - 0              (7th bit)
 /// - 0              (8th bit):
 #[derive(Default, Clone, Copy, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
@@ -149,12 +201,20 @@ impl Debug for TextStyleInfoFlag {
             .field("data", &format!("{:#010b}", self.data))
             .field("expand_before", &self.expand_before())
             .field("expand_after", &self.expand_after())
+            .field("non_exclusive", &self.is_non_exclusive())
+            .field("allow_nesting", &self.allow_nesting())
+            .field("removed", &self.is_removed())
             .finish()
     }
 }
 
 const EXPAND_BEFORE_MASK: u8 = 0b0000_0010;
 const EXPAND_AFTER_MASK: u8 = 0b0000_0100;
+// The bits below are ignored by the versions before the non-exclusive styles.
+// See [crate::configure::StyleOptions] for the compatibility of the encoding.
+const NON_EXCLUSIVE_MASK: u8 = 0b0000_1000;
+const NO_NESTING_MASK: u8 = 0b0001_0000;
+const REMOVED_MASK: u8 = 0b0010_0000;
 const ALIVE_MASK: u8 = 0b1000_0000;
 
 /// Whether to expand the style when inserting new text around it.
@@ -272,9 +332,45 @@ impl TextStyleInfoFlag {
         TextStyleInfoFlag { data }
     }
 
+    /// Whether the marks of the style with different values are kept separately
+    #[inline(always)]
+    pub const fn is_non_exclusive(self) -> bool {
+        self.data & NON_EXCLUSIVE_MASK != 0
+    }
+
+    /// Whether the marks of a non-exclusive style with different values can overlap
+    #[inline(always)]
+    pub const fn allow_nesting(self) -> bool {
+        self.data & NO_NESTING_MASK == 0
+    }
+
+    /// Whether the op removes the mark of its value of a non-exclusive style
+    #[inline(always)]
+    pub const fn is_removed(self) -> bool {
+        self.data & REMOVED_MASK != 0
+    }
+
+    pub const fn to_non_exclusive(self, allow_nesting: bool) -> Self {
+        let mut data = self.data | NON_EXCLUSIVE_MASK;
+        if !allow_nesting {
+            data |= NO_NESTING_MASK;
+        }
+
+        TextStyleInfoFlag { data }
+    }
+
+    /// Get the flag of the op that removes the style.
+    ///
+    /// The expand type is reversed. The op of a non-exclusive style only removes
+    /// the mark of its own value.
     #[inline(always)]
     pub const fn to_delete(self) -> Self {
-        TextStyleInfoFlag::new(self.expand_type().reverse())
+        let mut ans = TextStyleInfoFlag::new(self.expand_type().reverse());
+        if self.is_non_exclusive() {
+            ans.data |= (self.data & (NON_EXCLUSIVE_MASK | NO_NESTING_MASK)) | REMOVED_MASK;
+        }
+
+        ans
     }
 
     pub const BOLD: TextStyleInfoFlag = TextStyleInfoFlag::new(ExpandType::After);
@@ -1,25 +1,48 @@
 use fxhash::FxHashMap;
 use loro_common::InternalString;
 
-use super::{ExpandType, TextStyleInfoFlag};
+use crate::schema::ValueSchema;
+
+use super::{ExpandType, TextStyleInfoFlag, EMBED_STYLE_KEY};
+
+/// The built-in config of the embeds. It can't be overridden.
+static EMBED_STYLE_CONFIG: StyleConfig = StyleConfig {
+    expand: ExpandType::None,
+};
+
+static EMBED_STYLE_OPTIONS: StyleOptions = StyleOptions::new();
 
 #[derive(Debug, Default, Clone)]
 pub struct StyleConfigMap {
     map: FxHashMap<InternalString, StyleConfig>,
+    options: FxHashMap<InternalString, StyleOptions>,
 }
 
 impl StyleConfigMap {
     pub fn new() -> Self {
         Self {
             map: FxHashMap::default(),
+            options: FxHashMap::default(),
         }
     }
 
+    /// Insert the config of the style with the default [StyleOptions].
     pub fn insert(&mut self, key: InternalString, value: StyleConfig) {
+        self.insert_with_options(key, value, StyleOptions::new());
+    }
+
+    /// Insert the config of the style along with its [StyleOptions].
+    pub fn insert_with_options(
+        &mut self,
+        key: InternalString,
+        value: StyleConfig,
+        options: StyleOptions,
+    ) {
         if key.contains(':') {
             panic!("style key should not contain ':'");
         }
 
+        self.options.insert(key.clone(), options);
         self.map.insert(key, value);
     }
 
@@ -27,6 +50,39 @@ impl StyleConfigMap {
         self.map.get(key)
     }
 
+    /// Get the options of the style. It returns `None` if the style is not configured.
+    pub fn get_options(&self, key: &InternalString) -> Option<&StyleOptions> {
+        self.options.get(key)
+    }
+
+    /// Get the config of the style key used by the ops.
+    ///
+    /// The suffix after ':' is ignored.
+    pub fn get_by_style_key(&self, key: &InternalString) -> Option<&StyleConfig> {
+        if key.as_str() == EMBED_STYLE_KEY {
+            Some(&EMBED_STYLE_CONFIG)
+        } else if let Some(index) = key.find(':') {
+            let key: InternalString = key[..index].into();
+            self.map.get(&key)
+        } else {
+            self.map.get(key)
+        }
+    }
+
+    /// Get the options of the style key used by the ops.
+    ///
+    /// The suffix after ':' is ignored.
+    pub fn get_options_by_style_key(&self, key: &InternalString) -> Option<&StyleOptions> {
+        if key.as_str() == EMBED_STYLE_KEY {
+            Some(&EMBED_STYLE_OPTIONS)
+        } else if let Some(index) = key.find(':') {
+            let key: InternalString = key[..index].into();
+            self.get_options(&key)
+        } else {
+            self.get_options(key)
+        }
+    }
+
     pub fn get_style_flag(&self, key: &InternalString) -> Option<TextStyleInfoFlag> {
         self._get_style_flag(key, false)
     }
@@ -36,65 +92,63 @@ impl StyleConfigMap {
     }
 
     fn _get_style_flag(&self, key: &InternalString, is_del: bool) -> Option<TextStyleInfoFlag> {
-        let f = |x: &StyleConfig| {
-            TextStyleInfoFlag::new(if is_del { x.expand.reverse() } else { x.expand })
-        };
-        if let Some(index) = key.find(':') {
-            let key = key[..index].into();
-            self.map.get(&key).map(f)
+        let config = self.get_by_style_key(key)?;
+        let options = self.get_options_by_style_key(key)?;
+        let flag = TextStyleInfoFlag::new(config.expand);
+        let flag = if options.exclusive {
+            flag
         } else {
-            self.map.get(key).map(f)
-        }
+            flag.to_non_exclusive(options.allow_nesting)
+        };
+        Some(if is_del { flag.to_delete() } else { flag })
     }
 
     pub fn default_rich_text_config() -> Self {
-        let mut map = Self {
-            map: FxHashMap::default(),
-        };
+        let mut map = Self::new();
 
-        map.map.insert(
+        map.insert(
             "bold".into(),
             StyleConfig {
                 expand: ExpandType::After,
             },
         );
 
-        map.map.insert(
+        map.insert(
             "italic".into(),
             StyleConfig {
                 expand: ExpandType::After,
             },
         );
 
-        map.map.insert(
+        map.insert(
             "underline".into(),
             StyleConfig {
                 expand: ExpandType::After,
             },
         );
 
-        map.map.insert(
+        map.insert(
             "link".into(),
             StyleConfig {
                 expand: ExpandType::None,
             },
         );
 
-        map.map.insert(
+        map.insert(
             "highlight".into(),
             StyleConfig {
                 expand: ExpandType::None,
             },
         );
 
-        map.map.insert(
+        map.insert(
             "comment".into(),
             StyleConfig {
                 expand: ExpandType::None,
             },
         );
 
-        map.map.insert(
+        map.insert(
             "code".into(),
             StyleConfig {
                 expand: ExpandType::None,
@@ -128,3 +182,45 @@ impl Default for StyleConfig {
         Self::new()
     }
 }
+
+/// The options of a text style other than its expand behavior.
+///
+/// The ops of the non-exclusive styles are encoded with the flag bits that the
+/// versions of Loro before their introduction don't know. Such versions ignore
+/// the bits: they resolve the overlapping marks as the marks of an exclusive style
+/// and read the removal of a mark as a mark. So all the peers of a document that
+/// uses non-exclusive styles should run a version that supports them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleOptions {
+    /// Whether the marks of this style with different values replace each other.
+    ///
+    /// It's true by default. The overlapping marks of a non-exclusive style with
+    /// different values, such as comments with distinct ids, are kept separately.
+    /// The value of the style is then the list of the values in the order they
+    /// are marked.
+    pub exclusive: bool,
+    /// Whether a mark of a non-exclusive style can overlap the marks of the
+    /// same key with different values. It's true by default.
+    ///
+    /// Local edits that overlap fail. The concurrent marks that overlap are
+    /// resolved to the latest value.
+    pub allow_nesting: bool,
+    /// The type of the values of the style. Marking with other values fails.
+    pub value_type: ValueSchema,
+}
+
+impl StyleOptions {
+    pub const fn new() -> Self {
+        Self {
+            exclusive: true,
+            allow_nesting: true,
+            value_type: ValueSchema::Any,
+        }
+    }
+}
+
+impl Default for StyleOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -24,6 +24,7 @@ use crate::{
     container::richtext::style_range_map::EMPTY_STYLES,
     delta::{DeltaValue, StyleMeta},
     utils::query_by_len::{EntityIndexQueryWithEventIndex, IndexQueryWithEntityIndex, QueryByLen},
+    InternalString,
 };
 
 use self::query::{
@@ -33,7 +34,7 @@ use self::query::{
 
 use super::{
     style_range_map::{IterAnchorItem, StyleRangeMap, Styles},
-    AnchorType, RichtextSpan, StyleOp,
+    AnchorType, RichtextSpan, StyleKey, StyleOp, EMBED_STYLE_KEY,
 };
 
 pub(crate) use query::PosType;
@@ -686,7 +687,7 @@ impl RichtextStateChunk {
     pub fn len_with(&self, pos_type: PosType) -> usize {
         match self {
             RichtextStateChunk::Text(t) => match pos_type {
-                PosType::Bytes => t.utf8_len() as usize,
+                PosType::Utf8 => t.utf8_len() as usize,
                 PosType::Utf16 => t.utf16_len() as usize,
                 PosType::Event => t.unicode_len() as usize,
                 PosType::Entity => t.unicode_len() as usize,
@@ -980,7 +981,7 @@ impl PosCache {
     #[allow(unused)]
     fn get_len(&self, pos_type: PosType) -> i32 {
         match pos_type {
-            PosType::Bytes => self.bytes,
+            PosType::Utf8 => self.bytes,
             PosType::Unicode => self.unicode_len,
             PosType::Utf16 => self.utf16_len,
             PosType::Entity => self.entity_len,
@@ -1117,16 +1118,20 @@ mod query {
 
     use super::*;
 
+    /// The type of the positions used by the queries.
+    ///
+    /// It's [crate::container::richtext::PosType] plus the entity index.
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub(crate) enum PosType {
-        #[allow(unused)]
-        Bytes,
-        #[allow(unused)]
+        /// UTF-8 byte index
+        Utf8,
+        /// Unicode scalar value index
         Unicode,
-        #[allow(unused)]
+        /// UTF-16 code unit index
         Utf16,
-        #[allow(unused)]
+        /// Unicode index plus style anchor index
         Entity,
+        /// UTF-16 index if feature="wasm", Unicode index otherwise
         Event,
     }
 
@@ -1322,7 +1327,7 @@ impl RichtextState {
                 Ok((entity_index + c.offset, Some(c)))
             } else {
                 let (c, entity_index) = match pos_type {
-                    PosType::Bytes => self.find_best_insert_pos::<ByteQueryT>(pos),
+                    PosType::Utf8 => self.find_best_insert_pos::<ByteQueryT>(pos),
                     PosType::Unicode => self.find_best_insert_pos::<UnicodeQueryT>(pos),
                     PosType::Utf16 => self.find_best_insert_pos::<Utf16QueryT>(pos),
                     PosType::Entity => self.find_best_insert_pos::<EntityQueryT>(pos),
@@ -1803,7 +1808,7 @@ impl RichtextState {
 
             let mut ans: Vec<EntityRangeInfo> = Vec::new();
             let (start, end) = match pos_type {
-                PosType::Bytes => (
+                PosType::Utf8 => (
                     self.tree.query::<ByteQuery>(&pos).unwrap().cursor,
                     self.tree.query::<ByteQuery>(&(pos + len)).unwrap().cursor,
                 ),
@@ -1931,6 +1936,224 @@ impl RichtextState {
         result
     }
 
+    /// Get the styles of the char at the given position.
+    ///
+    /// Return `None` if the position is out of bound.
+    pub(crate) fn get_styles_at(&self, pos: usize, pos_type: PosType) -> Option<StyleMeta> {
+        self.check_cache();
+        let result = {
+            let ranges = self.get_text_entity_ranges(pos, 1, pos_type).ok()?;
+            // The range may start with an empty span at the end of the previous chunk
+            let entity_index = ranges.iter().find(|x| x.entity_len() > 0)?.entity_start;
+            Some(match &self.style_ranges {
+                Some(ranges) => ranges.get_styles_at(entity_index).into(),
+                None => StyleMeta::default(),
+            })
+        };
+        self.check_cache();
+        result
+    }
+
+    /// Get the ranges where the style of the given key is set, with the style values.
+    ///
+    /// All the style runs are scanned, including the ones of other keys, and each matching
+    /// run is converted to a position by a lookup in the text tree. So it's
+    /// O(runs + matching runs * log(text length)).
+    pub(crate) fn get_style_ranges(
+        &self,
+        key: &str,
+        pos_type: PosType,
+    ) -> Vec<(Range<usize>, LoroValue)> {
+        self.check_cache();
+        let result = {
+            let Some(style_ranges) = self.style_ranges.as_ref() else {
+                return Vec::new();
+            };
+            if self.tree.is_empty() {
+                return Vec::new();
+            }
+
+            let key: InternalString = key.into();
+            let mut ans: Vec<(Range<usize>, LoroValue)> = Vec::new();
+            for (range, styles) in style_ranges.iter() {
+                if !styles.keys().any(|k| k.key() == &key) {
+                    continue;
+                }
+
+                let Some(value) = StyleMeta::from(styles).get(&key).cloned() else {
+                    continue;
+                };
+                // The style is removed by unmark
+                if value.is_null() {
+                    continue;
+                }
+
+                let start = self.entity_index_to_pos(range.start, pos_type);
+                let end = self.entity_index_to_pos(range.end, pos_type);
+                if start == end {
+                    continue;
+                }
+
+                match ans.last_mut() {
+                    Some(last) if last.0.end == start && last.1 == value => last.0.end = end,
+                    _ => ans.push((start..end, value)),
+                }
+            }
+
+            ans
+        };
+        self.check_cache();
+        result
+    }
+
+    /// Get the values of the non-exclusive style `key` that overlap the given entity range
+    pub(crate) fn get_style_values_in_entity_range(
+        &self,
+        range: Range<usize>,
+        key: &str,
+    ) -> Vec<LoroValue> {
+        let Some(style_ranges) = self.style_ranges.as_ref() else {
+            return Vec::new();
+        };
+
+        let mut ans: Vec<LoroValue> = Vec::new();
+        for (r, styles) in style_ranges.iter() {
+            if r.start >= range.end {
+                break;
+            }
+            if r.end <= range.start {
+                continue;
+            }
+
+            for (style_key, value) in styles.iter() {
+                let StyleKey::KeyWithValue { key: k, .. } = style_key else {
+                    continue;
+                };
+                let Some(value) = value.get().map(|op| op.to_value()) else {
+                    continue;
+                };
+                if k.as_str() != key || value.is_null() || ans.contains(&value) {
+                    continue;
+                }
+
+                ans.push(value);
+            }
+        }
+
+        ans
+    }
+
+    /// Get the values of the style `key` in the given event range, as the event
+    /// lengths of the segments with their values
+    pub(crate) fn get_style_values_in_event_range(
+        &mut self,
+        range: Range<usize>,
+        key: &str,
+    ) -> Vec<(usize, LoroValue)> {
+        let (entity_range, _) =
+            self.get_entity_range_and_text_styles_at_range(range, PosType::Event);
+        if entity_range.is_empty() {
+            return Vec::new();
+        }
+
+        let key: InternalString = key.into();
+        let mut ans: Vec<(usize, LoroValue)> = Vec::new();
+        for item in self.iter_range(entity_range) {
+            if item.event_len == 0 {
+                continue;
+            }
+
+            let value = StyleMeta::from(item.styles)
+                .get(&key)
+                .cloned()
+                .unwrap_or(LoroValue::Null);
+            match ans.last_mut() {
+                Some(last) if last.1 == value => last.0 += item.event_len,
+                _ => ans.push((item.event_len, value)),
+            }
+        }
+
+        ans
+    }
+
+    /// Get the text spans with their styles in the given range
+    pub(crate) fn slice_spans(
+        &self,
+        start: usize,
+        end: usize,
+        pos_type: PosType,
+    ) -> LoroResult<Vec<RichtextSpan>> {
+        self.check_cache();
+        let result = {
+            if end > self.len(pos_type) {
+                return Err(LoroError::OutOfBound {
+                    pos: end,
+                    len: self.len(pos_type),
+                    info: format!("Position: {}:{}", file!(), line!()).into_boxed_str(),
+                });
+            }
+
+            let mut ans = Vec::new();
+            if start >= end || self.tree.is_empty() {
+                return Ok(ans);
+            }
+
+            let start_cursor = self.cursor_at(start, pos_type);
+            let end_cursor = self.cursor_at(end, pos_type);
+            let mut entity_index = self.get_entity_index_from_path(start_cursor);
+            for span in self.tree.iter_range(start_cursor..end_cursor) {
+                let start = span.start.unwrap_or(0);
+                let end = span.end.unwrap_or(span.elem.rle_len());
+                if end == 0 {
+                    break;
+                }
+
+                match span.elem {
+                    RichtextStateChunk::Text(_) if start == end => {}
+                    RichtextStateChunk::Text(s) => {
+                        let Ok(text) = unicode_slice(s.as_str(), start, end) else {
+                            return Err(LoroError::UTF16InUnicodeCodePoint { pos: end });
+                        };
+                        // The styles don't change inside a text chunk
+                        let attributes = match &self.style_ranges {
+                            Some(ranges) => ranges.get_styles_at(entity_index).into(),
+                            None => StyleMeta::default(),
+                        };
+                        ans.push(RichtextSpan {
+                            text: text.into(),
+                            attributes,
+                        });
+                        entity_index += end - start;
+                    }
+                    RichtextStateChunk::Style { .. } => {
+                        entity_index += 1;
+                    }
+                }
+            }
+
+            Ok(ans)
+        };
+        self.check_cache();
+        result
+    }
+
+    fn cursor_at(&self, pos: usize, pos_type: PosType) -> Cursor {
+        match pos_type {
+            PosType::Utf8 => self.tree.query::<ByteQuery>(&pos),
+            PosType::Unicode => self.tree.query::<UnicodeQuery>(&pos),
+            PosType::Utf16 => self.tree.query::<Utf16Query>(&pos),
+            PosType::Entity => self.tree.query::<EntityQuery>(&pos),
+            PosType::Event => self.tree.query::<EventIndexQuery>(&pos),
+        }
+        .unwrap()
+        .cursor
+    }
+
+    fn entity_index_to_pos(&self, index: usize, pos_type: PosType) -> usize {
+        let cursor = self.tree.query::<EntityQuery>(&index).unwrap().cursor;
+        self.get_index_from_cursor(cursor, pos_type).unwrap()
+    }
+
     // PERF: can be splitted into two methods. One is without cursor_to_event_index
     // PERF: can be speed up a lot by detecting whether the range is in a single leaf first
     /// This is used to accept changes from DiffCalculator
@@ -2110,7 +2333,7 @@ impl RichtextState {
         let cursor = match pos_type {
             PosType::Entity => self.tree.query::<EntityQuery>(&index).unwrap(),
             PosType::Utf16 => self.tree.query::<Utf16Query>(&index).unwrap(),
-            PosType::Bytes => self.tree.query::<ByteQuery>(&index).unwrap(),
+            PosType::Utf8 => self.tree.query::<ByteQuery>(&index).unwrap(),
             PosType::Event => return index,
             PosType::Unicode => self.tree.query::<UnicodeQuery>(&index).unwrap(),
         };
@@ -2118,6 +2341,27 @@ impl RichtextState {
         self.cursor_to_event_index(cursor.cursor)
     }
 
+    /// Convert the position from one type to another.
+    ///
+    /// Return `None` if the position is out of bound or not at a char boundary.
+    pub(crate) fn convert_pos(&self, pos: usize, from: PosType, to: PosType) -> Option<usize> {
+        if pos > self.len(from) {
+            return None;
+        }
+
+        if from == to || self.tree.is_empty() {
+            return Some(pos);
+        }
+
+        let ans = self.get_index_from_cursor(self.cursor_at(pos, from), to)?;
+        // The queries move the positions inside a char to the char boundary
+        if self.get_index_from_cursor(self.cursor_at(ans, to), from)? != pos {
+            return None;
+        }
+
+        Some(ans)
+    }
+
     pub fn event_index_to_unicode_index(&self, index: usize) -> usize {
         if !cfg!(feature = "wasm") {
             return index;
@@ -2217,6 +2461,27 @@ impl RichtextState {
             let mut last_attributes: Option<LoroValue> = None;
             for span in self.iter() {
                 let attributes: LoroValue = span.attributes.to_value();
+                if let Some(embed) = attributes.as_map().unwrap().get(EMBED_STYLE_KEY) {
+                    // Each embed is an insert of `{"$embed": value}`
+                    let mut rest = span.attributes.to_map_without_null_value();
+                    rest.remove(EMBED_STYLE_KEY);
+                    let mut insert = FxHashMap::default();
+                    insert.insert(EMBED_STYLE_KEY.to_string(), embed.clone());
+                    let insert = LoroValue::Map(insert.into());
+                    for _ in span.text.as_str().chars() {
+                        let mut value = FxHashMap::default();
+                        value.insert("insert".into(), insert.clone());
+                        if !rest.is_empty() {
+                            value.insert("attributes".into(), LoroValue::Map(rest.clone().into()));
+                        }
+
+                        ans.push(LoroValue::Map(value.into()));
+                    }
+
+                    last_attributes = None;
+                    continue;
+                }
+
                 if let Some(last) = last_attributes.as_ref() {
                     if &attributes == last {
                         let hash_map = ans.last_mut().unwrap().as_map_mut().unwrap();
@@ -2532,7 +2797,7 @@ impl RichtextState {
                 PosType::Utf16 => self.len_utf16(),
                 PosType::Entity => self.len_entity(),
                 PosType::Event => self.len_event(),
-                PosType::Bytes => self.len_utf8(),
+                PosType::Utf8 => self.len_utf8(),
             }
         };
         self.check_cache();
@@ -2546,7 +2811,7 @@ fn entity_offset_to_pos_type_offset(
     offset: usize,
 ) -> usize {
     match pos_type {
-        PosType::Bytes => match elem {
+        PosType::Utf8 => match elem {
             RichtextStateChunk::Text(t) => unicode_to_utf8_index(t.as_str(), offset).unwrap(),
             RichtextStateChunk::Style { .. } => 0,
         },
@@ -2576,7 +2841,7 @@ fn pos_type_offset_to_entity_offset(
     offset: usize,
 ) -> Option<usize> {
     match pos_type {
-        PosType::Bytes => match elem {
+        PosType::Utf8 => match elem {
             RichtextStateChunk::Text(t) => utf8_to_unicode_index(t.as_str(), offset).ok(),
             RichtextStateChunk::Style { .. } => {
                 if offset > 0 {
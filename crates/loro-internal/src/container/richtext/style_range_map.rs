@@ -37,10 +37,10 @@ pub(crate) struct Styles {
 }
 
 impl Styles {
-    pub(crate) fn has_key_value(&self, key: &str, value: &loro_common::LoroValue) -> bool {
-        match self.get(&StyleKey::Key(key.into())) {
+    pub(crate) fn has_key_value(&self, key: &StyleKey, value: &loro_common::LoroValue) -> bool {
+        match self.get(key) {
             Some(v) => match v.get() {
-                Some(v) => &v.value == value,
+                Some(v) => &v.to_value() == value,
                 _ => false,
             },
             _ => false,
@@ -188,6 +188,16 @@ impl StyleRangeMap {
         }
     }
 
+    /// Get the styles of the entity at the given index
+    pub(crate) fn get_styles_at(&self, index: usize) -> &Styles {
+        if !self.has_style {
+            return &EMPTY_STYLES;
+        }
+
+        let cursor = self.tree.query::<LengthFinder>(&index).unwrap().cursor;
+        &self.tree.get_elem(cursor.leaf).unwrap().styles
+    }
+
     /// Insert entities at `pos` with length of `len`
     ///
     /// # Internal
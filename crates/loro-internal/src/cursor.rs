@@ -60,6 +60,8 @@ pub enum CannotFindRelativePosition {
     HistoryCleared,
     #[error("Cannot find relative position. The id is not found.")]
     IdNotFound,
+    #[error("Cannot convert the position to the given position type.")]
+    PosTypeMismatch,
 }
 
 impl Cursor {
@@ -10,5 +10,6 @@ mod text;
 pub use text::{StyleMeta, StyleMetaItem};
 mod tree;
 pub use tree::{
-    TreeDelta, TreeDeltaItem, TreeDiff, TreeDiffItem, TreeExternalDiff, TreeInternalDiff,
+    RejectedTreeMove, TreeDelta, TreeDeltaItem, TreeDiff, TreeDiffItem, TreeExternalDiff,
+    TreeInternalDiff, TreeMoveRejectReason,
 };
@@ -1,3 +1,4 @@
+use std::sync::Arc;
 
 use fxhash::FxHashMap;
 use loro_common::{InternalString, LoroValue, PeerID};
@@ -5,7 +6,7 @@ use loro_delta::delta_trait::DeltaAttr;
 use serde::{Deserialize, Serialize};
 
 use crate::change::Lamport;
-use crate::container::richtext::{Style, Styles};
+use crate::container::richtext::{Style, StyleOp, Styles};
 use crate::ToJson;
 
 use super::Meta;
@@ -34,10 +35,20 @@ impl StyleMetaItem {
 }
 
 impl From<&Styles> for StyleMeta {
+    /// The values of a non-exclusive style are collected into a list in the order
+    /// they are marked. Only the latest value is kept if the style doesn't allow
+    /// nesting. The value is `Null` if all the values are removed.
     fn from(styles: &Styles) -> Self {
         let mut map = FxHashMap::with_capacity_and_hasher(styles.len(), Default::default());
+        let mut non_exclusive: FxHashMap<&InternalString, Vec<&Arc<StyleOp>>> =
+            FxHashMap::default();
         for (key, value) in styles.iter() {
             if let Some(value) = value.get() {
+                if value.info.is_non_exclusive() {
+                    non_exclusive.entry(key.key()).or_default().push(value);
+                    continue;
+                }
+
                 map.insert(
                     key.key().clone(),
                     StyleMetaItem {
@@ -48,6 +59,34 @@ impl From<&Styles> for StyleMeta {
                 );
             }
         }
+
+        for (key, mut ops) in non_exclusive {
+            ops.sort();
+            let last = ops.last().unwrap();
+            let (lamport, peer) = (last.lamport, last.peer);
+            ops.retain(|op| !op.info.is_removed());
+            if ops.iter().any(|op| !op.info.allow_nesting()) {
+                ops.drain(..ops.len().saturating_sub(1));
+            }
+
+            let value = if ops.is_empty() {
+                LoroValue::Null
+            } else {
+                LoroValue::List(ops.iter().map(|op| op.to_value()).collect())
+            };
+            let item = StyleMetaItem {
+                lamport,
+                peer,
+                value,
+            };
+            match map.get_mut(key) {
+                Some(old) => old.try_replace(&item),
+                None => {
+                    map.insert(key.clone(), item);
+                }
+            }
+        }
+
         Self { map }
     }
 }
@@ -59,6 +98,29 @@ impl From<Styles> for StyleMeta {
     }
 }
 
+impl From<Option<FxHashMap<String, LoroValue>>> for StyleMeta {
+    fn from(attributes: Option<FxHashMap<String, LoroValue>>) -> Self {
+        let Some(attributes) = attributes else {
+            return Self::default();
+        };
+
+        let map = attributes
+            .into_iter()
+            .map(|(key, value)| {
+                (
+                    key.into(),
+                    StyleMetaItem {
+                        lamport: 0,
+                        peer: 0,
+                        value,
+                    },
+                )
+            })
+            .collect();
+        Self { map }
+    }
+}
+
 impl Meta for StyleMeta {
     fn is_empty(&self) -> bool {
         self.map.is_empty()
@@ -101,6 +163,10 @@ impl StyleMeta {
         self.map.insert(key, value);
     }
 
+    pub(crate) fn get(&self, key: &InternalString) -> Option<&LoroValue> {
+        self.map.get(key).map(|x| &x.value)
+    }
+
     pub(crate) fn contains_key(&self, key: &InternalString) -> bool {
         self.map.contains_key(key)
     }
@@ -109,7 +175,7 @@ impl StyleMeta {
         LoroValue::Map(self.to_map_without_null_value().into())
     }
 
-    fn to_map_without_null_value(&self) -> FxHashMap<String, LoroValue> {
+    pub(crate) fn to_map_without_null_value(&self) -> FxHashMap<String, LoroValue> {
         self.map
             .iter()
             .filter_map(|(key, value)| {
@@ -1,7 +1,7 @@
 use fractional_index::FractionalIndex;
 use fxhash::{FxHashMap, FxHashSet};
 use itertools::Itertools;
-use loro_common::{IdFull, TreeID};
+use loro_common::{IdFull, TreeID, ID};
 use std::fmt::Debug;
 use std::ops::{Deref, DerefMut};
 
@@ -18,6 +18,11 @@ pub struct TreeDiffItem {
     pub action: TreeExternalDiff,
 }
 
+/// The change of a tree node in [TreeDiff].
+///
+/// Like [crate::event::Diff], new kinds of the changes may be reported in later
+/// versions, so the matches on it need a wildcard arm.
+#[non_exhaustive]
 #[derive(Debug, Clone, PartialEq)]
 pub enum TreeExternalDiff {
     Create {
@@ -36,6 +41,37 @@ pub enum TreeExternalDiff {
         old_parent: TreeParentId,
         old_index: usize,
     },
+    /// The create or move op `id` of the target is rejected when resolving the concurrent
+    /// edits, so the target is not placed under `parent`.
+    ///
+    /// It doesn't change the tree. The change of the tree caused by the rejection, if any,
+    /// is in the other items of the diff.
+    MoveRejected {
+        id: ID,
+        parent: TreeParentId,
+        reason: TreeMoveRejectReason,
+    },
+}
+
+/// The reason why a create or move op of a tree node is rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TreeMoveRejectReason {
+    /// Applying the op would create a cycle, because a concurrent op has moved
+    /// the new parent under the target.
+    Cycle,
+    /// The new parent has been deleted by a concurrent op, so the target is deleted with it.
+    ParentDeleted,
+}
+
+/// A create or move op of a tree node that is rejected when resolving the concurrent edits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedTreeMove {
+    /// The id of the op
+    pub id: ID,
+    pub target: TreeID,
+    /// The parent that the op moves the target to
+    pub parent: TreeParentId,
+    pub reason: TreeMoveRejectReason,
 }
 
 impl TreeDiff {
@@ -74,8 +110,10 @@ impl TreeDiff {
         }
         if !left_prior {
             let mut self_update = self.to_hash_map_mut();
+            // The rejected moves don't change the tree
             for i in b
                 .iter()
+                .filter(|x| !matches!(x.action, TreeExternalDiff::MoveRejected { .. }))
                 .map(|x| x.target)
                 .filter_map(|x| self_update.remove(&x))
                 .sorted()
@@ -136,6 +174,12 @@ pub enum TreeInternalDiff {
         parent: TreeParentId,
         position: Option<FractionalIndex>,
     },
+    /// The op `id` is rejected, the state doesn't change
+    Rejected {
+        id: ID,
+        parent: TreeParentId,
+        reason: TreeMoveRejectReason,
+    },
 }
 
 impl TreeDeltaItem {
@@ -0,0 +1,380 @@
+//! Diff algorithms that align the two sides by anchors first, then diff the
+//! regions between the anchors in the same way. The regions without anchors are
+//! diffed by Myers' algorithm.
+//!
+//! The regions are kept in an explicit stack instead of recursion, so a long
+//! text with many changes can't overflow the call stack.
+//!
+//! - Patience diff: the anchors are the tokens that are unique on both sides,
+//!   which form the longest increasing subsequence.
+//! - Histogram diff: the anchor is the longest common region around the least
+//!   frequent token.
+use std::ops::Range;
+
+use fxhash::FxHashMap;
+
+use super::diff_impl::{
+    common_prefix, common_suffix_len, conquer, DiffHandler, OffsetVec, OperateProxy,
+    UpdateTimeoutError,
+};
+use crate::change::get_sys_timestamp;
+
+/// The tokens that occur more often than this are not used as the anchors of
+/// histogram diff
+const MAX_CHAIN_LEN: usize = 64;
+
+pub(super) struct AnchoredDiff<'a, D: DiffHandler> {
+    pub proxy: &'a mut OperateProxy<D>,
+    pub old: &'a [u32],
+    pub new: &'a [u32],
+    pub use_refined_diff: bool,
+    pub timeout_ms: Option<f64>,
+    pub start_time: f64,
+    pub vf: &'a mut OffsetVec,
+    pub vb: &'a mut OffsetVec,
+}
+
+impl<D: DiffHandler> AnchoredDiff<'_, D> {
+    pub fn patience(
+        &mut self,
+        old: Range<usize>,
+        new: Range<usize>,
+    ) -> Result<(), UpdateTimeoutError> {
+        // The regions are popped from the top, so they are pushed in the reverse
+        // order to keep the ops in order
+        let mut stack = vec![(old, new)];
+        while let Some((old, new)) = stack.pop() {
+            self.check_timeout()?;
+            let Some((old, new)) = self.trim(old, new) else {
+                continue;
+            };
+
+            let anchors = self.patience_anchors(&old, &new);
+            if anchors.is_empty() {
+                self.myers(old, new)?;
+                continue;
+            }
+
+            let mut old_end = old.end;
+            let mut new_end = new.end;
+            for &(i, j) in anchors.iter().rev() {
+                stack.push((i + 1..old_end, j + 1..new_end));
+                old_end = i;
+                new_end = j;
+            }
+
+            stack.push((old.start..old_end, new.start..new_end));
+        }
+
+        Ok(())
+    }
+
+    /// Get the tokens that are unique in both ranges, which form the longest
+    /// increasing subsequence.
+    fn patience_anchors(&self, old: &Range<usize>, new: &Range<usize>) -> Vec<(usize, usize)> {
+        // token -> (count in old, index in old, count in new, index in new)
+        let mut occurrences: FxHashMap<u32, (usize, usize, usize, usize)> = FxHashMap::default();
+        for i in old.clone() {
+            let entry = occurrences.entry(self.old[i]).or_default();
+            entry.0 += 1;
+            entry.1 = i;
+        }
+
+        for j in new.clone() {
+            if let Some(entry) = occurrences.get_mut(&self.new[j]) {
+                entry.2 += 1;
+                entry.3 = j;
+            }
+        }
+
+        let mut pairs: Vec<(usize, usize)> = occurrences
+            .values()
+            .filter(|(old_count, _, new_count, _)| *old_count == 1 && *new_count == 1)
+            .map(|(_, i, _, j)| (*i, *j))
+            .collect();
+        pairs.sort_unstable();
+        longest_increasing_subsequence(&pairs)
+    }
+
+    pub fn histogram(
+        &mut self,
+        old: Range<usize>,
+        new: Range<usize>,
+    ) -> Result<(), UpdateTimeoutError> {
+        // The sorted positions of the tokens in old. It's built once, and the
+        // positions inside a region are found by binary search.
+        let mut positions: FxHashMap<u32, Vec<usize>> = FxHashMap::default();
+        for i in old.clone() {
+            positions.entry(self.old[i]).or_default().push(i);
+        }
+
+        let mut stack = vec![(old, new)];
+        while let Some((old, new)) = stack.pop() {
+            self.check_timeout()?;
+            let Some((old, new)) = self.trim(old, new) else {
+                continue;
+            };
+
+            match self.histogram_anchor(&positions, &old, &new) {
+                Some((i, j, len)) => {
+                    stack.push((i + len..old.end, j + len..new.end));
+                    stack.push((old.start..i, new.start..j));
+                }
+                None => self.myers(old, new)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Find the longest common region around the least frequent token.
+    ///
+    /// The ties are broken by the distance to the middle of `new`, so that the
+    /// regions are split evenly. It returns (old start, new start, len).
+    fn histogram_anchor(
+        &self,
+        positions: &FxHashMap<u32, Vec<usize>>,
+        old: &Range<usize>,
+        new: &Range<usize>,
+    ) -> Option<(usize, usize, usize)> {
+        let middle = new.start + new.len() / 2;
+        // (count of the token, len, distance to the middle, old start, new start)
+        let mut best: Option<(usize, usize, usize, usize, usize)> = None;
+        let mut j = new.start;
+        while j < new.end {
+            let mut next_j = j + 1;
+            let list = match positions.get(&self.new[j]) {
+                Some(list) => {
+                    let start = list.partition_point(|&i| i < old.start);
+                    let end = list.partition_point(|&i| i < old.end);
+                    &list[start..end]
+                }
+                None => &[][..],
+            };
+
+            let count = list.len();
+            if count == 0
+                || count > MAX_CHAIN_LEN
+                || best.is_some_and(|(best_count, ..)| count > best_count)
+            {
+                j = next_j;
+                continue;
+            }
+
+            for &i in list {
+                let (mut start_i, mut start_j) = (i, j);
+                while start_i > old.start
+                    && start_j > new.start
+                    && self.old[start_i - 1] == self.new[start_j - 1]
+                {
+                    start_i -= 1;
+                    start_j -= 1;
+                }
+
+                let (mut end_i, mut end_j) = (i + 1, j + 1);
+                while end_i < old.end && end_j < new.end && self.old[end_i] == self.new[end_j] {
+                    end_i += 1;
+                    end_j += 1;
+                }
+
+                let len = end_i - start_i;
+                let distance = (start_j + len / 2).abs_diff(middle);
+                let is_better = match best {
+                    None => true,
+                    Some((best_count, best_len, best_distance, ..)) => {
+                        count < best_count
+                            || (count == best_count
+                                && (len > best_len
+                                    || (len == best_len && distance < best_distance)))
+                    }
+                };
+                if is_better {
+                    best = Some((count, len, distance, start_i, start_j));
+                }
+
+                // The tokens inside the region can't give a longer one
+                next_j = next_j.max(end_j);
+            }
+
+            j = next_j;
+        }
+
+        best.map(|(_, len, _, i, j)| (i, j, len))
+    }
+
+    fn myers(&mut self, old: Range<usize>, new: Range<usize>) -> Result<(), UpdateTimeoutError> {
+        conquer(
+            self.proxy,
+            self.use_refined_diff,
+            self.timeout_ms,
+            self.start_time,
+            self.old,
+            old.start,
+            old.end,
+            self.new,
+            new.start,
+            new.end,
+            self.vf,
+            self.vb,
+        )
+    }
+
+    /// Skip the common prefix and suffix, and handle the cases that one side is empty.
+    ///
+    /// Return the remaining ranges if both of them are not empty.
+    fn trim(
+        &mut self,
+        mut old: Range<usize>,
+        mut new: Range<usize>,
+    ) -> Option<(Range<usize>, Range<usize>)> {
+        let prefix = common_prefix(&self.old[old.clone()], &self.new[new.clone()]);
+        old.start += prefix;
+        new.start += prefix;
+        let suffix = common_suffix_len(&self.old[old.clone()], &self.new[new.clone()]);
+        old.end -= suffix;
+        new.end -= suffix;
+        if new.is_empty() {
+            if !old.is_empty() {
+                self.proxy.delete(old.start, old.len());
+            }
+
+            None
+        } else if old.is_empty() {
+            self.proxy.insert(old.start, new.start, new.len());
+            None
+        } else {
+            Some((old, new))
+        }
+    }
+
+    fn check_timeout(&self) -> Result<(), UpdateTimeoutError> {
+        if let Some(timeout_ms) = self.timeout_ms {
+            if get_sys_timestamp() - self.start_time > timeout_ms {
+                return Err(UpdateTimeoutError::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the longest subsequence of the pairs whose second items are increasing.
+///
+/// The pairs should be sorted by the first items.
+fn longest_increasing_subsequence(pairs: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    // tails[k] is the index of the smallest tail of the subsequences of length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev: Vec<Option<usize>> = vec![None; pairs.len()];
+    for (i, &(_, y)) in pairs.iter().enumerate() {
+        let k = tails.partition_point(|&t| pairs[t].1 < y);
+        if k > 0 {
+            prev[i] = Some(tails[k - 1]);
+        }
+
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+
+    let mut ans = Vec::with_capacity(tails.len());
+    let mut cur = tails.last().copied();
+    while let Some(i) = cur {
+        ans.push(pairs[i]);
+        cur = prev[i];
+    }
+
+    ans.reverse();
+    ans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{
+        diff,
+        diff_impl::{DiffAlgorithm, UpdateOptions},
+    };
+
+    #[derive(Debug, Default)]
+    struct Recorder {
+        ops: Vec<(bool, usize, usize, usize)>,
+    }
+
+    impl DiffHandler for Recorder {
+        fn insert(&mut self, old_index: usize, new_index: usize, new_len: usize) {
+            self.ops.push((true, old_index, new_index, new_len));
+        }
+
+        fn delete(&mut self, old_index: usize, old_len: usize) {
+            self.ops.push((false, old_index, 0, old_len));
+        }
+    }
+
+    /// Apply the recorded ops to `old` and check that it becomes `new`
+    fn check(algorithm: DiffAlgorithm, old: &[u32], new: &[u32]) {
+        let mut proxy = OperateProxy::new(Recorder::default());
+        let options = UpdateOptions {
+            algorithm,
+            ..Default::default()
+        };
+        diff(&mut proxy, options, old, new).unwrap();
+        let mut ans = Vec::new();
+        let mut last = 0;
+        for (is_insert, old_index, new_index, len) in proxy.unwrap().ops {
+            if old_index > last {
+                ans.extend_from_slice(&old[last..old_index]);
+                last = old_index;
+            }
+
+            if is_insert {
+                ans.extend_from_slice(&new[new_index..new_index + len]);
+            } else {
+                last = old_index + len;
+            }
+        }
+        ans.extend_from_slice(&old[last..]);
+        assert_eq!(ans, new);
+    }
+
+    #[test]
+    fn lis() {
+        assert_eq!(
+            longest_increasing_subsequence(&[(0, 3), (1, 0), (2, 1), (3, 4), (4, 2), (5, 5)]),
+            vec![(1, 0), (2, 1), (4, 2), (5, 5)]
+        );
+        assert!(longest_increasing_subsequence(&[]).is_empty());
+    }
+
+    #[test]
+    fn anchored_diffs_produce_the_new_side() {
+        let cases: [(&[u32], &[u32]); 5] = [
+            (&[1, 2, 3, 4, 5], &[1, 4, 3, 2, 5]),
+            (&[1, 1, 1, 2, 2], &[2, 2, 1, 1, 1]),
+            (&[], &[1, 2]),
+            (&[1, 2], &[]),
+            (&[7, 1, 2, 3, 7, 4, 5, 7], &[4, 5, 7, 1, 2, 3, 6, 7]),
+        ];
+        for (old, new) in cases {
+            check(DiffAlgorithm::Patience, old, new);
+            check(DiffAlgorithm::Histogram, old, new);
+        }
+    }
+
+    #[test]
+    fn anchored_diffs_handle_many_lines() {
+        // 100k lines, where every 5th line is blank and every 7th line is changed
+        let old: Vec<u32> = (0..100_000)
+            .map(|i| if i % 5 == 0 { 0 } else { i })
+            .collect();
+        let mut new = old.clone();
+        for i in (0..new.len()).step_by(7) {
+            new[i] = 200_000 + i as u32;
+        }
+        new.drain(40_000..40_100);
+        new.extend_from_slice(&old[..100]);
+        check(DiffAlgorithm::Patience, &old, &new);
+        check(DiffAlgorithm::Histogram, &old, &new);
+    }
+}
@@ -14,8 +14,10 @@
 //!
 //! The implementation of this algorithm is based on the implementation by
 //! Brandon Williams.
+use super::anchored::AnchoredDiff;
 use crate::change::get_sys_timestamp;
 use fxhash::FxHashMap;
+use loro_common::LoroError;
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 use std::iter::zip;
@@ -25,10 +27,15 @@ use std::ops::{Index, IndexMut};
 ///
 /// - `timeout_ms`: Optional timeout in milliseconds for the diff computation
 /// - `use_refined_diff`: Whether to use a more refined but slower diff algorithm. Defaults to true.
+/// - `granularity`: The unit of the diff. Defaults to [DiffGranularity::Char].
+///   It's ignored by `update_by_line`, which always diffs the lines.
+/// - `algorithm`: The diff algorithm. Defaults to [DiffAlgorithm::Myers].
 #[derive(Clone, Debug)]
 pub struct UpdateOptions {
     pub timeout_ms: Option<f64>,
     pub use_refined_diff: bool,
+    pub granularity: DiffGranularity,
+    pub algorithm: DiffAlgorithm,
 }
 
 impl Default for UpdateOptions {
@@ -36,16 +43,76 @@ impl Default for UpdateOptions {
         Self {
             timeout_ms: None,
             use_refined_diff: true,
+            granularity: DiffGranularity::Char,
+            algorithm: DiffAlgorithm::Myers,
         }
     }
 }
 
+impl UpdateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail with [UpdateTimeoutError::Timeout] if the diff takes longer than `timeout_ms`.
+    pub fn timeout_ms(mut self, timeout_ms: f64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    pub fn use_refined_diff(mut self, use_refined_diff: bool) -> Self {
+        self.use_refined_diff = use_refined_diff;
+        self
+    }
+
+    pub fn granularity(mut self, granularity: DiffGranularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: DiffAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+}
+
+/// The unit of the text diff
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffGranularity {
+    /// Diff the Unicode chars
+    Char,
+    /// Diff the segments split by the Unicode word boundaries (UAX #29).
+    ///
+    /// A changed word is replaced as a whole instead of interleaving with the
+    /// concurrent edits char by char.
+    Word,
+}
+
+/// The algorithm of the text diff
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffAlgorithm {
+    /// Myers' algorithm, which finds the minimal diff
+    Myers,
+    /// Patience diff. It aligns the tokens that are unique on both sides first, which
+    /// keeps the moved or repeated blocks readable.
+    Patience,
+    /// Histogram diff. It aligns the longest common region around the least frequent
+    /// token first. It's usually faster than patience diff with similar results.
+    Histogram,
+}
+
 #[derive(thiserror::Error, Debug, PartialEq)]
 pub enum UpdateTimeoutError {
     #[error("Timeout")]
     Timeout,
 }
 
+impl From<UpdateTimeoutError> for LoroError {
+    fn from(_: UpdateTimeoutError) -> Self {
+        LoroError::UpdateTimeout
+    }
+}
+
 /// Utility function to check if a range is empty that works on older rust versions
 #[inline(always)]
 fn is_empty_range(start: usize, end: usize) -> bool {
@@ -57,7 +124,7 @@ fn is_not_empty_range(start: usize, end: usize) -> bool {
     start < end
 }
 
-fn common_prefix(xs: &[u32], ys: &[u32]) -> usize {
+pub(super) fn common_prefix(xs: &[u32], ys: &[u32]) -> usize {
     let chunk_size = 4;
     let off = zip(xs.chunks_exact(chunk_size), ys.chunks_exact(chunk_size))
         .take_while(|(xs_chunk, ys_chunk)| xs_chunk == ys_chunk)
@@ -68,7 +135,7 @@ fn common_prefix(xs: &[u32], ys: &[u32]) -> usize {
         .count()
 }
 
-fn common_suffix_len(old: &[u32], new: &[u32]) -> usize {
+pub(super) fn common_suffix_len(old: &[u32], new: &[u32]) -> usize {
     let chunk_size = 4;
     let old_len = old.len();
     let new_len = new.len();
@@ -110,7 +177,7 @@ impl<D: DiffHandler> OperateProxy<D> {
     }
 
     #[allow(unused)]
-    fn unwrap(self) -> D {
+    pub(super) fn unwrap(self) -> D {
         self.handler
     }
 }
@@ -130,23 +197,42 @@ pub(crate) fn diff<D: DiffHandler>(
         0.
     };
 
-    conquer(
-        proxy,
-        options.use_refined_diff,
-        options.timeout_ms,
-        start_time,
-        old,
-        0,
-        old.len(),
-        new,
-        0,
-        new.len(),
-        &mut vf,
-        &mut vb,
-    )
+    match options.algorithm {
+        DiffAlgorithm::Myers => conquer(
+            proxy,
+            options.use_refined_diff,
+            options.timeout_ms,
+            start_time,
+            old,
+            0,
+            old.len(),
+            new,
+            0,
+            new.len(),
+            &mut vf,
+            &mut vb,
+        ),
+        DiffAlgorithm::Patience | DiffAlgorithm::Histogram => {
+            let mut anchored = AnchoredDiff {
+                proxy,
+                old,
+                new,
+                use_refined_diff: options.use_refined_diff,
+                timeout_ms: options.timeout_ms,
+                start_time,
+                vf: &mut vf,
+                vb: &mut vb,
+            };
+            if options.algorithm == DiffAlgorithm::Patience {
+                anchored.patience(0..old.len(), 0..new.len())
+            } else {
+                anchored.histogram(0..old.len(), 0..new.len())
+            }
+        }
+    }
 }
 
-struct OffsetVec(isize, Vec<usize>);
+pub(super) struct OffsetVec(isize, Vec<usize>);
 
 impl OffsetVec {
     fn new(max_d: usize) -> Self {
@@ -267,7 +353,7 @@ fn find_middle_snake(
 }
 
 #[allow(clippy::too_many_arguments)]
-fn conquer<D: DiffHandler>(
+pub(super) fn conquer<D: DiffHandler>(
     proxy: &mut OperateProxy<D>,
     should_use_dj: bool,
     timeout_ms: Option<f64>,
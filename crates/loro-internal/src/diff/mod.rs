@@ -1,3 +1,4 @@
+mod anchored;
 pub mod diff_impl;
 pub(crate) use diff_impl::diff;
 pub(crate) use diff_impl::DiffHandler;
@@ -8,7 +8,7 @@ use loro_common::{ContainerID, IdFull, IdLp, Lamport, PeerID, TreeID, ID};
 use crate::{
     container::{idx::ContainerIdx, tree::tree_op::TreeOp},
     dag::DagUtils,
-    delta::{TreeDelta, TreeDeltaItem, TreeInternalDiff},
+    delta::{RejectedTreeMove, TreeDelta, TreeDeltaItem, TreeInternalDiff, TreeMoveRejectReason},
     event::InternalDiff,
     state::TreeParentId,
     version::Frontiers,
@@ -222,6 +222,7 @@ impl TreeDiffCalculator {
                     id,
                     op: op.value.clone(),
                     effected: false,
+                    rejected: None,
                 };
                 tree_cache.apply(op);
             }
@@ -259,6 +260,10 @@ impl TreeDiffCalculator {
             tracing::info!("start retreat");
             let mut diffs = vec![];
 
+            // The ops that are effective in the `from` version and will be applied again,
+            // mapped to whether the target was in a deleted subtree after applying them.
+            // They are used to find the ops that are undone by the concurrent ops.
+            let mut retreated_effective_ops = FxHashMap::default();
             if !(tree_cache.current_vv == lca_vv && &lca_vv == info.from_vv) {
                 let mut retreat_ops = vec![];
                 for (_target, ops) in tree_cache.tree.iter() {
@@ -271,6 +276,10 @@ impl TreeDiffCalculator {
                         }
                     }
                 }
+                for op in retreat_ops.iter().filter(|op| op.effected) {
+                    retreated_effective_ops
+                        .insert(op.id.id(), tree_cache.is_parent_deleted(op.op.parent_id()));
+                }
 
                 // tracing::info!("retreat ops {:?}", retreat_ops);
                 for op in retreat_ops.into_iter().sorted().rev() {
@@ -355,12 +364,36 @@ impl TreeDiffCalculator {
                         },
                         op: op.value.clone(),
                         effected: false,
+                        rejected: None,
                     };
                     let (old_parent, _position, _id) =
                         tree_cache.get_parent_with_id(op.op.target());
                     let is_parent_deleted = tree_cache.is_parent_deleted(op.op.parent_id());
                     let is_old_parent_deleted = tree_cache.is_parent_deleted(old_parent);
                     let effected = tree_cache.apply(op.clone());
+                    // Only report the new ops and the ops whose effect at the `from` version is undone
+                    let was_in_deleted = retreated_effective_ops.get(&id).copied();
+                    if !info.from_vv.includes_id(id) || was_in_deleted.is_some() {
+                        if let Some(reason) = reject_reason(
+                            &op,
+                            effected,
+                            was_in_deleted.unwrap_or(false),
+                            is_parent_deleted,
+                            is_old_parent_deleted,
+                        ) {
+                            diffs.push(TreeDeltaItem {
+                                target: op.op.target(),
+                                action: TreeInternalDiff::Rejected {
+                                    id,
+                                    parent: op.op.parent_id(),
+                                    reason,
+                                },
+                                last_effective_move_op_id: tree_cache
+                                    .get_parent_with_id(op.op.target())
+                                    .2,
+                            });
+                        }
+                    }
                     if effected {
                         let this_diff = TreeDeltaItem::new(
                             op.op.target(),
@@ -408,6 +441,79 @@ impl TreeDiffCalculator {
         })
     }
 
+    /// Get the create and move ops in `to` but not in `since` that are rejected in `to`.
+    ///
+    /// The reasons are recorded when the ops are applied to the tree cache of the history,
+    /// so only the ops between the versions are visited.
+    pub(crate) fn rejected_moves(
+        &mut self,
+        oplog: &OpLog,
+        since: &VersionVector,
+        to: &VersionVector,
+        to_frontiers: &Frontiers,
+    ) -> Vec<RejectedTreeMove> {
+        let Some(min_lamport) = to
+            .sub_iter(since)
+            .map(|span| oplog.get_min_lamport_at(span.norm_id_start()))
+            .min()
+        else {
+            return vec![];
+        };
+        let has_history = oplog.with_history_cache(|h| {
+            let mark = h.ensure_importing_caches_exist();
+            h.get_tree(&self.container, mark).is_some()
+        });
+        if !has_history {
+            return vec![];
+        }
+
+        self.checkout(to, to_frontiers, oplog);
+        oplog.with_history_cache(|h| {
+            let mark = h.ensure_importing_caches_exist();
+            let tree_ops = h.get_tree(&self.container, mark).unwrap();
+            let tree_cache = tree_ops.tree().try_lock().unwrap();
+            let mut ans = vec![];
+            for (idlp, op) in tree_ops.ops().range(
+                IdLp {
+                    lamport: min_lamport,
+                    peer: 0,
+                }..,
+            ) {
+                let id = ID::new(idlp.peer, op.counter);
+                if since.includes_id(id) || !to.includes_id(id) {
+                    continue;
+                }
+
+                let op = MoveLamportAndID {
+                    id: IdFull::new(idlp.peer, op.counter, idlp.lamport),
+                    op: op.value.clone(),
+                    effected: false,
+                    rejected: None,
+                };
+                let target = op.op.target();
+                let Some(reason) = tree_cache.get_rejected_reason(&op) else {
+                    continue;
+                };
+                // The target may be moved out of the deleted subtree by a later op
+                if reason == TreeMoveRejectReason::ParentDeleted
+                    && !tree_cache
+                        .get_last_effective_move(target)
+                        .is_some_and(|x| x.id == op.id)
+                {
+                    continue;
+                }
+
+                ans.push(RejectedTreeMove {
+                    id,
+                    target,
+                    parent: op.op.parent_id(),
+                    reason,
+                });
+            }
+            ans
+        })
+    }
+
     fn get_min_lamport_by_frontiers(&self, frontiers: &Frontiers, oplog: &OpLog) -> Lamport {
         frontiers
             .iter()
@@ -425,6 +531,27 @@ impl TreeDiffCalculator {
     }
 }
 
+/// Get the reason why the op is rejected after it's applied to the tree cache.
+///
+/// `is_parent_deleted` and `is_old_parent_deleted` are read before the op is applied.
+/// `was_in_deleted` is whether the op has already moved the target into a deleted
+/// subtree in the version that is compared with.
+fn reject_reason(
+    op: &MoveLamportAndID,
+    effected: bool,
+    was_in_deleted: bool,
+    is_parent_deleted: bool,
+    is_old_parent_deleted: bool,
+) -> Option<TreeMoveRejectReason> {
+    if !effected {
+        return Some(TreeMoveRejectReason::Cycle);
+    }
+
+    let is_delete = op.op.parent_id() == TreeParentId::Deleted;
+    (!is_delete && is_parent_deleted && !is_old_parent_deleted && !was_in_deleted)
+        .then_some(TreeMoveRejectReason::ParentDeleted)
+}
+
 /// All information of an operation for diff calculating of movable tree.
 #[derive(Debug, Clone)]
 pub struct MoveLamportAndID {
@@ -433,6 +560,9 @@ pub struct MoveLamportAndID {
     /// Whether this action is applied in the current version.
     /// If this action will cause a circular reference, then this action will not be applied.
     pub(crate) effected: bool,
+    /// Why this action is rejected when it's applied, if it is.
+    /// It's recorded once, so the rejections can be read without replaying the history.
+    pub(crate) rejected: Option<TreeMoveRejectReason>,
 }
 
 impl MoveLamportAndID {
@@ -514,6 +644,14 @@ impl TreeCacheForDiff {
         if self.is_ancestor_of(&node.op.target(), &node.op.parent_id()) {
             effected = false;
         }
+        let (old_parent, _, _) = self.get_parent_with_id(node.op.target());
+        node.rejected = reject_reason(
+            &node,
+            effected,
+            false,
+            self.is_parent_deleted(node.op.parent_id()),
+            self.is_parent_deleted(old_parent),
+        );
         node.effected = effected;
         self.current_vv.set_last(node.id.id());
         self.tree.entry(node.op.target()).or_default().insert(node);
@@ -583,6 +721,11 @@ impl TreeCacheForDiff {
         ans
     }
 
+    /// Get the reason why the op is rejected, if it's applied and rejected
+    fn get_rejected_reason(&self, op: &MoveLamportAndID) -> Option<TreeMoveRejectReason> {
+        self.tree.get(&op.op.target())?.get(op)?.rejected
+    }
+
     fn get_children_with_id(
         &self,
         parent: TreeParentId,
@@ -3,6 +3,7 @@ pub(crate) mod fast_snapshot;
 pub(crate) mod json_schema;
 mod outdated_encode_reordered;
 mod shallow_snapshot;
+pub(crate) mod stream;
 pub(crate) mod value;
 pub(crate) mod value_register;
 pub(crate) use outdated_encode_reordered::{
@@ -11,6 +12,9 @@ pub(crate) use outdated_encode_reordered::{
 use outdated_encode_reordered::{import_changes_to_oplog, ImportChangesResult};
 pub(crate) use value::OwnedValue;
 
+use crate::change::Change;
+use crate::import_filter;
+use crate::kv_store::CompressionType;
 use crate::op::OpWithId;
 use crate::version::{Frontiers, VersionRange};
 use crate::LoroDoc;
@@ -69,6 +73,63 @@ pub enum ExportMode<'a> {
     /// The snapshot at the specified frontiers. It contains the full history
     /// till the target frontiers and the state at the target frontiers.
     SnapshotAt { version: Cow<'a, Frontiers> },
+    /// The snapshot encoded with the given options, e.g. with another compression type.
+    ///
+    /// It's the same as [ExportMode::Snapshot] when the options are the default.
+    SnapshotWithOptions(SnapshotOptions),
+}
+
+/// The options of exporting a snapshot by [ExportMode::snapshot_with_options].
+///
+/// The default options export the same snapshot as [ExportMode::Snapshot].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SnapshotOptions {
+    compression: Option<CompressionType>,
+    state_encoding: StateEncoding,
+}
+
+impl SnapshotOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compress the blocks of the history and the state with the given compression type.
+    ///
+    /// It can be imported like other snapshots, the compression type of each block
+    /// is stored along with the block.
+    pub fn compression(mut self, compression_type: CompressionType) -> Self {
+        self.compression = Some(compression_type);
+        self
+    }
+
+    /// Encode the state of the containers with the given [StateEncoding]. The oplog
+    /// part is not affected.
+    ///
+    /// It can be imported like other snapshots. The encoded containers are converted
+    /// back to the default encoding when they are loaded.
+    ///
+    /// The versions that don't support the encoding fail to import the snapshot with
+    /// [LoroError::IncompatibleFutureEncodingError].
+    pub fn state_encoding(mut self, state_encoding: StateEncoding) -> Self {
+        self.state_encoding = state_encoding;
+        self
+    }
+}
+
+/// The encoding of the container states in a snapshot
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StateEncoding {
+    /// The default encoding of each container state
+    #[default]
+    Raw,
+    /// The content and the style runs of the text containers are compressed with a
+    /// word dictionary and the column codec.
+    ///
+    /// It makes the state of prose-heavy documents much smaller, at the cost of
+    /// decompressing the text when the container is loaded.
+    CompressedText,
 }
 
 impl<'a> ExportMode<'a> {
@@ -77,6 +138,11 @@ impl<'a> ExportMode<'a> {
         ExportMode::Snapshot
     }
 
+    /// The snapshot encoded with the given options, e.g. with another compression type.
+    pub fn snapshot_with_options(options: SnapshotOptions) -> Self {
+        ExportMode::SnapshotWithOptions(options)
+    }
+
     /// It contains the history since the `from` version vector.
     pub fn updates(from: &'a VersionVector) -> Self {
         ExportMode::Updates {
@@ -163,6 +229,11 @@ pub(crate) enum EncodeMode {
     OutdatedSnapshot = 2,
     FastSnapshot = 3,
     FastUpdates = 4,
+    /// [EncodeMode::FastSnapshot] with the text containers in [StateEncoding::CompressedText].
+    ///
+    /// The versions that don't know the compressed text containers reject it with
+    /// [LoroError::IncompatibleFutureEncodingError] instead of misreading the containers.
+    FastSnapshotCompressedText = 5,
 }
 
 impl num_traits::FromPrimitive for EncodeMode {
@@ -175,6 +246,9 @@ impl num_traits::FromPrimitive for EncodeMode {
             n if n == EncodeMode::OutdatedSnapshot as i64 => Some(EncodeMode::OutdatedSnapshot),
             n if n == EncodeMode::FastSnapshot as i64 => Some(EncodeMode::FastSnapshot),
             n if n == EncodeMode::FastUpdates as i64 => Some(EncodeMode::FastUpdates),
+            n if n == EncodeMode::FastSnapshotCompressedText as i64 => {
+                Some(EncodeMode::FastSnapshotCompressedText)
+            }
             _ => None,
         }
     }
@@ -194,6 +268,7 @@ impl num_traits::ToPrimitive for EncodeMode {
             EncodeMode::OutdatedSnapshot => EncodeMode::OutdatedSnapshot as i64,
             EncodeMode::FastSnapshot => EncodeMode::FastSnapshot as i64,
             EncodeMode::FastUpdates => EncodeMode::FastUpdates as i64,
+            EncodeMode::FastSnapshotCompressedText => EncodeMode::FastSnapshotCompressedText as i64,
         })
     }
     #[inline]
@@ -211,7 +286,9 @@ impl EncodeMode {
     pub fn is_snapshot(self) -> bool {
         matches!(
             self,
-            EncodeMode::OutdatedSnapshot | EncodeMode::FastSnapshot
+            EncodeMode::OutdatedSnapshot
+                | EncodeMode::FastSnapshot
+                | EncodeMode::FastSnapshotCompressedText
         )
     }
 }
@@ -225,10 +302,15 @@ impl TryFrom<[u8; 2]> for EncodeMode {
     }
 }
 
+#[non_exhaustive]
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct ImportStatus {
     pub success: VersionRange,
     pub pending: Option<VersionRange>,
+    /// The changes rejected by the import filter and the changes depending on them.
+    ///
+    /// See [crate::LoroDoc::set_import_filter].
+    pub rejected: Option<VersionRange>,
 }
 
 /// The encoder used to encode the container states.
@@ -314,10 +396,47 @@ pub(crate) fn decode_oplog(
         EncodeMode::OutdatedRle | EncodeMode::OutdatedSnapshot => {
             outdated_encode_reordered::decode_updates(oplog, body)
         }
-        EncodeMode::FastSnapshot => fast_snapshot::decode_oplog(oplog, body),
+        EncodeMode::FastSnapshot | EncodeMode::FastSnapshotCompressedText => {
+            fast_snapshot::decode_oplog(oplog, body)
+        }
         EncodeMode::FastUpdates => fast_snapshot::decode_updates(oplog, body.to_vec().into()),
         EncodeMode::Auto => unreachable!(),
     }?;
+    import_changes(oplog, changes)
+}
+
+/// Import the decoded changes into the oplog.
+///
+/// The changes whose dependencies are missing are kept as pending changes.
+/// The changes that don't match the schema of the doc or are rejected by the
+/// import filter are dropped together with the changes depending on them,
+/// including the ones rejected by the earlier imports.
+pub(crate) fn import_changes(
+    oplog: &mut OpLog,
+    mut changes: Vec<Change>,
+) -> Result<ImportStatus, LoroError> {
+    let violations = match oplog.configure.schema() {
+        Some(schema) => schema.check_changes(oplog, &changes),
+        None => Default::default(),
+    };
+    let mut rejected_from = std::mem::take(&mut oplog.rejected_from);
+    let rejected =
+        if violations.is_empty() && oplog.import_filter.is_none() && rejected_from.is_empty() {
+            VersionRange::default()
+        } else {
+            import_filter::reject_changes(oplog, &mut changes, &mut rejected_from, |change| {
+                if violations.contains(&change.id) {
+                    return true;
+                }
+
+                match &oplog.import_filter {
+                    Some(filter) => import_filter::is_rejected_by(oplog, filter, change),
+                    None => false,
+                }
+            })
+        };
+    oplog.rejected_from = rejected_from;
+
     let ImportChangesResult {
         mut imported,
         latest_ids,
@@ -338,6 +457,7 @@ pub(crate) fn decode_oplog(
     Ok(ImportStatus {
         success: imported,
         pending: (!pending.is_empty()).then_some(pending),
+        rejected: (!rejected.is_empty()).then_some(rejected),
     })
 }
 
@@ -358,7 +478,9 @@ impl ParsedHeaderAndBody<'_> {
                     return Err(LoroError::DecodeChecksumMismatchError);
                 }
             }
-            EncodeMode::FastSnapshot | EncodeMode::FastUpdates => {
+            EncodeMode::FastSnapshot
+            | EncodeMode::FastUpdates
+            | EncodeMode::FastSnapshotCompressedText => {
                 let expected = u32::from_le_bytes(self.checksum[12..16].try_into().unwrap());
                 if xxhash_rust::xxh32::xxh32(self.checksum_body, XXH_SEED) != expected {
                     return Err(LoroError::DecodeChecksumMismatchError);
@@ -436,6 +558,21 @@ pub(crate) fn export_fast_snapshot(doc: &LoroDoc) -> Vec<u8> {
     .unwrap()
 }
 
+pub(crate) fn export_fast_snapshot_with_options(
+    doc: &LoroDoc,
+    options: SnapshotOptions,
+) -> Vec<u8> {
+    let mode = match options.state_encoding {
+        StateEncoding::Raw => EncodeMode::FastSnapshot,
+        StateEncoding::CompressedText => EncodeMode::FastSnapshotCompressedText,
+    };
+    encode_with(mode, &mut |ans| {
+        fast_snapshot::encode_snapshot_with_options(doc, options, ans);
+        Ok(())
+    })
+    .unwrap()
+}
+
 pub(crate) fn export_snapshot_at(
     doc: &LoroDoc,
     frontiers: &Frontiers,
@@ -521,12 +658,15 @@ pub(crate) fn decode_snapshot(
 ) -> Result<ImportStatus, LoroError> {
     match mode {
         EncodeMode::OutdatedSnapshot => outdated_encode_reordered::decode_snapshot(doc, body)?,
-        EncodeMode::FastSnapshot => fast_snapshot::decode_snapshot(doc, body.to_vec().into())?,
+        EncodeMode::FastSnapshot | EncodeMode::FastSnapshotCompressedText => {
+            fast_snapshot::decode_snapshot(doc, body.to_vec().into())?
+        }
         _ => unreachable!(),
     };
     Ok(ImportStatus {
         success: VersionRange::from_vv(&doc.oplog_vv()),
         pending: None,
+        rejected: None,
     })
 }
 
@@ -595,7 +735,9 @@ impl LoroDoc {
             EncodeMode::OutdatedRle | EncodeMode::OutdatedSnapshot => {
                 outdated_encode_reordered::decode_import_blob_meta(parsed)
             }
-            EncodeMode::FastSnapshot => fast_snapshot::decode_snapshot_blob_meta(parsed),
+            EncodeMode::FastSnapshot | EncodeMode::FastSnapshotCompressedText => {
+                fast_snapshot::decode_snapshot_blob_meta(parsed)
+            }
             EncodeMode::FastUpdates => fast_snapshot::decode_updates_blob_meta(parsed),
         }
     }
@@ -13,16 +13,28 @@
 //!
 //!
 //!
-use std::io::{Read, Write};
+use std::{
+    io::{Read, Write},
+    ops::Bound,
+};
 
 use crate::{
-    change::Change, encoding::shallow_snapshot, oplog::ChangeStore, LoroDoc, OpLog, VersionVector,
+    change::Change,
+    encoding::shallow_snapshot,
+    kv_store::CompressionType,
+    oplog::ChangeStore,
+    state::container_store::{ContainerWrapper, FRONTIERS_KEY},
+    utils::kv_wrapper::KvWrapper,
+    LoroDoc, OpLog, VersionVector,
 };
 use bytes::{Buf, Bytes};
 use loro_common::{HasCounterSpan, IdSpan, LoroError, LoroResult};
+use loro_kv_store::sstable::SsTable;
 use tracing::trace;
 
-use super::{EncodedBlobMode, ImportBlobMetadata, ParsedHeaderAndBody};
+use super::{
+    EncodedBlobMode, ImportBlobMetadata, ParsedHeaderAndBody, SnapshotOptions, StateEncoding,
+};
 pub(crate) const EMPTY_MARK: &[u8] = b"E";
 pub(crate) struct Snapshot {
     pub oplog_bytes: Bytes,
@@ -30,19 +42,29 @@ pub(crate) struct Snapshot {
     pub shallow_root_state_bytes: Bytes,
 }
 
+impl Snapshot {
+    /// The encoded snapshot split into the length prefixes and the sections,
+    /// so they can be written without being concatenated.
+    pub(super) fn into_chunks(self) -> [Bytes; 6] {
+        let state_bytes = self
+            .state_bytes
+            .unwrap_or_else(|| Bytes::from_static(EMPTY_MARK));
+        let len_bytes = |bytes: &Bytes| Bytes::copy_from_slice(&(bytes.len() as u32).to_le_bytes());
+        [
+            len_bytes(&self.oplog_bytes),
+            self.oplog_bytes,
+            len_bytes(&state_bytes),
+            state_bytes,
+            len_bytes(&self.shallow_root_state_bytes),
+            self.shallow_root_state_bytes,
+        ]
+    }
+}
+
 pub(super) fn _encode_snapshot<W: Write>(s: Snapshot, w: &mut W) {
-    w.write_all(&(s.oplog_bytes.len() as u32).to_le_bytes())
-        .unwrap();
-    w.write_all(&s.oplog_bytes).unwrap();
-    let state_bytes = s
-        .state_bytes
-        .unwrap_or_else(|| Bytes::from_static(EMPTY_MARK));
-    w.write_all(&(state_bytes.len() as u32).to_le_bytes())
-        .unwrap();
-    w.write_all(&state_bytes).unwrap();
-    w.write_all(&(s.shallow_root_state_bytes.len() as u32).to_le_bytes())
-        .unwrap();
-    w.write_all(&s.shallow_root_state_bytes).unwrap();
+    for chunk in s.into_chunks() {
+        w.write_all(&chunk).unwrap();
+    }
 }
 
 pub(super) fn _decode_snapshot_bytes(bytes: Bytes) -> LoroResult<Snapshot> {
@@ -184,6 +206,86 @@ pub(crate) fn encode_snapshot<W: std::io::Write>(doc: &LoroDoc, w: &mut W) {
     _encode_snapshot(snapshot, w);
 }
 
+pub(crate) fn encode_snapshot_with_options<W: std::io::Write>(
+    doc: &LoroDoc,
+    options: SnapshotOptions,
+    w: &mut W,
+) {
+    let snapshot = encode_snapshot_inner_with_options(doc, options);
+    _encode_snapshot(snapshot, w);
+}
+
+pub(crate) fn encode_snapshot_inner_with_options(
+    doc: &LoroDoc,
+    options: SnapshotOptions,
+) -> Snapshot {
+    let mut snapshot = encode_snapshot_inner(doc);
+    if options.state_encoding != StateEncoding::Raw {
+        snapshot = encode_snapshot_state(snapshot, options.state_encoding);
+    }
+    if let Some(compression_type) = options.compression {
+        snapshot = compress_snapshot(snapshot, compression_type);
+    }
+    snapshot
+}
+
+/// Encode the kv store sections of the snapshot again with the given compression type
+fn compress_snapshot(snapshot: Snapshot, compression_type: CompressionType) -> Snapshot {
+    let recompress = |bytes: Bytes| {
+        if bytes.is_empty() {
+            return bytes;
+        }
+
+        // The sections are exported by the kv stores, the uncompressed bytes are
+        // still a valid section in case they cannot be parsed
+        match SsTable::import_all(bytes.clone()) {
+            Ok(table) => table.export_with_compression(compression_type),
+            Err(_) => bytes,
+        }
+    };
+    Snapshot {
+        oplog_bytes: recompress(snapshot.oplog_bytes),
+        state_bytes: snapshot.state_bytes.map(recompress),
+        shallow_root_state_bytes: recompress(snapshot.shallow_root_state_bytes),
+    }
+}
+
+/// Encode the containers in the state sections of the snapshot again with the given
+/// state encoding. The oplog section is kept as it is.
+fn encode_snapshot_state(snapshot: Snapshot, state_encoding: StateEncoding) -> Snapshot {
+    let reencode = |bytes: Bytes| {
+        if bytes.is_empty() {
+            return bytes;
+        }
+
+        let kv = KvWrapper::new_mem();
+        kv.import(bytes);
+        let encoded: Vec<(Bytes, Bytes)> = kv.with_kv(|kv| {
+            kv.scan(Bound::Unbounded, Bound::Unbounded)
+                .filter(|(k, _)| &k[..] != FRONTIERS_KEY)
+                .filter_map(|(k, v)| {
+                    let encoded = match state_encoding {
+                        StateEncoding::Raw => None,
+                        // The containers that cannot be parsed are kept in the raw
+                        // encoding, they are reported when the snapshot is imported
+                        StateEncoding::CompressedText => {
+                            ContainerWrapper::compress_text_bytes(&v).ok().flatten()
+                        }
+                    };
+                    encoded.map(|v| (k, v))
+                })
+                .collect()
+        });
+        kv.set_all(encoded.into_iter());
+        kv.export()
+    };
+    Snapshot {
+        oplog_bytes: snapshot.oplog_bytes,
+        state_bytes: snapshot.state_bytes.map(reencode),
+        shallow_root_state_bytes: reencode(snapshot.shallow_root_state_bytes),
+    }
+}
+
 pub(crate) fn encode_snapshot_inner(doc: &LoroDoc) -> Snapshot {
     assert!(doc.drop_pending_events().is_empty());
     let old_state_frontiers = doc.state_frontiers();
@@ -235,11 +337,16 @@ pub(crate) fn encode_snapshot_inner(doc: &LoroDoc) -> Snapshot {
 pub(crate) fn decode_oplog(oplog: &mut OpLog, bytes: &[u8]) -> Result<Vec<Change>, LoroError> {
     let oplog_len = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
     let oplog_bytes = &bytes[4..4 + oplog_len as usize];
-    let mut changes = ChangeStore::decode_snapshot_for_updates(
-        oplog_bytes.to_vec().into(),
-        &oplog.arena,
-        oplog.vv(),
-    )?;
+    decode_oplog_bytes(oplog, Bytes::copy_from_slice(oplog_bytes))
+}
+
+/// Decode the changes in the oplog section of a snapshot
+pub(crate) fn decode_oplog_bytes(
+    oplog: &mut OpLog,
+    oplog_bytes: Bytes,
+) -> Result<Vec<Change>, LoroError> {
+    let mut changes =
+        ChangeStore::decode_snapshot_for_updates(oplog_bytes, &oplog.arena, oplog.vv())?;
     changes.sort_unstable_by_key(|x| x.lamport);
     Ok(changes)
 }
@@ -260,18 +367,31 @@ pub(crate) fn encode_updates_in_range<W: std::io::Write>(
 pub(crate) fn decode_updates(oplog: &mut OpLog, body: Bytes) -> Result<Vec<Change>, LoroError> {
     let mut reader: &[u8] = body.as_ref();
     let mut index = 0;
-    let self_vv = oplog.vv();
-    let mut changes = Vec::new();
+    let mut blocks = Vec::new();
     while !reader.is_empty() {
         let old_reader_len = reader.len();
         let len = leb128::read::unsigned(&mut reader).unwrap() as usize;
         index += old_reader_len - reader.len();
-        let block_bytes = body.slice(index..index + len);
+        blocks.push(body.slice(index..index + len));
+        index += len;
+        reader = &reader[len..];
+    }
+
+    decode_update_blocks(oplog, blocks.into_iter().map(Ok))
+}
+
+/// Decode the changes in the blocks of the fast updates
+pub(crate) fn decode_update_blocks(
+    oplog: &mut OpLog,
+    blocks: impl IntoIterator<Item = LoroResult<Bytes>>,
+) -> Result<Vec<Change>, LoroError> {
+    let self_vv = oplog.vv();
+    let mut changes = Vec::new();
+    for block_bytes in blocks {
+        let block_bytes = block_bytes?;
         trace!("decoded block_bytes = {:?}", &block_bytes);
         let new_changes = ChangeStore::decode_block_bytes(block_bytes, &oplog.arena, self_vv)?;
         changes.extend(new_changes);
-        index += len;
-        reader = &reader[len..];
     }
 
     changes.sort_unstable_by_key(|x| x.lamport);
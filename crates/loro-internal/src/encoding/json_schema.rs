@@ -1,7 +1,4 @@
-use super::{
-    outdated_encode_reordered::{import_changes_to_oplog, ImportChangesResult, ValueRegister},
-    ImportStatus,
-};
+use super::{outdated_encode_reordered::ValueRegister, ImportStatus};
 use crate::{
     arena::SharedArena,
     change::Change,
@@ -13,14 +10,13 @@ use crate::{
     },
     op::{FutureInnerContent, InnerContent, Op, SliceRange},
     oplog::BlockChangeRef,
-    version::{Frontiers, VersionRange},
+    version::Frontiers,
     OpLog, VersionVector,
 };
 use either::Either;
 use json::{JsonOpContent, JsonSchema};
 use loro_common::{
-    ContainerID, ContainerType, HasCounterSpan, HasIdSpan, IdLp, LoroError, LoroResult, LoroValue,
-    PeerID, TreeID, ID,
+    ContainerID, ContainerType, HasCounterSpan, IdLp, LoroResult, LoroValue, PeerID, TreeID, ID,
 };
 use rle::{HasLength, RleVec, Sliceable};
 use std::sync::Arc;
@@ -66,29 +62,7 @@ pub(crate) fn export_json<'a, 'c: 'a>(
 
 pub(crate) fn import_json(oplog: &mut OpLog, json: JsonSchema) -> LoroResult<ImportStatus> {
     let changes = decode_changes(json, &oplog.arena)?;
-    let ImportChangesResult {
-        latest_ids,
-        pending_changes,
-        changes_that_have_deps_before_shallow_root,
-        mut imported,
-    } = import_changes_to_oplog(changes, oplog);
-    let mut pending = VersionRange::default();
-    pending_changes.iter().for_each(|c| {
-        pending.extends_to_include_id_span(c.id_span());
-    });
-    oplog.try_apply_pending(latest_ids, Some(&mut imported));
-    oplog.import_unknown_lamport_pending_changes(pending_changes)?;
-    if !changes_that_have_deps_before_shallow_root.is_empty() {
-        return Err(LoroError::ImportUpdatesThatDependsOnOutdatedVersion);
-    };
-    Ok(ImportStatus {
-        success: imported,
-        pending: if pending.is_empty() {
-            None
-        } else {
-            Some(pending)
-        },
-    })
+    super::import_changes(oplog, changes)
 }
 
 fn init_encode<'s, 'a: 's>(
@@ -334,7 +308,15 @@ fn encode_changes(
                             start: *start,
                             end: *end,
                             style_key: key.to_string(),
-                            style_value: value.clone(),
+                            // The child container embedded in the text
+                            style_value: if let LoroValue::Container(id) = value {
+                                LoroValue::Container(register_container_id(
+                                    id.clone(),
+                                    peer_register,
+                                ))
+                            } else {
+                                value.clone()
+                            },
                             info: info.to_byte(),
                         },
                         InnerListOp::StyleEnd => json::TextOp::MarkEnd,
@@ -516,15 +498,20 @@ fn decode_op(op: json::JsonOp, arena: &SharedArena, peers: &[PeerID]) -> LoroRes
                     start,
                     end,
                     style_key,
-                    style_value,
+                    mut style_value,
                     info,
-                } => InnerContent::List(InnerListOp::StyleStart {
-                    start,
-                    end,
-                    key: style_key.into(),
-                    value: style_value,
-                    info: TextStyleInfoFlag::from_byte(info),
-                }),
+                } => {
+                    if let LoroValue::Container(id) = &mut style_value {
+                        *id = convert_container_id(id.clone(), peers);
+                    }
+                    InnerContent::List(InnerListOp::StyleStart {
+                        start,
+                        end,
+                        key: style_key.into(),
+                        value: style_value,
+                        info: TextStyleInfoFlag::from_byte(info),
+                    })
+                }
                 json::TextOp::MarkEnd => InnerContent::List(InnerListOp::StyleEnd),
             },
             _ => unreachable!(),
@@ -1353,7 +1340,7 @@ pub mod json {
                     }
                     TextOp::Mark { style_value, .. } => {
                         assert!(range.start == 0 && range.len() == 1);
-                        *style_value = LoroValue::Null;
+                        redact_value(style_value);
                     }
                     TextOp::MarkEnd => {
                         // MarkEnd won't be changed
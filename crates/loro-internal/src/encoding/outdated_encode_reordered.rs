@@ -210,7 +210,9 @@ pub fn decode_import_blob_meta(parsed: ParsedHeaderAndBody) -> LoroResult<Import
         mode: match parsed.mode {
             super::EncodeMode::OutdatedRle => super::EncodedBlobMode::OutdatedRle,
             super::EncodeMode::OutdatedSnapshot => super::EncodedBlobMode::OutdatedSnapshot,
-            super::EncodeMode::FastSnapshot => super::EncodedBlobMode::Snapshot,
+            super::EncodeMode::FastSnapshot | super::EncodeMode::FastSnapshotCompressedText => {
+                super::EncodedBlobMode::Snapshot
+            }
             super::EncodeMode::FastUpdates => super::EncodedBlobMode::Updates,
             super::EncodeMode::Auto => unreachable!(),
         },
@@ -737,6 +739,7 @@ pub(crate) fn decode_snapshot(doc: &LoroDoc, bytes: &[u8]) -> LoroResult<()> {
                 Ok(ImportStatus {
                     success: Default::default(),
                     pending: None,
+                    rejected: None,
                 })
             },
             "".into(),
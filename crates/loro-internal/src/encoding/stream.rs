@@ -0,0 +1,324 @@
+//! Export and import the fast encoding formats through [Write] and [Read].
+//!
+//! The snapshot and the updates are written in a framed format so that they
+//! can be produced and consumed incrementally:
+//!
+//! ```log
+//! ┌────────────────────┬────────────┬─────────┬─────────┬─────┬───────────┐
+//! │ "lrst" Magic Bytes │ Mode (2 B) │ Frame 0 │ Frame 1 │ ... │ End Frame │
+//! └────────────────────┴────────────┴─────────┴─────────┴─────┴───────────┘
+//!
+//! ┌───────────────┬─────────────────┬─────────────────────────────┐
+//! │ Len (u32 LE)  │ Payload (Len B) │ xxh32 of Len and Payload    │
+//! └───────────────┴─────────────────┴─────────────────────────────┘
+//! ```
+//!
+//! A snapshot is written as three frames: the oplog, the state (or
+//! [EMPTY_MARK] when absent) and the shallow root state. The updates are
+//! written one frame per change block. The end frame has `Len = u32::MAX`
+//! and no payload.
+//!
+//! Every frame carries its own checksum, so a frame can be verified and
+//! decoded as soon as it arrives. The other export modes are written in the
+//! same format as [crate::LoroDoc::export].
+
+use std::io::{ErrorKind, Read, Write};
+
+use bytes::Bytes;
+use loro_common::{LoroEncodeError, LoroError, LoroResult};
+use xxhash_rust::xxh32::Xxh32;
+
+use super::{
+    fast_snapshot::{self, Snapshot, EMPTY_MARK},
+    EncodeMode, ExportMode, MAGIC_BYTES, XXH_SEED,
+};
+use crate::LoroDoc;
+
+pub(crate) const STREAM_MAGIC_BYTES: [u8; 4] = *b"lrst";
+const END_FRAME_LEN: u32 = u32::MAX;
+/// The size of the buffer to verify the frames that are skipped
+const SKIP_BUF_LEN: usize = 8 * 1024;
+
+/// The content of a blob read by [read_blob]
+pub(crate) enum ReadBlob<'a, R> {
+    /// The blob exported by [crate::LoroDoc::export]
+    Blob(Vec<u8>),
+    /// The sections of the snapshot that are read by [FrameReader::read_snapshot]
+    Snapshot(FrameReader<'a, R>),
+    /// The update blocks that are read from the reader on demand
+    Updates(FrameReader<'a, R>),
+}
+
+/// Write the blob of the given mode to the writer.
+///
+/// The snapshot and the updates modes are written in the framed format.
+/// The other modes are exported by [crate::LoroDoc::export] and then written.
+///
+/// The caller should commit the pending transaction first.
+pub(crate) fn export_to_writer<W: Write>(
+    doc: &LoroDoc,
+    mode: ExportMode,
+    w: &mut W,
+) -> Result<(), LoroEncodeError> {
+    match mode {
+        ExportMode::Snapshot => {
+            let snapshot = fast_snapshot::encode_snapshot_inner(doc);
+            write_snapshot(snapshot, w)
+        }
+        ExportMode::SnapshotWithOptions(options) => {
+            let snapshot = fast_snapshot::encode_snapshot_inner_with_options(doc, options);
+            write_snapshot(snapshot, w)
+        }
+        ExportMode::Updates { from } => {
+            let blocks = doc.oplog().try_lock().unwrap().encode_blocks_from(&from);
+            write_frames(EncodeMode::FastUpdates, blocks, w)
+        }
+        ExportMode::UpdatesInRange { spans } => {
+            let blocks = doc
+                .oplog()
+                .try_lock()
+                .unwrap()
+                .encode_blocks_in_range(&spans);
+            write_frames(EncodeMode::FastUpdates, blocks, w)
+        }
+        mode => {
+            let bytes = doc._export(mode)?;
+            w.write_all(&bytes).map_err(write_err)?;
+            w.flush().map_err(write_err)
+        }
+    }
+}
+
+fn write_snapshot<W: Write>(snapshot: Snapshot, w: &mut W) -> Result<(), LoroEncodeError> {
+    let frames = [
+        snapshot.oplog_bytes,
+        snapshot.state_bytes.unwrap_or(EMPTY_MARK.into()),
+        snapshot.shallow_root_state_bytes,
+    ];
+    write_frames(EncodeMode::FastSnapshot, frames, w)
+}
+
+fn write_frames<W: Write>(
+    mode: EncodeMode,
+    frames: impl IntoIterator<Item = Bytes>,
+    w: &mut W,
+) -> Result<(), LoroEncodeError> {
+    w.write_all(&STREAM_MAGIC_BYTES).map_err(write_err)?;
+    w.write_all(&mode.to_bytes()).map_err(write_err)?;
+    for frame in frames {
+        if frame.len() >= END_FRAME_LEN as usize {
+            return Err(LoroEncodeError::IoError(
+                "The frame is too large to be encoded".into(),
+            ));
+        }
+
+        write_frame(w, frame.len() as u32, &frame)?;
+    }
+
+    write_frame(w, END_FRAME_LEN, &[])?;
+    w.flush().map_err(write_err)
+}
+
+fn write_frame<W: Write>(w: &mut W, len: u32, payload: &[u8]) -> Result<(), LoroEncodeError> {
+    let len = len.to_le_bytes();
+    w.write_all(&len).map_err(write_err)?;
+    w.write_all(payload).map_err(write_err)?;
+    w.write_all(&frame_checksum(&len, payload).to_le_bytes())
+        .map_err(write_err)
+}
+
+fn frame_checksum(len: &[u8; 4], payload: &[u8]) -> u32 {
+    let mut hasher = Xxh32::new(XXH_SEED);
+    hasher.update(len);
+    hasher.update(payload);
+    hasher.digest()
+}
+
+fn write_err(e: std::io::Error) -> LoroEncodeError {
+    LoroEncodeError::IoError(e.to_string().into_boxed_str())
+}
+
+/// Read a blob written by [export_to_writer] or [crate::LoroDoc::export].
+///
+/// Only the header is read here. The frames of a snapshot or the updates are
+/// read from the reader when they are consumed, up to the end frame. Any other
+/// blob is read until the end of the reader.
+pub(crate) fn read_blob<R: Read>(r: &mut R) -> LoroResult<ReadBlob<'_, R>> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic).map_err(read_err)?;
+    if magic == MAGIC_BYTES {
+        let mut bytes = magic.to_vec();
+        r.read_to_end(&mut bytes).map_err(read_err)?;
+        return Ok(ReadBlob::Blob(bytes));
+    }
+
+    if magic != STREAM_MAGIC_BYTES {
+        return Err(LoroError::DecodeError("Invalid magic bytes".into()));
+    }
+
+    let mut mode_bytes = [0; 2];
+    r.read_exact(&mut mode_bytes).map_err(read_err)?;
+    let frames = FrameReader {
+        inner: r,
+        ended: false,
+    };
+    match EncodeMode::try_from(mode_bytes)? {
+        EncodeMode::FastSnapshot => Ok(ReadBlob::Snapshot(frames)),
+        EncodeMode::FastUpdates => Ok(ReadBlob::Updates(frames)),
+        mode => Err(LoroError::DecodeError(
+            format!("Unsupported mode {:?} in the stream", mode).into_boxed_str(),
+        )),
+    }
+}
+
+fn read_err(e: std::io::Error) -> LoroError {
+    if e.kind() == ErrorKind::UnexpectedEof {
+        LoroError::DecodeError("Unexpected end of import data".into())
+    } else {
+        LoroError::IoError(e.to_string().into_boxed_str())
+    }
+}
+
+/// Read the checksummed frames one by one until the end frame
+pub(crate) struct FrameReader<'a, R> {
+    inner: &'a mut R,
+    ended: bool,
+}
+
+impl<R: Read> FrameReader<'_, R> {
+    /// Read the sections of the snapshot and verify that there is nothing after them.
+    ///
+    /// If `with_state` is false, the state sections are verified without being kept
+    /// in memory, and they are left empty in the returned snapshot.
+    pub(crate) fn read_snapshot(mut self, with_state: bool) -> LoroResult<Snapshot> {
+        let missing = || LoroError::DecodeError("Missing sections in the snapshot".into());
+        let oplog_bytes = self.next_frame()?.ok_or_else(missing)?;
+        let (state_bytes, shallow_root_state_bytes) = if with_state {
+            let state_bytes = self.next_frame()?.ok_or_else(missing)?;
+            let shallow_root_state_bytes = self.next_frame()?.ok_or_else(missing)?;
+            let state_bytes = if state_bytes == EMPTY_MARK {
+                None
+            } else {
+                Some(state_bytes)
+            };
+            (state_bytes, shallow_root_state_bytes)
+        } else {
+            for _ in 0..2 {
+                if !self.skip_frame()? {
+                    return Err(missing());
+                }
+            }
+            (None, Bytes::new())
+        };
+
+        if self.next_frame()?.is_some() {
+            return Err(LoroError::DecodeError(
+                "Unexpected sections in the snapshot".into(),
+            ));
+        }
+
+        Ok(Snapshot {
+            oplog_bytes,
+            state_bytes,
+            shallow_root_state_bytes,
+        })
+    }
+
+    /// Read and verify the next frame without keeping its payload.
+    ///
+    /// It returns `false` after the end frame.
+    fn skip_frame(&mut self) -> LoroResult<bool> {
+        if self.ended {
+            return Ok(false);
+        }
+
+        self.ended = true;
+        let mut len = [0; 4];
+        self.inner.read_exact(&mut len).map_err(read_err)?;
+        let payload_len = u32::from_le_bytes(len);
+        let mut hasher = Xxh32::new(XXH_SEED);
+        hasher.update(&len);
+        if payload_len != END_FRAME_LEN {
+            let mut rest = payload_len as usize;
+            let mut buf = [0; SKIP_BUF_LEN];
+            while rest > 0 {
+                let chunk = &mut buf[..rest.min(SKIP_BUF_LEN)];
+                self.inner.read_exact(chunk).map_err(read_err)?;
+                hasher.update(chunk);
+                rest -= chunk.len();
+            }
+        }
+
+        let mut checksum = [0; 4];
+        self.inner.read_exact(&mut checksum).map_err(read_err)?;
+        if u32::from_le_bytes(checksum) != hasher.digest() {
+            return Err(LoroError::DecodeChecksumMismatchError);
+        }
+
+        if payload_len == END_FRAME_LEN {
+            return Ok(false);
+        }
+
+        self.ended = false;
+        Ok(true)
+    }
+
+    /// Read and verify the next frame.
+    ///
+    /// It returns `None` after the end frame.
+    fn next_frame(&mut self) -> LoroResult<Option<Bytes>> {
+        if self.ended {
+            return Ok(None);
+        }
+
+        // Stop at the first error so that a broken stream is not read further
+        self.ended = true;
+        let mut len = [0; 4];
+        self.inner.read_exact(&mut len).map_err(read_err)?;
+        let payload_len = u32::from_le_bytes(len);
+        let payload = if payload_len == END_FRAME_LEN {
+            Bytes::new()
+        } else {
+            self.read_bytes(payload_len as u64)?
+        };
+
+        let mut checksum = [0; 4];
+        self.inner.read_exact(&mut checksum).map_err(read_err)?;
+        if u32::from_le_bytes(checksum) != frame_checksum(&len, &payload) {
+            return Err(LoroError::DecodeChecksumMismatchError);
+        }
+
+        if payload_len == END_FRAME_LEN {
+            return Ok(None);
+        }
+
+        self.ended = false;
+        Ok(Some(payload))
+    }
+
+    fn read_bytes(&mut self, len: u64) -> LoroResult<Bytes> {
+        // The buffer grows with the data that actually arrives instead of
+        // trusting the length in the data
+        let mut buf = Vec::new();
+        self.inner
+            .by_ref()
+            .take(len)
+            .read_to_end(&mut buf)
+            .map_err(read_err)?;
+        if buf.len() as u64 != len {
+            return Err(LoroError::DecodeError(
+                "Unexpected end of import data".into(),
+            ));
+        }
+
+        Ok(buf.into())
+    }
+}
+
+impl<R: Read> Iterator for FrameReader<'_, R> {
+    type Item = LoroResult<Bytes>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
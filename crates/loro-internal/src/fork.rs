@@ -42,10 +42,13 @@ impl LoroDoc {
             .unwrap();
         let doc = LoroDoc::new();
         doc.set_config(&self.config);
+        // The content is already in this doc, so it's not checked by the schema again
+        let schema = doc.config.schema.write().unwrap().take();
         if self.auto_commit.load(std::sync::atomic::Ordering::Relaxed) {
             doc.start_auto_commit();
         }
         doc.import(&bytes).unwrap();
+        *doc.config.schema.write().unwrap() = schema;
         doc
     }
 }
@@ -4,12 +4,15 @@ use crate::{
     container::{
         idx::ContainerIdx,
         list::list_op::{DeleteSpan, DeleteSpanWithId, ListOp},
-        richtext::{richtext_state::PosType, RichtextState, StyleOp, TextStyleInfoFlag},
+        richtext::{
+            self, config::StyleConfigMap, richtext_state::PosType, ExpandType, RichtextState,
+            StyleKey, StyleOp, TextStyleInfoFlag, EMBED_CHAR, EMBED_STYLE_KEY,
+        },
     },
     cursor::{Cursor, Side},
     delta::{DeltaItem, Meta, StyleMeta, TreeExternalDiff},
     diff::{diff, diff_impl::UpdateTimeoutError, OperateProxy},
-    event::{Diff, TextDiffItem},
+    event::{Diff, TextDiff, TextDiffItem},
     op::ListSlice,
     state::{IndexType, State, TreeParentId},
     txn::EventHint,
@@ -29,15 +32,22 @@ use std::{
     cmp::Reverse,
     collections::BinaryHeap,
     fmt::Debug,
-    ops::Deref,
+    ops::{Deref, Range},
     sync::{Arc, Mutex, Weak},
 };
 use tracing::{error, info, instrument, trace};
 
-pub use crate::diff::diff_impl::UpdateOptions;
+pub use crate::diff::diff_impl::{DiffAlgorithm, DiffGranularity, UpdateOptions};
 pub use tree::TreeHandler;
+pub use tree_rebalance::TreePositionKeyStats;
+pub use tree_traversal::TreeWalk;
+pub use tree_value::TreeValueOptions;
 mod movable_list_apply_delta;
 mod tree;
+mod tree_rebalance;
+mod tree_subtree;
+mod tree_traversal;
+mod tree_value;
 
 const INSERT_CONTAINER_VALUE_ARG_ERROR: &str =
     "Cannot insert a LoroValue::Container directly. To create child container, use insert_container";
@@ -289,10 +299,11 @@ impl HandlerTrait for TextHandler {
                 let text = inner.into_text().unwrap();
                 let mut delta: Vec<TextDelta> = Vec::new();
                 for span in t.value.iter() {
-                    delta.push(TextDelta::Insert {
-                        insert: span.text.to_string(),
-                        attributes: span.attributes.to_option_map(),
-                    });
+                    TextDelta::push_insert(
+                        &mut delta,
+                        span.text.as_str(),
+                        span.attributes.to_option_map(),
+                    );
                 }
 
                 text.apply_delta_with_txn(txn, &delta)?;
@@ -377,11 +388,41 @@ pub enum TextDelta {
         insert: String,
         attributes: Option<FxHashMap<String, LoroValue>>,
     },
+    /// An embed inserted by [TextHandler::insert_embed]. Its length is 1.
+    ///
+    /// It's serialized as `{"insert": {"$embed": value}}`, so a string embed can't be
+    /// confused with an insert of text.
+    Embed {
+        #[serde(with = "embed_serde")]
+        insert: LoroValue,
+        attributes: Option<FxHashMap<String, LoroValue>>,
+    },
     Delete {
         delete: usize,
     },
 }
 
+/// (De)serialize the value of [TextDelta::Embed] as `{"$embed": value}`
+mod embed_serde {
+    use loro_common::LoroValue;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Embed<T> {
+        #[serde(rename = "$embed")]
+        embed: T,
+    }
+
+    pub(super) fn serialize<S: Serializer>(value: &LoroValue, s: S) -> Result<S::Ok, S::Error> {
+        Embed { embed: value }.serialize(s)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<LoroValue, D::Error> {
+        Embed::deserialize(d).map(|x| x.embed)
+    }
+}
+
 impl TextDelta {
     pub fn from_text_diff<'a>(diff: impl Iterator<Item = &'a TextDiffItem>) -> Vec<TextDelta> {
         let mut ans = Vec::with_capacity(diff.size_hint().0);
@@ -399,10 +440,7 @@ impl TextDelta {
                     delete,
                 } => {
                     if value.rle_len() > 0 {
-                        ans.push(TextDelta::Insert {
-                            insert: value.to_string(),
-                            attributes: attr.to_option_map(),
-                        });
+                        TextDelta::push_insert(&mut ans, value.as_str(), attr.to_option_map());
                     }
                     if *delete > 0 {
                         ans.push(TextDelta::Delete { delete: *delete });
@@ -413,6 +451,65 @@ impl TextDelta {
 
         ans
     }
+
+    /// Convert a list of [TextDelta] back into a [TextDiff].
+    ///
+    /// The styles inside the result don't carry the lamport and peer info.
+    pub fn into_text_diff(vec: impl Iterator<Item = Self>) -> TextDiff {
+        let mut delta = TextDiff::new();
+        for item in vec {
+            match item {
+                TextDelta::Retain { retain, attributes } => {
+                    delta.push_retain(retain, attributes.into());
+                }
+                TextDelta::Insert { insert, attributes } => {
+                    delta.push_insert(StringSlice::from(insert), attributes.into());
+                }
+                TextDelta::Embed { insert, attributes } => {
+                    let mut attributes = attributes.unwrap_or_default();
+                    attributes.insert(EMBED_STYLE_KEY.to_string(), insert);
+                    delta.push_insert(
+                        StringSlice::from(EMBED_CHAR.to_string()),
+                        Some(attributes).into(),
+                    );
+                }
+                TextDelta::Delete { delete } => {
+                    delta.push_delete(delete);
+                }
+            }
+        }
+
+        delta
+    }
+
+    /// Push the inserted text with the given attributes.
+    ///
+    /// The chars marked as embeds are pushed as [TextDelta::Embed]s.
+    pub(crate) fn push_insert(
+        ans: &mut Vec<TextDelta>,
+        insert: &str,
+        mut attributes: Option<FxHashMap<String, LoroValue>>,
+    ) {
+        let embed = attributes
+            .as_mut()
+            .and_then(|attr| attr.remove(EMBED_STYLE_KEY))
+            .filter(|value| !value.is_null());
+        let attributes = attributes.filter(|attr| !attr.is_empty());
+        match embed {
+            Some(embed) => {
+                for _ in insert.chars() {
+                    ans.push(TextDelta::Embed {
+                        insert: embed.clone(),
+                        attributes: attributes.clone(),
+                    });
+                }
+            }
+            None => ans.push(TextDelta::Insert {
+                insert: insert.to_string(),
+                attributes,
+            }),
+        }
+    }
 }
 
 impl From<&DeltaItem<StringSlice, StyleMeta>> for TextDelta {
@@ -1109,7 +1206,10 @@ impl Handler {
             }
             Self::Text(x) => {
                 let delta = diff.into_text().unwrap();
-                x.apply_delta(&TextDelta::from_text_diff(delta.iter()))?;
+                x.apply_delta_with_remap(
+                    &TextDelta::from_text_diff(delta.iter()),
+                    on_container_remap,
+                )?;
             }
             Self::List(x) => {
                 let delta = diff.into_list().unwrap();
@@ -1208,6 +1308,7 @@ impl Handler {
                                 x.delete(target)?;
                             }
                         }
+                        TreeExternalDiff::MoveRejected { .. } => {}
                     }
                 }
             }
@@ -1362,6 +1463,59 @@ impl TextHandler {
         }
     }
 
+    /// Convert the position from one type to another.
+    ///
+    /// Return `None` if the position is out of bound or not at a char boundary.
+    pub fn convert_pos(
+        &self,
+        pos: usize,
+        from: richtext::PosType,
+        to: richtext::PosType,
+    ) -> Option<usize> {
+        match &self.inner {
+            MaybeDetached::Detached(t) => {
+                let t = t.try_lock().unwrap();
+                t.value.convert_pos(pos, from.into(), to.into())
+            }
+            MaybeDetached::Attached(a) => a.with_state(|state| {
+                state
+                    .as_richtext_state_mut()
+                    .unwrap()
+                    .convert_pos(pos, from.into(), to.into())
+            }),
+        }
+    }
+
+    fn len_with_pos_type(&self, pos_type: richtext::PosType) -> usize {
+        match pos_type {
+            richtext::PosType::Utf8 => self.len_utf8(),
+            richtext::PosType::Utf16 => self.len_utf16(),
+            richtext::PosType::Event => self.len_event(),
+            richtext::PosType::Unicode => self.len_unicode(),
+        }
+    }
+
+    /// Convert the position of the given type to an Event Index
+    fn to_event_index(&self, pos: usize, pos_type: richtext::PosType) -> LoroResult<usize> {
+        if let Some(index) = self.convert_pos(pos, pos_type, richtext::PosType::Event) {
+            return Ok(index);
+        }
+
+        let len = self.len_with_pos_type(pos_type);
+        if pos > len {
+            return Err(LoroError::OutOfBound {
+                pos,
+                len,
+                info: format!("Position: {}:{}", file!(), line!()).into_boxed_str(),
+            });
+        }
+
+        match pos_type {
+            richtext::PosType::Utf8 => Err(LoroError::UTF8InUnicodeCodePoint { pos }),
+            _ => Err(LoroError::UTF16InUnicodeCodePoint { pos }),
+        }
+    }
+
     pub fn diagnose(&self) {
         match &self.inner {
             MaybeDetached::Detached(t) => {
@@ -1426,6 +1580,15 @@ impl TextHandler {
         }
     }
 
+    /// Like [`Self::char_at`], but `pos` is of the given type
+    pub fn char_at_with_pos_type(
+        &self,
+        pos: usize,
+        pos_type: richtext::PosType,
+    ) -> LoroResult<char> {
+        self.char_at(self.to_event_index(pos, pos_type)?)
+    }
+
     /// `start_index` and `end_index` are Event Index:
     ///
     /// - if feature="wasm", pos is a UTF-16 index
@@ -1453,6 +1616,154 @@ impl TextHandler {
         }
     }
 
+    /// Like [`Self::slice`], but `start_index` and `end_index` are of the given type
+    pub fn slice_with_pos_type(
+        &self,
+        start_index: usize,
+        end_index: usize,
+        pos_type: richtext::PosType,
+    ) -> LoroResult<String> {
+        if end_index < start_index {
+            return Err(LoroError::EndIndexLessThanStartIndex {
+                start: start_index,
+                end: end_index,
+            });
+        }
+
+        let start = self.to_event_index(start_index, pos_type)?;
+        let end = self.to_event_index(end_index, pos_type)?;
+        self.slice(start, end)
+    }
+
+    /// Get the styles of the char at `pos`. The styles removed by unmark are not included.
+    ///
+    /// `pos` is a Event Index:
+    ///
+    /// - if feature="wasm", pos is a UTF-16 index
+    /// - if feature!="wasm", pos is a Unicode index
+    pub fn get_styles_at(&self, pos: usize) -> LoroResult<FxHashMap<String, LoroValue>> {
+        let styles = match &self.inner {
+            MaybeDetached::Detached(t) => {
+                let t = t.try_lock().unwrap();
+                t.value.get_styles_at(pos, PosType::Event)
+            }
+            MaybeDetached::Attached(a) => a.with_state(|state| {
+                state
+                    .as_richtext_state_mut()
+                    .unwrap()
+                    .get_styles_at(pos, PosType::Event)
+            }),
+        };
+        match styles {
+            Some(styles) => Ok(styles.to_map_without_null_value()),
+            None => Err(LoroError::OutOfBound {
+                pos,
+                len: self.len_event(),
+                info: format!("Position: {}:{}", file!(), line!()).into_boxed_str(),
+            }),
+        }
+    }
+
+    /// Get the ranges where the style of `key` is set, with the style values.
+    ///
+    /// The adjacent ranges with the same value are merged. The ranges are in Event Index:
+    ///
+    /// - if feature="wasm", they are UTF-16 indexes
+    /// - if feature!="wasm", they are Unicode indexes
+    pub fn style_ranges(&self, key: &str) -> Vec<(Range<usize>, LoroValue)> {
+        match &self.inner {
+            MaybeDetached::Detached(t) => {
+                let t = t.try_lock().unwrap();
+                t.value.get_style_ranges(key, PosType::Event)
+            }
+            MaybeDetached::Attached(a) => a.with_state(|state| {
+                state
+                    .as_richtext_state_mut()
+                    .unwrap()
+                    .get_style_ranges(key, PosType::Event)
+            }),
+        }
+    }
+
+    /// Get the text between `start_index` and `end_index` in the delta format.
+    ///
+    /// `start_index` and `end_index` are Event Index:
+    ///
+    /// - if feature="wasm", pos is a UTF-16 index
+    /// - if feature!="wasm", pos is a Unicode index
+    pub fn slice_delta(&self, start_index: usize, end_index: usize) -> LoroResult<Vec<TextDelta>> {
+        if end_index < start_index {
+            return Err(LoroError::EndIndexLessThanStartIndex {
+                start: start_index,
+                end: end_index,
+            });
+        }
+
+        let spans = match &self.inner {
+            MaybeDetached::Detached(t) => {
+                let t = t.try_lock().unwrap();
+                t.value.slice_spans(start_index, end_index, PosType::Event)
+            }
+            MaybeDetached::Attached(a) => a.with_state(|state| {
+                state.as_richtext_state_mut().unwrap().slice_spans(
+                    start_index,
+                    end_index,
+                    PosType::Event,
+                )
+            }),
+        }?;
+
+        let mut ans: Vec<TextDelta> = Vec::new();
+        for span in spans {
+            let styles = span.attributes.to_map_without_null_value();
+            let attributes = (!styles.is_empty()).then_some(styles);
+            if attributes
+                .as_ref()
+                .is_some_and(|attr| attr.contains_key(EMBED_STYLE_KEY))
+            {
+                TextDelta::push_insert(&mut ans, span.text.as_str(), attributes);
+                continue;
+            }
+
+            if let Some(TextDelta::Insert {
+                insert,
+                attributes: last_attributes,
+            }) = ans.last_mut()
+            {
+                if *last_attributes == attributes {
+                    insert.push_str(span.text.as_str());
+                    continue;
+                }
+            }
+
+            ans.push(TextDelta::Insert {
+                insert: span.text.as_str().to_string(),
+                attributes,
+            });
+        }
+
+        Ok(ans)
+    }
+
+    /// Like [`Self::slice_delta`], but `start_index` and `end_index` are of the given type
+    pub fn slice_delta_with_pos_type(
+        &self,
+        start_index: usize,
+        end_index: usize,
+        pos_type: richtext::PosType,
+    ) -> LoroResult<Vec<TextDelta>> {
+        if end_index < start_index {
+            return Err(LoroError::EndIndexLessThanStartIndex {
+                start: start_index,
+                end: end_index,
+            });
+        }
+
+        let start = self.to_event_index(start_index, pos_type)?;
+        let end = self.to_event_index(end_index, pos_type)?;
+        self.slice_delta(start, end)
+    }
+
     /// `pos` is a Event Index:
     ///
     /// - if feature="wasm", pos is a UTF-16 index
@@ -1466,6 +1777,26 @@ impl TextHandler {
         Ok(x)
     }
 
+    /// Like [`Self::splice`], but `pos` and `len` are of the given type
+    ///
+    /// This method requires auto_commit to be enabled.
+    pub fn splice_with_pos_type(
+        &self,
+        pos: usize,
+        len: usize,
+        s: &str,
+        pos_type: richtext::PosType,
+    ) -> LoroResult<String> {
+        let end = pos.checked_add(len).ok_or_else(|| LoroError::OutOfBound {
+            pos: pos.saturating_add(len),
+            len: self.len_with_pos_type(pos_type),
+            info: format!("Position: {}:{}", file!(), line!()).into_boxed_str(),
+        })?;
+        let start = self.to_event_index(pos, pos_type)?;
+        let end = self.to_event_index(end, pos_type)?;
+        self.splice(start, end - start, s)
+    }
+
     pub fn splice_utf8(&self, pos: usize, len: usize, s: &str) -> LoroResult<()> {
         // let x = self.slice(pos, pos + len)?;
         self.delete_utf8(pos, len)?;
@@ -1504,7 +1835,7 @@ impl TextHandler {
                 let mut t = t.try_lock().unwrap();
                 let (index, _) = t
                     .value
-                    .get_entity_index_for_text_insert(pos, PosType::Bytes)
+                    .get_entity_index_for_text_insert(pos, PosType::Utf8)
                     .unwrap();
                 t.value.insert_at_entity_index(
                     index,
@@ -1554,10 +1885,131 @@ impl TextHandler {
         pos: usize,
         s: &str,
     ) -> LoroResult<()> {
-        self.insert_with_txn_and_attr(txn, pos, s, None, PosType::Bytes)?;
+        self.insert_with_txn_and_attr(txn, pos, s, None, PosType::Utf8)?;
         Ok(())
     }
 
+    /// Insert an embed, such as an inline image or a mention, at the given position.
+    ///
+    /// The embed has length 1 and it's shown as [EMBED_CHAR] in the plain text.
+    /// It's an insert of the value in [TextDelta], and an insert of `{"$embed": value}`
+    /// in [Self::get_richtext_value].
+    ///
+    /// The value can't be null or a container. Use [Self::insert_container] to embed
+    /// a child container.
+    ///
+    /// `pos` is a Event Index:
+    ///
+    /// - if feature="wasm", pos is a UTF-16 index
+    /// - if feature!="wasm", pos is a Unicode index
+    ///
+    /// This method requires auto_commit to be enabled.
+    pub fn insert_embed(&self, pos: usize, value: LoroValue) -> LoroResult<()> {
+        match &self.inner {
+            MaybeDetached::Detached(t) => {
+                check_embed_value(&value)?;
+                let len = self.len_event();
+                if pos > len {
+                    return Err(LoroError::OutOfBound {
+                        pos,
+                        len,
+                        info: format!("Position: {}:{}", file!(), line!()).into_boxed_str(),
+                    });
+                }
+
+                let mut t = t.try_lock().unwrap();
+                let (index, _) = t
+                    .value
+                    .get_entity_index_for_text_insert(pos, PosType::Event)?;
+                t.value.insert_at_entity_index(
+                    index,
+                    BytesSlice::from_bytes(EMBED_CHAR.to_string().as_bytes()),
+                    IdFull::NONE_ID,
+                );
+                self.mark_for_detached(&mut t.value, EMBED_STYLE_KEY, &value, pos, pos + 1, false)
+            }
+            MaybeDetached::Attached(a) => {
+                a.with_txn(|txn| self.insert_embed_with_txn(txn, pos, value))
+            }
+        }
+    }
+
+    /// `pos` is a Event Index:
+    ///
+    /// - if feature="wasm", pos is a UTF-16 index
+    /// - if feature!="wasm", pos is a Unicode index
+    pub fn insert_embed_with_txn(
+        &self,
+        txn: &mut Transaction,
+        pos: usize,
+        value: LoroValue,
+    ) -> LoroResult<()> {
+        check_embed_value(&value)?;
+        self.insert_with_txn_and_attr(txn, pos, &EMBED_CHAR.to_string(), None, PosType::Event)?;
+        self.mark_with_txn(txn, pos, pos + 1, EMBED_STYLE_KEY, value, false)
+    }
+
+    /// Insert a child container as an embed at the given position.
+    ///
+    /// The embed has length 1 like the other embeds, and it's an insert of the
+    /// [LoroValue::Container] of the child in [TextDelta]. The child is deleted
+    /// along with the embed.
+    ///
+    /// It's not supported on a detached text.
+    ///
+    /// `pos` is a Event Index:
+    ///
+    /// - if feature="wasm", pos is a UTF-16 index
+    /// - if feature!="wasm", pos is a Unicode index
+    pub fn insert_container<H: HandlerTrait>(&self, pos: usize, child: H) -> LoroResult<H> {
+        match &self.inner {
+            MaybeDetached::Detached(_) => Err(LoroError::MisuseDetachedContainer {
+                method: "insert_container",
+            }),
+            MaybeDetached::Attached(a) => {
+                a.with_txn(|txn| self.insert_container_with_txn(txn, pos, child))
+            }
+        }
+    }
+
+    /// `pos` is a Event Index:
+    ///
+    /// - if feature="wasm", pos is a UTF-16 index
+    /// - if feature!="wasm", pos is a Unicode index
+    pub fn insert_container_with_txn<H: HandlerTrait>(
+        &self,
+        txn: &mut Transaction,
+        pos: usize,
+        child: H,
+    ) -> LoroResult<H> {
+        self.insert_with_txn_and_attr(txn, pos, &EMBED_CHAR.to_string(), None, PosType::Event)?;
+        self.mark_container_embed_with_txn(txn, pos, child)
+    }
+
+    /// Mark the embed char at `pos` with a new child container.
+    ///
+    /// The child is created by the StyleStart op of the mark, so its id is the id of
+    /// the op, which is how the value is encoded.
+    fn mark_container_embed_with_txn<H: HandlerTrait>(
+        &self,
+        txn: &mut Transaction,
+        pos: usize,
+        child: H,
+    ) -> LoroResult<H> {
+        let inner = self.inner.try_attached_state()?;
+        let id = txn.next_id();
+        let container_id = ContainerID::new_normal(id, child.kind());
+        self.mark_with_txn(
+            txn,
+            pos,
+            pos + 1,
+            EMBED_STYLE_KEY,
+            LoroValue::Container(container_id.clone()),
+            false,
+        )?;
+        child.attach(txn, inner, container_id)
+    }
+
     /// `pos` is a Event Index:
     ///
     /// - if feature="wasm", pos is a UTF-16 index
@@ -1586,7 +2038,7 @@ impl TextHandler {
         match &self.inner {
             MaybeDetached::Detached(t) => {
                 let mut t = t.try_lock().unwrap();
-                let ranges = match t.value.get_text_entity_ranges(pos, len, PosType::Bytes) {
+                let ranges = match t.value.get_text_entity_ranges(pos, len, PosType::Utf8) {
                     Err(x) => return Err(x),
                     Ok(x) => x,
                 };
@@ -1597,7 +2049,7 @@ impl TextHandler {
                 Ok(())
             }
             MaybeDetached::Attached(a) => {
-                a.with_txn(|txn| self.delete_with_txn_inline(txn, pos, len, PosType::Bytes))
+                a.with_txn(|txn| self.delete_with_txn_inline(txn, pos, len, PosType::Utf8))
             }
         }
     }
@@ -1646,7 +2098,7 @@ impl TextHandler {
                     });
                 }
             }
-            PosType::Bytes => {
+            PosType::Utf8 => {
                 if pos > self.len_utf8() {
                     return Err(LoroError::OutOfBound {
                         pos,
@@ -1682,7 +2134,7 @@ impl TextHandler {
             let ret = richtext_state.get_entity_index_for_text_insert(pos, pos_type);
             let (entity_index, cursor) = match ret {
                 Err(_) => match pos_type {
-                    PosType::Bytes => {
+                    PosType::Utf8 => {
                         return (
                             Err(LoroError::UTF8InUnicodeCodePoint { pos }),
                             0,
@@ -1808,7 +2260,7 @@ impl TextHandler {
                     });
                 }
             }
-            PosType::Bytes => {
+            PosType::Utf8 => {
                 if pos + len > self.len_utf8() {
                     error!("pos={} len={} len_bytes={}", pos, len, self.len_utf8());
                     return Err(LoroError::OutOfBound {
@@ -1876,6 +2328,8 @@ impl TextHandler {
         key: impl Into<InternalString>,
         value: LoroValue,
     ) -> LoroResult<()> {
+        let key: InternalString = key.into();
+        check_style_key(&key)?;
         match &self.inner {
             MaybeDetached::Detached(t) => self.mark_for_detached(
                 &mut t.try_lock().unwrap().value,
@@ -1891,6 +2345,22 @@ impl TextHandler {
         }
     }
 
+    /// Like [`Self::mark`], but `start` and `end` are of the given type
+    ///
+    /// This method requires auto_commit to be enabled.
+    pub fn mark_with_pos_type(
+        &self,
+        start: usize,
+        end: usize,
+        key: impl Into<InternalString>,
+        value: LoroValue,
+        pos_type: richtext::PosType,
+    ) -> LoroResult<()> {
+        let start = self.to_event_index(start, pos_type)?;
+        let end = self.to_event_index(end, pos_type)?;
+        self.mark(start, end, key, value)
+    }
+
     fn mark_for_detached(
         &self,
         state: &mut RichtextState,
@@ -1901,7 +2371,8 @@ impl TextHandler {
         is_delete: bool,
     ) -> Result<(), LoroError> {
         let key: InternalString = key.into();
-        let len = self.len_event();
+        // The state is locked by the caller, so its length is read directly
+        let len = state.len_event();
         if start >= end {
             return Err(loro_common::LoroError::ArgErr(
                 "Start must be less than end".to_string().into_boxed_str(),
@@ -1917,24 +2388,28 @@ impl TextHandler {
         let (entity_range, styles) =
             state.get_entity_range_and_text_styles_at_range(start..end, PosType::Event);
         if let Some(styles) = styles {
-            if styles.has_key_value(&key, value) {
+            if styles.has_key_value(&StyleKey::Key(key.clone()), value) {
                 // already has the same style, skip
                 return Ok(());
             }
         }
 
+        // TODO: describe this behavior in the document
+        let info = if is_delete {
+            TextStyleInfoFlag::BOLD.to_delete()
+        } else if key == EMBED_STYLE_KEY {
+            // The embeds have a built-in config, so they never expand
+            TextStyleInfoFlag::new(ExpandType::None)
+        } else {
+            TextStyleInfoFlag::BOLD
+        };
         let style_op = Arc::new(StyleOp {
             lamport: 0,
             peer: 0,
             cnt: 0,
             key,
             value: value.clone(),
-            // TODO: describe this behavior in the document
-            info: if is_delete {
-                TextStyleInfoFlag::BOLD.to_delete()
-            } else {
-                TextStyleInfoFlag::BOLD
-            },
+            info,
         });
         state.mark_with_entity_index(entity_range, style_op);
         Ok(())
@@ -1952,6 +2427,8 @@ impl TextHandler {
         end: usize,
         key: impl Into<InternalString>,
     ) -> LoroResult<()> {
+        let key: InternalString = key.into();
+        check_style_key(&key)?;
         match &self.inner {
             MaybeDetached::Detached(t) => self.mark_for_detached(
                 &mut t.try_lock().unwrap().value,
@@ -1967,6 +2444,49 @@ impl TextHandler {
         }
     }
 
+    /// Like [`Self::unmark`], but `start` and `end` are of the given type
+    ///
+    /// This method requires auto_commit to be enabled.
+    pub fn unmark_with_pos_type(
+        &self,
+        start: usize,
+        end: usize,
+        key: impl Into<InternalString>,
+        pos_type: richtext::PosType,
+    ) -> LoroResult<()> {
+        let start = self.to_event_index(start, pos_type)?;
+        let end = self.to_event_index(end, pos_type)?;
+        self.unmark(start, end, key)
+    }
+
+    /// Remove the mark of `value` of the non-exclusive style `key` in the range.
+    /// The marks of the other values are kept.
+    ///
+    /// It's the same as [`Self::unmark`] if the style is exclusive.
+    ///
+    /// `start` and `end` are [Event Index]s:
+    ///
+    /// - if feature="wasm", pos is a UTF-16 index
+    /// - if feature!="wasm", pos is a Unicode index
+    ///
+    /// This method requires auto_commit to be enabled.
+    pub fn unmark_value(
+        &self,
+        start: usize,
+        end: usize,
+        key: impl Into<InternalString>,
+        value: LoroValue,
+    ) -> LoroResult<()> {
+        match &self.inner {
+            MaybeDetached::Detached(_) => self.unmark(start, end, key),
+            MaybeDetached::Attached(a) => {
+                let key: InternalString = key.into();
+                check_style_key(&key)?;
+                a.with_txn(|txn| self.mark_with_txn(txn, start, end, key, value, true))
+            }
+        }
+    }
+
     /// `start` and `end` are [Event Index]s:
     ///
     /// - if feature="wasm", pos is a UTF-16 index
@@ -2000,28 +2520,6 @@ impl TextHandler {
 
         let mutex = &inner.state.upgrade().unwrap();
         let mut doc_state = mutex.try_lock().unwrap();
-        let (entity_range, skip) = doc_state.with_state_mut(inner.container_idx, |state| {
-            let (entity_range, styles) = state
-                .as_richtext_state_mut()
-                .unwrap()
-                .get_entity_range_and_styles_at_range(start..end, PosType::Event);
-
-            let skip = match styles {
-                Some(styles) if styles.has_key_value(&key, &value) => {
-                    // already has the same style, skip
-                    true
-                }
-                _ => false,
-            };
-            (entity_range, skip)
-        });
-
-        if skip {
-            return Ok(());
-        }
-
-        let entity_start = entity_range.start;
-        let entity_end = entity_range.end;
         let style_config = doc_state.config.text_style_config.try_read().unwrap();
         let flag = if is_delete {
             style_config
@@ -2032,9 +2530,82 @@ impl TextHandler {
                 .get_style_flag(&key)
                 .ok_or_else(|| LoroError::StyleConfigMissing(key.clone()))?
         };
+        let options = style_config.get_options_by_style_key(&key).unwrap();
+        if !is_delete {
+            if let Err(msg) = options.value_type.check_value(&value) {
+                return Err(LoroError::ArgErr(
+                    format!("Invalid value of style `{}`: {}", key, msg).into_boxed_str(),
+                ));
+            }
+        }
 
+        let exclusive = options.exclusive;
+        let allow_nesting = options.allow_nesting;
         drop(style_config);
+        let value = if is_delete && exclusive {
+            LoroValue::Null
+        } else {
+            value
+        };
+        // The marks of a non-exclusive style with different values are kept separately
+        let style_key = if exclusive {
+            StyleKey::Key(key.clone())
+        } else {
+            StyleKey::KeyWithValue {
+                key: key.clone(),
+                value: value.clone(),
+            }
+        };
+        // The value of the style after the op is applied
+        let expected = if is_delete {
+            LoroValue::Null
+        } else {
+            value.clone()
+        };
+        let (entity_range, skip, overlapped) =
+            doc_state.with_state_mut(inner.container_idx, |state| {
+                let state = state.as_richtext_state_mut().unwrap();
+                let (entity_range, styles) =
+                    state.get_entity_range_and_styles_at_range(start..end, PosType::Event);
+
+                let skip = match styles {
+                    Some(styles) if styles.has_key_value(&style_key, &expected) => {
+                        // already has the same style, skip
+                        true
+                    }
+                    _ => false,
+                };
+                // The values of a non-exclusive style in the range
+                let overlapped = if exclusive {
+                    Vec::new()
+                } else {
+                    state.get_style_values_in_entity_range(entity_range.clone(), &key)
+                };
+                (entity_range, skip, overlapped)
+            });
+
         drop(doc_state);
+        if is_delete && !exclusive && value.is_null() {
+            // Unmarking a non-exclusive style removes the marks of all the values
+            for v in overlapped {
+                self.mark_with_txn(txn, start, end, key.clone(), v, true)?;
+            }
+            return Ok(());
+        }
+
+        if !is_delete && !allow_nesting && overlapped.iter().any(|v| v != &value) {
+            return Err(LoroError::ArgErr(
+                format!("Style `{}` cannot overlap the marks with other values", key)
+                    .into_boxed_str(),
+            ));
+        }
+
+        if skip || (is_delete && !exclusive && !overlapped.contains(&value)) {
+            return Ok(());
+        }
+
+        let entity_start = entity_range.start;
+        let entity_end = entity_range.end;
         txn.apply_local_op(
             inner.container_idx,
             crate::op::RawOpContent::List(ListOp::StyleStart {
@@ -2048,6 +2619,7 @@ impl TextHandler {
                 start: start as u32,
                 end: end as u32,
                 style: crate::container::richtext::Style { key, data: value },
+                values: None,
             },
             &inner.state,
         )?;
@@ -2094,6 +2666,32 @@ impl TextHandler {
         &self,
         txn: &mut Transaction,
         delta: &[TextDelta],
+    ) -> LoroResult<()> {
+        self.apply_delta_with_txn_and_remap(txn, delta, &mut |_, _| {})
+    }
+
+    /// Apply the delta. The embedded containers in the delta are created as new
+    /// child containers, and their old ids and new ids are passed to `on_container_remap`.
+    pub(crate) fn apply_delta_with_remap(
+        &self,
+        delta: &[TextDelta],
+        on_container_remap: &mut dyn FnMut(ContainerID, ContainerID),
+    ) -> LoroResult<()> {
+        match &self.inner {
+            MaybeDetached::Detached(_) => Err(LoroError::NotImplemented(
+                "`apply_delta` on a detached text container",
+            )),
+            MaybeDetached::Attached(a) => a.with_txn(|txn| {
+                self.apply_delta_with_txn_and_remap(txn, delta, on_container_remap)
+            }),
+        }
+    }
+
+    fn apply_delta_with_txn_and_remap(
+        &self,
+        txn: &mut Transaction,
+        delta: &[TextDelta],
+        on_container_remap: &mut dyn FnMut(ContainerID, ContainerID),
     ) -> LoroResult<()> {
         let mut index = 0;
         let mut marks = Vec::new();
@@ -2115,6 +2713,36 @@ impl TextHandler {
 
                     index = end;
                 }
+                TextDelta::Embed { insert, attributes } => {
+                    if !insert.is_container() {
+                        check_embed_value(insert)?;
+                    }
+                    let end = index + 1;
+                    let override_styles = self.insert_with_txn_and_attr(
+                        txn,
+                        index,
+                        &EMBED_CHAR.to_string(),
+                        Some(attributes.as_ref().unwrap_or(&Default::default())),
+                        PosType::Event,
+                    )?;
+
+                    for (key, value) in override_styles {
+                        marks.push((index, end, key, value));
+                    }
+
+                    match insert {
+                        LoroValue::Container(old_id) => {
+                            let child = self.mark_container_embed_with_txn(
+                                txn,
+                                index,
+                                Handler::new_unattached(old_id.container_type()),
+                            )?;
+                            on_container_remap(old_id.clone(), child.id());
+                        }
+                        _ => marks.push((index, end, EMBED_STYLE_KEY.into(), insert.clone())),
+                    }
+                    index = end;
+                }
                 TextDelta::Delete { delete } => {
                     self.delete_with_txn(txn, index, *delete)?;
                 }
@@ -2146,12 +2774,121 @@ impl TextHandler {
         Ok(())
     }
 
+    /// Find the non-overlapping occurrences of `pattern`, and return their Unicode ranges.
+    ///
+    /// The chunks of the text are scanned in place, only the tail of a chunk that may
+    /// be the start of a match across the chunk boundary is copied.
+    pub fn find(&self, pattern: &str) -> Vec<Range<usize>> {
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+
+        let pattern_len = pattern.chars().count();
+        let mut ans = Vec::new();
+        // The unscanned tail of the previous chunks and the Unicode index of its start
+        let mut window = String::new();
+        let mut window_start = 0;
+        self.iter(|chunk| {
+            window.push_str(chunk);
+            let mut index = window_start;
+            let mut last_end = 0;
+            for (start, _) in window.match_indices(pattern) {
+                index += window[last_end..start].chars().count();
+                ans.push(index..index + pattern_len);
+                index += pattern_len;
+                last_end = start + pattern.len();
+            }
+
+            // Keep the bytes that may still be the prefix of a match
+            let mut keep_from = window.len().saturating_sub(pattern.len() - 1).max(last_end);
+            while !window.is_char_boundary(keep_from) {
+                keep_from += 1;
+            }
+            window_start = index + window[last_end..keep_from].chars().count();
+            window.drain(..keep_from);
+            true
+        });
+
+        ans
+    }
+
+    /// Replace all the matches of the regex `pattern` with `replacement`, and return
+    /// the number of the matches.
+    ///
+    /// `replacement` can refer to the capture groups, like `$1` or `${name}`. All the
+    /// replacements of an attached text are done in one transaction, and only the chars
+    /// that differ from the matched text are deleted and inserted, so the marks on the
+    /// rest are kept.
+    ///
+    /// The text is scanned line by line from its chunks instead of being copied as a
+    /// whole, so a match never spans a `\n`, and `^` and `$` match at the line bounds.
+    #[cfg(feature = "regex")]
+    pub fn replace_all(&self, pattern: &str, replacement: &str) -> LoroResult<usize> {
+        let regex = regex::Regex::new(pattern)
+            .map_err(|e| LoroError::ArgErr(e.to_string().into_boxed_str()))?;
+        let mut count = 0;
+        // (utf8 pos, utf8 len to delete, str to insert)
+        let mut edits = Vec::new();
+        let mut line = String::new();
+        let mut line_start = 0;
+        self.iter(|chunk| {
+            let mut rest = chunk;
+            while let Some(i) = rest.find('\n') {
+                line.push_str(&rest[..i]);
+                count += regex_edits(&regex, replacement, &line, line_start, &mut edits);
+                line_start += line.len() + 1;
+                line.clear();
+                rest = &rest[i + 1..];
+            }
+            line.push_str(rest);
+            true
+        });
+        count += regex_edits(&regex, replacement, &line, line_start, &mut edits);
+
+        // Apply the edits from the end, so the positions of the others are not shifted
+        match &self.inner {
+            MaybeDetached::Detached(_) => {
+                for (pos, len, s) in edits.into_iter().rev() {
+                    if len > 0 {
+                        self.delete_utf8(pos, len)?;
+                    }
+                    if !s.is_empty() {
+                        self.insert_utf8(pos, &s)?;
+                    }
+                }
+            }
+            MaybeDetached::Attached(a) => a.with_txn(|txn| {
+                for (pos, len, s) in edits.iter().rev() {
+                    self.delete_with_txn_inline(txn, *pos, *len, PosType::Utf8)?;
+                    self.insert_with_txn_and_attr(txn, *pos, s, None, PosType::Utf8)?;
+                }
+                Ok(())
+            })?,
+        }
+
+        Ok(count)
+    }
+
     pub fn update(&self, text: &str, options: UpdateOptions) -> Result<(), UpdateTimeoutError> {
+        self.update_in(None, text, options)
+    }
+
+    /// Like [Self::update], but the ops are applied in `txn` if it's given
+    fn update_in(
+        &self,
+        txn: Option<&mut Transaction>,
+        text: &str,
+        options: UpdateOptions,
+    ) -> Result<(), UpdateTimeoutError> {
+        if options.granularity == DiffGranularity::Word {
+            return self.update_by_token(txn, text, options, text_update::split_words);
+        }
+
         let old_str = self.to_string();
         let new = text.chars().map(|x| x as u32).collect::<Vec<u32>>();
         let old = old_str.chars().map(|x| x as u32).collect::<Vec<u32>>();
         diff(
-            &mut OperateProxy::new(text_update::DiffHook::new(self, &new)),
+            &mut OperateProxy::new(text_update::DiffHook::new(self, txn, &new)),
             options,
             &old,
             &new,
@@ -2164,17 +2901,147 @@ impl TextHandler {
         text: &str,
         options: UpdateOptions,
     ) -> Result<(), UpdateTimeoutError> {
-        let hook = text_update::DiffHookForLine::new(self, text);
-        let old_lines = hook.get_old_arr().to_vec();
-        let new_lines = hook.get_new_arr().to_vec();
+        self.update_by_token(None, text, options, text_update::split_lines)
+    }
+
+    fn update_by_token(
+        &self,
+        txn: Option<&mut Transaction>,
+        text: &str,
+        options: UpdateOptions,
+        split: fn(&str) -> Vec<&str>,
+    ) -> Result<(), UpdateTimeoutError> {
+        let hook = text_update::DiffHookForToken::new(self, txn, text, split);
+        let old_tokens = hook.get_old_arr().to_vec();
+        let new_tokens = hook.get_new_arr().to_vec();
         diff(
             &mut OperateProxy::new(hook),
             options,
-            &old_lines,
-            &new_lines,
+            &old_tokens,
+            &new_tokens,
         )
     }
 
+    /// Update the text to the rich text of `delta`, which can only contain inserts.
+    ///
+    /// The text is updated by [Self::update] first. Then the styles are reconciled
+    /// with the minimal marks and unmarks, so the unchanged text keeps its ids and
+    /// styles, and the cursors of the collaborators are kept. All the ops are in the
+    /// same transaction.
+    pub fn update_with_marks(&self, delta: &[TextDelta], options: UpdateOptions) -> LoroResult<()> {
+        let mut text = String::new();
+        let mut target = Vec::with_capacity(delta.len());
+        for item in delta {
+            match item {
+                TextDelta::Insert { insert, attributes } => {
+                    let attributes = attributes.clone().unwrap_or_default();
+                    for key in attributes.keys() {
+                        check_style_key(key)?;
+                    }
+
+                    text.push_str(insert);
+                    target.push((event_len(insert), attributes));
+                }
+                TextDelta::Embed { insert, attributes } => {
+                    check_embed_value(insert)?;
+                    let mut attributes = attributes.clone().unwrap_or_default();
+                    for key in attributes.keys() {
+                        check_style_key(key)?;
+                    }
+
+                    attributes.insert(EMBED_STYLE_KEY.to_string(), insert.clone());
+                    text.push(EMBED_CHAR);
+                    target.push((1, attributes));
+                }
+                TextDelta::Retain { .. } | TextDelta::Delete { .. } => {
+                    return Err(LoroError::ArgErr(
+                        "The target delta of `update_with_marks` can only contain inserts".into(),
+                    ));
+                }
+            }
+        }
+
+        // The values of a non-exclusive style are marked separately. A detached
+        // text has no style config, so all of its styles are exclusive.
+        let config = match self.inner.try_attached_state() {
+            Ok(inner) => inner
+                .state
+                .upgrade()
+                .unwrap()
+                .try_lock()
+                .unwrap()
+                .config
+                .text_style_config
+                .try_read()
+                .unwrap()
+                .clone(),
+            Err(_) => StyleConfigMap::new(),
+        };
+        let target: Vec<_> = target
+            .into_iter()
+            .map(|(len, attributes)| (len, split_style_values(&config, attributes)))
+            .collect();
+
+        match &self.inner {
+            MaybeDetached::Detached(t) => {
+                self.update(&text, options)?;
+                for (start, end, key, value) in self.marks_to_styles(&config, &target)? {
+                    let is_delete = value.is_none();
+                    self.mark_for_detached(
+                        &mut t.try_lock().unwrap().value,
+                        key.key().clone(),
+                        &value.unwrap_or(LoroValue::Null),
+                        start,
+                        end,
+                        is_delete,
+                    )?;
+                }
+                Ok(())
+            }
+            // The text and the styles are updated in the same txn
+            MaybeDetached::Attached(a) => a.with_txn(|txn| {
+                self.update_in(Some(&mut *txn), &text, options)?;
+                for (start, end, key, value) in self.marks_to_styles(&config, &target)? {
+                    let is_delete = value.is_none();
+                    let value = match key {
+                        // Only the mark of this value is removed
+                        StyleKey::KeyWithValue { value: v, .. } if is_delete => v.clone(),
+                        _ => value.unwrap_or(LoroValue::Null),
+                    };
+                    self.mark_with_txn(txn, start, end, key.key().clone(), value, is_delete)?;
+                }
+                Ok(())
+            }),
+        }
+    }
+
+    /// Get the marks that turn the styles of the current text into `target`, whose
+    /// text should be the same as the current one.
+    fn marks_to_styles(
+        &self,
+        config: &StyleConfigMap,
+        target: &[(usize, FxHashMap<StyleKey, LoroValue>)],
+    ) -> LoroResult<Vec<(usize, usize, StyleKey, Option<LoroValue>)>> {
+        let current: Vec<_> = self
+            .slice_delta(0, self.len_event())?
+            .into_iter()
+            .map(|item| match item {
+                TextDelta::Insert { insert, attributes } => {
+                    (event_len(&insert), attributes.unwrap_or_default())
+                }
+                TextDelta::Embed { insert, attributes } => {
+                    let mut attributes = attributes.unwrap_or_default();
+                    attributes.insert(EMBED_STYLE_KEY.to_string(), insert);
+                    (1, attributes)
+                }
+                TextDelta::Retain { .. } | TextDelta::Delete { .. } => unreachable!(),
+            })
+            .map(|(len, attributes)| (len, split_style_values(config, attributes)))
+            .collect();
+
+        Ok(diff_styles(&current, target))
+    }
+
     #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         match &self.inner {
@@ -2187,6 +3054,19 @@ impl TextHandler {
         self.get_cursor_internal(event_index, side, true)
     }
 
+    /// Like [`Self::get_cursor`], but `pos` is of the given type.
+    ///
+    /// Return `None` if `pos` is out of bound or not at a char boundary.
+    pub fn get_cursor_with_pos_type(
+        &self,
+        pos: usize,
+        side: Side,
+        pos_type: richtext::PosType,
+    ) -> Option<Cursor> {
+        let event_index = self.convert_pos(pos, pos_type, richtext::PosType::Event)?;
+        self.get_cursor(event_index, side)
+    }
+
     /// Get the stable position representation for the target pos
     pub(crate) fn get_cursor_internal(
         &self,
@@ -2285,6 +3165,189 @@ impl TextHandler {
     }
 }
 
+/// Push the edits that replace the matches of `regex` in `line` to `edits`, and return
+/// the number of the matches. `offset` is the utf8 pos of `line` in the text.
+#[cfg(feature = "regex")]
+fn regex_edits(
+    regex: &regex::Regex,
+    replacement: &str,
+    line: &str,
+    offset: usize,
+    edits: &mut Vec<(usize, usize, String)>,
+) -> usize {
+    let mut count = 0;
+    for caps in regex.captures_iter(line) {
+        count += 1;
+        let matched = caps.get(0).unwrap();
+        let mut new = String::new();
+        caps.expand(replacement, &mut new);
+        let old = matched.as_str();
+        let prefix = common_prefix_len(old, &new);
+        let suffix = common_suffix_len(&old[prefix..], &new[prefix..]);
+        let delete_len = old.len() - prefix - suffix;
+        let insert = &new[prefix..new.len() - suffix];
+        if delete_len > 0 || !insert.is_empty() {
+            edits.push((
+                offset + matched.start() + prefix,
+                delete_len,
+                insert.to_string(),
+            ));
+        }
+    }
+
+    count
+}
+
+/// The utf8 length of the common prefix of the strings
+#[cfg(feature = "regex")]
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.chars()
+        .zip(b.chars())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x.len_utf8())
+        .sum()
+}
+
+/// The utf8 length of the common suffix of the strings
+#[cfg(feature = "regex")]
+fn common_suffix_len(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x.len_utf8())
+        .sum()
+}
+
+/// Get the marks that turn the styles of `current` into the styles of `target`.
+///
+/// Both of them are the lists of (event length, attributes) of the same text.
+/// It returns the list of (start, end, key, value), where `None` means unmark.
+/// The ranges of the same key and value are merged.
+fn diff_styles(
+    current: &[(usize, FxHashMap<StyleKey, LoroValue>)],
+    target: &[(usize, FxHashMap<StyleKey, LoroValue>)],
+) -> Vec<(usize, usize, StyleKey, Option<LoroValue>)> {
+    let get = |attributes: &FxHashMap<StyleKey, LoroValue>, key: &StyleKey| {
+        attributes.get(key).filter(|v| !v.is_null()).cloned()
+    };
+    let mut ans = Vec::new();
+    let mut pending: FxHashMap<StyleKey, (usize, usize, Option<LoroValue>)> = FxHashMap::default();
+    let (mut i, mut j) = (0, 0);
+    let (mut i_offset, mut j_offset) = (0, 0);
+    let mut pos = 0;
+    while i < current.len() && j < target.len() {
+        let (cur_len, cur) = &current[i];
+        let (target_len, target_attrs) = &target[j];
+        let len = (cur_len - i_offset).min(target_len - j_offset);
+        let (start, end) = (pos, pos + len);
+        // The empty items are skipped
+        let keys = cur.keys().chain(target_attrs.keys()).filter(|_| len > 0);
+        for key in keys {
+            let value = get(target_attrs, key);
+            if get(cur, key) == value {
+                continue;
+            }
+
+            match pending.get_mut(key) {
+                Some(p) if p.1 == start && p.2 == value => p.1 = end,
+                Some(p) if p.1 == end && p.2 == value => {}
+                _ => {
+                    if let Some((start, end, value)) =
+                        pending.insert(key.clone(), (start, end, value))
+                    {
+                        ans.push((start, end, key.clone(), value));
+                    }
+                }
+            }
+        }
+
+        pos = end;
+        i_offset += len;
+        j_offset += len;
+        if i_offset == *cur_len {
+            i += 1;
+            i_offset = 0;
+        }
+        if j_offset == *target_len {
+            j += 1;
+            j_offset = 0;
+        }
+    }
+
+    for (key, (start, end, value)) in pending {
+        ans.push((start, end, key, value));
+    }
+
+    // Unmark first, so the new values don't overlap the old ones of the styles
+    // that don't allow nesting
+    ans.sort_by(|a, b| {
+        (a.3.is_some(), a.0, a.2.key())
+            .cmp(&(b.3.is_some(), b.0, b.2.key()))
+            .then_with(|| a.1.cmp(&b.1))
+    });
+    ans
+}
+
+/// Get the attributes keyed by the style keys of the ops. The values of a
+/// non-exclusive style are split into separate keys.
+fn split_style_values(
+    config: &StyleConfigMap,
+    attributes: FxHashMap<String, LoroValue>,
+) -> FxHashMap<StyleKey, LoroValue> {
+    let mut ans = FxHashMap::default();
+    for (key, value) in attributes {
+        let key: InternalString = key.into();
+        let exclusive = config
+            .get_options_by_style_key(&key)
+            .map(|o| o.exclusive)
+            .unwrap_or(true);
+        if exclusive {
+            ans.insert(StyleKey::Key(key), value);
+            continue;
+        }
+
+        let values = match value {
+            LoroValue::Null => Vec::new(),
+            LoroValue::List(list) => list.to_vec(),
+            value => vec![value],
+        };
+        for value in values {
+            ans.insert(
+                StyleKey::KeyWithValue {
+                    key: key.clone(),
+                    value: value.clone(),
+                },
+                value,
+            );
+        }
+    }
+
+    ans
+}
+
+fn check_style_key(key: &str) -> LoroResult<()> {
+    if key == EMBED_STYLE_KEY {
+        return Err(LoroError::ArgErr(
+            format!("`{}` is reserved for the embeds", EMBED_STYLE_KEY).into_boxed_str(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn check_embed_value(value: &LoroValue) -> LoroResult<()> {
+    match value {
+        LoroValue::Null => Err(LoroError::ArgErr(
+            "The value of an embed cannot be null".into(),
+        )),
+        LoroValue::Container(_) => Err(LoroError::ArgErr(
+            "The value of an embed cannot be a container. Use `insert_container` instead".into(),
+        )),
+        _ => Ok(()),
+    }
+}
+
 fn event_len(s: &str) -> usize {
     if cfg!(feature = "wasm") {
         count_utf16_len(s.as_bytes())
@@ -3904,7 +4967,7 @@ pub mod counter {
             }
         }
 
-        fn increment_with_txn(&self, txn: &mut Transaction, n: f64) -> LoroResult<()> {
+        pub(crate) fn increment_with_txn(&self, txn: &mut Transaction, n: f64) -> LoroResult<()> {
             let inner = self.inner.try_attached_state()?;
             txn.apply_local_op(
                 inner.container_idx,
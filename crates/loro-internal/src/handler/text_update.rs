@@ -3,22 +3,49 @@ use std::{char, sync::Arc};
 use fxhash::FxHashMap;
 use itertools::Itertools;
 use tracing::trace;
+use unicode_segmentation::UnicodeSegmentation;
 
-use crate::diff::DiffHandler;
+use crate::{container::richtext::richtext_state::PosType, diff::DiffHandler, txn::Transaction};
 
 use super::TextHandler;
 
+/// Insert at the unicode position, in `txn` if it's given
+fn insert_unicode(text: &TextHandler, txn: Option<&mut Transaction>, pos: usize, s: &str) {
+    match txn {
+        Some(txn) => text
+            .insert_with_txn_and_attr(txn, pos, s, None, PosType::Unicode)
+            .map(drop),
+        None => text.insert_unicode(pos, s),
+    }
+    .unwrap();
+}
+
+/// Delete at the unicode position, in `txn` if it's given
+fn delete_unicode(text: &TextHandler, txn: Option<&mut Transaction>, pos: usize, len: usize) {
+    match txn {
+        Some(txn) => text.delete_with_txn_inline(txn, pos, len, PosType::Unicode),
+        None => text.delete_unicode(pos, len),
+    }
+    .unwrap();
+}
+
 pub(super) struct DiffHook<'a> {
     text: &'a TextHandler,
+    txn: Option<&'a mut Transaction>,
     new: &'a [u32],
     last_old_index: usize,
     current_index: usize,
 }
 
 impl<'a> DiffHook<'a> {
-    pub(crate) fn new(text: &'a TextHandler, new: &'a [u32]) -> Self {
+    pub(crate) fn new(
+        text: &'a TextHandler,
+        txn: Option<&'a mut Transaction>,
+        new: &'a [u32],
+    ) -> Self {
         Self {
             text,
+            txn,
             new,
             last_old_index: 0,
             current_index: 0,
@@ -34,30 +61,46 @@ impl DiffHandler for DiffHook<'_> {
             self.last_old_index = old_index;
         }
 
-        self.text
-            .insert_unicode(
-                self.current_index,
-                &self.new[new_index..new_index + new_len]
-                    .iter()
-                    .map(|x| char::from_u32(*x).unwrap())
-                    .collect::<String>(),
-            )
-            .unwrap();
+        insert_unicode(
+            self.text,
+            self.txn.as_deref_mut(),
+            self.current_index,
+            &self.new[new_index..new_index + new_len]
+                .iter()
+                .map(|x| char::from_u32(*x).unwrap())
+                .collect::<String>(),
+        );
         self.current_index += new_len;
     }
 
     fn delete(&mut self, old_index: usize, old_len: usize) {
         trace!("delete {old_index} {old_len}");
         self.current_index += old_index - self.last_old_index;
-        self.text
-            .delete_unicode(self.current_index, old_len)
-            .unwrap();
+        delete_unicode(
+            self.text,
+            self.txn.as_deref_mut(),
+            self.current_index,
+            old_len,
+        );
         self.last_old_index = old_index + old_len;
     }
 }
 
-pub(super) struct DiffHookForLine<'a> {
+/// Split the text into lines, each of which includes its line break
+pub(super) fn split_lines(s: &str) -> Vec<&str> {
+    s.split_inclusive('\n').collect()
+}
+
+/// Split the text by the Unicode word boundaries defined in
+/// [UAX #29](https://www.unicode.org/reports/tr29/#Word_Boundaries)
+pub(super) fn split_words(s: &str) -> Vec<&str> {
+    s.split_word_bounds().collect()
+}
+
+/// Apply the diff of the tokens, such as lines or words, to the text
+pub(super) struct DiffHookForToken<'a> {
     text: &'a TextHandler,
+    txn: Option<&'a mut Transaction>,
     old: Vec<u32>,
     new: Vec<u32>,
     lines: Vec<Arc<str>>,
@@ -67,10 +110,16 @@ pub(super) struct DiffHookForLine<'a> {
     current_index: usize,
 }
 
-impl<'a> DiffHookForLine<'a> {
-    pub(crate) fn new(text: &'a TextHandler, new_str: &str) -> Self {
+impl<'a> DiffHookForToken<'a> {
+    pub(crate) fn new(
+        text: &'a TextHandler,
+        txn: Option<&'a mut Transaction>,
+        new_str: &str,
+        split: fn(&str) -> Vec<&str>,
+    ) -> Self {
         let mut this = Self {
             text,
+            txn,
             old: Vec::new(),
             new: Vec::new(),
             lines: Vec::new(),
@@ -80,13 +129,13 @@ impl<'a> DiffHookForLine<'a> {
         };
 
         let text_str = text.to_string();
-        for line in text_str.split_inclusive('\n') {
+        for line in split(&text_str) {
             let line: Arc<str> = Arc::from(line);
             let id = this.register_line(line);
             this.old.push(id as u32);
         }
 
-        for line in new_str.split_inclusive('\n') {
+        for line in split(new_str) {
             let line: Arc<str> = Arc::from(line);
             let id = this.register_line(line);
             this.new.push(id as u32);
@@ -115,9 +164,9 @@ impl<'a> DiffHookForLine<'a> {
     }
 }
 
-impl DiffHandler for DiffHookForLine<'_> {
+impl DiffHandler for DiffHookForToken<'_> {
     fn insert(&mut self, old_index: usize, new_index: usize, new_len: usize) {
-        trace!("insert line {old_index} {new_index} {new_len}");
+        trace!("insert token {old_index} {new_index} {new_len}");
         if self.last_old_index < old_index {
             trace!(
                 "current_index: {} last_old_index: {} old_index: {old_index}",
@@ -136,12 +185,12 @@ impl DiffHandler for DiffHookForLine<'_> {
             .map(|x| self.lines[*x as usize].clone())
             .join("");
         trace!("insert at {} {:?}", self.current_index, &s);
-        self.text.insert_unicode(self.current_index, &s).unwrap();
+        insert_unicode(self.text, self.txn.as_deref_mut(), self.current_index, &s);
         self.current_index += s.chars().count();
     }
 
     fn delete(&mut self, old_index: usize, old_len: usize) {
-        trace!("delete line {old_index} {old_len}");
+        trace!("delete token {old_index} {old_len}");
         if self.last_old_index != old_index {
             assert!(self.last_old_index < old_index);
             self.current_index += (self.last_old_index..old_index)
@@ -155,8 +204,11 @@ impl DiffHandler for DiffHookForLine<'_> {
             .sum::<usize>();
 
         trace!("delete at {} with len {}", self.current_index, delete_len);
-        self.text
-            .delete_unicode(self.current_index, delete_len)
-            .unwrap();
+        delete_unicode(
+            self.text,
+            self.txn.as_deref_mut(),
+            self.current_index,
+            delete_len,
+        );
     }
 }
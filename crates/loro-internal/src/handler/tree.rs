@@ -510,7 +510,10 @@ impl TreeHandler {
 
         match self.generate_position_at(&target, &parent, index, cfg) {
             FractionalIndexGenResult::Ok(position) => {
-                self.create_with_position(inner, txn, target, parent, index, position)
+                let key_len = position.as_bytes().len();
+                self.create_with_position(inner, txn, target, parent, index, position)?;
+                self.rebalance_if_needed_with_txn(txn, parent, key_len)?;
+                Ok(target)
             }
             FractionalIndexGenResult::Rearrange(ids) => {
                 for (i, (id, position)) in ids.into_iter().enumerate() {
@@ -625,7 +628,9 @@ impl TreeHandler {
 
         match self.generate_position_at(&target, &parent, index, cfg) {
             FractionalIndexGenResult::Ok(position) => {
-                self.mov_with_position(inner, txn, target, parent, index, position, old_index)
+                let key_len = position.as_bytes().len();
+                self.mov_with_position(inner, txn, target, parent, index, position, old_index)?;
+                self.rebalance_if_needed_with_txn(txn, parent, key_len)
             }
             FractionalIndexGenResult::Rearrange(ids) => {
                 for (i, (id, position)) in ids.into_iter().enumerate() {
@@ -670,7 +675,7 @@ impl TreeHandler {
     }
 
     #[allow(clippy::too_many_arguments)]
-    fn mov_with_position(
+    pub(super) fn mov_with_position(
         &self,
         inner: &BasicHandler,
         txn: &mut Transaction,
@@ -931,7 +936,7 @@ impl TreeHandler {
         }
     }
 
-    fn delete_position(&self, parent: &TreeParentId, target: &TreeID) {
+    pub(super) fn delete_position(&self, parent: &TreeParentId, target: &TreeID) {
         let MaybeDetached::Attached(a) = &self.inner else {
             unreachable!()
         };
@@ -0,0 +1,219 @@
+use fractional_index::FractionalIndex;
+use loro_common::{LoroError, LoroResult, LoroTreeError, TreeID};
+
+use super::{MaybeDetached, TreeHandler};
+use crate::{
+    state::{NodePosition, TreeParentId},
+    txn::Transaction,
+};
+
+/// The distribution of the lengths of the position keys of the children under a parent.
+///
+/// The keys grow when nodes are repeatedly inserted between the same siblings.
+/// [TreeHandler::rebalance_positions] can be used to shrink them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreePositionKeyStats {
+    pub parent: TreeParentId,
+    /// The number of the children
+    pub children_num: usize,
+    /// The total length of the keys in bytes
+    pub total_len: usize,
+    /// The length of the longest key in bytes
+    pub max_len: usize,
+    /// `histogram[i]` is the number of the keys that are `i` bytes long
+    pub histogram: Vec<usize>,
+}
+
+impl TreePositionKeyStats {
+    fn new(parent: TreeParentId, positions: &[(TreeID, FractionalIndex)]) -> Self {
+        let mut histogram = vec![];
+        let mut total_len = 0;
+        for (_, position) in positions.iter() {
+            let len = position.as_bytes().len();
+            if histogram.len() <= len {
+                histogram.resize(len + 1, 0);
+            }
+            histogram[len] += 1;
+            total_len += len;
+        }
+
+        Self {
+            parent,
+            children_num: positions.len(),
+            total_len,
+            max_len: histogram.len().saturating_sub(1),
+            histogram,
+        }
+    }
+
+    /// The average length of the keys in bytes
+    pub fn mean_len(&self) -> f64 {
+        if self.children_num == 0 {
+            return 0.;
+        }
+
+        self.total_len as f64 / self.children_num as f64
+    }
+}
+
+impl TreeHandler {
+    /// Assign fresh evenly spaced position keys to the children of `parent` without
+    /// changing their order, and return the number of the moved nodes.
+    ///
+    /// The children whose keys don't change are not moved. All the moves are in
+    /// the same transaction.
+    ///
+    /// The moves compete with the concurrent edits of the siblings: a concurrent move
+    /// of a sibling to another parent may be overridden, and the siblings inserted
+    /// concurrently are positioned by the old keys.
+    pub fn rebalance_positions(&self, parent: TreeParentId) -> LoroResult<usize> {
+        let MaybeDetached::Attached(a) = &self.inner else {
+            return Err(LoroError::MisuseDetachedContainer {
+                method: "rebalance_positions",
+            });
+        };
+        a.with_txn(|txn| self.rebalance_positions_with_txn(txn, parent))
+    }
+
+    pub(crate) fn rebalance_positions_with_txn(
+        &self,
+        txn: &mut Transaction,
+        parent: TreeParentId,
+    ) -> LoroResult<usize> {
+        let inner = self.inner.try_attached_state()?;
+        match parent {
+            TreeParentId::Node(p) => {
+                if self.is_node_deleted(&p)? {
+                    return Err(LoroTreeError::TreeNodeDeletedOrNotExist(p).into());
+                }
+            }
+            TreeParentId::Root => {}
+            TreeParentId::Deleted | TreeParentId::Unexist => {
+                return Err(LoroTreeError::InvalidParent.into());
+            }
+        }
+        if !self.is_fractional_index_enabled() {
+            return Err(LoroTreeError::FractionalIndexNotEnabled.into());
+        }
+
+        let children = inner
+            .with_state(|state| {
+                let a = state.as_tree_state().unwrap();
+                a.get_children_positions(&parent)
+            })
+            .unwrap_or_default();
+        let positions = FractionalIndex::generate_n_evenly(None, None, children.len()).unwrap();
+        let mut moved = 0;
+        for ((target, old_position), position) in children.into_iter().zip(positions) {
+            if old_position == position {
+                continue;
+            }
+
+            // The other children may still have the old keys, so the index is
+            // calculated by the new key
+            let old_index = self.get_index_by_tree_id(&target).unwrap();
+            self.delete_position(&parent, &target);
+            let index = self
+                .get_index_by_fractional_index(
+                    &parent,
+                    &NodePosition {
+                        position: position.clone(),
+                        idlp: txn.next_idlp(),
+                    },
+                )
+                .unwrap_or(0);
+            self.mov_with_position(inner, txn, target, parent, index, position, old_index)?;
+            moved += 1;
+        }
+
+        Ok(moved)
+    }
+
+    /// Rebalance the children of `parent` if the key generated by a local op is
+    /// longer than the auto rebalance threshold.
+    pub(super) fn rebalance_if_needed_with_txn(
+        &self,
+        txn: &mut Transaction,
+        parent: TreeParentId,
+        key_len: usize,
+    ) -> LoroResult<()> {
+        if self
+            .auto_rebalance_threshold()
+            .is_some_and(|threshold| key_len > threshold)
+        {
+            self.rebalance_positions_with_txn(txn, parent)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the threshold of the automatic rebalancing.
+    pub fn auto_rebalance_threshold(&self) -> Option<usize> {
+        match &self.inner {
+            MaybeDetached::Detached(_) => None,
+            MaybeDetached::Attached(a) => {
+                a.with_doc_state(|state| state.config.tree_rebalance_threshold(&a.id))
+            }
+        }
+    }
+
+    /// Rebalance the positions of the siblings automatically when a local create or
+    /// move generates a position key longer than `threshold` bytes.
+    ///
+    /// The threshold should be larger than the keys of the evenly spaced children, which
+    /// are about `log256(children_num)` bytes long, otherwise every create or move rebalances
+    /// the siblings. It's disabled by default and can be disabled again by passing None.
+    ///
+    /// It's a local setting of the document, like the text style config. It's kept after
+    /// checkout and inherited by the forks, but it's not exported, so it needs to be set
+    /// again on the documents that import the snapshot.
+    ///
+    /// See [TreeHandler::rebalance_positions] for how the rebalancing competes with
+    /// the concurrent edits.
+    ///
+    /// Return [LoroError::MisuseDetachedContainer] if the tree is detached.
+    pub fn set_auto_rebalance_threshold(&self, threshold: Option<usize>) -> LoroResult<()> {
+        let MaybeDetached::Attached(a) = &self.inner else {
+            return Err(LoroError::MisuseDetachedContainer {
+                method: "set_auto_rebalance_threshold",
+            });
+        };
+        a.with_doc_state(|state| state.config.set_tree_rebalance_threshold(&a.id, threshold));
+        Ok(())
+    }
+
+    /// Get the distribution of the position key lengths of the children of `parent`.
+    ///
+    /// Return None if the parent has no children or the tree is detached.
+    pub fn get_position_key_stats(&self, parent: &TreeParentId) -> Option<TreePositionKeyStats> {
+        let MaybeDetached::Attached(a) = &self.inner else {
+            return None;
+        };
+
+        a.with_state(|state| {
+            let a = state.as_tree_state().unwrap();
+            a.get_children_positions(parent)
+        })
+        .filter(|x| !x.is_empty())
+        .map(|positions| TreePositionKeyStats::new(*parent, &positions))
+    }
+
+    /// Get the distribution of the position key lengths for each alive parent
+    /// that has children, including the root.
+    pub fn get_all_position_key_stats(&self) -> Vec<TreePositionKeyStats> {
+        let MaybeDetached::Attached(a) = &self.inner else {
+            return vec![];
+        };
+
+        a.with_state(|state| {
+            let a = state.as_tree_state().unwrap();
+            std::iter::once(TreeParentId::Root)
+                .chain(a.walk_dfs(TreeParentId::Root).map(TreeParentId::Node))
+                .filter_map(|parent| {
+                    let positions = a.get_children_positions(&parent)?;
+                    (!positions.is_empty()).then(|| TreePositionKeyStats::new(parent, &positions))
+                })
+                .collect()
+        })
+    }
+}
@@ -0,0 +1,446 @@
+use fxhash::FxHashMap;
+use loro_common::{ContainerType, LoroError, LoroResult, LoroTreeError, LoroValue, TreeID};
+
+use super::{create_handler, Handler, MaybeDetached, TextDelta, TreeHandler};
+use crate::{
+    container::richtext::EMBED_STYLE_KEY,
+    state::{FiIfNotConfigured, TreeNodeWithChildren, TreeParentId},
+    txn::Transaction,
+    BasicHandler, HandlerTrait, MapHandler,
+};
+
+/// The key of the type in an exported container
+const CONTAINER_KEY: &str = "$container";
+/// The key of the content in an exported container
+const CONTAINER_VALUE_KEY: &str = "value";
+/// The prefix of the keys that are reserved for the exported containers.
+///
+/// The keys of the user maps that start with it are escaped by doubling it.
+const RESERVED_PREFIX: char = '$';
+
+impl TreeHandler {
+    /// Create a deep copy of the `target` node and all its descendants under `parent`
+    /// at the given index, and return the id of the new node.
+    ///
+    /// The meta maps are copied along with the containers nested in them. All the
+    /// operations are in the same transaction.
+    pub fn duplicate_subtree(
+        &self,
+        target: TreeID,
+        parent: TreeParentId,
+        index: usize,
+    ) -> LoroResult<TreeID> {
+        let MaybeDetached::Attached(a) = &self.inner else {
+            return Err(LoroError::MisuseDetachedContainer {
+                method: "duplicate_subtree",
+            });
+        };
+        self.check_alive(target)?;
+        self.check_new_child_index(parent, index)?;
+        // Collect the nodes first, so the subtree can be duplicated into itself
+        let children = self.get_all_hierarchy_nodes_under(TreeParentId::Node(target));
+        a.with_txn(|txn| self.duplicate_node_with_txn(txn, a, target, children, parent, index))
+    }
+
+    /// Export the `target` node and all its descendants as a value, which can be
+    /// rebuilt by [TreeHandler::import_subtree].
+    ///
+    /// Each node is a map of `meta` and `children`, plus the `id` of the node for reference.
+    /// A container in the meta is exported as a map of its type at `$container` and its
+    /// content at `value`:
+    ///
+    /// - Text: the rich text delta
+    /// - Map / List / MovableList: the entries exported in the same way
+    /// - Tree: the exported root nodes
+    /// - Counter: the value
+    ///
+    /// The keys of the map values that start with `$` are escaped as `$$`, so a map
+    /// value can't be mistaken for an exported container.
+    pub fn export_subtree(&self, target: TreeID) -> LoroResult<LoroValue> {
+        if !self.is_attached() {
+            return Err(LoroError::MisuseDetachedContainer {
+                method: "export_subtree",
+            });
+        }
+
+        self.check_alive(target)?;
+        let children = self.get_all_hierarchy_nodes_under(TreeParentId::Node(target));
+        Ok(self.export_node(target, children))
+    }
+
+    /// Rebuild the nodes exported by [TreeHandler::export_subtree] as the last child
+    /// of `parent`, and return the id of the new root node.
+    ///
+    /// All the operations are in the same transaction.
+    pub fn import_subtree(&self, value: &LoroValue, parent: TreeParentId) -> LoroResult<TreeID> {
+        let MaybeDetached::Attached(a) = &self.inner else {
+            return Err(LoroError::MisuseDetachedContainer {
+                method: "import_subtree",
+            });
+        };
+        let index = self.children_num(&parent).unwrap_or(0);
+        self.check_new_child_index(parent, index)?;
+        a.with_txn(|txn| self.import_node_with_txn(txn, value, parent, index))
+    }
+
+    fn check_alive(&self, target: TreeID) -> LoroResult<()> {
+        if self.is_node_deleted(&target)? {
+            return Err(LoroTreeError::TreeNodeDeletedOrNotExist(target).into());
+        }
+
+        Ok(())
+    }
+
+    fn check_new_child_index(&self, parent: TreeParentId, index: usize) -> LoroResult<()> {
+        match parent {
+            TreeParentId::Node(p) => self.check_alive(p)?,
+            TreeParentId::Root => {}
+            TreeParentId::Deleted | TreeParentId::Unexist => {
+                return Err(LoroTreeError::InvalidParent.into());
+            }
+        }
+
+        let len = self.children_num(&parent).unwrap_or(0);
+        if index > len {
+            return Err(LoroTreeError::IndexOutOfBound { len, index }.into());
+        }
+
+        Ok(())
+    }
+
+    fn duplicate_node_with_txn(
+        &self,
+        txn: &mut Transaction,
+        a: &BasicHandler,
+        source: TreeID,
+        children: Vec<TreeNodeWithChildren>,
+        parent: TreeParentId,
+        index: usize,
+    ) -> LoroResult<TreeID> {
+        let id = self.create_with_txn(txn, parent, index, FiIfNotConfigured::Zero)?;
+        self.get_meta(source)?
+            .attach(txn, a, id.associated_meta_container())?;
+        for (i, child) in children.into_iter().enumerate() {
+            self.duplicate_node_with_txn(
+                txn,
+                a,
+                child.id,
+                child.children,
+                TreeParentId::Node(id),
+                i,
+            )?;
+        }
+
+        Ok(id)
+    }
+
+    fn export_node(&self, id: TreeID, children: Vec<TreeNodeWithChildren>) -> LoroValue {
+        let mut node = FxHashMap::default();
+        node.insert("id".to_string(), id.to_string().into());
+        node.insert("meta".to_string(), export_map(&self.get_meta(id).unwrap()));
+        node.insert(
+            "children".to_string(),
+            children
+                .into_iter()
+                .map(|child| self.export_node(child.id, child.children))
+                .collect::<Vec<_>>()
+                .into(),
+        );
+        node.into()
+    }
+
+    fn import_node_with_txn(
+        &self,
+        txn: &mut Transaction,
+        value: &LoroValue,
+        parent: TreeParentId,
+        index: usize,
+    ) -> LoroResult<TreeID> {
+        let node = value
+            .as_map()
+            .ok_or_else(|| invalid_value("a tree node should be a map"))?;
+        let id = self.create_with_txn(txn, parent, index, FiIfNotConfigured::Zero)?;
+        match node.get("meta") {
+            Some(LoroValue::Map(meta)) => {
+                let map = self.get_meta(id)?;
+                for (key, value) in meta.iter() {
+                    import_map_entry(txn, &map, key, value)?;
+                }
+            }
+            None | Some(LoroValue::Null) => {}
+            Some(_) => return Err(invalid_value("the meta of a tree node should be a map")),
+        }
+
+        match node.get("children") {
+            Some(LoroValue::List(children)) => {
+                for (i, child) in children.iter().enumerate() {
+                    self.import_node_with_txn(txn, child, TreeParentId::Node(id), i)?;
+                }
+            }
+            None | Some(LoroValue::Null) => {}
+            Some(_) => {
+                return Err(invalid_value(
+                    "the children of a tree node should be a list",
+                ))
+            }
+        }
+
+        Ok(id)
+    }
+}
+
+fn invalid_value(reason: &str) -> LoroError {
+    LoroError::ArgErr(format!("Invalid subtree value: {}", reason).into_boxed_str())
+}
+
+fn export_value(parent: &BasicHandler, value: LoroValue) -> LoroValue {
+    match value {
+        LoroValue::Container(id) => export_container(&create_handler(parent, id)),
+        value => escape_value(value),
+    }
+}
+
+/// Escape the keys starting with `$` in the maps of a value, recursively
+fn escape_value(value: LoroValue) -> LoroValue {
+    match value {
+        LoroValue::Map(map) => map
+            .iter()
+            .map(|(key, value)| {
+                let key = if key.starts_with(RESERVED_PREFIX) {
+                    format!("{}{}", RESERVED_PREFIX, key)
+                } else {
+                    key.clone()
+                };
+                (key, escape_value(value.clone()))
+            })
+            .collect::<FxHashMap<_, _>>()
+            .into(),
+        LoroValue::List(list) => list
+            .iter()
+            .map(|value| escape_value(value.clone()))
+            .collect::<Vec<_>>()
+            .into(),
+        value => value,
+    }
+}
+
+/// The reverse of [escape_value]. The unescaped keys starting with `$` are invalid.
+fn unescape_value(value: &LoroValue) -> LoroResult<LoroValue> {
+    match value {
+        LoroValue::Map(map) => Ok(map
+            .iter()
+            .map(|(key, value)| {
+                let key = match key.strip_prefix(RESERVED_PREFIX) {
+                    Some(rest) if rest.starts_with(RESERVED_PREFIX) => rest.to_string(),
+                    Some(_) => {
+                        return Err(invalid_value(&format!(
+                            "the map key `{}` should be escaped as `${}`",
+                            key, key
+                        )))
+                    }
+                    None => key.clone(),
+                };
+                Ok((key, unescape_value(value)?))
+            })
+            .collect::<LoroResult<FxHashMap<_, _>>>()?
+            .into()),
+        LoroValue::List(list) => Ok(list
+            .iter()
+            .map(unescape_value)
+            .collect::<LoroResult<Vec<_>>>()?
+            .into()),
+        value => Ok(value.clone()),
+    }
+}
+
+fn export_map(map: &MapHandler) -> LoroValue {
+    let a = map.attached_handler().unwrap();
+    let mut ans = FxHashMap::default();
+    for (key, value) in map.get_value().into_map().unwrap().iter() {
+        ans.insert(key.clone(), export_value(a, value.clone()));
+    }
+    ans.into()
+}
+
+fn export_container(handler: &Handler) -> LoroValue {
+    let value = match handler {
+        Handler::Text(text) => text.get_richtext_value(),
+        Handler::Map(map) => export_map(map),
+        Handler::List(_) | Handler::MovableList(_) => {
+            let a = handler.attached_handler().unwrap();
+            handler
+                .get_value()
+                .into_list()
+                .unwrap()
+                .iter()
+                .map(|value| export_value(a, value.clone()))
+                .collect::<Vec<_>>()
+                .into()
+        }
+        Handler::Tree(tree) => tree
+            .roots()
+            .into_iter()
+            .map(|root| {
+                let children = tree.get_all_hierarchy_nodes_under(TreeParentId::Node(root));
+                tree.export_node(root, children)
+            })
+            .collect::<Vec<_>>()
+            .into(),
+        #[cfg(feature = "counter")]
+        Handler::Counter(counter) => counter.get_value(),
+        Handler::Unknown(_) => LoroValue::Null,
+    };
+
+    let mut ans = FxHashMap::default();
+    ans.insert(CONTAINER_KEY.to_string(), handler.kind().to_string().into());
+    ans.insert(CONTAINER_VALUE_KEY.to_string(), value);
+    ans.into()
+}
+
+/// Return the type and the content if the value is an exported container.
+///
+/// The map values have their `$` keys escaped, so a map with the `$container` key
+/// must be an exported container.
+fn as_exported_container(value: &LoroValue) -> LoroResult<Option<(ContainerType, &LoroValue)>> {
+    let Some(map) = value.as_map() else {
+        return Ok(None);
+    };
+    let Some(kind) = map.get(CONTAINER_KEY) else {
+        return Ok(None);
+    };
+    let (LoroValue::String(kind), Some(content), 2) =
+        (kind, map.get(CONTAINER_VALUE_KEY), map.len())
+    else {
+        return Err(invalid_value(
+            "an exported container should be `{\"$container\": type, \"value\": content}`",
+        ));
+    };
+
+    match ContainerType::try_from(kind.as_str())? {
+        ContainerType::Unknown(_) => Err(invalid_value("unknown container type")),
+        kind => Ok(Some((kind, content))),
+    }
+}
+
+fn import_map_entry(
+    txn: &mut Transaction,
+    map: &MapHandler,
+    key: &str,
+    value: &LoroValue,
+) -> LoroResult<()> {
+    match as_exported_container(value)? {
+        Some((kind, content)) => {
+            let child = map.insert_container_with_txn(txn, key, Handler::new_unattached(kind))?;
+            import_container(txn, &child, content)
+        }
+        None => map.insert_with_txn(txn, key, unescape_value(value)?),
+    }
+}
+
+fn import_container(
+    txn: &mut Transaction,
+    handler: &Handler,
+    content: &LoroValue,
+) -> LoroResult<()> {
+    match handler {
+        Handler::Text(text) => text.apply_delta_with_txn(txn, &text_delta_from_value(content)?),
+        Handler::Map(map) => {
+            let content = content
+                .as_map()
+                .ok_or_else(|| invalid_value("the content of a map should be a map"))?;
+            for (key, value) in content.iter() {
+                import_map_entry(txn, map, key, value)?;
+            }
+            Ok(())
+        }
+        Handler::List(list) => {
+            let content = content
+                .as_list()
+                .ok_or_else(|| invalid_value("the content of a list should be a list"))?;
+            for (i, value) in content.iter().enumerate() {
+                match as_exported_container(value)? {
+                    Some((kind, content)) => {
+                        let child =
+                            list.insert_container_with_txn(txn, i, Handler::new_unattached(kind))?;
+                        import_container(txn, &child, content)?;
+                    }
+                    None => list.insert_with_txn(txn, i, unescape_value(value)?)?,
+                }
+            }
+            Ok(())
+        }
+        Handler::MovableList(list) => {
+            let content = content
+                .as_list()
+                .ok_or_else(|| invalid_value("the content of a list should be a list"))?;
+            for (i, value) in content.iter().enumerate() {
+                match as_exported_container(value)? {
+                    Some((kind, content)) => {
+                        let child =
+                            list.insert_container_with_txn(txn, i, Handler::new_unattached(kind))?;
+                        import_container(txn, &child, content)?;
+                    }
+                    None => list.insert_with_txn(txn, i, unescape_value(value)?)?,
+                }
+            }
+            Ok(())
+        }
+        Handler::Tree(tree) => {
+            let content = content
+                .as_list()
+                .ok_or_else(|| invalid_value("the content of a tree should be a list"))?;
+            for (i, node) in content.iter().enumerate() {
+                tree.import_node_with_txn(txn, node, TreeParentId::Root, i)?;
+            }
+            Ok(())
+        }
+        #[cfg(feature = "counter")]
+        Handler::Counter(counter) => {
+            let n = content
+                .as_double()
+                .ok_or_else(|| invalid_value("the content of a counter should be a number"))?;
+            counter.increment_with_txn(txn, *n)
+        }
+        Handler::Unknown(_) => unreachable!(),
+    }
+}
+
+/// Convert the value of [crate::handler::TextHandler::get_richtext_value] into a delta
+fn text_delta_from_value(value: &LoroValue) -> LoroResult<Vec<TextDelta>> {
+    let items = value
+        .as_list()
+        .ok_or_else(|| invalid_value("the content of a text should be a list"))?;
+    items
+        .iter()
+        .map(|item| {
+            let item = item
+                .as_map()
+                .ok_or_else(|| invalid_value("a text delta item should be a map"))?;
+            let attributes = match item.get("attributes") {
+                Some(LoroValue::Map(attributes)) => Some(
+                    attributes
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect(),
+                ),
+                None | Some(LoroValue::Null) => None,
+                Some(_) => return Err(invalid_value("the attributes should be a map")),
+            };
+            match item.get("insert") {
+                Some(LoroValue::String(s)) => Ok(TextDelta::Insert {
+                    insert: s.to_string(),
+                    attributes,
+                }),
+                Some(LoroValue::Map(embed)) => match embed.get(EMBED_STYLE_KEY) {
+                    Some(value) if embed.len() == 1 => Ok(TextDelta::Embed {
+                        insert: value.clone(),
+                        attributes,
+                    }),
+                    _ => Err(invalid_value("an embed should be `{\"$embed\": value}`")),
+                },
+                Some(_) => Err(invalid_value("the insert should be a string or an embed")),
+                None => Err(invalid_value("a text delta item should be an insert")),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+
+use loro_common::TreeID;
+
+use super::{MaybeDetached, TreeHandler};
+use crate::state::{TreeParentId, TreeState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WalkOrder {
+    DepthFirst,
+    BreadthFirst,
+}
+
+impl WalkOrder {
+    /// Add the nodes to visit, which are visited in the given order
+    fn push(self, pending: &mut VecDeque<TreeID>, nodes: impl DoubleEndedIterator<Item = TreeID>) {
+        match self {
+            WalkOrder::DepthFirst => pending.extend(nodes.rev()),
+            WalkOrder::BreadthFirst => pending.extend(nodes),
+        }
+    }
+
+    fn pop(self, pending: &mut VecDeque<TreeID>) -> Option<TreeID> {
+        match self {
+            WalkOrder::DepthFirst => pending.pop_back(),
+            WalkOrder::BreadthFirst => pending.pop_front(),
+        }
+    }
+}
+
+/// A lazy walk over the nodes of a tree, created by [TreeHandler::walk_dfs] or
+/// [TreeHandler::walk_bfs].
+///
+/// It only holds the nodes to visit. The children of a node are read from the tree
+/// state when the node is visited, locking the state once per step. If the tree is
+/// changed during the walk, the rest of the walk follows the new structure.
+#[derive(Debug, Clone)]
+pub struct TreeWalk {
+    tree: TreeHandler,
+    order: WalkOrder,
+    /// The stack of the depth-first walk, or the queue of the breadth-first walk
+    pending: VecDeque<TreeID>,
+    /// Whether to walk the deleted nodes after the alive nodes are exhausted
+    then_deleted: bool,
+}
+
+impl TreeWalk {
+    fn new(tree: &TreeHandler, order: WalkOrder, start: TreeParentId, with_deleted: bool) -> Self {
+        let mut walk = Self {
+            tree: tree.clone(),
+            order,
+            pending: VecDeque::new(),
+            then_deleted: with_deleted && start == TreeParentId::Root,
+        };
+        tree.with_tree_state(|state| {
+            let start_deleted = match start {
+                TreeParentId::Node(id) => state.is_node_deleted(&id).unwrap_or(false),
+                TreeParentId::Deleted => true,
+                TreeParentId::Root | TreeParentId::Unexist => false,
+            };
+            // The descendants of an alive node are all alive
+            if !start_deleted || with_deleted {
+                order.push(&mut walk.pending, state.walk_start(start).into_iter());
+            }
+        });
+        walk
+    }
+}
+
+impl Iterator for TreeWalk {
+    type Item = TreeID;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Self {
+            tree,
+            order,
+            pending,
+            then_deleted,
+        } = self;
+        tree.with_tree_state(|state| loop {
+            let Some(id) = order.pop(pending) else {
+                if !std::mem::take(then_deleted) {
+                    return None;
+                }
+
+                order.push(pending, state.walk_start(TreeParentId::Deleted).into_iter());
+                continue;
+            };
+            // The node may be gone if the document is checked out during the walk
+            if state.is_node_unexist(&id) {
+                continue;
+            }
+
+            if let Some(children) = state.get_children(&TreeParentId::Node(id)) {
+                order.push(pending, children.collect::<Vec<_>>().into_iter());
+            }
+            return Some(id);
+        })
+        .flatten()
+    }
+}
+
+impl TreeHandler {
+    /// Walk the nodes under `start` in depth-first pre-order. If `start` is a node,
+    /// it's the first visited node.
+    ///
+    /// The deleted nodes are skipped unless `with_deleted` is true, in which case
+    /// walking from the root also visits the deleted subtrees after the alive ones.
+    ///
+    /// The walk is lazy. See [TreeWalk] for how it reads the tree state.
+    /// It's empty if the tree is detached.
+    pub fn walk_dfs(&self, start: TreeParentId, with_deleted: bool) -> TreeWalk {
+        TreeWalk::new(self, WalkOrder::DepthFirst, start, with_deleted)
+    }
+
+    /// Walk the nodes under `start` in breadth-first order. If `start` is a node,
+    /// it's the first visited node.
+    ///
+    /// See [TreeHandler::walk_dfs] for how the deleted nodes are handled.
+    pub fn walk_bfs(&self, start: TreeParentId, with_deleted: bool) -> TreeWalk {
+        TreeWalk::new(self, WalkOrder::BreadthFirst, start, with_deleted)
+    }
+
+    /// Get the ancestors of the node, ordered from its parent to the root of its tree.
+    ///
+    /// Return None if the node does not exist or the tree is detached.
+    pub fn ancestors(&self, target: &TreeID) -> Option<Vec<TreeID>> {
+        self.with_tree_state(|state| {
+            if state.is_node_unexist(target) {
+                return None;
+            }
+
+            Some(state.ancestors(target).collect())
+        })
+        .flatten()
+    }
+
+    /// Get the depth of the node, which is 0 for the root nodes.
+    ///
+    /// Return None if the node does not exist or the tree is detached.
+    pub fn depth(&self, target: &TreeID) -> Option<usize> {
+        self.with_tree_state(|state| state.depth(target)).flatten()
+    }
+
+    /// Whether `maybe_ancestor` is a strict ancestor of `node`.
+    pub fn is_ancestor_of(&self, maybe_ancestor: &TreeID, node: &TreeID) -> bool {
+        maybe_ancestor != node
+            && self
+                .with_tree_state(|state| state.ancestors(node).any(|x| &x == maybe_ancestor))
+                .unwrap_or(false)
+    }
+
+    /// Get the lowest common ancestor of the two nodes. A node is regarded as the
+    /// ancestor of itself.
+    ///
+    /// Return None if they are not in the same tree, any of them does not exist
+    /// or the tree is detached.
+    pub fn lowest_common_ancestor(&self, a: &TreeID, b: &TreeID) -> Option<TreeID> {
+        self.with_tree_state(|state| state.lowest_common_ancestor(a, b))
+            .flatten()
+    }
+
+    fn with_tree_state<R>(&self, f: impl FnOnce(&TreeState) -> R) -> Option<R> {
+        match &self.inner {
+            MaybeDetached::Detached(_) => None,
+            MaybeDetached::Attached(a) => {
+                Some(a.with_state(|state| f(state.as_tree_state().unwrap())))
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use fractional_index::FractionalIndex;
+use fxhash::FxHashMap;
+use loro_common::{LoroError, LoroResult, LoroTreeError, LoroValue, TreeID};
+
+use super::{MaybeDetached, TreeHandler};
+use crate::{
+    state::{TreeNodeWithChildren, TreeParentId},
+    HandlerTrait,
+};
+
+/// The options of [TreeHandler::get_value_with_meta_filtered].
+///
+/// The default options return the whole forest with every meta key, the same as
+/// the deep value of the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeValueOptions {
+    /// The parent whose children are returned
+    pub root: TreeParentId,
+    /// The number of the levels below `root` to include. `Some(1)` returns only the
+    /// children of `root`, with empty `children`. None means no limit.
+    pub max_depth: Option<usize>,
+    /// The meta keys to include. The missing keys are skipped. None means all the keys.
+    pub meta_keys: Option<Vec<String>>,
+    /// The index of the first returned child of `root`
+    pub offset: usize,
+    /// The max number of the returned children of `root`. None means no limit.
+    ///
+    /// The window only applies to the children of `root`. The deeper levels are
+    /// bounded by `max_depth`, and a branch can be paginated by passing it as `root`.
+    pub limit: Option<usize>,
+}
+
+impl Default for TreeValueOptions {
+    fn default() -> Self {
+        Self {
+            root: TreeParentId::Root,
+            max_depth: None,
+            meta_keys: None,
+            offset: 0,
+            limit: None,
+        }
+    }
+}
+
+impl TreeHandler {
+    /// Return the hierarchy array of the children of `options.root` with their metadata,
+    /// limited by the depth, the meta keys and the child window in `options`.
+    ///
+    /// Each node has the same keys as the nodes in the deep value of the tree, plus
+    /// `children_num`, the number of all its children, so that the unloaded branches
+    /// and pages can be told apart from the empty ones.
+    pub fn get_value_with_meta_filtered(
+        &self,
+        options: &TreeValueOptions,
+    ) -> LoroResult<LoroValue> {
+        if let MaybeDetached::Detached(_) = &self.inner {
+            return Err(LoroError::MisuseDetachedContainer {
+                method: "get_value_with_meta_filtered",
+            });
+        }
+        match options.root {
+            TreeParentId::Node(p) => {
+                if self.is_node_deleted(&p)? {
+                    return Err(LoroTreeError::TreeNodeDeletedOrNotExist(p).into());
+                }
+            }
+            TreeParentId::Root => {}
+            TreeParentId::Deleted | TreeParentId::Unexist => {
+                return Err(LoroTreeError::InvalidParent.into());
+            }
+        }
+
+        if options.max_depth == Some(0) {
+            return Ok(Vec::<LoroValue>::new().into());
+        }
+
+        let parent = options.root;
+        let len = self.children_num(&parent).unwrap_or(0);
+        let end = match options.limit {
+            Some(limit) => len.min(options.offset.saturating_add(limit)),
+            None => len,
+        };
+        let ans = (options.offset..end)
+            .filter_map(|index| {
+                let id = self.get_child_at(&parent, index)?;
+                Some(self.node_value(id, parent, index, 1, options))
+            })
+            .collect::<Vec<_>>();
+        Ok(ans.into())
+    }
+
+    fn node_value(
+        &self,
+        id: TreeID,
+        parent: TreeParentId,
+        index: usize,
+        depth: usize,
+        options: &TreeValueOptions,
+    ) -> LoroValue {
+        let node_parent = TreeParentId::Node(id);
+        let position = self.get_position_by_tree_id(&id);
+        let Some(max_depth) = options.max_depth else {
+            // The whole branch is needed, so read the hierarchy at once
+            let children = self.get_all_hierarchy_nodes_under(node_parent);
+            let children_num = children.len();
+            let children = children
+                .into_iter()
+                .map(|child| self.hierarchy_value(child, options))
+                .collect();
+            return self.build_node_value(
+                id,
+                parent,
+                index,
+                position,
+                children_num,
+                children,
+                options,
+            );
+        };
+
+        let children_num = self.children_num(&node_parent).unwrap_or(0);
+        let children = if depth >= max_depth {
+            vec![]
+        } else {
+            self.children(&node_parent)
+                .unwrap_or_default()
+                .into_iter()
+                .enumerate()
+                .map(|(i, child)| self.node_value(child, node_parent, i, depth + 1, options))
+                .collect()
+        };
+        self.build_node_value(id, parent, index, position, children_num, children, options)
+    }
+
+    fn hierarchy_value(&self, node: TreeNodeWithChildren, options: &TreeValueOptions) -> LoroValue {
+        let children_num = node.children.len();
+        let children = node
+            .children
+            .into_iter()
+            .map(|child| self.hierarchy_value(child, options))
+            .collect();
+        self.build_node_value(
+            node.id,
+            node.parent,
+            node.index,
+            Some(node.fractional_index),
+            children_num,
+            children,
+            options,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build_node_value(
+        &self,
+        id: TreeID,
+        parent: TreeParentId,
+        index: usize,
+        position: Option<FractionalIndex>,
+        children_num: usize,
+        children: Vec<LoroValue>,
+        options: &TreeValueOptions,
+    ) -> LoroValue {
+        let mut t = FxHashMap::default();
+        t.insert("id".to_string(), id.to_string().into());
+        let p = parent
+            .tree_id()
+            .map(|p| p.to_string().into())
+            .unwrap_or(LoroValue::Null);
+        t.insert("parent".to_string(), p);
+        t.insert("meta".to_string(), self.meta_value(id, options));
+        t.insert("index".to_string(), (index as i64).into());
+        t.insert(
+            "fractional_index".to_string(),
+            position
+                .map(|x| x.to_string().into())
+                .unwrap_or(LoroValue::Null),
+        );
+        t.insert("children_num".to_string(), (children_num as i64).into());
+        t.insert("children".to_string(), children.into());
+        t.into()
+    }
+
+    fn meta_value(&self, id: TreeID, options: &TreeValueOptions) -> LoroValue {
+        let meta = self.get_meta(id).unwrap();
+        let Some(keys) = &options.meta_keys else {
+            return meta.get_deep_value();
+        };
+
+        let mut ans = FxHashMap::default();
+        for key in keys {
+            if let Some(value) = meta.get_(key) {
+                ans.insert(key.clone(), value.to_deep_value());
+            }
+        }
+        ans.into()
+    }
+}
@@ -258,6 +258,7 @@ impl ContainerHistoryCache {
                                             position: node.fractional_index.clone(),
                                         }),
                                         effected: true,
+                                        rejected: None,
                                     })
                                     .collect(),
                             );
@@ -0,0 +1,223 @@
+//! Hooks that decide whether the imported changes are accepted.
+//!
+//! A filter set by [LoroDoc::set_import_filter] is called with every decoded
+//! change before it's added to the oplog. A rejected change is dropped together
+//! with all the imported changes that depend on it, including the later changes
+//! of the same peer, and reported in [crate::encoding::ImportStatus::rejected].
+//! The rejections are remembered, so the changes depending on a rejected change
+//! are rejected in the later imports too.
+use fxhash::FxHashMap;
+use loro_common::{ContainerID, Counter, IdSpan, PeerID, ID};
+
+use crate::{
+    change::Change,
+    container::{list::list_op::InnerListOp, map::MapSet, tree::tree_op::TreeOp},
+    op::{FutureInnerContent, InnerContent, Op},
+    version::VersionRange,
+    ChangeMeta, InternalString, LoroDoc, OpLog,
+};
+
+/// The filter of the imported changes. See [LoroDoc::set_import_filter].
+pub type ImportFilter = Box<dyn Fn(&ChangeMeta, &[OpRef]) -> Decision + Send + Sync + 'static>;
+
+/// Whether an imported change is accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Accept,
+    /// Reject the change and the changes that depend on it
+    Reject,
+}
+
+/// An op of an imported change
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpRef {
+    /// The id of the first atom of the op
+    pub id: ID,
+    /// The container changed by the op
+    pub container: ContainerID,
+    pub kind: OpKind,
+}
+
+/// The kind of an op
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpKind {
+    /// Insert items into a list or text into a text container
+    Insert,
+    /// Delete items of a list or a range of text
+    Delete,
+    /// Move an item of a movable list
+    Move,
+    /// Set an item of a movable list
+    Set,
+    /// Mark or unmark a style on a range of text
+    Mark,
+    /// Set a key of a map
+    MapSet {
+        key: InternalString,
+    },
+    /// Delete a key of a map
+    MapDelete {
+        key: InternalString,
+    },
+    TreeCreate,
+    TreeMove,
+    TreeDelete,
+    #[cfg(feature = "counter")]
+    Increment,
+    /// An op of a container type that is unknown to this version
+    Unknown,
+}
+
+impl OpKind {
+    fn from_content(content: &InnerContent) -> Self {
+        match content {
+            InnerContent::List(list) => match list {
+                InnerListOp::Insert { .. } | InnerListOp::InsertText { .. } => OpKind::Insert,
+                InnerListOp::Delete(_) => OpKind::Delete,
+                InnerListOp::Move { .. } => OpKind::Move,
+                InnerListOp::Set { .. } => OpKind::Set,
+                InnerListOp::StyleStart { .. } | InnerListOp::StyleEnd => OpKind::Mark,
+            },
+            InnerContent::Map(MapSet { key, value }) => match value {
+                Some(_) => OpKind::MapSet { key: key.clone() },
+                None => OpKind::MapDelete { key: key.clone() },
+            },
+            InnerContent::Tree(tree) => match &**tree {
+                TreeOp::Create { .. } => OpKind::TreeCreate,
+                TreeOp::Move { .. } => OpKind::TreeMove,
+                TreeOp::Delete { .. } => OpKind::TreeDelete,
+            },
+            #[cfg(feature = "counter")]
+            InnerContent::Future(FutureInnerContent::Counter(_)) => OpKind::Increment,
+            InnerContent::Future(FutureInnerContent::Unknown { .. }) => OpKind::Unknown,
+        }
+    }
+}
+
+impl OpRef {
+    /// It returns `None` if the container of the op is not registered
+    fn new(oplog: &OpLog, peer: PeerID, op: &Op) -> Option<Self> {
+        Some(Self {
+            id: ID::new(peer, op.counter),
+            container: oplog.arena.idx_to_id(op.container)?,
+            kind: OpKind::from_content(&op.content),
+        })
+    }
+}
+
+/// Whether the filter rejects the change.
+///
+/// A change with an op on an unknown container is rejected without calling
+/// the filter, because the op cannot be described to it.
+pub(crate) fn is_rejected_by(oplog: &OpLog, filter: &ImportFilter, change: &Change) -> bool {
+    let ops: Option<Vec<OpRef>> = change
+        .ops
+        .iter()
+        .map(|op| OpRef::new(oplog, change.id.peer, op))
+        .collect();
+    match ops {
+        Some(ops) => filter(&ChangeMeta::from_change(change), &ops) == Decision::Reject,
+        None => true,
+    }
+}
+
+/// Remove the changes for which `should_reject` returns true and the changes
+/// that depend on them.
+///
+/// `rejected_from` is the first rejected counter of each peer. All the later
+/// ops of the peer depend on it, so they are rejected too. It's updated with
+/// the new rejections.
+///
+/// It returns the removed ops. The changes that are already included by the
+/// oplog are neither passed to `should_reject` nor removed.
+pub(crate) fn reject_changes(
+    oplog: &OpLog,
+    changes: &mut Vec<Change>,
+    rejected_from: &mut FxHashMap<PeerID, Counter>,
+    mut should_reject: impl FnMut(&Change) -> bool,
+) -> VersionRange {
+    let vv = oplog.vv();
+    let known = |peer: PeerID| vv.get(&peer).copied().unwrap_or(0);
+    let reject = |rejected_from: &mut FxHashMap<PeerID, Counter>, change: &Change| {
+        let peer = change.id.peer;
+        let start = change.id.counter.max(known(peer));
+        let from = rejected_from.entry(peer).or_insert(start);
+        *from = (*from).min(start);
+    };
+
+    for change in changes.iter() {
+        if change.ctr_end() <= known(change.id.peer) {
+            continue;
+        }
+
+        if should_reject(change) {
+            reject(&mut *rejected_from, change);
+        }
+    }
+
+    if rejected_from.is_empty() {
+        return VersionRange::default();
+    }
+
+    let is_rejected = |rejected_from: &FxHashMap<PeerID, Counter>, id: ID| matches!(rejected_from.get(&id.peer), Some(&from) if id.counter >= from);
+    // The changes are not sorted in causal order, so the rejection is
+    // propagated until nothing changes
+    loop {
+        let mut changed = false;
+        for change in changes.iter() {
+            let last = ID::new(change.id.peer, change.ctr_end() - 1);
+            if change.ctr_end() <= known(change.id.peer) || is_rejected(&*rejected_from, last) {
+                continue;
+            }
+
+            if change
+                .deps
+                .iter()
+                .any(|id| is_rejected(&*rejected_from, id))
+            {
+                reject(&mut *rejected_from, change);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    let mut ans = VersionRange::default();
+    changes.retain(|change| {
+        let peer = change.id.peer;
+        match rejected_from.get(&peer) {
+            Some(&from) if change.ctr_end() > from => {
+                let start = change.id.counter.max(from);
+                ans.extends_to_include_id_span(IdSpan::new(peer, start, change.ctr_end()));
+                false
+            }
+            _ => true,
+        }
+    });
+    ans
+}
+
+impl LoroDoc {
+    /// Set the filter of the imported changes. `None` removes the filter.
+    ///
+    /// The filter is called with every imported change that is new to the
+    /// document, before anything is applied. A rejected change and the imported
+    /// changes that depend on it are dropped and reported in
+    /// [crate::encoding::ImportStatus::rejected]. The changes depending on a
+    /// rejected change that arrive in later imports are rejected too.
+    ///
+    /// Setting the filter forgets the changes rejected so far, so they can be
+    /// imported again.
+    ///
+    /// The filter is called while the document is locked, so it must not access
+    /// the document. When a filter is set, snapshots are imported change by change
+    /// so that they can be filtered too.
+    pub fn set_import_filter(&self, filter: Option<ImportFilter>) {
+        let mut oplog = self.oplog.try_lock().unwrap();
+        oplog.import_filter = filter;
+        oplog.rejected_from.clear();
+    }
+}
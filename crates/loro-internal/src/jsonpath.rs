@@ -1,4 +1,4 @@
-use loro_common::{ContainerID, LoroValue};
+use loro_common::{ContainerID, ContainerType, LoroError, LoroValue, TreeID};
 use thiserror::Error;
 use tracing::trace;
 
@@ -6,14 +6,26 @@ use crate::handler::{
     Handler, ListHandler, MapHandler, MovableListHandler, TextHandler, TreeHandler, ValueOrHandler,
 };
 use crate::loro::LoroDoc;
+use crate::state::TreeParentId;
 use std::ops::ControlFlow;
 
+mod edit;
+mod filter;
+use filter::FilterExpr;
+
 #[derive(Error, Debug)]
 pub enum JsonPathError {
     #[error("Invalid JSONPath: {0}")]
     InvalidJsonPath(String),
     #[error("JSONPath evaluation error: {0}")]
     EvaluationError(String),
+    #[error("Cannot edit the {container_type} container by path: {path}")]
+    UnsupportedContainer {
+        path: String,
+        container_type: ContainerType,
+    },
+    #[error(transparent)]
+    LoroError(#[from] LoroError),
 }
 
 impl LoroDoc {
@@ -33,7 +45,7 @@ enum JSONPathToken {
     UnionIndex(Vec<isize>),
     UnionKey(Vec<String>),
     Slice(Option<isize>, Option<isize>, Option<isize>),
-    Filter(Box<dyn for<'a> Fn(&'a ValueOrHandler) -> bool>),
+    Filter(FilterExpr),
 }
 
 use std::fmt;
@@ -51,7 +63,7 @@ impl fmt::Debug for JSONPathToken {
             }
             JSONPathToken::UnionIndex(indices) => write!(f, "UnionIndex({:?})", indices),
             JSONPathToken::UnionKey(keys) => write!(f, "UnionKey({:?})", keys),
-            JSONPathToken::Filter(_) => write!(f, "Filter(<function>)"),
+            JSONPathToken::Filter(filter) => write!(f, "Filter({:?})", filter),
         }
     }
 }
@@ -68,7 +80,7 @@ impl PartialEq for JSONPathToken {
                 a1 == b1 && a2 == b2 && a3 == b3
             }
             (JSONPathToken::Filter(_), JSONPathToken::Filter(_)) => {
-                // We can't compare functions for equality, so we'll consider all filters unequal
+                // Filters contain compiled regexes, so we'll consider all filters unequal
                 false
             }
             _ => false,
@@ -108,34 +120,54 @@ fn parse_jsonpath(path: &str) -> Result<Vec<JSONPathToken>, JsonPathError> {
             '[' => {
                 // Handle array index, slice, filter, or wildcard
                 let mut content = String::new();
-                let mut in_quotes = false;
+                let mut quote: Option<char> = None;
+                let mut escaped = false;
+                // Filters may contain nested brackets, e.g. `[?(@.tags[0] == 'a')]`
+                let mut depth = 0usize;
+                let mut closed = false;
                 for &c in iter.by_ref() {
-                    if c == ']' && !in_quotes {
-                        break;
-                    }
-                    if c == '\'' {
-                        in_quotes = !in_quotes;
+                    if let Some(q) = quote {
+                        if escaped {
+                            escaped = false;
+                        } else if c == '\\' {
+                            escaped = true;
+                        } else if c == q {
+                            quote = None;
+                        }
+                    } else {
+                        match c {
+                            ']' if depth == 0 => {
+                                closed = true;
+                                break;
+                            }
+                            '\'' | '"' => quote = Some(c),
+                            '[' | '(' => depth += 1,
+                            ']' | ')' => depth = depth.saturating_sub(1),
+                            _ => {}
+                        }
                     }
                     content.push(c);
                 }
 
+                if !closed {
+                    return Err(JsonPathError::InvalidJsonPath(format!(
+                        "Unclosed bracket in JSONPath: {}",
+                        path
+                    )));
+                }
+
                 if content == "*" {
                     tokens.push(JSONPathToken::Wildcard);
                 } else if let Ok(index) = content.parse::<isize>() {
                     tokens.push(JSONPathToken::Index(index));
+                } else if let Some(predicate) = content.strip_prefix('?') {
+                    tokens.push(JSONPathToken::Filter(filter::parse_filter(predicate)?));
                 } else if content.contains(':') {
                     let slice: Vec<&str> = content.split(':').collect();
                     let start = slice.first().and_then(|s| s.parse().ok());
                     let end = slice.get(1).and_then(|s| s.parse().ok());
                     let step = slice.get(2).and_then(|s| s.parse().ok()).unwrap_or(1);
                     tokens.push(JSONPathToken::Slice(start, end, Some(step as isize)));
-                } else if content.starts_with('?') {
-                    // let predicate = content[1..].to_string();
-                    tokens.push(JSONPathToken::Filter(Box::new(|_v| {
-                        // let result = evaluate_predicate(predicate, v);
-                        // result
-                        unimplemented!("JSONPath filter not implemented")
-                    })));
                 } else if content.starts_with('\'') && content.ends_with('\'') {
                     // Handle quoted keys
                     tokens.push(JSONPathToken::Child(
@@ -221,7 +253,7 @@ pub fn evaluate_jsonpath(
 
     // Start with the root
     if let Some(JSONPathToken::Root) = tokens.first() {
-        evaluate_tokens(doc, &tokens[1..], &mut results);
+        evaluate_tokens(doc, doc, &tokens[1..], &mut results);
     } else {
         return Err(JsonPathError::InvalidJsonPath(
             "JSONPath must start with $".to_string(),
@@ -232,6 +264,7 @@ pub fn evaluate_jsonpath(
 }
 
 fn evaluate_tokens(
+    root: &dyn PathValue,
     value: &dyn PathValue,
     tokens: &[JSONPathToken],
     results: &mut Vec<ValueOrHandler>,
@@ -244,39 +277,39 @@ fn evaluate_tokens(
     match &tokens[0] {
         JSONPathToken::Child(key) => {
             if let Some(child) = value.get_by_key(key) {
-                evaluate_tokens(&child, &tokens[1..], results);
+                evaluate_tokens(root, &child, &tokens[1..], results);
             }
         }
         JSONPathToken::RecursiveDescend => {
             // Implement recursive descent
             value.for_each_for_path(&mut |child| {
-                evaluate_tokens(&child, tokens, results);
+                evaluate_tokens(root, &child, tokens, results);
                 ControlFlow::Continue(())
             });
-            evaluate_tokens(value, &tokens[1..], results);
+            evaluate_tokens(root, value, &tokens[1..], results);
         }
         JSONPathToken::Wildcard => {
             value.for_each_for_path(&mut |child| {
-                evaluate_tokens(&child, &tokens[1..], results);
+                evaluate_tokens(root, &child, &tokens[1..], results);
                 ControlFlow::Continue(())
             });
         }
         JSONPathToken::Index(index) => {
             if let Some(child) = value.get_by_index(*index) {
-                evaluate_tokens(&child, &tokens[1..], results);
+                evaluate_tokens(root, &child, &tokens[1..], results);
             }
         }
         JSONPathToken::UnionIndex(indices) => {
             for index in indices {
                 if let Some(child) = value.get_by_index(*index) {
-                    evaluate_tokens(&child, &tokens[1..], results);
+                    evaluate_tokens(root, &child, &tokens[1..], results);
                 }
             }
         }
         JSONPathToken::UnionKey(keys) => {
             for key in keys {
                 if let Some(child) = value.get_by_key(key) {
-                    evaluate_tokens(&child, &tokens[1..], results);
+                    evaluate_tokens(root, &child, &tokens[1..], results);
                 }
             }
         }
@@ -300,22 +333,21 @@ fn evaluate_tokens(
             if step > 0 {
                 for i in (start..end).step_by(step as usize) {
                     if let Some(child) = value.get_by_index(i) {
-                        evaluate_tokens(&child, &tokens[1..], results);
+                        evaluate_tokens(root, &child, &tokens[1..], results);
                     }
                 }
             } else {
                 for i in (start..end).rev().step_by((-step) as usize) {
                     if let Some(child) = value.get_by_index(i) {
-                        evaluate_tokens(&child, &tokens[1..], results);
+                        evaluate_tokens(root, &child, &tokens[1..], results);
                     }
                 }
             }
         }
-        JSONPathToken::Filter(predicate) => {
-            // Implement filter logic
+        JSONPathToken::Filter(filter) => {
             value.for_each_for_path(&mut |child| {
-                if predicate(&child) {
-                    evaluate_tokens(&child, &tokens[1..], results);
+                if filter.eval(root, &child) {
+                    evaluate_tokens(root, &child, &tokens[1..], results);
                 }
                 ControlFlow::Continue(())
             });
@@ -431,6 +463,7 @@ impl PathValue for Handler {
         match self {
             Handler::List(h) => h.get_by_index(index),
             Handler::MovableList(h) => h.get_by_index(index),
+            Handler::Tree(h) => h.get_by_index(index),
             _ => None,
         }
     }
@@ -514,7 +547,11 @@ impl PathValue for ListHandler {
 
     fn get_by_index(&self, index: isize) -> Option<ValueOrHandler> {
         if index < 0 {
-            self.get_(self.len() - (-index) as usize)
+            if self.len() >= (-index) as usize {
+                self.get_(self.len() - (-index) as usize)
+            } else {
+                None
+            }
         } else {
             self.get_(index as usize)
         }
@@ -553,7 +590,7 @@ impl PathValue for MovableListHandler {
 
     fn get_by_index(&self, index: isize) -> Option<ValueOrHandler> {
         if index < 0 {
-            if self.len() > (-index) as usize {
+            if self.len() >= (-index) as usize {
                 self.get_(self.len() - (-index) as usize)
             } else {
                 None
@@ -615,25 +652,61 @@ impl PathValue for TextHandler {
     }
 }
 
+// A tree is viewed as the list of its root nodes, and each node is represented by its meta map.
+// A node can also be accessed by its id, e.g. `$.tree['0@1']`.
 impl PathValue for TreeHandler {
-    fn get_by_key(&self, _key: &str) -> Option<ValueOrHandler> {
-        None
+    fn get_by_key(&self, key: &str) -> Option<ValueOrHandler> {
+        let target = TreeID::try_from(key).ok()?;
+        if !self.contains(target) {
+            return None;
+        }
+
+        self.get_meta(target)
+            .ok()
+            .map(|m| ValueOrHandler::Handler(Handler::Map(m)))
     }
 
-    fn get_by_index(&self, _index: isize) -> Option<ValueOrHandler> {
-        None
+    fn get_by_index(&self, index: isize) -> Option<ValueOrHandler> {
+        let len = self.length_for_path();
+        let index = if index < 0 {
+            if len >= (-index) as usize {
+                len - (-index) as usize
+            } else {
+                return None;
+            }
+        } else {
+            index as usize
+        };
+
+        let target = self.get_child_at(&TreeParentId::Root, index)?;
+        self.get_meta(target)
+            .ok()
+            .map(|m| ValueOrHandler::Handler(Handler::Map(m)))
     }
 
-    fn for_each_for_path(&self, _f: &mut dyn FnMut(ValueOrHandler) -> ControlFlow<()>) {
-        unimplemented!()
+    fn for_each_for_path(&self, f: &mut dyn FnMut(ValueOrHandler) -> ControlFlow<()>) {
+        for target in self.roots() {
+            let Ok(meta) = self.get_meta(target) else {
+                continue;
+            };
+
+            if let ControlFlow::Break(_) = f(ValueOrHandler::Handler(Handler::Map(meta))) {
+                break;
+            }
+        }
     }
 
     fn length_for_path(&self) -> usize {
-        unimplemented!()
+        self.children_num(&TreeParentId::Root).unwrap_or(0)
     }
 
-    fn get_child_by_id(&self, _id: ContainerID) -> Option<Handler> {
-        unimplemented!()
+    fn get_child_by_id(&self, id: ContainerID) -> Option<Handler> {
+        let (peer, counter) = match &id {
+            ContainerID::Normal { peer, counter, .. } => (*peer, *counter),
+            ContainerID::Root { .. } => return None,
+        };
+        self.get_by_key(&TreeID::new(peer, counter).to_string())
+            .and_then(|v| v.into_handler().ok())
     }
 
     fn clone_this(&self) -> Result<ValueOrHandler, JsonPathError> {
@@ -0,0 +1,318 @@
+//! Editing a document through JSONPath-like paths.
+//!
+//! Only concrete paths are supported here, i.e. paths made of keys and
+//! indexes such as `$.settings.theme` or `$.items[3]`. Wildcards, slices,
+//! recursive descent and filters are rejected because they may resolve to
+//! an arbitrary number of targets.
+
+use loro_common::{LoroError, LoroValue};
+
+use super::{parse_jsonpath, JSONPathToken, JsonPathError};
+use crate::event::Index;
+use crate::handler::{Handler, MapHandler, ValueOrHandler};
+use crate::loro::LoroDoc;
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(isize),
+}
+
+impl std::fmt::Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::Key(key) => write!(f, "key \"{}\"", key),
+            Segment::Index(index) => write!(f, "index {}", index),
+        }
+    }
+}
+
+/// The resolved location of an edit: the container holding the target and
+/// the keys of the maps that have to be created on the way to it.
+struct Target {
+    parent: Handler,
+    missing_maps: Vec<String>,
+    last: Segment,
+}
+
+impl Target {
+    fn not_found(&self, path: &str) -> JsonPathError {
+        let segment = match self.missing_maps.first() {
+            Some(key) => Segment::Key(key.clone()),
+            None => self.last.clone(),
+        };
+        not_found(path, &self.parent, &segment)
+    }
+}
+
+impl LoroDoc {
+    /// Set the value at the given path.
+    ///
+    /// Missing maps on the path are created on demand. A list item is
+    /// replaced by the new value.
+    ///
+    /// All the edits, including the creation of the intermediate maps, are
+    /// made in the current transaction. The whole path is resolved before
+    /// editing, so nothing is edited if it is invalid or cannot be found.
+    /// An error raised by the edits themselves is not rolled back: the maps
+    /// created before it stay in the current transaction.
+    pub fn set_by_path(&self, path: &str, value: LoroValue) -> Result<(), JsonPathError> {
+        check_value(&value)?;
+        let target = self.resolve_edit_target(path)?;
+        match (&target.parent, &target.last) {
+            (Handler::Map(map), Segment::Key(key)) => {
+                let map = create_maps(map.clone(), &target.missing_maps)?;
+                map.insert(key, value)?;
+            }
+            _ if !target.missing_maps.is_empty() => return Err(target.not_found(path)),
+            (Handler::List(list), Segment::Index(index)) => {
+                let pos =
+                    normalize_index(*index, list.len()).ok_or_else(|| target.not_found(path))?;
+                list.delete(pos, 1)?;
+                list.insert(pos, value)?;
+            }
+            (Handler::MovableList(list), Segment::Index(index)) => {
+                let pos =
+                    normalize_index(*index, list.len()).ok_or_else(|| target.not_found(path))?;
+                list.set(pos, value)?;
+            }
+            _ => return Err(unsupported(path, &target.parent, &target.last)),
+        }
+
+        Ok(())
+    }
+
+    /// Insert the value at the given path.
+    ///
+    /// For lists the value is inserted before the item at the given index,
+    /// and the index may be equal to the length of the list to append it.
+    /// A negative index counts from the end of the list. For maps it
+    /// behaves like [`LoroDoc::set_by_path`].
+    ///
+    /// All the edits, including the creation of the intermediate maps, are
+    /// made in the current transaction. The whole path is resolved before
+    /// editing, so nothing is edited if it is invalid or cannot be found.
+    /// An error raised by the edits themselves is not rolled back: the maps
+    /// created before it stay in the current transaction.
+    pub fn insert_by_path(&self, path: &str, value: LoroValue) -> Result<(), JsonPathError> {
+        check_value(&value)?;
+        let target = self.resolve_edit_target(path)?;
+        match (&target.parent, &target.last) {
+            (Handler::Map(map), Segment::Key(key)) => {
+                let map = create_maps(map.clone(), &target.missing_maps)?;
+                map.insert(key, value)?;
+            }
+            _ if !target.missing_maps.is_empty() => return Err(target.not_found(path)),
+            (Handler::List(list), Segment::Index(index)) => {
+                let pos = normalize_index(*index, list.len() + 1)
+                    .ok_or_else(|| target.not_found(path))?;
+                list.insert(pos, value)?;
+            }
+            (Handler::MovableList(list), Segment::Index(index)) => {
+                let pos = normalize_index(*index, list.len() + 1)
+                    .ok_or_else(|| target.not_found(path))?;
+                list.insert(pos, value)?;
+            }
+            _ => return Err(unsupported(path, &target.parent, &target.last)),
+        }
+
+        Ok(())
+    }
+
+    /// Delete the value at the given path.
+    ///
+    /// It returns an error if there is no value at the path.
+    pub fn delete_by_path(&self, path: &str) -> Result<(), JsonPathError> {
+        let target = self.resolve_edit_target(path)?;
+        match (&target.parent, &target.last) {
+            _ if !target.missing_maps.is_empty() => return Err(target.not_found(path)),
+            (Handler::Map(map), Segment::Key(key)) => {
+                if !map.contains_key(key) {
+                    return Err(target.not_found(path));
+                }
+                map.delete(key)?;
+            }
+            (Handler::List(list), Segment::Index(index)) => {
+                let pos =
+                    normalize_index(*index, list.len()).ok_or_else(|| target.not_found(path))?;
+                list.delete(pos, 1)?;
+            }
+            (Handler::MovableList(list), Segment::Index(index)) => {
+                let pos =
+                    normalize_index(*index, list.len()).ok_or_else(|| target.not_found(path))?;
+                list.delete(pos, 1)?;
+            }
+            _ => return Err(unsupported(path, &target.parent, &target.last)),
+        }
+
+        Ok(())
+    }
+
+    /// Walk the path down to the container that holds the target, without
+    /// editing anything.
+    fn resolve_edit_target(&self, path: &str) -> Result<Target, JsonPathError> {
+        if !self.can_edit() {
+            return Err(LoroError::EditWhenDetached.into());
+        }
+
+        let mut segments = parse_edit_path(path)?;
+        let last = segments.pop().unwrap();
+        if segments.is_empty() {
+            return Err(JsonPathError::InvalidJsonPath(format!(
+                "Root containers cannot be replaced or deleted: {}",
+                path
+            )));
+        }
+
+        let Segment::Key(root) = &segments[0] else {
+            unreachable!()
+        };
+        // Root containers exist implicitly, a missing one is created as a map
+        let mut parent = match self.get_by_path(&[Index::Key(root.as_str().into())]) {
+            Some(ValueOrHandler::Handler(h)) => h,
+            _ => Handler::Map(self.get_map(root.as_str())),
+        };
+        let mut missing_maps = Vec::new();
+        for segment in &segments[1..] {
+            if !missing_maps.is_empty() {
+                match segment {
+                    Segment::Key(key) => missing_maps.push(key.clone()),
+                    Segment::Index(_) => {
+                        let missing = Segment::Key(missing_maps[0].clone());
+                        return Err(not_found(path, &parent, &missing));
+                    }
+                }
+                continue;
+            }
+
+            let child = match (&parent, segment) {
+                (Handler::Map(map), Segment::Key(key)) => match map.get_(key) {
+                    Some(child) => child,
+                    None => {
+                        missing_maps.push(key.clone());
+                        continue;
+                    }
+                },
+                (Handler::List(list), Segment::Index(index)) => normalize_index(*index, list.len())
+                    .and_then(|i| list.get_(i))
+                    .ok_or_else(|| not_found(path, &parent, segment))?,
+                (Handler::MovableList(list), Segment::Index(index)) => {
+                    normalize_index(*index, list.len())
+                        .and_then(|i| list.get_(i))
+                        .ok_or_else(|| not_found(path, &parent, segment))?
+                }
+                _ => return Err(unsupported(path, &parent, segment)),
+            };
+
+            parent = match child {
+                ValueOrHandler::Handler(h) => h,
+                ValueOrHandler::Value(_) => {
+                    return Err(JsonPathError::EvaluationError(format!(
+                        "The {} in {} points to a value that is not a container",
+                        segment, path
+                    )))
+                }
+            };
+        }
+
+        Ok(Target {
+            parent,
+            missing_maps,
+            last,
+        })
+    }
+}
+
+fn parse_edit_path(path: &str) -> Result<Vec<Segment>, JsonPathError> {
+    let mut tokens = parse_jsonpath(path)?.into_iter();
+    if !matches!(tokens.next(), Some(JSONPathToken::Root)) {
+        return Err(JsonPathError::InvalidJsonPath(
+            "JSONPath must start with $".to_string(),
+        ));
+    }
+
+    let segments = tokens
+        .map(|token| match token {
+            JSONPathToken::Child(key) => Ok(Segment::Key(key)),
+            JSONPathToken::Index(index) => Ok(Segment::Index(index)),
+            JSONPathToken::UnionKey(mut keys) if keys.len() == 1 => {
+                Ok(Segment::Key(keys.pop().unwrap()))
+            }
+            JSONPathToken::UnionIndex(indices) if indices.len() == 1 => {
+                Ok(Segment::Index(indices[0]))
+            }
+            token => Err(JsonPathError::InvalidJsonPath(format!(
+                "Only keys and indexes can be used to edit by path, but found {:?} in {}",
+                token, path
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match segments.first() {
+        Some(Segment::Key(_)) => Ok(segments),
+        _ => Err(JsonPathError::InvalidJsonPath(format!(
+            "The path should start with the name of a root container: {}",
+            path
+        ))),
+    }
+}
+
+fn check_value(value: &LoroValue) -> Result<(), JsonPathError> {
+    if matches!(value, LoroValue::Container(_)) {
+        return Err(LoroError::ArgErr(
+            "Containers cannot be inserted by path, insert the value instead"
+                .to_string()
+                .into_boxed_str(),
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+fn create_maps(mut map: MapHandler, keys: &[String]) -> Result<MapHandler, JsonPathError> {
+    for key in keys {
+        map = map.insert_container(key, MapHandler::new_detached())?;
+    }
+
+    Ok(map)
+}
+
+/// Convert a possibly negative index into a position in `0..len`
+fn normalize_index(index: isize, len: usize) -> Option<usize> {
+    let pos = if index < 0 {
+        len.checked_sub(index.unsigned_abs())?
+    } else {
+        index as usize
+    };
+
+    (pos < len).then_some(pos)
+}
+
+fn not_found(path: &str, parent: &Handler, segment: &Segment) -> JsonPathError {
+    JsonPathError::EvaluationError(format!(
+        "Cannot find {} in the {} container {}, path: {}",
+        segment,
+        parent.c_type(),
+        parent.id(),
+        path
+    ))
+}
+
+fn unsupported(path: &str, parent: &Handler, segment: &Segment) -> JsonPathError {
+    match parent {
+        Handler::Map(_) | Handler::List(_) | Handler::MovableList(_) => {
+            JsonPathError::EvaluationError(format!(
+                "Cannot access {} in the {} container {}, path: {}",
+                segment,
+                parent.c_type(),
+                parent.id(),
+                path
+            ))
+        }
+        _ => JsonPathError::UnsupportedContainer {
+            path: path.to_string(),
+            container_type: parent.c_type(),
+        },
+    }
+}
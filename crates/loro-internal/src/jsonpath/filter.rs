@@ -0,0 +1,610 @@
+//! Filter expressions of JSONPath, e.g. `$.books[?(@.price < 10 && @.author =~ /orwell/i)]`.
+//!
+//! Supported syntax:
+//!
+//! - `@` refers to the current element, `$` refers to the document root
+//! - Paths on them: `@.key`, `@['key']`, `@[0]`, `@.list[-1]`
+//! - Literals: numbers, `'single'` or `"double"` quoted strings, `true`, `false` and `null`
+//! - Comparisons: `==`, `!=`, `<`, `<=`, `>`, `>=`
+//! - Regex match: `@.name =~ /pattern/flags`, flags can be `i`, `m`, `s` and `x`
+//! - Logical operators: `&&`, `||`, `!` and parentheses
+//! - Existence check: a path without comparison, e.g. `?(@.isbn)`
+//!
+//! Containers are resolved lazily. A path only descends into the containers it needs,
+//! and a container is only converted into a value when it is compared.
+use std::cmp::Ordering;
+
+use loro_common::LoroValue;
+use regex::{Regex, RegexBuilder};
+
+use super::{JsonPathError, PathValue};
+use crate::handler::ValueOrHandler;
+use crate::HandlerTrait;
+
+#[derive(Debug)]
+pub(crate) enum FilterExpr {
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Not(Box<FilterExpr>),
+    /// Whether the path exists
+    Exists(FilterPath),
+    Bool(bool),
+    Compare(Operand, CmpOp, Operand),
+    Match(Operand, Regex),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug)]
+pub(crate) enum Operand {
+    Path(FilterPath),
+    Literal(LoroValue),
+}
+
+#[derive(Debug)]
+pub(crate) struct FilterPath {
+    from_root: bool,
+    segments: Vec<PathSegment>,
+}
+
+#[derive(Debug)]
+enum PathSegment {
+    Key(String),
+    Index(isize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Dot,
+    Current,
+    Root,
+    Not,
+    And,
+    Or,
+    Cmp(CmpOp),
+    Match,
+    Ident(String),
+    Str(String),
+    Number(LoroValue),
+    Regex(String, String),
+}
+
+fn invalid(msg: impl Into<String>) -> JsonPathError {
+    JsonPathError::InvalidJsonPath(msg.into())
+}
+
+/// Parse the content of a filter selector, without the leading `?`.
+pub(crate) fn parse_filter(expr: &str) -> Result<FilterExpr, JsonPathError> {
+    let tokens = tokenize(expr)?;
+    if tokens.is_empty() {
+        return Err(invalid("Empty filter expression"));
+    }
+
+    let mut parser = Parser { tokens, pos: 0 };
+    let ans = parser.parse_or()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(invalid(format!(
+            "Unexpected token {:?} in filter expression: {}",
+            parser.tokens[parser.pos], expr
+        )));
+    }
+
+    Ok(ans)
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, JsonPathError> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => {
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            '[' => {
+                tokens.push(Token::LBracket);
+                i += 1;
+            }
+            ']' => {
+                tokens.push(Token::RBracket);
+                i += 1;
+            }
+            '.' => {
+                tokens.push(Token::Dot);
+                i += 1;
+            }
+            '@' => {
+                tokens.push(Token::Current);
+                i += 1;
+            }
+            '$' => {
+                tokens.push(Token::Root);
+                i += 1;
+            }
+            '&' | '|' => {
+                if chars.get(i + 1) != Some(&c) {
+                    return Err(invalid(format!(
+                        "Expected '{c}{c}' in filter expression: {expr}"
+                    )));
+                }
+                tokens.push(if c == '&' { Token::And } else { Token::Or });
+                i += 2;
+            }
+            '=' | '!' | '<' | '>' => {
+                let next = chars.get(i + 1).copied();
+                let (token, len) = match (c, next) {
+                    ('=', Some('=')) => (Token::Cmp(CmpOp::Eq), 2),
+                    ('=', Some('~')) => (Token::Match, 2),
+                    ('!', Some('=')) => (Token::Cmp(CmpOp::Ne), 2),
+                    ('!', _) => (Token::Not, 1),
+                    ('<', Some('=')) => (Token::Cmp(CmpOp::Le), 2),
+                    ('<', _) => (Token::Cmp(CmpOp::Lt), 1),
+                    ('>', Some('=')) => (Token::Cmp(CmpOp::Ge), 2),
+                    ('>', _) => (Token::Cmp(CmpOp::Gt), 1),
+                    _ => {
+                        return Err(invalid(format!(
+                            "Unexpected character '{c}' in filter expression: {expr}"
+                        )))
+                    }
+                };
+                tokens.push(token);
+                i += len;
+            }
+            '\'' | '"' => {
+                let (s, next) = read_delimited(&chars, i, c)
+                    .ok_or_else(|| invalid(format!("Unclosed string in filter: {expr}")))?;
+                tokens.push(Token::Str(s));
+                i = next;
+            }
+            '/' => {
+                let (pattern, mut next) = read_delimited(&chars, i, '/')
+                    .ok_or_else(|| invalid(format!("Unclosed regex in filter: {expr}")))?;
+                let mut flags = String::new();
+                while next < chars.len() && chars[next].is_ascii_alphabetic() {
+                    flags.push(chars[next]);
+                    next += 1;
+                }
+                tokens.push(Token::Regex(pattern, flags));
+                i = next;
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let start = i;
+                i += 1;
+                while i < chars.len()
+                    && (chars[i].is_ascii_digit()
+                        || matches!(chars[i], '.' | 'e' | 'E')
+                        || (matches!(chars[i], '+' | '-') && matches!(chars[i - 1], 'e' | 'E')))
+                {
+                    i += 1;
+                }
+                let s: String = chars[start..i].iter().collect();
+                let value = if let Ok(v) = s.parse::<i64>() {
+                    LoroValue::I64(v)
+                } else if let Ok(v) = s.parse::<f64>() {
+                    LoroValue::Double(v)
+                } else {
+                    return Err(invalid(format!("Invalid number '{s}' in filter: {expr}")));
+                };
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "Unexpected character '{c}' in filter expression: {expr}"
+                )))
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Read the content between `chars[start]` and the next unescaped `delimiter`.
+///
+/// Returns the content and the index after the closing delimiter.
+fn read_delimited(chars: &[char], start: usize, delimiter: char) -> Option<(String, usize)> {
+    let mut ans = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && i + 1 < chars.len() {
+            let next = chars[i + 1];
+            if next == delimiter || (delimiter != '/' && next == '\\') {
+                ans.push(next);
+            } else {
+                // Keep the escape sequence, it's meaningful in regex
+                ans.push(c);
+                ans.push(next);
+            }
+            i += 2;
+            continue;
+        }
+
+        if c == delimiter {
+            return Some((ans, i + 1));
+        }
+
+        ans.push(c);
+        i += 1;
+    }
+
+    None
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let ans = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        ans
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), JsonPathError> {
+        match self.next() {
+            Some(t) if t == token => Ok(()),
+            Some(t) => Err(invalid(format!(
+                "Expected {:?} but found {:?} in filter expression",
+                token, t
+            ))),
+            None => Err(invalid(format!(
+                "Expected {:?} but the filter expression ended",
+                token
+            ))),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<FilterExpr, JsonPathError> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = FilterExpr::Or(Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpr, JsonPathError> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = FilterExpr::And(Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<FilterExpr, JsonPathError> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(FilterExpr::Not(Box::new(self.parse_unary()?)));
+        }
+
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<FilterExpr, JsonPathError> {
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.parse_or()?;
+            self.expect(Token::RParen)?;
+            return Ok(expr);
+        }
+
+        let left = self.parse_operand()?;
+        match self.peek() {
+            Some(Token::Cmp(op)) => {
+                let op = *op;
+                self.pos += 1;
+                let right = self.parse_operand()?;
+                Ok(FilterExpr::Compare(left, op, right))
+            }
+            Some(Token::Match) => {
+                self.pos += 1;
+                let regex = match self.next() {
+                    Some(Token::Regex(pattern, flags)) => build_regex(&pattern, &flags)?,
+                    Some(Token::Str(pattern)) => build_regex(&pattern, "")?,
+                    other => {
+                        return Err(invalid(format!(
+                            "Expected a regex after '=~' but found {:?}",
+                            other
+                        )))
+                    }
+                };
+                Ok(FilterExpr::Match(left, regex))
+            }
+            _ => match left {
+                Operand::Path(path) => Ok(FilterExpr::Exists(path)),
+                Operand::Literal(LoroValue::Bool(b)) => Ok(FilterExpr::Bool(b)),
+                Operand::Literal(v) => Err(invalid(format!(
+                    "Literal {:?} cannot be used as a filter test",
+                    v
+                ))),
+            },
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, JsonPathError> {
+        match self.next() {
+            Some(Token::Current) => Ok(Operand::Path(self.parse_path(false)?)),
+            Some(Token::Root) => Ok(Operand::Path(self.parse_path(true)?)),
+            Some(Token::Str(s)) => Ok(Operand::Literal(LoroValue::from(s))),
+            Some(Token::Number(n)) => Ok(Operand::Literal(n)),
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "true" => Ok(Operand::Literal(LoroValue::Bool(true))),
+                "false" => Ok(Operand::Literal(LoroValue::Bool(false))),
+                "null" => Ok(Operand::Literal(LoroValue::Null)),
+                _ => Err(invalid(format!(
+                    "Unexpected identifier '{}' in filter expression",
+                    ident
+                ))),
+            },
+            Some(t) => Err(invalid(format!(
+                "Unexpected token {:?} in filter expression",
+                t
+            ))),
+            None => Err(invalid("Unexpected end of filter expression")),
+        }
+    }
+
+    fn parse_path(&mut self, from_root: bool) -> Result<FilterPath, JsonPathError> {
+        let mut segments = Vec::new();
+        loop {
+            match self.peek() {
+                Some(Token::Dot) => {
+                    self.pos += 1;
+                    match self.next() {
+                        Some(Token::Ident(key)) => segments.push(PathSegment::Key(key)),
+                        other => {
+                            return Err(invalid(format!(
+                                "Expected a key after '.' but found {:?}",
+                                other
+                            )))
+                        }
+                    }
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    match self.next() {
+                        Some(Token::Str(key)) => segments.push(PathSegment::Key(key)),
+                        Some(Token::Number(LoroValue::I64(index))) => {
+                            segments.push(PathSegment::Index(index as isize))
+                        }
+                        other => {
+                            return Err(invalid(format!(
+                                "Expected a key or an index inside '[]' but found {:?}",
+                                other
+                            )))
+                        }
+                    }
+                    self.expect(Token::RBracket)?;
+                }
+                _ => break,
+            }
+        }
+
+        Ok(FilterPath {
+            from_root,
+            segments,
+        })
+    }
+}
+
+fn build_regex(pattern: &str, flags: &str) -> Result<Regex, JsonPathError> {
+    let mut builder = RegexBuilder::new(pattern);
+    for flag in flags.chars() {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            'm' => builder.multi_line(true),
+            's' => builder.dot_matches_new_line(true),
+            'x' => builder.ignore_whitespace(true),
+            _ => return Err(invalid(format!("Unsupported regex flag '{}'", flag))),
+        };
+    }
+
+    builder
+        .build()
+        .map_err(|e| invalid(format!("Invalid regex /{}/: {}", pattern, e)))
+}
+
+impl FilterExpr {
+    /// Test whether the `current` element matches the filter.
+    pub(crate) fn eval(&self, root: &dyn PathValue, current: &ValueOrHandler) -> bool {
+        match self {
+            FilterExpr::Or(a, b) => a.eval(root, current) || b.eval(root, current),
+            FilterExpr::And(a, b) => a.eval(root, current) && b.eval(root, current),
+            FilterExpr::Not(a) => !a.eval(root, current),
+            FilterExpr::Exists(path) => path.resolve(root, current).is_some(),
+            FilterExpr::Bool(b) => *b,
+            FilterExpr::Compare(a, op, b) => {
+                let a = a.resolve(root, current);
+                let b = b.resolve(root, current);
+                compare(a.as_ref(), *op, b.as_ref())
+            }
+            FilterExpr::Match(a, regex) => match a.resolve(root, current) {
+                Some(LoroValue::String(s)) => regex.is_match(&s),
+                _ => false,
+            },
+        }
+    }
+}
+
+impl Operand {
+    fn resolve(&self, root: &dyn PathValue, current: &ValueOrHandler) -> Option<LoroValue> {
+        match self {
+            Operand::Literal(v) => Some(v.clone()),
+            Operand::Path(path) => path.resolve(root, current).map(|v| match v {
+                ValueOrHandler::Value(v) => v,
+                // Text is converted into string, counter into number, etc.
+                ValueOrHandler::Handler(h) => h.get_deep_value(),
+            }),
+        }
+    }
+}
+
+impl FilterPath {
+    fn resolve(&self, root: &dyn PathValue, current: &ValueOrHandler) -> Option<ValueOrHandler> {
+        let mut segments = self.segments.iter();
+        let mut value = if self.from_root {
+            match segments.next() {
+                Some(segment) => segment.get(root)?,
+                None => root.clone_this().ok()?,
+            }
+        } else {
+            current.clone()
+        };
+
+        for segment in segments {
+            value = segment.get(&value)?;
+        }
+
+        Some(value)
+    }
+}
+
+impl PathSegment {
+    fn get(&self, value: &dyn PathValue) -> Option<ValueOrHandler> {
+        match self {
+            PathSegment::Key(key) => value.get_by_key(key),
+            PathSegment::Index(index) => value.get_by_index(*index),
+        }
+    }
+}
+
+fn as_f64(v: &LoroValue) -> Option<f64> {
+    match v {
+        LoroValue::I64(i) => Some(*i as f64),
+        LoroValue::Double(d) => Some(*d),
+        _ => None,
+    }
+}
+
+fn value_eq(a: &LoroValue, b: &LoroValue) -> bool {
+    match (as_f64(a), as_f64(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn value_cmp(a: &LoroValue, b: &LoroValue) -> Option<Ordering> {
+    if let (Some(a), Some(b)) = (as_f64(a), as_f64(b)) {
+        return a.partial_cmp(&b);
+    }
+
+    match (a, b) {
+        (LoroValue::String(a), LoroValue::String(b)) => Some(a.as_str().cmp(b.as_str())),
+        _ => None,
+    }
+}
+
+/// Compare two operands. A missing operand is only equal to another missing operand.
+fn compare(a: Option<&LoroValue>, op: CmpOp, b: Option<&LoroValue>) -> bool {
+    let (a, b) = match (a, b) {
+        (None, None) => return matches!(op, CmpOp::Eq | CmpOp::Le | CmpOp::Ge),
+        (None, _) | (_, None) => return op == CmpOp::Ne,
+        (Some(a), Some(b)) => (a, b),
+    };
+
+    match op {
+        CmpOp::Eq => value_eq(a, b),
+        CmpOp::Ne => !value_eq(a, b),
+        CmpOp::Lt => value_cmp(a, b) == Some(Ordering::Less),
+        CmpOp::Le => value_eq(a, b) || value_cmp(a, b) == Some(Ordering::Less),
+        CmpOp::Gt => value_cmp(a, b) == Some(Ordering::Greater),
+        CmpOp::Ge => value_eq(a, b) || value_cmp(a, b) == Some(Ordering::Greater),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_valid_filters() {
+        for expr in [
+            "(@.isbn)",
+            "(@.price < 10)",
+            "@.price <= $.store.expensive",
+            "(@.a == 'x' && (@.b != \"y\" || !@.c))",
+            "(@['key with space'][0] >= -1.5e3)",
+            "(@.name =~ /^moby/i)",
+            "(true)",
+        ] {
+            parse_filter(expr).unwrap_or_else(|e| panic!("{expr}: {e}"));
+        }
+    }
+
+    #[test]
+    fn parse_invalid_filters() {
+        for expr in [
+            "",
+            "()",
+            "(@.price <)",
+            "(@.price < 10",
+            "(@.a & @.b)",
+            "('abc)",
+            "(@.a =~ /[/)",
+            "(@.a =~ /a/q)",
+            "(42)",
+            "(@.a == foo)",
+            "(@.*)",
+        ] {
+            assert!(
+                matches!(parse_filter(expr), Err(JsonPathError::InvalidJsonPath(_))),
+                "{expr} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn compare_values() {
+        let one = LoroValue::I64(1);
+        let one_f = LoroValue::Double(1.0);
+        let two = LoroValue::Double(2.0);
+        let a = LoroValue::from("a");
+        assert!(compare(Some(&one), CmpOp::Eq, Some(&one_f)));
+        assert!(compare(Some(&one), CmpOp::Lt, Some(&two)));
+        assert!(compare(Some(&two), CmpOp::Ge, Some(&one)));
+        assert!(!compare(Some(&a), CmpOp::Lt, Some(&one)));
+        assert!(compare(Some(&a), CmpOp::Ne, Some(&one)));
+        assert!(!compare(None, CmpOp::Eq, Some(&one)));
+        assert!(compare(None, CmpOp::Ne, Some(&one)));
+        assert!(compare(None, CmpOp::Eq, None));
+    }
+}
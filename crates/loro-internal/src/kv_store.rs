@@ -1,6 +1,6 @@
 use bytes::Bytes;
 pub use loro_kv_store::compress::CompressionType;
-pub use loro_kv_store::MemKvStore;
+pub use loro_kv_store::{FileKvConfig, FileKvStore, MemKvStore};
 use std::{
     collections::BTreeMap,
     ops::Bound,
@@ -24,6 +24,10 @@ pub trait KvStore: std::fmt::Debug + Send + Sync {
     fn export_all(&mut self) -> Bytes;
     fn import_all(&mut self, bytes: Bytes) -> Result<(), String>;
     fn clone_store(&self) -> Arc<Mutex<dyn KvStore>>;
+    /// Persist the pending writes. It's a no-op for the in-memory stores.
+    fn flush(&mut self) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 fn get_common_prefix_len_and_strip<'a, T: AsRef<[u8]> + ?Sized>(
@@ -98,6 +102,67 @@ impl KvStore for MemKvStore {
     }
 }
 
+impl KvStore for FileKvStore {
+    fn get(&self, key: &[u8]) -> Option<Bytes> {
+        self.get(key)
+    }
+
+    fn set(&mut self, key: &[u8], value: Bytes) {
+        self.set(key, value)
+    }
+
+    fn compare_and_swap(&mut self, key: &[u8], old: Option<Bytes>, new: Bytes) -> bool {
+        self.compare_and_swap(key, old, new)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<Bytes> {
+        let ans = self.get(key);
+        self.remove(key);
+        ans
+    }
+
+    fn contains_key(&self, key: &[u8]) -> bool {
+        self.contains_key(key)
+    }
+
+    fn scan(
+        &self,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+    ) -> Box<dyn DoubleEndedIterator<Item = (Bytes, Bytes)> + '_> {
+        self.scan(start, end)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn size(&self) -> usize {
+        self.size()
+    }
+
+    fn export_all(&mut self) -> Bytes {
+        FileKvStore::export_all(self)
+    }
+
+    fn import_all(&mut self, bytes: Bytes) -> Result<(), String> {
+        FileKvStore::import_all(self, bytes).map_err(|e| e.to_string())
+    }
+
+    /// The forked store lives in memory, so the files of this store are not edited by it
+    fn clone_store(&self) -> Arc<Mutex<dyn KvStore>> {
+        Arc::new(Mutex::new(self.to_mem_store()))
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        FileKvStore::flush(self).map_err(|e| e.to_string())
+    }
+}
+
 mod default_binary_format {
     //! Default binary format for the key-value store.
     //!
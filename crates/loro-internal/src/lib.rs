@@ -31,6 +31,7 @@ pub use oplog::OpLog;
 pub use state::DocState;
 pub use state::{TreeNode, TreeNodeWithChildren, TreeParentId};
 use subscription::{LocalUpdateCallback, Observer, PeerIdUpdateCallback};
+pub use text_blame::TextBlameSpan;
 use txn::Transaction;
 pub use undo::UndoManager;
 use utils::subscription::SubscriberSetWithQueue;
@@ -45,13 +46,17 @@ pub mod dag;
 pub mod encoding;
 pub(crate) mod fork;
 pub mod id;
+pub mod import_filter;
 #[cfg(feature = "jsonpath")]
 pub mod jsonpath;
 pub mod kv_store;
 pub mod loro;
 pub mod op;
 pub mod oplog;
+pub mod schema;
 pub mod subscription;
+mod text_blame;
+mod tree_conflict;
 pub mod txn;
 pub mod version;
 
@@ -1,3 +1,4 @@
+use bytes::Bytes;
 use either::Either;
 use fxhash::{FxHashMap, FxHashSet};
 use itertools::Itertools;
@@ -11,6 +12,7 @@ use std::{
     cmp::Ordering,
     collections::{hash_map::Entry, BinaryHeap},
     ops::ControlFlow,
+    path::Path,
     sync::{
         atomic::{
             AtomicBool,
@@ -26,21 +28,27 @@ use crate::{
     change::Timestamp,
     configure::{Configure, DefaultRandom, SecureRandomGenerator},
     container::{
-        idx::ContainerIdx, list::list_op::InnerListOp, richtext::config::StyleConfigMap,
+        idx::ContainerIdx,
+        list::list_op::InnerListOp,
+        richtext::{config::StyleConfigMap, PosType},
         IntoContainerId,
     },
     cursor::{AbsolutePosition, CannotFindRelativePosition, Cursor, PosQueryResult},
     dag::Dag,
     diff_calc::DiffCalculator,
     encoding::{
-        self, decode_snapshot, export_fast_snapshot, export_fast_updates,
-        export_fast_updates_in_range, export_shallow_snapshot, export_snapshot, export_snapshot_at,
-        export_state_only_snapshot, json_schema::json::JsonSchema, parse_header_and_body,
+        self, decode_snapshot, export_fast_snapshot, export_fast_snapshot_with_options,
+        export_fast_updates, export_fast_updates_in_range, export_shallow_snapshot,
+        export_snapshot, export_snapshot_at, export_state_only_snapshot, fast_snapshot,
+        json_schema::json::JsonSchema,
+        parse_header_and_body,
+        stream::{read_blob, ReadBlob},
         EncodeMode, ImportBlobMetadata, ImportStatus, ParsedHeaderAndBody,
     },
     event::{str_to_path, EventTriggerKind, Index, InternalDocDiff},
     handler::{Handler, MovableListHandler, TextHandler, TreeHandler, ValueOrHandler},
     id::PeerID,
+    kv_store::{FileKvStore, KvStore},
     op::InnerContent,
     oplog::{loro_dag::FrontiersNotIncluded, OpLog},
     state::DocState,
@@ -53,7 +61,7 @@ use crate::{
     VersionVector,
 };
 
-pub use crate::encoding::ExportMode;
+pub use crate::encoding::{ExportMode, SnapshotOptions};
 pub use crate::state::analyzer::{ContainerAnalysisInfo, DocAnalysis};
 pub(crate) use crate::LoroDoc;
 
@@ -75,7 +83,10 @@ impl std::fmt::Debug for LoroDoc {
 
 impl LoroDoc {
     pub fn new() -> Self {
-        let oplog = OpLog::new();
+        Self::from_oplog(OpLog::new())
+    }
+
+    fn from_oplog(oplog: OpLog) -> Self {
         let arena = oplog.arena.clone();
         let global_txn = Arc::new(Mutex::new(None));
         let config: Configure = oplog.configure.clone();
@@ -201,11 +212,132 @@ impl LoroDoc {
         }
     }
 
+    /// Open the document persisted in the directory, or create a new one if
+    /// the directory doesn't contain a document yet.
+    ///
+    /// The history is paged in lazily from the disk. Call [`LoroDoc::flush`] to
+    /// persist the new changes.
+    pub fn open(path: impl AsRef<Path>) -> LoroResult<Self> {
+        let kv = FileKvStore::open(path.as_ref())?;
+        Self::new_with_kv_store(Arc::new(Mutex::new(kv)))
+    }
+
+    /// Create a document whose history is stored in the given kv store.
+    ///
+    /// If the kv store already contains a document, it will be loaded.
+    pub fn new_with_kv_store(kv: Arc<Mutex<dyn KvStore>>) -> LoroResult<Self> {
+        let doc = Self::from_oplog(OpLog::new_with_kv_store(kv));
+        let mut oplog = doc.oplog.try_lock().unwrap();
+        if !oplog.change_store().has_persisted_version() {
+            drop(oplog);
+            return Ok(doc);
+        }
+
+        oplog.load_from_kv()?;
+        drop(oplog);
+        doc.load_persisted_state()?;
+        Ok(doc)
+    }
+
+    /// Load the doc state persisted along with the changes that are just loaded.
+    ///
+    /// The state is computed from the history if it's missing or outdated.
+    fn load_persisted_state(&self) -> LoroResult<()> {
+        let mut state = self.state.try_lock().unwrap();
+        let oplog = self.oplog.try_lock().unwrap();
+        let persisted_state = oplog.change_store().get_doc_state();
+        let need_calc = match persisted_state {
+            Some((bytes, frontiers)) if &frontiers == oplog.frontiers() => {
+                state.store.decode(bytes)?;
+                state.store.mark_all_persisted();
+                state.init_with_states_and_version(frontiers, &oplog, vec![], false);
+                false
+            }
+            // The persisted state is outdated, it's computed from the history instead
+            _ => true,
+        };
+
+        drop(oplog);
+        drop(state);
+        if need_calc {
+            self.detach();
+            self.checkout_to_latest();
+        }
+
+        Ok(())
+    }
+
+    /// Persist the changes that are not flushed yet, along with the states of the
+    /// containers that have changed since the last flush, into the kv store of the
+    /// document. The whole state is written if no state has been persisted yet.
+    ///
+    /// The state is not persisted if the document is detached or shallow, and it will
+    /// be computed from the history when the document is opened.
+    ///
+    /// The kv store of a document created by [`LoroDoc::new`] lives in memory, so nothing
+    /// is written to the disk.
+    pub fn flush(&self) -> LoroResult<()> {
+        self.commit_then_stop();
+        let mut state = self.state.try_lock().unwrap();
+        let oplog = self.oplog.try_lock().unwrap();
+        let ans = if !self.is_detached() && !oplog.is_shallow() && !oplog.is_empty() {
+            state.ensure_all_alive_containers();
+            let change_store = oplog.change_store();
+            state
+                .store
+                .encode_unpersisted(!change_store.has_doc_state())
+                .map(|containers| change_store.set_doc_state(Some((containers, &state.frontiers))))
+        } else {
+            oplog.change_store().set_doc_state(None);
+            Ok(())
+        };
+
+        drop(state);
+        let ans = ans.and_then(|_| oplog.flush_change_store());
+        drop(oplog);
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
+    /// Export the entries of the change store that have changed since the last call.
+    ///
+    /// The entries are `(key, value)` pairs of the kv store that holds the history,
+    /// made up of the encoded change blocks and the version entries. Only the blocks
+    /// flushed since the last call are returned, and the first call returns all of them.
+    pub fn export_change_store_delta(&self) -> LoroResult<Vec<(Bytes, Bytes)>> {
+        self.commit_then_renew();
+        self.oplog.try_lock().unwrap().export_change_store_delta()
+    }
+
+    /// Create a document from the entries exported by [`LoroDoc::export_change_store_delta`].
+    ///
+    /// The entries of all the deltas should be given in the order they were exported.
+    /// Later calls to [`LoroDoc::export_change_store_delta`] on the new document only
+    /// return the entries that changed after the import.
+    ///
+    /// The entries don't contain the doc state, so it's computed from the history.
+    pub fn from_change_store_delta(
+        entries: impl IntoIterator<Item = (Bytes, Bytes)>,
+    ) -> LoroResult<Self> {
+        let doc = Self::new();
+        doc.oplog
+            .try_lock()
+            .unwrap()
+            .import_change_store_delta(entries)?;
+        doc.load_persisted_state()?;
+        Ok(doc)
+    }
+
     /// Is the document empty? (no ops)
     #[inline(always)]
     pub fn can_reset_with_snapshot(&self) -> bool {
+        // The snapshot needs to be imported change by change to be checked by the schema
+        if self.config.schema().is_some() {
+            return false;
+        }
+
         let oplog = self.oplog.try_lock().unwrap();
-        if oplog.batch_importing {
+        if oplog.batch_importing || oplog.import_filter.is_some() {
             return false;
         }
 
@@ -417,6 +549,66 @@ impl LoroDoc {
         ans
     }
 
+    /// Import the data exported by [LoroDoc::export] or [LoroDoc::export_to_writer]
+    /// from the reader.
+    ///
+    /// The data written by [LoroDoc::export_to_writer] is read frame by frame
+    /// up to its end frame. Each frame is verified when it arrives, and the
+    /// update blocks are decoded one by one. Nothing is applied to the document
+    /// before the end frame is read. The data exported by [LoroDoc::export] is
+    /// read until the end of the reader.
+    ///
+    /// Wrap the reader in a [std::io::BufReader] if reading it in small pieces
+    /// is slow.
+    pub fn import_from_reader<R: std::io::Read>(&self, r: &mut R) -> LoroResult<ImportStatus> {
+        self.commit_then_stop();
+        let ans = self._import_from_reader(r, Default::default());
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
+    fn _import_from_reader<R: std::io::Read>(
+        &self,
+        r: &mut R,
+        origin: InternalString,
+    ) -> LoroResult<ImportStatus> {
+        let result = match read_blob(r)? {
+            ReadBlob::Blob(bytes) => return self._import_with(&bytes, origin),
+            ReadBlob::Snapshot(frames) => {
+                if self.can_reset_with_snapshot() {
+                    tracing::info!("Init by fast snapshot {}", self.peer_id());
+                    let snapshot = frames.read_snapshot(true)?;
+                    fast_snapshot::decode_snapshot_inner(snapshot, self).map(|_| ImportStatus {
+                        success: VersionRange::from_vv(&self.oplog_vv()),
+                        pending: None,
+                        rejected: None,
+                    })
+                } else {
+                    // Only the history is imported, so the state sections are not kept
+                    let snapshot = frames.read_snapshot(false)?;
+                    self.update_oplog_and_apply_delta_to_state_if_needed(
+                        |oplog| {
+                            let changes =
+                                fast_snapshot::decode_oplog_bytes(oplog, snapshot.oplog_bytes)?;
+                            encoding::import_changes(oplog, changes)
+                        },
+                        origin,
+                    )
+                }
+            }
+            ReadBlob::Updates(frames) => self.update_oplog_and_apply_delta_to_state_if_needed(
+                |oplog| {
+                    let changes = fast_snapshot::decode_update_blocks(oplog, frames)?;
+                    encoding::import_changes(oplog, changes)
+                },
+                origin,
+            ),
+        };
+
+        self.emit_events();
+        result
+    }
+
     #[tracing::instrument(skip_all)]
     fn _import_with(
         &self,
@@ -424,6 +616,10 @@ impl LoroDoc {
         origin: InternalString,
     ) -> Result<ImportStatus, LoroError> {
         ensure_cov::notify_cov("loro_internal::import");
+        if bytes.starts_with(&encoding::stream::STREAM_MAGIC_BYTES) {
+            return self._import_from_reader(&mut &bytes[..], origin);
+        }
+
         let parsed = parse_header_and_body(bytes, true)?;
         info!("Importing with mode={:?}", &parsed.mode);
         let result = match parsed.mode {
@@ -454,7 +650,7 @@ impl LoroDoc {
                     )
                 }
             }
-            EncodeMode::FastSnapshot => {
+            EncodeMode::FastSnapshot | EncodeMode::FastSnapshotCompressedText => {
                 if self.can_reset_with_snapshot() {
                     ensure_cov::notify_cov("loro_internal::import::snapshot");
                     tracing::info!("Init by fast snapshot {}", self.peer_id());
@@ -831,10 +1027,33 @@ impl LoroDoc {
         self.apply_diff(diff, &mut Default::default(), false)
     }
 
+    /// Revert the current doc state to the target version.
+    ///
+    /// It calculates the diff between the current state and the target version, and applies
+    /// it as new local operations. The history is preserved, and other peers will receive the
+    /// revert as ordinary updates.
+    ///
+    /// The pending transaction is committed before calculating the diff. The generated
+    /// operations are left in the current transaction, so the caller decides how to commit them.
+    ///
+    /// The diff is applied on a fork first. If it fails there, an error is returned and
+    /// the doc is not modified, so a failed revert never leaves a half-reverted state.
+    pub fn revert_to(&self, target: &Frontiers) -> LoroResult<()> {
+        if !self.can_edit() {
+            return Err(LoroError::EditWhenDetached);
+        }
+
+        self.commit_then_renew();
+        let f = self.state_frontiers();
+        let diff = self.diff(&f, target)?;
+        let fork = self.fork();
+        fork.apply_diff(diff.clone(), &mut Default::default(), true)?;
+        self.apply_diff(diff, &mut Default::default(), true)
+    }
+
     /// Calculate the diff between two versions so that apply diff on a will make the state same as b.
     ///
-    /// NOTE: This method will make the doc enter the **detached mode**.
-    // FIXME: This method needs testing (no event should be emitted during processing this)
+    /// The doc state is restored to the original version afterwards, and no event is emitted.
     pub fn diff(&self, a: &Frontiers, b: &Frontiers) -> LoroResult<DiffBatch> {
         {
             // check whether a and b are valid
@@ -856,18 +1075,30 @@ impl LoroDoc {
         let ans = {
             let was_detached = self.is_detached();
             let old_frontiers = self.state_frontiers();
-            self.state.try_lock().unwrap().stop_and_clear_recording();
+            let was_recording = {
+                let mut state = self.state.try_lock().unwrap();
+                let was_recording = state.is_recording();
+                state.stop_and_clear_recording();
+                was_recording
+            };
             self.checkout_without_emitting(a, true).unwrap();
             self.state.try_lock().unwrap().start_recording();
             self.checkout_without_emitting(b, true).unwrap();
-            let mut state = self.state.try_lock().unwrap();
-            let e = state.take_events();
-            state.stop_and_clear_recording();
+            let e = {
+                let mut state = self.state.try_lock().unwrap();
+                let e = state.take_events();
+                state.stop_and_clear_recording();
+                e
+            };
             self.checkout_without_emitting(&old_frontiers, false)
                 .unwrap();
             if !was_detached {
                 self.set_detached(false);
             }
+            if was_recording {
+                self.state.try_lock().unwrap().start_recording();
+            }
+            self.renew_txn_if_auto_commit();
             DiffBatch::new(e)
         };
 
@@ -896,9 +1127,17 @@ impl LoroDoc {
         }
 
         // Sort container from the top to the bottom, so that we can have correct container remap
+        // Containers unknown to this doc (e.g. the diff is calculated on a fork that has
+        // newer history) are put at the end, they are only reachable after being remapped.
         let containers = diff.0.keys().cloned().sorted_by_cached_key(|cid| {
-            let idx = self.arena.id_to_idx(cid).unwrap();
-            self.arena.get_depth(idx).unwrap().get()
+            if cid.is_root() {
+                return 1;
+            }
+
+            self.arena
+                .id_to_idx(cid)
+                .and_then(|idx| self.arena.get_depth(idx))
+                .map_or(u16::MAX, |d| d.get())
         });
 
         let mut ans: LoroResult<()> = Ok(());
@@ -911,7 +1150,11 @@ impl LoroDoc {
                 id = rid.clone();
             }
 
-            if skip_unreachable && !remapped && !self.state.try_lock().unwrap().get_reachable(&id) {
+            if skip_unreachable
+                && !remapped
+                && !id.is_root()
+                && !self.state.try_lock().unwrap().get_reachable(&id)
+            {
                 continue;
             }
 
@@ -997,6 +1240,7 @@ impl LoroDoc {
 
         let mut success = VersionRange::default();
         let mut pending = VersionRange::default();
+        let mut rejected = VersionRange::default();
         let mut meta_arr = bytes
             .iter()
             .map(|b| Ok((LoroDoc::decode_import_blob_meta(b, false)?, b)))
@@ -1039,6 +1283,12 @@ impl LoroDoc {
                             }
                         }
                     }
+
+                    if let Some(r) = s.rejected.as_ref() {
+                        for (&peer, &(start, end)) in r.iter() {
+                            rejected.extends_to_include_id_span(IdSpan::new(peer, start, end));
+                        }
+                    }
                 }
                 Err(e) => {
                     err = Some(e);
@@ -1066,6 +1316,11 @@ impl LoroDoc {
             } else {
                 Some(pending)
             },
+            rejected: if rejected.is_empty() {
+                None
+            } else {
+                Some(rejected)
+            },
         })
     }
 
@@ -1353,6 +1608,26 @@ impl LoroDoc {
         self.query_pos_internal(pos, true)
     }
 
+    /// Like [`Self::query_pos`], but the positions in text containers are of the given type.
+    ///
+    /// It returns [CannotFindRelativePosition::PosTypeMismatch] if the position cannot be
+    /// converted to the given type.
+    pub fn query_pos_with_pos_type(
+        &self,
+        pos: &Cursor,
+        pos_type: PosType,
+    ) -> Result<PosQueryResult, CannotFindRelativePosition> {
+        let mut ans = self.query_pos(pos)?;
+        if pos.container.container_type() == ContainerType::Text {
+            let text = self.get_text(&pos.container);
+            ans.current.pos = text
+                .convert_pos(ans.current.pos, PosType::Event, pos_type)
+                .ok_or(CannotFindRelativePosition::PosTypeMismatch)?;
+        }
+
+        Ok(ans)
+    }
+
     /// Get position in a seq container
     pub(crate) fn query_pos_internal(
         &self,
@@ -1562,6 +1837,13 @@ impl LoroDoc {
     #[instrument(skip(self))]
     pub fn export(&self, mode: ExportMode) -> Result<Vec<u8>, LoroEncodeError> {
         self.commit_then_stop();
+        let ans = self._export(mode);
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
+    /// Export the document without committing the pending transaction.
+    pub(crate) fn _export(&self, mode: ExportMode) -> Result<Vec<u8>, LoroEncodeError> {
         let ans = match mode {
             ExportMode::Snapshot => export_fast_snapshot(self),
             ExportMode::Updates { from } => export_fast_updates(self, &from),
@@ -1574,12 +1856,34 @@ impl LoroDoc {
                 None => export_state_only_snapshot(self, &self.oplog_frontiers())?,
             },
             ExportMode::SnapshotAt { version } => export_snapshot_at(self, &version)?,
+            ExportMode::SnapshotWithOptions(options) => {
+                export_fast_snapshot_with_options(self, options)
+            }
         };
 
-        self.renew_txn_if_auto_commit();
         Ok(ans)
     }
 
+    /// Export the document in the given mode to the writer.
+    ///
+    /// The snapshot and the updates modes are written as a sequence of
+    /// checksummed frames, one per snapshot section or change block, so the
+    /// data is never concatenated into a single buffer. The other modes write
+    /// the same bytes as [LoroDoc::export].
+    ///
+    /// The output can be imported by [LoroDoc::import_from_reader] or
+    /// [LoroDoc::import].
+    pub fn export_to_writer<W: std::io::Write>(
+        &self,
+        mode: ExportMode,
+        w: &mut W,
+    ) -> Result<(), LoroEncodeError> {
+        self.commit_then_stop();
+        let ans = encoding::stream::export_to_writer(self, mode, w);
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
     /// The doc only contains the history since the shallow history start version vector.
     ///
     /// This is empty if the doc is not shallow.
@@ -1798,9 +2102,16 @@ impl Default for CommitOptions {
 
 #[cfg(test)]
 mod test {
-    use loro_common::ID;
+    use std::{
+        collections::BTreeMap,
+        ops::Bound,
+        sync::{Arc, Mutex},
+    };
 
-    use crate::{version::Frontiers, LoroDoc, ToJson};
+    use bytes::Bytes;
+    use loro_common::{ContainerID, ID};
+
+    use crate::{kv_store::KvStore, oplog::DOC_STATE_PREFIX, version::Frontiers, LoroDoc, ToJson};
 
     #[test]
     fn test_sync() {
@@ -1853,6 +2164,98 @@ mod test {
         }
     }
 
+    /// A kv store that records the containers whose states are written into it
+    #[derive(Debug, Default)]
+    struct StateWriteRecorder {
+        kv: BTreeMap<Bytes, Bytes>,
+        written: Vec<ContainerID>,
+    }
+
+    impl KvStore for StateWriteRecorder {
+        fn get(&self, key: &[u8]) -> Option<Bytes> {
+            KvStore::get(&self.kv, key)
+        }
+
+        fn set(&mut self, key: &[u8], value: Bytes) {
+            if let Some(cid) = key.strip_prefix(DOC_STATE_PREFIX) {
+                self.written.push(ContainerID::from_bytes(cid));
+            }
+            KvStore::set(&mut self.kv, key, value)
+        }
+
+        fn compare_and_swap(&mut self, key: &[u8], old: Option<Bytes>, new: Bytes) -> bool {
+            KvStore::compare_and_swap(&mut self.kv, key, old, new)
+        }
+
+        fn remove(&mut self, key: &[u8]) -> Option<Bytes> {
+            KvStore::remove(&mut self.kv, key)
+        }
+
+        fn contains_key(&self, key: &[u8]) -> bool {
+            KvStore::contains_key(&self.kv, key)
+        }
+
+        fn scan(
+            &self,
+            start: Bound<&[u8]>,
+            end: Bound<&[u8]>,
+        ) -> Box<dyn DoubleEndedIterator<Item = (Bytes, Bytes)> + '_> {
+            KvStore::scan(&self.kv, start, end)
+        }
+
+        fn len(&self) -> usize {
+            KvStore::len(&self.kv)
+        }
+
+        fn is_empty(&self) -> bool {
+            KvStore::is_empty(&self.kv)
+        }
+
+        fn size(&self) -> usize {
+            KvStore::size(&self.kv)
+        }
+
+        fn export_all(&mut self) -> Bytes {
+            KvStore::export_all(&mut self.kv)
+        }
+
+        fn import_all(&mut self, bytes: Bytes) -> Result<(), String> {
+            KvStore::import_all(&mut self.kv, bytes)
+        }
+
+        fn clone_store(&self) -> Arc<Mutex<dyn KvStore>> {
+            KvStore::clone_store(&self.kv)
+        }
+    }
+
+    #[test]
+    fn flush_writes_only_changed_containers() {
+        let recorder = Arc::new(Mutex::new(StateWriteRecorder::default()));
+        let doc = LoroDoc::new_with_kv_store(recorder.clone()).unwrap();
+        doc.start_auto_commit();
+        let text = doc.get_text("text");
+        let map = doc.get_map("map");
+        text.insert(0, "hello").unwrap();
+        map.insert("a", 1).unwrap();
+        doc.flush().unwrap();
+        let written = std::mem::take(&mut recorder.try_lock().unwrap().written);
+        assert_eq!(written.len(), 2);
+
+        map.insert("b", 2).unwrap();
+        doc.flush().unwrap();
+        let written = std::mem::take(&mut recorder.try_lock().unwrap().written);
+        assert_eq!(written, vec![map.id()]);
+
+        doc.flush().unwrap();
+        assert!(recorder.try_lock().unwrap().written.is_empty());
+
+        let value = doc.get_deep_value();
+        drop(doc);
+        let doc = LoroDoc::new_with_kv_store(recorder.clone()).unwrap();
+        assert_eq!(doc.get_deep_value(), value);
+        assert!(recorder.try_lock().unwrap().written.is_empty());
+    }
+
     #[test]
     fn import_batch_err_181() {
         let a = LoroDoc::new_auto_commit();
@@ -3,11 +3,12 @@ pub(crate) mod loro_dag;
 mod pending_changes;
 
 use bytes::Bytes;
+use fxhash::FxHashMap;
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::rc::Rc;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use tracing::{debug, trace, trace_span};
 
 use self::change_store::iter::MergedChangeIter;
@@ -22,16 +23,20 @@ use crate::encoding::{decode_oplog, encode_oplog, EncodeMode};
 use crate::encoding::{ImportStatus, ParsedHeaderAndBody};
 use crate::history_cache::ContainerHistoryCache;
 use crate::id::{Counter, PeerID, ID};
+use crate::import_filter::ImportFilter;
+use crate::kv_store::KvStore;
 use crate::op::{FutureInnerContent, ListSlice, RawOpContent, RemoteOp, RichOp};
 use crate::span::{HasCounterSpan, HasLamportSpan};
 use crate::version::{Frontiers, ImVersionVector, VersionVector};
-use crate::LoroError;
-use change_store::BlockOpRef;
+use crate::{LoroError, LoroResult};
+use change_store::{BatchDecodeInfo, BlockOpRef};
 use loro_common::{IdLp, IdSpan};
 use rle::{HasLength, RleVec, Sliceable};
 use smallvec::SmallVec;
 
 pub use self::loro_dag::{AppDag, AppDagNode, FrontiersNotIncluded};
+#[cfg(test)]
+pub(crate) use change_store::DOC_STATE_PREFIX;
 pub use change_store::{BlockChangeRef, ChangeStore};
 
 /// [OpLog] store all the ops i.e. the history.
@@ -54,6 +59,11 @@ pub struct OpLog {
     /// If so the Dag's frontiers won't be updated until the batch is finished.
     pub(crate) batch_importing: bool,
     pub(crate) configure: Configure,
+    pub(crate) import_filter: Option<ImportFilter>,
+    /// The first counter of each peer rejected by the schema or the import filter.
+    /// The later imported changes of the peer and the changes depending on them
+    /// are rejected too.
+    pub(crate) rejected_from: FxHashMap<PeerID, Counter>,
 }
 
 impl std::fmt::Debug for OpLog {
@@ -71,6 +81,24 @@ impl OpLog {
         let arena = SharedArena::new();
         let cfg = Configure::default();
         let change_store = ChangeStore::new_mem(&arena, cfg.merge_interval.clone());
+        Self::new_with_change_store(arena, cfg, change_store)
+    }
+
+    /// Create an oplog whose changes are stored in the given kv store.
+    ///
+    /// [OpLog::load_from_kv] should be called if the kv store is not empty.
+    pub(crate) fn new_with_kv_store(kv: Arc<Mutex<dyn KvStore>>) -> Self {
+        let arena = SharedArena::new();
+        let cfg = Configure::default();
+        let change_store = ChangeStore::new_with_kv(&arena, kv, cfg.merge_interval.clone());
+        Self::new_with_change_store(arena, cfg, change_store)
+    }
+
+    fn new_with_change_store(
+        arena: SharedArena,
+        cfg: Configure,
+        change_store: ChangeStore,
+    ) -> Self {
         Self {
             history_cache: Mutex::new(ContainerHistoryCache::new(change_store.clone(), None)),
             dag: AppDag::new(change_store.clone()),
@@ -79,6 +107,8 @@ impl OpLog {
             pending_changes: Default::default(),
             batch_importing: false,
             configure: cfg,
+            import_filter: None,
+            rejected_from: Default::default(),
         }
     }
 
@@ -357,6 +387,17 @@ impl OpLog {
         self.change_store.export_blocks_in_range(spans, w)
     }
 
+    #[inline(always)]
+    pub(crate) fn encode_blocks_from(&self, vv: &VersionVector) -> Vec<Bytes> {
+        self.change_store
+            .encode_blocks_from(vv, self.shallow_since_vv(), self.vv())
+    }
+
+    #[inline(always)]
+    pub(crate) fn encode_blocks_in_range(&self, spans: &[IdSpan]) -> Vec<Bytes> {
+        self.change_store.encode_blocks_in_range(spans)
+    }
+
     pub(crate) fn fork_changes_up_to(&self, frontiers: &Frontiers) -> Option<Bytes> {
         let vv = self.dag.frontiers_to_vv(frontiers)?;
         Some(
@@ -567,6 +608,55 @@ impl OpLog {
             .flush_and_compact(self.dag.vv(), self.dag.frontiers());
     }
 
+    /// Load the version of the changes persisted in the kv store.
+    ///
+    /// The changes themselves are loaded lazily.
+    pub(crate) fn load_from_kv(&mut self) -> LoroResult<()> {
+        let v = self.change_store.load_from_kv()?;
+        self.set_version_from_kv(v)
+    }
+
+    /// Get the entries of the change store that have changed since the last call.
+    ///
+    /// See [ChangeStore::export_delta] for the details.
+    pub(crate) fn export_change_store_delta(&self) -> LoroResult<Vec<(Bytes, Bytes)>> {
+        if self.is_shallow() {
+            return Err(LoroError::NotImplemented(
+                "Exporting the change store delta of a shallow doc",
+            ));
+        }
+
+        Ok(self
+            .change_store
+            .export_delta(self.dag.vv(), self.dag.frontiers()))
+    }
+
+    /// Import the entries exported by [OpLog::export_change_store_delta] into an empty oplog
+    pub(crate) fn import_change_store_delta(
+        &mut self,
+        entries: impl IntoIterator<Item = (Bytes, Bytes)>,
+    ) -> LoroResult<()> {
+        let v = self.change_store.import_delta(entries)?;
+        self.set_version_from_kv(v)
+    }
+
+    fn set_version_from_kv(&mut self, v: BatchDecodeInfo) -> LoroResult<()> {
+        if v.start_version.is_some() {
+            return Err(LoroError::NotImplemented(
+                "Loading a shallow doc from a kv store",
+            ));
+        }
+
+        self.dag.set_version_by_fast_snapshot_import(v);
+        Ok(())
+    }
+
+    /// Write the changes that are not persisted yet into the kv store and flush it.
+    pub(crate) fn flush_change_store(&self) -> LoroResult<()> {
+        self.change_store
+            .flush_to_disk(self.dag.vv(), self.dag.frontiers())
+    }
+
     #[inline]
     pub fn change_store_kv_size(&self) -> usize {
         self.change_store.kv_size()
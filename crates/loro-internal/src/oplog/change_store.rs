@@ -22,7 +22,7 @@ use once_cell::sync::OnceCell;
 use rle::{HasLength, Mergable, RlePush, RleVec, Sliceable};
 use std::{
     cmp::Ordering,
-    collections::{BTreeMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     ops::{Bound, Deref},
     sync::{atomic::AtomicI64, Arc, Mutex},
 };
@@ -55,7 +55,13 @@ const MAX_BLOCK_SIZE: usize = 128;
 /// |b"fr"                        |Frontiers         |
 /// |b"sv"                        |Shallow VV        |
 /// |b"sf"                        |Shallow Frontiers |
+/// |b"dF"                        |Doc State Frontiers|
+/// |b"doc_state::" + ContainerID |Container State   |
 /// |12 bytes PeerID + Counter    |Encoded Block     |
+///
+/// The doc state entries only exist in the kv stores that persist a doc, see
+/// [crate::LoroDoc::flush]. They are not included in the exported snapshots.
+/// Their keys are longer than 12 bytes so that they are never taken as blocks.
 #[derive(Debug, Clone)]
 pub struct ChangeStore {
     inner: Arc<Mutex<ChangeStoreInner>>,
@@ -79,6 +85,11 @@ struct ChangeStoreInner {
     start_frontiers: Frontiers,
     /// It's more like a parsed cache for binary_kv.
     mem_parsed_kv: BTreeMap<ID, Arc<ChangesBlock>>,
+    /// The blocks flushed into the kv store since the last [ChangeStore::export_delta].
+    ///
+    /// It's None if [ChangeStore::export_delta] is never called, in which case all the
+    /// blocks are returned by the first call.
+    delta_blocks: Option<BTreeSet<ID>>,
 }
 
 #[derive(Debug, Clone)]
@@ -110,19 +121,40 @@ pub const START_VV_KEY: &[u8] = b"sv";
 pub const START_FRONTIERS_KEY: &[u8] = b"sf";
 pub const VV_KEY: &[u8] = b"vv";
 pub const FRONTIERS_KEY: &[u8] = b"fr";
+pub const DOC_STATE_FRONTIERS_KEY: &[u8] = b"dF";
+pub const DOC_STATE_PREFIX: &[u8] = b"doc_state::";
+/// The exclusive end of the keys with [DOC_STATE_PREFIX]
+const DOC_STATE_PREFIX_END: &[u8] = b"doc_state:;";
 
 impl ChangeStore {
     pub fn new_mem(a: &SharedArena, merge_interval: Arc<AtomicI64>) -> Self {
+        Self::new_with_kv(
+            a,
+            Arc::new(Mutex::new(MemKvStore::new(MemKvConfig::default()))),
+            // Arc::new(Mutex::new(BTreeMap::default())),
+            merge_interval,
+        )
+    }
+
+    /// Create a change store backed by the given kv store.
+    ///
+    /// If the kv store is not empty, [ChangeStore::load_from_kv] should be called
+    /// before using the change store.
+    pub fn new_with_kv(
+        a: &SharedArena,
+        kv: Arc<Mutex<dyn KvStore>>,
+        merge_interval: Arc<AtomicI64>,
+    ) -> Self {
         Self {
             inner: Arc::new(Mutex::new(ChangeStoreInner {
                 start_vv: ImVersionVector::new(),
                 start_frontiers: Frontiers::default(),
                 mem_parsed_kv: BTreeMap::new(),
+                delta_blocks: None,
             })),
             arena: a.clone(),
             external_vv: Arc::new(Mutex::new(VersionVector::new())),
-            external_kv: Arc::new(Mutex::new(MemKvStore::new(MemKvConfig::default()))),
-            // external_kv: Arc::new(Mutex::new(BTreeMap::default())),
+            external_kv: kv,
             merge_interval,
         }
     }
@@ -135,9 +167,85 @@ impl ChangeStore {
     pub(super) fn encode_all(&self, vv: &VersionVector, frontiers: &Frontiers) -> Bytes {
         self.flush_and_compact(vv, frontiers);
         let mut kv = self.external_kv.try_lock().unwrap();
+        if kv.contains_key(DOC_STATE_FRONTIERS_KEY) {
+            // The doc state persisted in the kv store is not a part of the oplog
+            let mut store = MemKvStore::new(MemKvConfig::default());
+            for (k, v) in kv.scan(Bound::Unbounded, Bound::Unbounded) {
+                if k != DOC_STATE_FRONTIERS_KEY && !k.starts_with(DOC_STATE_PREFIX) {
+                    store.set(&k, v);
+                }
+            }
+            return store.export_all();
+        }
+
         kv.export_all()
     }
 
+    /// Flush the cached changes and get the entries of the kv store that have changed
+    /// since the last call, i.e. the blocks that are flushed since then.
+    ///
+    /// The version entries are always included. Applying the entries returned by all the
+    /// calls in order on an empty kv store produces the same history as [ChangeStore::encode_all].
+    pub(crate) fn export_delta(
+        &self,
+        vv: &VersionVector,
+        frontiers: &Frontiers,
+    ) -> Vec<(Bytes, Bytes)> {
+        self.flush_and_compact(vv, frontiers);
+        let mut inner = self.inner.try_lock().unwrap();
+        let kv = self.external_kv.try_lock().unwrap();
+        let mut ans = match inner.delta_blocks.replace(BTreeSet::new()) {
+            Some(blocks) => blocks
+                .into_iter()
+                .map(|id| {
+                    let key = id.to_bytes();
+                    let value = kv.get(&key).unwrap();
+                    (Bytes::from(key), value)
+                })
+                .collect(),
+            None => kv
+                .scan(Bound::Unbounded, Bound::Unbounded)
+                .filter(|(id, _)| id.len() == 12)
+                .collect::<Vec<_>>(),
+        };
+
+        for key in [VV_KEY, FRONTIERS_KEY, START_VV_KEY, START_FRONTIERS_KEY] {
+            if let Some(value) = kv.get(key) {
+                ans.push((Bytes::from_static(key), value));
+            }
+        }
+
+        ans
+    }
+
+    /// Whether the kv store contains the version of the persisted changes
+    pub(crate) fn has_persisted_version(&self) -> bool {
+        self.external_kv.try_lock().unwrap().contains_key(VV_KEY)
+    }
+
+    /// Whether the kv store contains a persisted doc state
+    pub(crate) fn has_doc_state(&self) -> bool {
+        self.external_kv
+            .try_lock()
+            .unwrap()
+            .contains_key(DOC_STATE_FRONTIERS_KEY)
+    }
+
+    /// Get the doc state persisted in the kv store, encoded in the format of
+    /// the container store, and its version
+    pub(crate) fn get_doc_state(&self) -> Option<(Bytes, Frontiers)> {
+        let kv = self.external_kv.try_lock().unwrap();
+        let frontiers = Frontiers::decode(&kv.get(DOC_STATE_FRONTIERS_KEY)?).ok()?;
+        let mut store = MemKvStore::new(MemKvConfig::default().should_encode_none(false));
+        for (k, v) in kv.scan(
+            Bound::Included(DOC_STATE_PREFIX),
+            Bound::Excluded(DOC_STATE_PREFIX_END),
+        ) {
+            store.set(&k[DOC_STATE_PREFIX.len()..], v);
+        }
+        Some((store.export_all(), frontiers))
+    }
+
     #[tracing::instrument(skip(self), level = "debug")]
     pub(super) fn export_from(
         &self,
@@ -175,6 +283,11 @@ impl ChangeStore {
     }
 
     pub(super) fn export_blocks_in_range<W: std::io::Write>(&self, spans: &[IdSpan], w: &mut W) {
+        write_blocks(self.encode_blocks_in_range(spans), w);
+    }
+
+    /// Encode the changes in the given spans into blocks
+    pub(super) fn encode_blocks_in_range(&self, spans: &[IdSpan]) -> Vec<Bytes> {
         let new_store = ChangeStore::new_mem(&self.arena, self.merge_interval.clone());
         for span in spans {
             let mut span = *span;
@@ -193,7 +306,7 @@ impl ChangeStore {
             }
         }
 
-        encode_blocks_in_store(new_store, &self.arena, w);
+        encode_blocks_in_store(new_store, &self.arena)
     }
 
     fn encode_from(
@@ -456,6 +569,7 @@ impl ChangeStore {
                 start_vv: inner.start_vv.clone(),
                 start_frontiers: inner.start_frontiers.clone(),
                 mem_parsed_kv: BTreeMap::new(),
+                delta_blocks: None,
             })),
             arena,
             external_vv: Arc::new(Mutex::new(self.external_vv.try_lock().unwrap().clone())),
@@ -480,6 +594,19 @@ impl ChangeStore {
         latest_vv: &VersionVector,
         w: &mut W,
     ) {
+        write_blocks(
+            self.encode_blocks_from(start_vv, shallow_since_vv, latest_vv),
+            w,
+        );
+    }
+
+    /// Encode the changes between `start_vv` and `latest_vv` into blocks
+    pub(crate) fn encode_blocks_from(
+        &self,
+        start_vv: &VersionVector,
+        shallow_since_vv: &ImVersionVector,
+        latest_vv: &VersionVector,
+    ) -> Vec<Bytes> {
         let new_store = ChangeStore::new_mem(&self.arena, self.merge_interval.clone());
         for mut span in latest_vv.sub_iter(start_vv) {
             let counter_lower_bound = shallow_since_vv.get(&span.peer).copied().unwrap_or(0);
@@ -505,8 +632,7 @@ impl ChangeStore {
             }
         }
 
-        let arena = &self.arena;
-        encode_blocks_in_store(new_store, arena, w);
+        encode_blocks_in_store(new_store, &self.arena)
     }
 
     pub(crate) fn fork_changes_up_to(
@@ -544,16 +670,20 @@ impl ChangeStore {
     }
 }
 
-fn encode_blocks_in_store<W: std::io::Write>(
-    new_store: ChangeStore,
-    arena: &SharedArena,
-    w: &mut W,
-) {
+fn encode_blocks_in_store(new_store: ChangeStore, arena: &SharedArena) -> Vec<Bytes> {
     let mut inner = new_store.inner.try_lock().unwrap();
+    let mut ans = Vec::with_capacity(inner.mem_parsed_kv.len());
     for (_id, block) in inner.mem_parsed_kv.iter_mut() {
-        let bytes = block.to_bytes(arena);
-        leb128::write::unsigned(w, bytes.bytes.len() as u64).unwrap();
-        w.write_all(&bytes.bytes).unwrap();
+        ans.push(block.to_bytes(arena).bytes);
+    }
+    ans
+}
+
+/// Write the blocks in the format of the fast updates, i.e. each block is prefixed by its length
+fn write_blocks<W: std::io::Write>(blocks: Vec<Bytes>, w: &mut W) {
+    for bytes in blocks {
+        leb128::write::unsigned(w, bytes.len() as u64).unwrap();
+        w.write_all(&bytes).unwrap();
     }
 }
 
@@ -574,8 +704,59 @@ mod mut_external_kv {
             kv_store
                 .import_all(bytes)
                 .map_err(|e| LoroError::DecodeError(e.into_boxed_str()))?;
+            drop(kv_store);
+            self.load_from_kv()
+        }
+
+        /// Import the entries exported by [ChangeStore::export_delta] into an empty change store.
+        ///
+        /// The entries of all the deltas should be given in the order they were exported,
+        /// so the later blocks override the earlier ones.
+        ///
+        /// # Errors
+        ///
+        /// - [LoroError::DecodeError] if the change store is not empty or the entries are invalid
+        pub(crate) fn import_delta(
+            &self,
+            entries: impl IntoIterator<Item = (Bytes, Bytes)>,
+        ) -> Result<BatchDecodeInfo, LoroError> {
+            let mut kv_store = self.external_kv.try_lock().unwrap();
+            if !kv_store.is_empty() {
+                return Err(LoroError::DecodeError(
+                    "The change store delta can only be imported into an empty change store".into(),
+                ));
+            }
+            for (key, value) in entries {
+                let is_meta = [VV_KEY, FRONTIERS_KEY, START_VV_KEY, START_FRONTIERS_KEY]
+                    .contains(&key.as_ref());
+                if key.len() != 12 && !is_meta {
+                    return Err(LoroError::DecodeError(
+                        format!("Invalid key in the change store delta: {:?}", key).into(),
+                    ));
+                }
+                kv_store.set(&key, value);
+            }
+
+            if !kv_store.contains_key(VV_KEY) || !kv_store.contains_key(FRONTIERS_KEY) {
+                return Err(LoroError::DecodeError(
+                    "The version is missing in the change store delta".into(),
+                ));
+            }
+
+            drop(kv_store);
+            let info = self.load_from_kv()?;
+            let mut inner = self.inner.try_lock().unwrap();
+            inner.delta_blocks = Some(BTreeSet::new());
+            Ok(info)
+        }
+
+        /// Load the version info of the changes in the external kv store.
+        ///
+        /// The blocks are loaded lazily when they are accessed.
+        pub(crate) fn load_from_kv(&self) -> Result<BatchDecodeInfo, LoroError> {
+            let kv_store = self.external_kv.try_lock().unwrap();
             let vv_bytes = kv_store.get(VV_KEY).unwrap_or_default();
-            let vv = VersionVector::decode(&vv_bytes).unwrap();
+            let vv = VersionVector::decode(&vv_bytes)?;
             let start_vv_bytes = kv_store.get(START_VV_KEY).unwrap_or_default();
             let start_vv = if start_vv_bytes.is_empty() {
                 Default::default()
@@ -583,19 +764,9 @@ mod mut_external_kv {
                 VersionVector::decode(&start_vv_bytes).unwrap()
             };
 
-            #[cfg(test)]
-            {
-                // This is for tests
-                drop(kv_store);
-                for (peer, cnt) in vv.iter() {
-                    self.get_change(ID::new(*peer, *cnt - 1)).unwrap();
-                }
-                kv_store = self.external_kv.try_lock().unwrap();
-            }
-
             *self.external_vv.try_lock().unwrap() = vv.clone();
             let frontiers_bytes = kv_store.get(FRONTIERS_KEY).unwrap_or_default();
-            let frontiers = Frontiers::decode(&frontiers_bytes).unwrap();
+            let frontiers = Frontiers::decode(&frontiers_bytes)?;
             let start_frontiers = kv_store.get(START_FRONTIERS_KEY).unwrap_or_default();
             let start_frontiers = if start_frontiers.is_empty() {
                 Default::default()
@@ -606,8 +777,20 @@ mod mut_external_kv {
             let mut max_lamport = None;
             let mut max_timestamp = 0;
             drop(kv_store);
+            #[cfg(test)]
+            {
+                // This is for tests
+                for (peer, cnt) in vv.iter() {
+                    self.get_change(ID::new(*peer, *cnt - 1)).unwrap();
+                }
+            }
+
             for id in frontiers.iter() {
-                let c = self.get_change(id).unwrap();
+                let c = self.get_change(id).ok_or_else(|| {
+                    LoroError::DecodeError(
+                        format!("The change {} in the frontiers is missing", id).into(),
+                    )
+                })?;
                 debug_assert_ne!(c.atom_len(), 0);
                 let l = c.lamport_last();
                 if let Some(x) = max_lamport {
@@ -661,6 +844,9 @@ mod mut_external_kv {
                     let bytes = block.to_bytes(&self.arena);
                     store.set(&id_bytes, bytes.bytes);
                     Arc::make_mut(block).flushed = true;
+                    if let Some(delta) = inner.delta_blocks.as_mut() {
+                        delta.insert(*id);
+                    }
                 }
             }
 
@@ -677,6 +863,52 @@ mod mut_external_kv {
             store.set(VV_KEY, vv_bytes.into());
             store.set(FRONTIERS_KEY, frontiers_bytes.into());
         }
+
+        /// Flush the cached changes to the kv store and persist the kv store
+        pub(crate) fn flush_to_disk(
+            &self,
+            vv: &VersionVector,
+            frontiers: &Frontiers,
+        ) -> LoroResult<()> {
+            self.flush_and_compact(vv, frontiers);
+            self.external_kv
+                .try_lock()
+                .unwrap()
+                .flush()
+                .map_err(|e| LoroError::IoError(e.into_boxed_str()))
+        }
+
+        /// Persist the encoded states of the given containers and the version of the
+        /// doc state along with the changes. The states of the other containers are kept.
+        ///
+        /// The persisted state is removed if `state` is `None`.
+        pub(crate) fn set_doc_state(&self, state: Option<(Vec<(Bytes, Bytes)>, &Frontiers)>) {
+            let mut store = self.external_kv.try_lock().unwrap();
+            match state {
+                Some((containers, frontiers)) => {
+                    let mut key = DOC_STATE_PREFIX.to_vec();
+                    for (cid, value) in containers {
+                        key.truncate(DOC_STATE_PREFIX.len());
+                        key.extend_from_slice(&cid);
+                        store.set(&key, value);
+                    }
+                    store.set(DOC_STATE_FRONTIERS_KEY, frontiers.encode().into());
+                }
+                None => {
+                    let keys: Vec<Bytes> = store
+                        .scan(
+                            Bound::Included(DOC_STATE_PREFIX),
+                            Bound::Excluded(DOC_STATE_PREFIX_END),
+                        )
+                        .map(|(k, _)| k)
+                        .collect();
+                    for key in keys {
+                        store.remove(&key);
+                    }
+                    store.remove(DOC_STATE_FRONTIERS_KEY);
+                }
+            }
+        }
     }
 }
 
@@ -37,6 +37,14 @@ impl PendingChanges {
     pub fn is_empty(&self) -> bool {
         self.changes.is_empty()
     }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Change> + '_ {
+        self.changes
+            .values()
+            .flat_map(|x| x.values())
+            .flatten()
+            .map(|x| &**x)
+    }
 }
 
 impl OpLog {
@@ -9,7 +9,7 @@ use loro_common::LoroValue;
 use crate::{
     arena::SharedArena,
     change::Change,
-    container::{list::list_op::ListOp, map::MapSet},
+    container::{list::list_op::ListOp, map::MapSet, richtext::EMBED_STYLE_KEY},
     op::{ListSlice, RawOp, RawOpContent},
     DocState, OpLog,
 };
@@ -65,6 +65,18 @@ impl DocState {
                     let idx = self.arena.register_container(c);
                     self.arena.set_parent(idx, Some(container));
                 }
+                // The child container embedded in a text
+                if let ListOp::StyleStart {
+                    key,
+                    value: LoroValue::Container(c),
+                    ..
+                } = op
+                {
+                    if key.as_str() == EMBED_STYLE_KEY {
+                        let idx = self.arena.register_container(c);
+                        self.arena.set_parent(idx, Some(container));
+                    }
+                }
             }
             RawOpContent::Map(MapSet { key: _, value }) => {
                 if let Some(LoroValue::Container(c)) = value {
@@ -0,0 +1,625 @@
+//! Typed schemas for the containers of a document.
+//!
+//! A [Schema] declares the root containers of a document, the type of each
+//! container and the types of the values stored in them. For example, a list of
+//! todos can be declared as
+//!
+//! ```text
+//! root "todos": MovableList<Map { title: String, done: Bool }>
+//! ```
+//!
+//! Once a schema is set by [LoroDoc::set_schema], every local op is checked
+//! before it's applied, and the imported changes are checked before they are
+//! added to the oplog. The check of an op only looks at the values written by
+//! the op and at the path from the root to its container, so its cost doesn't
+//! grow with the size of the document.
+use std::fmt::Display;
+
+use fxhash::{FxHashMap, FxHashSet};
+use loro_common::{ContainerID, ContainerType, LoroError, LoroResult, LoroValue, ID};
+
+use crate::{
+    change::Change,
+    container::{
+        idx::ContainerIdx,
+        list::list_op::{InnerListOp, ListOp},
+        map::MapSet,
+    },
+    event::Index,
+    op::{InnerContent, ListSlice, RawOpContent},
+    InternalString, LoroDoc, OpLog,
+};
+
+/// The schema of a document.
+///
+/// # Example
+///
+/// ```
+/// use loro_internal::schema::{ContainerSchema, MapSchema, Schema, ValueSchema};
+///
+/// let todo = MapSchema::new()
+///     .field("title", ValueSchema::String)
+///     .field("done", ValueSchema::Bool);
+/// let schema = Schema::new().root("todos", ContainerSchema::movable_list(ContainerSchema::Map(todo)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    roots: FxHashMap<String, ContainerSchema>,
+    allow_unknown_roots: bool,
+}
+
+/// The schema of a container
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContainerSchema {
+    Map(MapSchema),
+    /// A list with the schema of its items
+    List(Box<ValueSchema>),
+    /// A movable list with the schema of its items
+    MovableList(Box<ValueSchema>),
+    Text,
+    /// A tree with the schema of the metadata map of its nodes
+    Tree(MapSchema),
+    #[cfg(feature = "counter")]
+    Counter,
+}
+
+/// The schema of a map container.
+///
+/// Only the declared keys can be set, unless [MapSchema::other_fields] is used.
+/// A declared key may be absent from the map.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MapSchema {
+    fields: FxHashMap<String, ValueSchema>,
+    other_fields: Option<Box<ValueSchema>>,
+}
+
+/// The schema of a value stored in a container
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueSchema {
+    /// Any value or container
+    Any,
+    Null,
+    Bool,
+    I64,
+    Double,
+    String,
+    Binary,
+    /// A child container
+    Container(ContainerSchema),
+    /// A value that matches any of the schemas
+    OneOf(Vec<ValueSchema>),
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a root container
+    pub fn root(mut self, name: &str, schema: ContainerSchema) -> Self {
+        self.roots.insert(name.into(), schema);
+        self
+    }
+
+    /// Whether the root containers that are not declared can be edited.
+    ///
+    /// It's false by default.
+    pub fn allow_unknown_roots(mut self, allow: bool) -> Self {
+        self.allow_unknown_roots = allow;
+        self
+    }
+}
+
+impl ContainerSchema {
+    pub fn list(item: impl Into<ValueSchema>) -> Self {
+        Self::List(Box::new(item.into()))
+    }
+
+    pub fn movable_list(item: impl Into<ValueSchema>) -> Self {
+        Self::MovableList(Box::new(item.into()))
+    }
+
+    pub fn container_type(&self) -> ContainerType {
+        match self {
+            ContainerSchema::Map(_) => ContainerType::Map,
+            ContainerSchema::List(_) => ContainerType::List,
+            ContainerSchema::MovableList(_) => ContainerType::MovableList,
+            ContainerSchema::Text => ContainerType::Text,
+            ContainerSchema::Tree(_) => ContainerType::Tree,
+            #[cfg(feature = "counter")]
+            ContainerSchema::Counter => ContainerType::Counter,
+        }
+    }
+}
+
+impl MapSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a key of the map
+    pub fn field(mut self, key: &str, schema: impl Into<ValueSchema>) -> Self {
+        self.fields.insert(key.into(), schema.into());
+        self
+    }
+
+    /// Allow the keys that are not declared, with the given schema for their values
+    pub fn other_fields(mut self, schema: impl Into<ValueSchema>) -> Self {
+        self.other_fields = Some(Box::new(schema.into()));
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ValueSchema> {
+        self.fields.get(key).or(self.other_fields.as_deref())
+    }
+}
+
+impl From<ContainerSchema> for ValueSchema {
+    fn from(value: ContainerSchema) -> Self {
+        ValueSchema::Container(value)
+    }
+}
+
+impl ValueSchema {
+    /// Check whether the value matches the schema.
+    ///
+    /// Nested list and map values only match [ValueSchema::Any].
+    pub(crate) fn check_value(&self, value: &LoroValue) -> Result<(), String> {
+        let matched = match (self, value) {
+            (ValueSchema::Any, _)
+            | (ValueSchema::Null, LoroValue::Null)
+            | (ValueSchema::Bool, LoroValue::Bool(_))
+            | (ValueSchema::I64, LoroValue::I64(_))
+            | (ValueSchema::Double, LoroValue::Double(_))
+            | (ValueSchema::String, LoroValue::String(_))
+            | (ValueSchema::Binary, LoroValue::Binary(_)) => true,
+            (ValueSchema::Container(c), LoroValue::Container(id)) => {
+                c.container_type() == id.container_type()
+            }
+            (ValueSchema::OneOf(schemas), value) => {
+                schemas.iter().any(|s| s.check_value(value).is_ok())
+            }
+            _ => false,
+        };
+
+        if matched {
+            Ok(())
+        } else {
+            Err(format!(
+                "expected {} but found {}",
+                self,
+                value_type_name(value)
+            ))
+        }
+    }
+
+    /// Find the schema of a child container with the given type
+    fn find_container(&self, container_type: ContainerType) -> Option<Resolved<'_>> {
+        match self {
+            ValueSchema::Any => Some(Resolved::Any),
+            ValueSchema::Container(c) if c.container_type() == container_type => Some(c.into()),
+            ValueSchema::OneOf(schemas) => schemas
+                .iter()
+                .find_map(|s| s.find_container(container_type)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ValueSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueSchema::Any => f.write_str("Any"),
+            ValueSchema::Null => f.write_str("Null"),
+            ValueSchema::Bool => f.write_str("Bool"),
+            ValueSchema::I64 => f.write_str("I64"),
+            ValueSchema::Double => f.write_str("Double"),
+            ValueSchema::String => f.write_str("String"),
+            ValueSchema::Binary => f.write_str("Binary"),
+            ValueSchema::Container(c) => write!(f, "{}", c.container_type()),
+            ValueSchema::OneOf(schemas) => {
+                for (i, s) in schemas.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "{}", s)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn value_type_name(value: &LoroValue) -> String {
+    match value {
+        LoroValue::Null => "Null".to_string(),
+        LoroValue::Bool(_) => "Bool".to_string(),
+        LoroValue::I64(_) => "I64".to_string(),
+        LoroValue::Double(_) => "Double".to_string(),
+        LoroValue::String(_) => "String".to_string(),
+        LoroValue::Binary(_) => "Binary".to_string(),
+        LoroValue::List(_) => "a list value".to_string(),
+        LoroValue::Map(_) => "a map value".to_string(),
+        LoroValue::Container(id) => id.container_type().to_string(),
+    }
+}
+
+/// The schema found for a container
+#[derive(Clone, Copy)]
+enum Resolved<'a> {
+    /// The container is not constrained by the schema
+    Any,
+    Map(&'a MapSchema),
+    List(&'a ValueSchema),
+    MovableList(&'a ValueSchema),
+    Text,
+    Tree(&'a MapSchema),
+    #[cfg(feature = "counter")]
+    Counter,
+}
+
+impl<'a> From<&'a ContainerSchema> for Resolved<'a> {
+    fn from(value: &'a ContainerSchema) -> Self {
+        match value {
+            ContainerSchema::Map(m) => Resolved::Map(m),
+            ContainerSchema::List(item) => Resolved::List(item),
+            ContainerSchema::MovableList(item) => Resolved::MovableList(item),
+            ContainerSchema::Text => Resolved::Text,
+            ContainerSchema::Tree(meta) => Resolved::Tree(meta),
+            #[cfg(feature = "counter")]
+            ContainerSchema::Counter => Resolved::Counter,
+        }
+    }
+}
+
+impl Resolved<'_> {
+    fn container_type(&self) -> Option<ContainerType> {
+        Some(match self {
+            Resolved::Any => return None,
+            Resolved::Map(_) => ContainerType::Map,
+            Resolved::List(_) => ContainerType::List,
+            Resolved::MovableList(_) => ContainerType::MovableList,
+            Resolved::Text => ContainerType::Text,
+            Resolved::Tree(_) => ContainerType::Tree,
+            #[cfg(feature = "counter")]
+            Resolved::Counter => ContainerType::Counter,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PathSegment {
+    Key(InternalString),
+    /// An item of a list or a node of a tree
+    Item,
+}
+
+/// The path from the root to a container, with the type of each container on the path
+#[derive(Debug, Clone)]
+pub(crate) struct SchemaPath(Vec<(ContainerType, PathSegment)>);
+
+impl SchemaPath {
+    pub(crate) fn from_index_path(path: Vec<(ContainerID, Index)>) -> Self {
+        Self(
+            path.into_iter()
+                .map(|(id, index)| {
+                    let segment = match index {
+                        Index::Key(key) => PathSegment::Key(key),
+                        Index::Seq(_) | Index::Node(_) => PathSegment::Item,
+                    };
+                    (id.container_type(), segment)
+                })
+                .collect(),
+        )
+    }
+
+    /// Format the first `len` segments of the path and the extra segment, like `$.todos[*].title`
+    fn display(&self, len: usize, extra: Option<&PathSegment>) -> String {
+        let mut ans = "$".to_string();
+        for segment in self.0[..len].iter().map(|(_, s)| s).chain(extra) {
+            match segment {
+                PathSegment::Key(key) => {
+                    ans.push('.');
+                    ans.push_str(key);
+                }
+                PathSegment::Item => ans.push_str("[*]"),
+            }
+        }
+        ans
+    }
+}
+
+/// The values written by an op
+pub(crate) enum OpValues<'a> {
+    MapSet {
+        key: &'a InternalString,
+        value: Option<&'a LoroValue>,
+    },
+    Insert(&'a [LoroValue]),
+    Set(&'a LoroValue),
+    Other,
+}
+
+impl<'a> OpValues<'a> {
+    pub(crate) fn from_raw(content: &'a RawOpContent<'_>) -> Self {
+        match content {
+            RawOpContent::Map(MapSet { key, value }) => OpValues::MapSet {
+                key,
+                value: value.as_ref(),
+            },
+            RawOpContent::List(ListOp::Insert {
+                slice: ListSlice::RawData(values),
+                ..
+            }) => OpValues::Insert(values),
+            RawOpContent::List(ListOp::Set { value, .. }) => OpValues::Set(value),
+            _ => OpValues::Other,
+        }
+    }
+}
+
+fn violation(path: String, reason: impl Into<String>) -> LoroError {
+    LoroError::SchemaViolation {
+        path: path.into_boxed_str(),
+        reason: reason.into().into_boxed_str(),
+    }
+}
+
+impl Schema {
+    /// Find the schema of the last container on the path.
+    ///
+    /// It returns [Resolved::Any] if the container is not constrained by the schema.
+    fn resolve(&self, path: &SchemaPath) -> LoroResult<Resolved<'_>> {
+        let Some((root_type, PathSegment::Key(root))) = path.0.first() else {
+            return Err(violation(
+                path.display(path.0.len().min(1), None),
+                "the path doesn't start from a root container",
+            ));
+        };
+        let mut current: Resolved = match self.roots.get(root.as_str()) {
+            Some(schema) => schema.into(),
+            None if self.allow_unknown_roots => return Ok(Resolved::Any),
+            None => {
+                return Err(violation(
+                    path.display(1, None),
+                    "the root container is not declared in the schema",
+                ))
+            }
+        };
+        if current.container_type() != Some(*root_type) {
+            return Err(violation(
+                path.display(1, None),
+                format!(
+                    "expected {} but found {}",
+                    current.container_type().unwrap(),
+                    root_type
+                ),
+            ));
+        }
+
+        for (i, (container_type, segment)) in path.0.iter().enumerate().skip(1) {
+            let schema = match (current, segment) {
+                (Resolved::Map(map), PathSegment::Key(key)) => map.get(key),
+                (Resolved::List(item) | Resolved::MovableList(item), PathSegment::Item) => {
+                    Some(item)
+                }
+                (Resolved::Tree(meta), PathSegment::Item) => {
+                    current = Resolved::Map(meta);
+                    continue;
+                }
+                _ => None,
+            };
+            let Some(schema) = schema else {
+                return Err(violation(
+                    path.display(i + 1, None),
+                    "the container is not declared in the schema",
+                ));
+            };
+            current = match schema.find_container(*container_type) {
+                Some(Resolved::Any) => return Ok(Resolved::Any),
+                Some(c) => c,
+                None => {
+                    return Err(violation(
+                        path.display(i + 1, None),
+                        format!("expected {} but found {}", schema, container_type),
+                    ))
+                }
+            };
+        }
+
+        Ok(current)
+    }
+
+    /// Check an op on the last container of the path
+    pub(crate) fn check_op(&self, path: &SchemaPath, op: OpValues) -> LoroResult<()> {
+        let len = path.0.len();
+        match (self.resolve(path)?, op) {
+            (Resolved::Map(map), OpValues::MapSet { key, value }) => {
+                let Some(value) = value else {
+                    return Ok(());
+                };
+                let field_path = || path.display(len, Some(&PathSegment::Key(key.clone())));
+                let Some(schema) = map.get(key) else {
+                    return Err(violation(
+                        field_path(),
+                        "the key is not declared in the schema",
+                    ));
+                };
+                schema
+                    .check_value(value)
+                    .map_err(|reason| violation(field_path(), reason))
+            }
+            (Resolved::List(item) | Resolved::MovableList(item), OpValues::Insert(values)) => {
+                for value in values {
+                    item.check_value(value).map_err(|reason| {
+                        violation(path.display(len, Some(&PathSegment::Item)), reason)
+                    })?;
+                }
+                Ok(())
+            }
+            (Resolved::MovableList(item), OpValues::Set(value)) => item
+                .check_value(value)
+                .map_err(|reason| violation(path.display(len, Some(&PathSegment::Item)), reason)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the ops of the changes that are not included by the oplog yet.
+    ///
+    /// It returns the ids of the changes that don't match the schema. A change
+    /// on a container that cannot be located doesn't match either, because it
+    /// cannot be checked.
+    pub(crate) fn check_changes(&self, oplog: &OpLog, changes: &[Change]) -> FxHashSet<ID> {
+        let arena = &oplog.arena;
+        // The child containers created by the imported and the pending changes,
+        // whose parent links are not registered in the arena yet
+        let mut created = FxHashMap::default();
+        for change in changes.iter().chain(oplog.pending_changes.iter()) {
+            for op in change.ops.iter() {
+                let Some(parent) = arena.idx_to_id(op.container) else {
+                    continue;
+                };
+                let segment = match &op.content {
+                    InnerContent::Map(MapSet { key, .. }) => PathSegment::Key(key.clone()),
+                    _ => PathSegment::Item,
+                };
+                op.content.visit_created_children(arena, &mut |c| {
+                    created.insert(c.clone(), (parent.clone(), segment.clone()));
+                });
+            }
+        }
+
+        let mut paths: FxHashMap<ContainerIdx, Option<SchemaPath>> = FxHashMap::default();
+        let mut ans = FxHashSet::default();
+        for change in changes {
+            if change.ctr_end() <= oplog.vv().get(&change.id.peer).copied().unwrap_or(0) {
+                continue;
+            }
+
+            for op in change.ops.iter() {
+                let op_id = ID::new(change.id.peer, op.counter);
+                let path = paths
+                    .entry(op.container)
+                    .or_insert_with(|| locate_in_oplog(oplog, &created, op.container));
+                let result = match path {
+                    Some(path) => {
+                        let values;
+                        let op_values = match &op.content {
+                            InnerContent::Map(MapSet { key, value }) => OpValues::MapSet {
+                                key,
+                                value: value.as_ref(),
+                            },
+                            InnerContent::List(InnerListOp::Insert { slice, .. })
+                                if !slice.is_unknown() =>
+                            {
+                                values = arena.get_values(slice.to_range());
+                                OpValues::Insert(&values)
+                            }
+                            InnerContent::List(InnerListOp::Set { value, .. }) => {
+                                OpValues::Set(value)
+                            }
+                            _ => OpValues::Other,
+                        };
+                        self.check_op(path, op_values)
+                    }
+                    None => Err(violation(
+                        arena
+                            .idx_to_id(op.container)
+                            .map_or_else(String::new, |id| id.to_string()),
+                        "the container cannot be located",
+                    )),
+                };
+
+                if let Err(e) = result {
+                    tracing::warn!("Rejected the op {} of an imported change: {}", op_id, e);
+                    ans.insert(change.id);
+                    break;
+                }
+            }
+        }
+
+        ans
+    }
+}
+
+/// Find the path of the container by the ops that created the containers on the path.
+///
+/// The child-parent relationship of the containers never changes, so the key of
+/// a child container in its parent map is the key of the op that created it.
+fn locate_in_oplog(
+    oplog: &OpLog,
+    created: &FxHashMap<ContainerID, (ContainerID, PathSegment)>,
+    idx: ContainerIdx,
+) -> Option<SchemaPath> {
+    let arena = &oplog.arena;
+    let mut id = arena.idx_to_id(idx)?;
+    let mut ans = Vec::new();
+    loop {
+        match &id {
+            ContainerID::Root {
+                name,
+                container_type,
+            } => {
+                ans.push((*container_type, PathSegment::Key(name.clone())));
+                break;
+            }
+            ContainerID::Normal {
+                peer,
+                counter,
+                container_type,
+            } => {
+                let (parent, segment) = match created.get(&id) {
+                    Some((parent, segment)) => (parent.clone(), segment.clone()),
+                    None => {
+                        let idx = arena.id_to_idx(&id)?;
+                        let parent = arena.idx_to_id(arena.get_registered_parent(idx)?)?;
+                        let segment = if parent.container_type() == ContainerType::Map {
+                            let op = oplog.get_op_that_includes(ID::new(*peer, *counter))?;
+                            match &op.content {
+                                InnerContent::Map(MapSet { key, .. }) => {
+                                    PathSegment::Key(key.clone())
+                                }
+                                _ => return None,
+                            }
+                        } else {
+                            PathSegment::Item
+                        };
+                        (parent, segment)
+                    }
+                };
+                ans.push((*container_type, segment));
+                id = parent;
+            }
+        }
+    }
+
+    ans.reverse();
+    Some(SchemaPath(ans))
+}
+
+impl LoroDoc {
+    /// Set the schema of the document. `None` removes the schema.
+    ///
+    /// The local ops that don't match the schema are rejected with
+    /// [LoroError::SchemaViolation]. The imported changes that don't match the
+    /// schema are dropped together with the imported changes depending on them,
+    /// and reported in [crate::encoding::ImportStatus::rejected]. The other
+    /// changes of the import are applied.
+    ///
+    /// An imported change on a container that cannot be located, e.g. a child
+    /// container created by a change that is missing, is rejected too. The
+    /// rejections are remembered until the schema or the import filter is set
+    /// again, so the changes depending on them are rejected in later imports.
+    ///
+    /// The existing content of the document is not checked. When a schema is set,
+    /// snapshots are imported change by change so that they can be checked too.
+    pub fn set_schema(&self, schema: Option<Schema>) {
+        *self.config.schema.write().unwrap() = schema.map(std::sync::Arc::new);
+        // The changes rejected by the old schema may be accepted now
+        self.oplog.try_lock().unwrap().rejected_from.clear();
+    }
+
+    /// Get the schema of the document
+    pub fn schema(&self) -> Option<std::sync::Arc<Schema>> {
+        self.config.schema()
+    }
+}
@@ -19,7 +19,11 @@ use tracing::{info_span, instrument, warn};
 
 use crate::{
     configure::{Configure, DefaultRandom, SecureRandomGenerator},
-    container::{idx::ContainerIdx, richtext::config::StyleConfigMap, ContainerIdRaw},
+    container::{
+        idx::ContainerIdx,
+        richtext::{config::StyleConfigMap, EMBED_STYLE_KEY},
+        ContainerIdRaw,
+    },
     cursor::Cursor,
     delta::TreeExternalDiff,
     diff_calc::{DiffCalculator, DiffMode},
@@ -1199,6 +1203,14 @@ impl DocState {
 
     pub(crate) fn get_alive_children_of(&mut self, id: &ContainerID, ans: &mut Vec<ContainerID>) {
         let idx = self.arena.register_container(id);
+        if idx.get_type() == ContainerType::Text {
+            // The value of a text doesn't contain its embedded containers
+            if let Some(state) = self.store.get_container_mut(idx) {
+                ans.extend(state.get_child_containers());
+            }
+            return;
+        }
+
         let Some(value) = self.store.get_value(idx) else {
             return;
         };
@@ -1655,6 +1667,16 @@ fn trigger_on_new_container(
                 }
             }
         }
+        Diff::Text(text) => {
+            let key = InternalString::from(EMBED_STYLE_KEY);
+            for delta in text.iter() {
+                if let DeltaItem::Replace { attr, .. } = delta {
+                    if let Some(LoroValue::Container(id)) = attr.get(&key) {
+                        listener(arena.register_container(id));
+                    }
+                }
+            }
+        }
         Diff::Tree(tree) => {
             for item in tree.iter() {
                 if matches!(item.action, TreeExternalDiff::Create { .. }) {
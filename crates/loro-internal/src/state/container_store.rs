@@ -67,6 +67,9 @@ macro_rules! ctx {
     };
 }
 
+// The errors of the lazy loading in [InnerStore] are unwrapped here, like the decode errors
+// in [ContainerWrapper::get_state]. The headers of the containers in the kv store are
+// already checked when the state is imported, so they only fail on corrupted bytes.
 impl ContainerStore {
     pub fn new(arena: SharedArena, conf: Configure, peer: Arc<AtomicU64>) -> Self {
         ContainerStore {
@@ -89,6 +92,7 @@ impl ContainerStore {
     pub fn get_container_mut(&mut self, idx: ContainerIdx) -> Option<&mut State> {
         self.store
             .get_mut(idx)
+            .unwrap()
             .map(|x| x.get_state_mut(idx, ctx!(self)))
     }
 
@@ -96,6 +100,7 @@ impl ContainerStore {
     pub fn get_container(&mut self, idx: ContainerIdx) -> Option<&State> {
         self.store
             .get_mut(idx)
+            .unwrap()
             .map(|x| x.get_state(idx, ctx!(self)))
     }
 
@@ -106,6 +111,7 @@ impl ContainerStore {
     pub fn get_value(&mut self, idx: ContainerIdx) -> Option<LoroValue> {
         self.store
             .get_mut(idx)
+            .unwrap()
             .map(|c| c.get_value(idx, ctx!(self)))
     }
 
@@ -117,6 +123,17 @@ impl ContainerStore {
         self.store.flush()
     }
 
+    /// Encode the containers that are changed since the last [crate::LoroDoc::flush],
+    /// or all the containers if `all` is true.
+    pub(crate) fn encode_unpersisted(&mut self, all: bool) -> LoroResult<Vec<(Bytes, Bytes)>> {
+        self.store.encode_unpersisted(all)
+    }
+
+    /// Mark the containers as persisted after they are loaded from the persisted state
+    pub(crate) fn mark_all_persisted(&mut self) {
+        self.store.mark_all_persisted()
+    }
+
     pub fn shallow_root_frontiers(&self) -> Option<&Frontiers> {
         self.shallow_root_store
             .as_ref()
@@ -153,15 +170,18 @@ impl ContainerStore {
     }
 
     pub fn iter_and_decode_all(&mut self) -> impl Iterator<Item = &mut State> {
-        self.store.iter_all_containers_mut().map(|(idx, v)| {
-            v.get_state_mut(
-                *idx,
-                ContainerCreationContext {
-                    configure: &self.conf,
-                    peer: self.peer.load(std::sync::atomic::Ordering::Relaxed),
-                },
-            )
-        })
+        self.store
+            .iter_all_containers_mut()
+            .unwrap()
+            .map(|(idx, v)| {
+                v.get_state_mut(
+                    *idx,
+                    ContainerCreationContext {
+                        configure: &self.conf,
+                        peer: self.peer.load(std::sync::atomic::Ordering::Relaxed),
+                    },
+                )
+            })
     }
 
     pub fn get_kv(&self) -> &KvWrapper {
@@ -180,11 +200,11 @@ impl ContainerStore {
     pub fn iter_all_containers(
         &mut self,
     ) -> impl Iterator<Item = (&ContainerIdx, &mut ContainerWrapper)> {
-        self.store.iter_all_containers_mut()
+        self.store.iter_all_containers_mut().unwrap()
     }
 
     pub fn iter_all_container_ids(&mut self) -> impl Iterator<Item = ContainerID> + '_ {
-        self.store.iter_all_container_ids()
+        self.store.iter_all_container_ids().unwrap()
     }
 
     pub(super) fn get_or_create_mut(&mut self, idx: ContainerIdx) -> &mut State {
@@ -197,6 +217,7 @@ impl ContainerStore {
                 );
                 ContainerWrapper::new(state, &self.arena)
             })
+            .unwrap()
             .get_state_mut(idx, ctx!(self))
     }
 
@@ -222,6 +243,7 @@ impl ContainerStore {
                 );
                 ContainerWrapper::new(state, &self.arena)
             })
+            .unwrap()
             .get_state(idx, ctx!(self))
     }
 
@@ -250,12 +272,13 @@ impl ContainerStore {
             panic!("store len mismatch");
         }
 
-        for (idx, container) in self.store.iter_all_containers_mut() {
+        for (idx, container) in self.store.iter_all_containers_mut().unwrap() {
             let id = self.arena.get_container_id(*idx).unwrap();
             let other_idx = other.arena.register_container(&id);
             let other_container = other
                 .store
                 .get_mut(other_idx)
+                .unwrap()
                 .expect("container not found on other store");
             let other_id = other.arena.get_container_id(other_idx).unwrap();
             assert_eq!(
@@ -396,4 +419,46 @@ mod test {
         let mut new_store = decode_container_store(bytes);
         s.store.check_eq_after_parsing(&mut new_store);
     }
+
+    #[test]
+    fn compressed_text_is_exported_in_the_raw_encoding() {
+        use crate::{
+            encoding::{EncodeMode, ExportMode, SnapshotOptions, StateEncoding},
+            ContainerType,
+        };
+
+        let doc = init_doc();
+        let text = doc.get_text("text");
+        for i in 0..100 {
+            let line = format!("It was the best of times, it was the worst of times {i}\n");
+            text.insert(text.len_unicode(), &line).unwrap();
+        }
+        doc.commit_then_renew();
+        let compressed = doc
+            .export(ExportMode::snapshot_with_options(
+                SnapshotOptions::new().state_encoding(StateEncoding::CompressedText),
+            ))
+            .unwrap();
+        // The versions that cannot read the compressed texts reject the mode
+        assert_eq!(
+            compressed[20..22],
+            EncodeMode::FastSnapshotCompressedText.to_bytes()
+        );
+
+        let new_doc = LoroDoc::new();
+        new_doc.import(&compressed).unwrap();
+        // The state section of the snapshot, which is not loaded after the import
+        let bytes = new_doc.app_state().try_lock().unwrap().store.encode();
+        let kv = KvWrapper::new_mem();
+        kv.import(bytes);
+        let kinds: Vec<u8> = kv.with_kv(|kv| {
+            kv.scan(std::ops::Bound::Unbounded, std::ops::Bound::Unbounded)
+                .filter(|(k, _)| &k[..] != FRONTIERS_KEY)
+                .map(|(_, v)| v[0])
+                .collect()
+        });
+        assert!(kinds.contains(&ContainerType::Text.to_u8()));
+        assert!(!kinds.contains(&0x82));
+        assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    }
 }
@@ -1,5 +1,5 @@
 use bytes::Bytes;
-use loro_common::{ContainerID, ContainerType, LoroResult, LoroValue};
+use loro_common::{ContainerID, ContainerType, LoroError, LoroResult, LoroValue};
 
 #[cfg(feature = "counter")]
 use crate::state::counter_state::CounterState;
@@ -12,6 +12,13 @@ use crate::{
     },
 };
 
+/// The kind byte of the text containers in [crate::encoding::StateEncoding::CompressedText].
+///
+/// It's only used in the exported snapshots. The payload is decompressed into the raw
+/// encoding when the value or the state of the container is decoded, or when the
+/// container is encoded, so the flushed bytes are always raw.
+const COMPRESSED_TEXT_KIND: u8 = 0x80 | 2;
+
 #[derive(Debug)]
 pub(crate) struct ContainerWrapper {
     depth: usize,
@@ -29,6 +36,8 @@ pub(crate) struct ContainerWrapper {
     bytes_offset_for_state: Option<usize>,
     state: Option<State>,
     flushed: bool,
+    /// Whether the state is not changed since it was persisted by [crate::LoroDoc::flush]
+    persisted: bool,
 }
 
 impl ContainerWrapper {
@@ -48,6 +57,7 @@ impl ContainerWrapper {
             bytes_offset_for_state: None,
             bytes_offset_for_value: None,
             flushed: false,
+            persisted: false,
         }
     }
 
@@ -77,6 +87,7 @@ impl ContainerWrapper {
         self.bytes = None;
         self.value = None;
         self.flushed = false;
+        self.persisted = false;
         self.state.as_mut().unwrap()
     }
 
@@ -94,6 +105,7 @@ impl ContainerWrapper {
     }
 
     pub fn encode(&mut self) -> Bytes {
+        self.decompress_text().unwrap();
         if let Some(bytes) = self.bytes.as_ref() {
             return bytes.clone();
         }
@@ -123,23 +135,43 @@ impl ContainerWrapper {
         parent
     }
 
-    pub fn new_from_bytes(bytes: Bytes) -> Self {
-        let kind = ContainerType::try_from_u8(bytes[0]).unwrap();
-        let mut reader = &bytes[1..];
-        let depth = leb128::read::unsigned(&mut reader).unwrap();
-        let (parent, reader) = postcard::take_from_bytes(reader).unwrap();
-        let size = bytes.len() - reader.len();
-        Self {
+    /// Encode the raw bytes of a text container in the compressed text encoding.
+    ///
+    /// Returns `None` if it's not a text container, or the bytes are not smaller.
+    pub(crate) fn compress_text_bytes(bytes: &[u8]) -> LoroResult<Option<Bytes>> {
+        if bytes.first() != Some(&ContainerType::Text.to_u8()) {
+            return Ok(None);
+        }
+
+        let (_, _, offset) = decode_header(bytes)?;
+        let mut output = Vec::with_capacity(bytes.len());
+        output.push(COMPRESSED_TEXT_KIND);
+        output.extend_from_slice(&bytes[1..offset]);
+        RichtextState::compress_snapshot(&bytes[offset..], &mut output)?;
+        // Tiny texts may not benefit from the dictionary
+        Ok((output.len() < bytes.len()).then(|| output.into()))
+    }
+
+    pub fn new_from_bytes(bytes: Bytes) -> LoroResult<Self> {
+        // The compressed texts are not flushed, so they are written back in the raw encoding
+        let (kind, flushed) = match bytes.first() {
+            Some(&COMPRESSED_TEXT_KIND) => (ContainerType::Text, false),
+            Some(&kind) => (ContainerType::try_from_u8(kind)?, true),
+            None => return Err(header_err()),
+        };
+        let (depth, parent, size) = decode_header(&bytes)?;
+        Ok(Self {
             depth: depth as usize,
             kind,
             parent,
             state: None,
             value: None,
-            bytes: Some(bytes.clone()),
+            bytes: Some(bytes),
             bytes_offset_for_value: Some(size),
             bytes_offset_for_state: None,
-            flushed: true,
-        }
+            flushed,
+            persisted: false,
+        })
     }
 
     #[allow(unused)]
@@ -160,18 +192,13 @@ impl ContainerWrapper {
             return Ok(());
         }
 
+        self.decompress_text()?;
         let Some(bytes) = self.bytes.as_ref() else {
             return Ok(());
         };
 
         if self.bytes_offset_for_value.is_none() {
-            let mut reader: &[u8] = bytes;
-            reader = &reader[1..];
-            let _depth = leb128::read::unsigned(&mut reader).unwrap();
-            let (_parent, reader) =
-                postcard::take_from_bytes::<Option<ContainerID>>(reader).unwrap();
-            // SAFETY: bytes is a slice of b
-            let size = bytes.len() - reader.len();
+            let (_, _, size) = decode_header(bytes)?;
             self.bytes_offset_for_value = Some(size);
         }
 
@@ -241,6 +268,18 @@ impl ContainerWrapper {
         Ok(())
     }
 
+    /// Convert the bytes to the raw encoding if they are in the compressed text encoding
+    fn decompress_text(&mut self) -> LoroResult<()> {
+        if let Some(bytes) = self.bytes.as_ref() {
+            if bytes.first() == Some(&COMPRESSED_TEXT_KIND) {
+                // The header is kept as it is, so the offset of the value is not changed
+                self.bytes = Some(decompress_text_bytes(bytes)?);
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn estimate_size(&self) -> usize {
         if let Some(bytes) = self.bytes.as_ref() {
             return bytes.len();
@@ -274,8 +313,41 @@ impl ContainerWrapper {
         self.flushed = flushed;
     }
 
+    pub(crate) fn is_persisted(&self) -> bool {
+        self.persisted
+    }
+
+    pub(crate) fn set_persisted(&mut self, persisted: bool) {
+        self.persisted = persisted;
+    }
+
     #[allow(unused)]
     pub(crate) fn parent(&self) -> Option<&ContainerID> {
         self.parent.as_ref()
     }
 }
+
+fn header_err() -> LoroError {
+    LoroError::DecodeError("Decode container header failed".into())
+}
+
+/// Decode the depth and the parent after the kind byte.
+///
+/// Returns them with the length of the header.
+fn decode_header(bytes: &[u8]) -> LoroResult<(u64, Option<ContainerID>, usize)> {
+    let mut reader = bytes.get(1..).ok_or_else(header_err)?;
+    let depth = leb128::read::unsigned(&mut reader).map_err(|_| header_err())?;
+    let (parent, reader) =
+        postcard::take_from_bytes::<Option<ContainerID>>(reader).map_err(|_| header_err())?;
+    Ok((depth, parent, bytes.len() - reader.len()))
+}
+
+/// Convert the bytes of a compressed text container into the raw encoding
+fn decompress_text_bytes(bytes: &[u8]) -> LoroResult<Bytes> {
+    let (_, _, offset) = decode_header(bytes)?;
+    let mut output = Vec::with_capacity(bytes.len() * 2);
+    output.push(ContainerType::Text.to_u8());
+    output.extend_from_slice(&bytes[1..offset]);
+    RichtextState::decompress_snapshot(&bytes[offset..], &mut output)?;
+    Ok(output.into())
+}
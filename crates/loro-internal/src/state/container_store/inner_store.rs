@@ -4,7 +4,7 @@ use crate::{
 };
 use bytes::Bytes;
 use fxhash::FxHashMap;
-use loro_common::ContainerID;
+use loro_common::{ContainerID, LoroResult};
 use std::ops::Bound;
 
 use super::ContainerWrapper;
@@ -35,22 +35,22 @@ impl InnerStore {
         &mut self,
         idx: ContainerIdx,
         f: impl FnOnce() -> ContainerWrapper,
-    ) -> &mut ContainerWrapper {
+    ) -> LoroResult<&mut ContainerWrapper> {
         match self.store.entry(idx) {
             std::collections::hash_map::Entry::Vacant(e) => {
                 let id = self.arena.get_container_id(idx).unwrap();
                 let key = id.to_bytes();
                 if !self.all_loaded {
                     if let Some(v) = self.kv.get(&key) {
-                        let c = ContainerWrapper::new_from_bytes(v);
-                        return e.insert(c);
+                        let c = ContainerWrapper::new_from_bytes(v)?;
+                        return Ok(e.insert(c));
                     }
                 }
                 let c = f();
                 self.len += 1;
-                e.insert(c)
+                Ok(e.insert(c))
             }
-            std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+            std::collections::hash_map::Entry::Occupied(e) => Ok(e.into_mut()),
         }
     }
 
@@ -68,34 +68,40 @@ impl InnerStore {
         self.len += 1;
     }
 
-    pub(crate) fn get_mut(&mut self, idx: ContainerIdx) -> Option<&mut ContainerWrapper> {
+    pub(crate) fn get_mut(
+        &mut self,
+        idx: ContainerIdx,
+    ) -> LoroResult<Option<&mut ContainerWrapper>> {
         if let std::collections::hash_map::Entry::Vacant(e) = self.store.entry(idx) {
             let id = self.arena.get_container_id(idx).unwrap();
             let key = id.to_bytes();
             if !self.all_loaded {
                 if let Some(v) = self.kv.get(&key) {
-                    let c = ContainerWrapper::new_from_bytes(v);
+                    let c = ContainerWrapper::new_from_bytes(v)?;
                     e.insert(c);
                 }
             }
         }
 
-        self.store.get_mut(&idx)
+        Ok(self.store.get_mut(&idx))
     }
 
     pub(crate) fn iter_all_containers_mut(
         &mut self,
-    ) -> impl Iterator<Item = (&ContainerIdx, &mut ContainerWrapper)> {
-        self.load_all();
-        self.store.iter_mut()
+    ) -> LoroResult<impl Iterator<Item = (&ContainerIdx, &mut ContainerWrapper)>> {
+        self.load_all()?;
+        Ok(self.store.iter_mut())
     }
 
-    pub(crate) fn iter_all_container_ids(&mut self) -> impl Iterator<Item = ContainerID> + '_ {
+    pub(crate) fn iter_all_container_ids(
+        &mut self,
+    ) -> LoroResult<impl Iterator<Item = ContainerID> + '_> {
         // PERF: we don't need to load all the containers here
-        self.load_all();
-        self.store
+        self.load_all()?;
+        Ok(self
+            .store
             .keys()
-            .map(|idx| self.arena.get_container_id(*idx).unwrap())
+            .map(|idx| self.arena.get_container_id(*idx).unwrap()))
     }
 
     pub(crate) fn encode(&mut self) -> Bytes {
@@ -119,6 +125,35 @@ impl InnerStore {
             }));
     }
 
+    /// Encode the containers that are changed since they were persisted, or all the
+    /// containers if `all` is true, and mark them as persisted.
+    pub(crate) fn encode_unpersisted(&mut self, all: bool) -> LoroResult<Vec<(Bytes, Bytes)>> {
+        if all {
+            self.load_all()?;
+        }
+
+        Ok(self
+            .store
+            .iter_mut()
+            .filter_map(|(idx, c)| {
+                if c.is_persisted() && !all {
+                    return None;
+                }
+
+                let cid = self.arena.get_container_id(*idx).unwrap();
+                c.set_persisted(true);
+                Some((cid.to_bytes().into(), c.encode()))
+            })
+            .collect())
+    }
+
+    /// Mark all the loaded containers as persisted
+    pub(crate) fn mark_all_persisted(&mut self) {
+        for c in self.store.values_mut() {
+            c.set_persisted(true);
+        }
+    }
+
     pub(crate) fn get_kv(&self) -> &KvWrapper {
         &self.kv
     }
@@ -135,28 +170,7 @@ impl InnerStore {
             fr = Some(Frontiers::decode(&f)?);
         }
 
-        self.kv.with_kv(|kv| {
-            let mut count = self.len;
-            self.arena.with_guards(|guards| {
-                let iter = kv.scan(Bound::Unbounded, Bound::Unbounded);
-                for (k, v) in iter {
-                    count += 1;
-                    let cid = ContainerID::from_bytes(&k);
-                    let c = ContainerWrapper::new_from_bytes(v);
-                    let parent = c.parent();
-                    let idx = guards.register_container(&cid);
-                    let p = parent.as_ref().map(|p| guards.register_container(p));
-                    guards.set_parent(idx, p);
-                    if self.store.insert(idx, c).is_some() {
-                        count -= 1;
-                    }
-                }
-            });
-
-            self.len = count;
-        });
-
-        self.all_loaded = true;
+        self.load_all_from_kv()?;
         Ok(fr)
     }
 
@@ -171,55 +185,72 @@ impl InnerStore {
         self.kv.import(bytes_a);
         self.kv.import(bytes_b);
         self.kv.remove(FRONTIERS_KEY);
-        self.kv.with_kv(|kv| {
-            let mut count = self.len;
-            self.arena.with_guards(|guards| {
-                let iter = kv.scan(Bound::Unbounded, Bound::Unbounded);
-                for (k, v) in iter {
-                    count += 1;
+        self.load_all_from_kv()?;
+        Ok(())
+    }
+
+    /// Decode all the containers in the kv store into the store.
+    ///
+    /// The arena is not changed if any of them fails to decode.
+    fn load_all_from_kv(&mut self) -> LoroResult<()> {
+        let containers = self.kv.with_kv(|kv| {
+            kv.scan(Bound::Unbounded, Bound::Unbounded)
+                .map(|(k, v)| {
                     let cid = ContainerID::from_bytes(&k);
-                    let c = ContainerWrapper::new_from_bytes(v);
-                    let parent = c.parent();
-                    let idx = guards.register_container(&cid);
-                    let p = parent.as_ref().map(|p| guards.register_container(p));
-                    guards.set_parent(idx, p);
-                    if self.store.insert(idx, c).is_some() {
-                        count -= 1;
-                    }
+                    Ok((cid, ContainerWrapper::new_from_bytes(v)?))
+                })
+                .collect::<LoroResult<Vec<_>>>()
+        })?;
+
+        let mut count = self.len;
+        self.arena.with_guards(|guards| {
+            for (cid, c) in containers {
+                count += 1;
+                let parent = c.parent();
+                let idx = guards.register_container(&cid);
+                let p = parent.as_ref().map(|p| guards.register_container(p));
+                guards.set_parent(idx, p);
+                if self.store.insert(idx, c).is_some() {
+                    count -= 1;
                 }
-            });
-
-            self.len = count;
+            }
         });
 
+        self.len = count;
         self.all_loaded = true;
         Ok(())
     }
 
-    fn load_all(&mut self) {
+    fn load_all(&mut self) -> LoroResult<()> {
         if self.all_loaded {
-            return;
+            return Ok(());
         }
 
-        self.kv.with_kv(|kv| {
-            let iter = kv.scan(Bound::Unbounded, Bound::Unbounded);
-            self.arena.with_guards(|guards| {
-                for (k, v) in iter {
+        // Decode them first, so the arena is not changed if any of them fails to decode
+        let containers = self.kv.with_kv(|kv| {
+            kv.scan(Bound::Unbounded, Bound::Unbounded)
+                .map(|(k, v)| {
                     let cid = ContainerID::from_bytes(&k);
-                    let idx = guards.register_container(&cid);
-                    if self.store.contains_key(&idx) {
-                        // the container is already loaded
-                        // the content in `store` is guaranteed to be newer than the content in `kv`
-                        continue;
-                    }
-
-                    let container = ContainerWrapper::new_from_bytes(v);
-                    self.store.insert(idx, container);
+                    Ok((cid, ContainerWrapper::new_from_bytes(v)?))
+                })
+                .collect::<LoroResult<Vec<_>>>()
+        })?;
+
+        self.arena.with_guards(|guards| {
+            for (cid, container) in containers {
+                let idx = guards.register_container(&cid);
+                if self.store.contains_key(&idx) {
+                    // the container is already loaded
+                    // the content in `store` is guaranteed to be newer than the content in `kv`
+                    continue;
                 }
-            });
+
+                self.store.insert(idx, container);
+            }
         });
 
         self.all_loaded = true;
+        Ok(())
     }
 
     pub(crate) fn can_import_snapshot(&self) -> bool {
@@ -5,7 +5,7 @@ use std::{
 
 use fxhash::{FxHashMap, FxHashSet};
 use generic_btree::{rle::HasLength, Cursor};
-use loro_common::{ContainerID, InternalString, LoroError, LoroResult, LoroValue, ID};
+use loro_common::{ContainerID, IdFull, InternalString, LoroError, LoroResult, LoroValue, ID};
 use loro_delta::DeltaRopeBuilder;
 
 use crate::{
@@ -18,7 +18,8 @@ use crate::{
             richtext_state::{
                 DrainInfo, EntityRangeInfo, IterRangeItem, PosType, RichtextStateChunk,
             },
-            AnchorType, RichtextState as InnerState, StyleOp, Styles,
+            AnchorType, RichtextSpan, RichtextState as InnerState, StyleOp, Styles, EMBED_CHAR,
+            EMBED_STYLE_KEY,
         },
     },
     delta::{StyleMeta, StyleMetaItem},
@@ -49,6 +50,16 @@ struct Pos {
 }
 
 impl RichtextState {
+    /// Get the child containers embedded in the text, with their event indexes.
+    ///
+    /// The index is `None` if the char of the embed is deleted. It scans all the chunks.
+    fn embedded_containers(&self) -> Vec<(Option<usize>, ContainerID)> {
+        match &self.state {
+            LazyLoad::Src(s) => embedded_containers(s.elements.iter()),
+            LazyLoad::Dst(s) => embedded_containers(s.iter_chunk()),
+        }
+    }
+
     #[inline]
     pub fn new(idx: ContainerIdx, config: Arc<RwLock<StyleConfigMap>>) -> Self {
         Self {
@@ -104,6 +115,27 @@ impl RichtextState {
         self.state.get_mut().get_text_slice_by_event_index(pos, len)
     }
 
+    pub(crate) fn get_styles_at(&mut self, pos: usize, pos_type: PosType) -> Option<StyleMeta> {
+        self.state.get_mut().get_styles_at(pos, pos_type)
+    }
+
+    pub(crate) fn get_style_ranges(
+        &mut self,
+        key: &str,
+        pos_type: PosType,
+    ) -> Vec<(Range<usize>, LoroValue)> {
+        self.state.get_mut().get_style_ranges(key, pos_type)
+    }
+
+    pub(crate) fn slice_spans(
+        &mut self,
+        start: usize,
+        end: usize,
+        pos_type: PosType,
+    ) -> LoroResult<Vec<RichtextSpan>> {
+        self.state.get_mut().slice_spans(start, end, pos_type)
+    }
+
     pub(crate) fn get_char_by_event_index(&mut self, pos: usize) -> Result<char, ()> {
         self.state.get_mut().get_char_by_event_index(pos)
     }
@@ -261,10 +293,11 @@ impl RichtextState {
         let mut delta = Vec::new();
         // TODO: merge last
         for span in self.state.get_mut().iter() {
-            delta.push(TextDelta::Insert {
-                insert: span.text.as_str().to_string(),
-                attributes: span.attributes.to_option_map(),
-            })
+            TextDelta::push_insert(
+                &mut delta,
+                span.text.as_str(),
+                span.attributes.to_option_map(),
+            );
         }
         delta
     }
@@ -573,6 +606,7 @@ impl ContainerState for RichtextState {
 
     fn apply_local_op(&mut self, r_op: &RawOp, op: &Op) -> LoroResult<ApplyLocalOpReturn> {
         self.update_version();
+        let mut ans = ApplyLocalOpReturn::default();
         match &op.content {
             crate::op::InnerContent::List(l) => match l {
                 list_op::InnerListOp::Insert { slice: _, pos: _ } => {
@@ -591,11 +625,24 @@ impl ContainerState for RichtextState {
                     );
                 }
                 list_op::InnerListOp::Delete(del) => {
+                    let mut has_embed = false;
                     self.state.get_mut().drain_by_entity_index(
                         del.start() as usize,
                         rle::HasLength::atom_len(&del),
-                        None,
+                        Some(&mut |chunk| {
+                            if let RichtextStateChunk::Text(t) = chunk {
+                                has_embed |= t.as_str().contains(EMBED_CHAR);
+                            }
+                        }),
                     );
+                    if has_embed {
+                        // The embedded containers whose chars are removed are deleted
+                        ans.deleted_containers = self
+                            .embedded_containers()
+                            .into_iter()
+                            .filter_map(|(pos, id)| pos.is_none().then_some(id))
+                            .collect();
+                    }
                 }
                 list_op::InnerListOp::StyleStart {
                     start,
@@ -633,7 +680,7 @@ impl ContainerState for RichtextState {
         }
 
         // self.check_consistency_between_content_and_style_ranges();
-        Ok(Default::default())
+        Ok(ans)
     }
 
     fn to_diff(
@@ -656,18 +703,23 @@ impl ContainerState for RichtextState {
     }
 
     #[doc = r" Get the index of the child container"]
-    #[allow(unused)]
     fn get_child_index(&self, id: &ContainerID) -> Option<Index> {
-        None
+        self.embedded_containers()
+            .into_iter()
+            .find(|(_, x)| x == id)
+            .and_then(|(pos, _)| pos)
+            .map(Index::Seq)
     }
 
-    #[allow(unused)]
     fn get_child_containers(&self) -> Vec<ContainerID> {
-        Vec::new()
+        self.embedded_containers()
+            .into_iter()
+            .filter_map(|(pos, id)| pos.map(|_| id))
+            .collect()
     }
 
-    fn contains_child(&self, _id: &ContainerID) -> bool {
-        false
+    fn contains_child(&self, id: &ContainerID) -> bool {
+        self.get_child_index(id).is_some()
     }
 
     #[doc = " Get a list of ops that can be used to restore the state to the current state"]
@@ -820,6 +872,33 @@ impl RichtextState {
             .get_entity_range_and_text_styles_at_range(range, pos_type)
     }
 
+    #[inline]
+    pub(crate) fn convert_pos(&mut self, pos: usize, from: PosType, to: PosType) -> Option<usize> {
+        self.state.get_mut().convert_pos(pos, from, to)
+    }
+
+    #[inline]
+    pub(crate) fn get_style_values_in_entity_range(
+        &mut self,
+        range: Range<usize>,
+        key: &str,
+    ) -> Vec<LoroValue> {
+        self.state
+            .get_mut()
+            .get_style_values_in_entity_range(range, key)
+    }
+
+    #[inline]
+    pub(crate) fn get_style_values_in_event_range(
+        &mut self,
+        range: Range<usize>,
+        key: &str,
+    ) -> Vec<(usize, LoroValue)> {
+        self.state
+            .get_mut()
+            .get_style_values_in_event_range(range, key)
+    }
+
     #[inline]
     pub(crate) fn get_styles_at_entity_index(&mut self, entity_index: usize) -> StyleMeta {
         self.state
@@ -859,6 +938,22 @@ impl RichtextState {
         )
     }
 
+    /// The ids and the entity lengths of the chunks, in the order of the text.
+    ///
+    /// The style anchors have no id.
+    pub(crate) fn get_entity_chunk_ids(&mut self) -> Vec<(Option<IdFull>, usize)> {
+        self.state
+            .get_mut()
+            .iter_chunk()
+            .map(|chunk| match chunk {
+                RichtextStateChunk::Text(text) => {
+                    (Some(text.id_full()), text.unicode_len() as usize)
+                }
+                RichtextStateChunk::Style { .. } => (None, 1),
+            })
+            .collect()
+    }
+
     pub(crate) fn entity_index_to_event_index(&mut self, entity_index: usize) -> usize {
         self.state
             .get_mut()
@@ -877,6 +972,45 @@ impl RichtextState {
 }
 
 #[derive(Debug, Default, Clone)]
+/// The container of an embed is the value of the mark around its char. The mark
+/// doesn't expand, so the first text chunk inside the mark is the char of the embed.
+fn embedded_containers<'a>(
+    chunks: impl Iterator<Item = &'a RichtextStateChunk>,
+) -> Vec<(Option<usize>, ContainerID)> {
+    let mut ans = Vec::new();
+    let mut event_index = 0;
+    let mut current: Option<&Arc<StyleOp>> = None;
+    for chunk in chunks {
+        match chunk {
+            RichtextStateChunk::Text(t) => {
+                if let Some(style) = current.take() {
+                    let id = style.value.as_container().unwrap().clone();
+                    ans.push((Some(event_index), id));
+                }
+                event_index += t.event_len() as usize;
+            }
+            RichtextStateChunk::Style { style, anchor_type } => {
+                if style.key.as_str() != EMBED_STYLE_KEY || !style.value.is_container() {
+                    continue;
+                }
+
+                match anchor_type {
+                    AnchorType::Start => current = Some(style),
+                    AnchorType::End => {
+                        // The char of the embed is deleted
+                        if current.is_some_and(|x| x.peer == style.peer && x.cnt == style.cnt) {
+                            current = None;
+                            ans.push((None, style.value.as_container().unwrap().clone()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    ans
+}
+
 pub(crate) struct RichtextStateLoader {
     start_anchor_pos: FxHashMap<ID, usize>,
     elements: Vec<RichtextStateChunk>,
@@ -932,7 +1066,7 @@ impl RichtextStateLoader {
 
 mod snapshot {
     use fxhash::FxHashMap;
-    use loro_common::{IdFull, InternalString, LoroValue, PeerID};
+    use loro_common::{IdFull, InternalString, LoroError, LoroResult, LoroValue, PeerID};
     use serde_columnar::columnar;
     use std::{io::Read, sync::Arc};
 
@@ -980,6 +1114,191 @@ mod snapshot {
         marks: Vec<EncodedMark>,
     }
 
+    #[columnar(vec, ser, de, iterable)]
+    #[derive(Debug, Clone)]
+    struct EncodedWord {
+        index: usize,
+    }
+
+    #[columnar(vec, ser, de, iterable)]
+    #[derive(Debug, Clone)]
+    struct EncodedMarkIndex {
+        #[columnar(strategy = "Rle")]
+        index: usize,
+    }
+
+    /// The compressed form of [EncodedText] along with the text content.
+    ///
+    /// - The text is stored as the indexes of its words in `words`
+    /// - The marks are deduplicated, so the same style used many times is only stored once
+    #[columnar(ser, de)]
+    struct CompressedText {
+        words: Vec<String>,
+        #[columnar(class = "vec", iter = "EncodedWord")]
+        word_indexes: Vec<EncodedWord>,
+        #[columnar(class = "vec", iter = "EncodedTextSpan")]
+        spans: Vec<EncodedTextSpan>,
+        keys: Vec<InternalString>,
+        marks: Vec<EncodedMark>,
+        #[columnar(class = "vec", iter = "EncodedMarkIndex")]
+        mark_indexes: Vec<EncodedMarkIndex>,
+    }
+
+    fn compressed_text_err<E>(_: E) -> LoroError {
+        LoroError::DecodeError("Decode compressed text failed".into())
+    }
+
+    /// Split the bytes of the peers section from the start of the bytes
+    fn split_peers(bytes: &[u8]) -> LoroResult<(&[u8], &[u8])> {
+        let mut reader = bytes;
+        let peer_num = leb128::read::unsigned(&mut reader).map_err(compressed_text_err)? as usize;
+        let len = bytes.len() - reader.len() + peer_num * 8;
+        if len > bytes.len() {
+            return Err(compressed_text_err(()));
+        }
+
+        Ok(bytes.split_at(len))
+    }
+
+    /// Split the text into words. A word is a run of letters and digits or a single
+    /// other char, followed by at most one space.
+    ///
+    /// The chars that take 3 or more bytes in UTF-8, such as CJK, are not grouped,
+    /// because they are usually not separated by spaces.
+    fn split_words(text: &str) -> Vec<&str> {
+        let is_word_char = |c: char| c.is_alphanumeric() && c.len_utf8() < 3;
+        let mut words = Vec::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            let mut end = start + c.len_utf8();
+            if is_word_char(c) {
+                while let Some(&(i, c)) = chars.peek() {
+                    if !is_word_char(c) {
+                        break;
+                    }
+
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+            }
+
+            if let Some(&(i, ' ')) = chars.peek() {
+                end = i + 1;
+                chars.next();
+            }
+
+            words.push(&text[start..end]);
+        }
+
+        words
+    }
+
+    /// Encode the text as a dictionary of the unique words and the indexes of the words.
+    ///
+    /// The dictionary is sorted by frequency, so the common words take fewer bytes.
+    fn encode_words(text: &str) -> (Vec<String>, Vec<EncodedWord>) {
+        let words = split_words(text);
+        let mut counts: FxHashMap<&str, usize> = FxHashMap::default();
+        let mut dict: Vec<&str> = Vec::new();
+        for &word in words.iter() {
+            *counts.entry(word).or_insert_with(|| {
+                dict.push(word);
+                0
+            }) += 1;
+        }
+
+        dict.sort_by_key(|w| std::cmp::Reverse(counts[w]));
+        let indexes: FxHashMap<&str, usize> =
+            dict.iter().enumerate().map(|(i, w)| (*w, i)).collect();
+        let word_indexes = words
+            .iter()
+            .map(|w| EncodedWord { index: indexes[w] })
+            .collect();
+        (
+            dict.into_iter().map(|w| w.to_string()).collect(),
+            word_indexes,
+        )
+    }
+
+    impl RichtextState {
+        /// Convert the bytes encoded by [FastStateSnapshot::encode_snapshot_fast] into the
+        /// compressed form, which can be converted back by [RichtextState::decompress_snapshot].
+        ///
+        /// The peers section is kept as it is.
+        pub(crate) fn compress_snapshot(bytes: &[u8], w: &mut Vec<u8>) -> LoroResult<()> {
+            let (text, bytes): (String, _) =
+                postcard::take_from_bytes(bytes).map_err(compressed_text_err)?;
+            let (peers, bytes) = split_peers(bytes)?;
+            let EncodedText { spans, keys, marks } =
+                serde_columnar::from_bytes(bytes).map_err(compressed_text_err)?;
+            let (words, word_indexes) = encode_words(&text);
+            let mut unique_marks: ValueRegister<(usize, LoroValue, u8)> = ValueRegister::new();
+            let mark_indexes = marks
+                .into_iter()
+                .map(|m| EncodedMarkIndex {
+                    index: unique_marks.register(&(m.key_idx, m.value, m.info)),
+                })
+                .collect();
+            let marks = unique_marks
+                .unwrap_vec()
+                .into_iter()
+                .map(|(key_idx, value, info)| EncodedMark {
+                    key_idx,
+                    value,
+                    info,
+                })
+                .collect();
+
+            w.extend_from_slice(peers);
+            let bytes = serde_columnar::to_vec(&CompressedText {
+                words,
+                word_indexes,
+                spans,
+                keys,
+                marks,
+                mark_indexes,
+            })
+            .map_err(compressed_text_err)?;
+            w.extend_from_slice(&bytes);
+            Ok(())
+        }
+
+        /// Convert the bytes encoded by [RichtextState::compress_snapshot] back into the
+        /// form of [FastStateSnapshot::encode_snapshot_fast]
+        pub(crate) fn decompress_snapshot(bytes: &[u8], w: &mut Vec<u8>) -> LoroResult<()> {
+            let (peers, bytes) = split_peers(bytes)?;
+            let CompressedText {
+                words,
+                word_indexes,
+                spans,
+                keys,
+                marks,
+                mark_indexes,
+            } = serde_columnar::from_bytes(bytes).map_err(compressed_text_err)?;
+            let mut text = String::new();
+            for EncodedWord { index } in word_indexes {
+                text.push_str(words.get(index).ok_or_else(|| compressed_text_err(()))?);
+            }
+
+            let marks = mark_indexes
+                .into_iter()
+                .map(|EncodedMarkIndex { index }| {
+                    marks
+                        .get(index)
+                        .cloned()
+                        .ok_or_else(|| compressed_text_err(()))
+                })
+                .collect::<LoroResult<Vec<_>>>()?;
+
+            postcard::to_io(text.as_str(), &mut *w).unwrap();
+            w.extend_from_slice(peers);
+            let bytes = serde_columnar::to_vec(&EncodedText { spans, keys, marks })
+                .map_err(compressed_text_err)?;
+            w.extend_from_slice(&bytes);
+            Ok(())
+        }
+    }
+
     impl FastStateSnapshot for RichtextState {
         /// Encodes the RichtextState into a compact binary format for fast snapshot storage and retrieval.
         ///
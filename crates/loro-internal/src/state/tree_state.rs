@@ -908,6 +908,68 @@ impl TreeState {
             .map_or(false, |x| x.parent == *parent)
     }
 
+    /// Iterate the ancestors of the node from its parent to the root of its tree.
+    ///
+    /// The root of a deleted subtree is the node whose parent is [TreeParentId::Deleted].
+    pub(crate) fn ancestors(&self, target: &TreeID) -> impl Iterator<Item = TreeID> + '_ {
+        let parent_node = move |id: &TreeID| self.parent(id).and_then(|p| p.tree_id());
+        std::iter::successors(parent_node(target), parent_node)
+    }
+
+    /// The depth of the node, which is 0 for the root nodes.
+    /// If the node does not exist, return None
+    pub(crate) fn depth(&self, target: &TreeID) -> Option<usize> {
+        if self.is_node_unexist(target) {
+            return None;
+        }
+
+        Some(self.ancestors(target).count())
+    }
+
+    /// Get the lowest node that is `a` or the ancestor of `a` and also `b` or the ancestor of `b`.
+    ///
+    /// Return None if they are not in the same tree.
+    pub(crate) fn lowest_common_ancestor(&self, a: &TreeID, b: &TreeID) -> Option<TreeID> {
+        if self.is_node_unexist(a) || self.is_node_unexist(b) {
+            return None;
+        }
+
+        let a_chain: FxHashSet<TreeID> = std::iter::once(*a).chain(self.ancestors(a)).collect();
+        std::iter::once(*b)
+            .chain(self.ancestors(b))
+            .find(|x| a_chain.contains(x))
+    }
+
+    /// Walk the nodes under `start` in depth-first pre-order.
+    /// If `start` is a node, it's the first visited node.
+    pub(crate) fn walk_dfs(&self, start: TreeParentId) -> impl Iterator<Item = TreeID> + '_ {
+        let mut stack = self.walk_start(start);
+        stack.reverse();
+        std::iter::from_fn(move || {
+            let id = stack.pop()?;
+            if let Some(children) = self.get_children(&TreeParentId::Node(id)) {
+                let len = stack.len();
+                stack.extend(children);
+                stack[len..].reverse();
+            }
+
+            Some(id)
+        })
+    }
+
+    /// The nodes to visit first when walking from `start`
+    pub(crate) fn walk_start(&self, start: TreeParentId) -> Vec<TreeID> {
+        match start {
+            TreeParentId::Node(id) if self.is_node_unexist(&id) => vec![],
+            TreeParentId::Node(id) => vec![id],
+            TreeParentId::Unexist => vec![],
+            parent => self
+                .get_children(&parent)
+                .map(|x| x.collect())
+                .unwrap_or_default(),
+        }
+    }
+
     /// Delete the position cache of the node
     pub(crate) fn delete_position(&mut self, parent: &TreeParentId, target: &TreeID) {
         if let Some(x) = self.children.get_mut(parent) {
@@ -973,6 +1035,18 @@ impl TreeState {
         self.fractional_index_config = TreeFractionalIndexConfigInner::MoveDisabled;
     }
 
+    /// Get the children of the parent with their positions in order
+    pub(crate) fn get_children_positions(
+        &self,
+        parent: &TreeParentId,
+    ) -> Option<Vec<(TreeID, FractionalIndex)>> {
+        self.children.get(parent).map(|x| {
+            x.iter()
+                .map(|(position, id)| (*id, position.position.clone()))
+                .collect()
+        })
+    }
+
     pub(crate) fn get_position(&self, target: &TreeID) -> Option<FractionalIndex> {
         self.trees.get(target).and_then(|x| x.position.clone())
     }
@@ -1204,6 +1278,18 @@ impl ContainerState for TreeState {
                         self.mov(target, *parent, last_move_op, position.clone(), false)
                             .unwrap();
                     }
+                    // The rejections are only reported from the diff calculator, which
+                    // tells the rejected op and the reason
+                    TreeInternalDiff::Rejected { id, parent, reason } => {
+                        ans.push(TreeDiffItem {
+                            target,
+                            action: TreeExternalDiff::MoveRejected {
+                                id: *id,
+                                parent: *parent,
+                                reason: *reason,
+                            },
+                        });
+                    }
                     TreeInternalDiff::UnCreate => {
                         // maybe the node created and moved to the parent deleted
                         if !self.is_node_deleted(&target).unwrap() {
@@ -1266,6 +1352,7 @@ impl ContainerState for TreeState {
                         self.mov(target, *parent, last_move_op, position.clone(), false)
                             .unwrap();
                     }
+                    TreeInternalDiff::Rejected { .. } => {}
                     TreeInternalDiff::UnCreate => {
                         // delete it from state
                         let parent = self.trees.remove(&target);
@@ -0,0 +1,245 @@
+//! The blame of the text, i.e. the changes that inserted each run of the text.
+//!
+//! The ids of the current text are read from the richtext state. The text at an
+//! older version is calculated by the diff from the current version to it, which
+//! only visits the ops between the two versions, so the document is not checked out.
+use std::{ops::Range, sync::Arc};
+
+use generic_btree::rle::HasLength;
+use loro_common::{
+    ContainerID, ContainerType, HasCounterSpan, IdFull, Lamport, LoroError, LoroResult, PeerID,
+    Timestamp, ID,
+};
+
+use crate::{
+    container::richtext::richtext_state::RichtextStateChunk,
+    dag::Dag,
+    diff_calc::DiffCalculator,
+    event::{DiffVariant, InternalDiff},
+    version::Frontiers,
+    LoroDoc, OpLog,
+};
+
+/// A run of the text that is inserted by the same change.
+///
+/// The range is in unicode chars.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextBlameSpan {
+    pub range: Range<usize>,
+    /// The peer that inserted the text
+    pub peer: PeerID,
+    /// The id of the change that inserted the text.
+    ///
+    /// If the change is not available, e.g. it's before the shallow root or it's
+    /// still in the pending transaction, it's the id of the first char of the span.
+    pub change_id: ID,
+    /// The lamport of the first char of the span
+    pub lamport: Lamport,
+    /// The timestamp of the change. It's 0 if the timestamp was not recorded.
+    pub timestamp: Timestamp,
+    /// The commit message of the change
+    pub message: Option<Arc<str>>,
+}
+
+/// The id and the entity length of a chunk of the richtext state.
+///
+/// The style anchors have no id, and their entity length is 1.
+type EntityChunk = (Option<IdFull>, usize);
+
+impl LoroDoc {
+    /// Get the spans of the text grouped by the changes that inserted them.
+    ///
+    /// It's computed from the ids of the current state and the metadata of the changes.
+    /// The text in the pending transaction doesn't have the change metadata until the
+    /// transaction is committed.
+    pub fn text_blame(&self, id: &ContainerID) -> LoroResult<Vec<TextBlameSpan>> {
+        check_text_id(id)?;
+        let oplog = self.oplog.try_lock().unwrap();
+        let mut state = self.state.try_lock().unwrap();
+        let Some(idx) = self.arena.id_to_idx(id) else {
+            return Ok(Vec::new());
+        };
+
+        let chunks = state.with_state_mut(idx, |s| {
+            s.as_richtext_state_mut().unwrap().get_entity_chunk_ids()
+        });
+        drop(state);
+        Ok(blame_chunks(&oplog, chunks))
+    }
+
+    /// Get the blame of the text at the given version.
+    ///
+    /// The text at the version is calculated by the diff from the current version, so
+    /// the state of the document is not changed. The pending transaction is committed
+    /// first.
+    pub fn text_blame_at(
+        &self,
+        id: &ContainerID,
+        frontiers: &Frontiers,
+    ) -> LoroResult<Vec<TextBlameSpan>> {
+        check_text_id(id)?;
+        self.commit_then_stop();
+        let ans = self.text_blame_at_inner(id, frontiers);
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
+    fn text_blame_at_inner(
+        &self,
+        id: &ContainerID,
+        frontiers: &Frontiers,
+    ) -> LoroResult<Vec<TextBlameSpan>> {
+        let oplog = self.oplog.try_lock().unwrap();
+        for id in frontiers.iter() {
+            if !oplog.dag().contains(id) {
+                return Err(LoroError::FrontiersNotFound(id));
+            }
+        }
+
+        if oplog.dag().is_before_shallow_root(frontiers) {
+            return Err(LoroError::SwitchToVersionBeforeShallowRoot);
+        }
+
+        let Some(idx) = self.arena.id_to_idx(id) else {
+            return Ok(Vec::new());
+        };
+
+        let mut state = self.state.try_lock().unwrap();
+        let mut chunks = state.with_state_mut(idx, |s| {
+            s.as_richtext_state_mut().unwrap().get_entity_chunk_ids()
+        });
+        if &state.frontiers != frontiers {
+            let before = oplog.dag().frontiers_to_vv(&state.frontiers).unwrap();
+            let Some(after) = oplog.dag().frontiers_to_vv(frontiers) else {
+                return Err(LoroError::SwitchToVersionBeforeShallowRoot);
+            };
+
+            let mut calc = DiffCalculator::new(false);
+            let (diffs, _) = calc.calc_diff_internal(
+                &oplog,
+                &before,
+                &state.frontiers,
+                &after,
+                frontiers,
+                Some(&|x| x == idx),
+            );
+            for diff in diffs {
+                if let DiffVariant::Internal(InternalDiff::RichtextRaw(delta)) = diff.diff {
+                    chunks = apply_richtext_delta(chunks, delta.iter());
+                }
+            }
+        }
+
+        drop(state);
+        Ok(blame_chunks(&oplog, chunks))
+    }
+}
+
+fn check_text_id(id: &ContainerID) -> LoroResult<()> {
+    if id.container_type() != ContainerType::Text {
+        return Err(LoroError::ArgErr(
+            format!(
+                "The blame is only available on text containers, but got {}",
+                id
+            )
+            .into_boxed_str(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Apply the diff between the versions to the chunks, whose indexes are entity indexes
+fn apply_richtext_delta<'a>(
+    chunks: Vec<EntityChunk>,
+    delta: impl Iterator<Item = &'a loro_delta::DeltaItem<RichtextStateChunk, ()>>,
+) -> Vec<EntityChunk> {
+    let mut ans = Vec::with_capacity(chunks.len());
+    let mut old = chunks.into_iter();
+    // The rest of the old chunk that is partially consumed
+    let mut current: Option<EntityChunk> = None;
+    let mut next = |mut len: usize, mut ans: Option<&mut Vec<EntityChunk>>| {
+        while len > 0 {
+            let Some((id, chunk_len)) = current.take().or_else(|| old.next()) else {
+                break;
+            };
+
+            let taken = chunk_len.min(len);
+            if let Some(ans) = ans.as_deref_mut() {
+                ans.push((id, taken));
+            }
+
+            if taken < chunk_len {
+                current = Some((id.map(|id| id.inc(taken as i32)), chunk_len - taken));
+            }
+
+            len -= taken;
+        }
+    };
+
+    for item in delta {
+        match item {
+            loro_delta::DeltaItem::Retain { len, .. } => next(*len, Some(&mut ans)),
+            loro_delta::DeltaItem::Replace { value, delete, .. } => {
+                next(*delete, None);
+                if value.rle_len() == 0 {
+                    continue;
+                }
+
+                match value {
+                    RichtextStateChunk::Text(text) => {
+                        ans.push((Some(text.id_full()), text.unicode_len() as usize))
+                    }
+                    RichtextStateChunk::Style { .. } => ans.push((None, 1)),
+                }
+            }
+        }
+    }
+
+    next(usize::MAX, Some(&mut ans));
+    ans
+}
+
+/// Split the text chunks by the changes, then merge the adjacent spans of the same change
+fn blame_chunks(oplog: &OpLog, chunks: Vec<EntityChunk>) -> Vec<TextBlameSpan> {
+    let mut ans: Vec<TextBlameSpan> = Vec::new();
+    let mut pos = 0;
+    for (id, mut len) in chunks {
+        let Some(mut id) = id else {
+            continue;
+        };
+
+        while len > 0 {
+            let change = oplog.get_change_at(id.id());
+            let (span_len, change_id, timestamp, message) = match &change {
+                Some(change) => (
+                    len.min((change.ctr_end() - id.counter) as usize),
+                    change.id,
+                    change.timestamp,
+                    change.message().cloned(),
+                ),
+                None => (len, id.id(), 0, None),
+            };
+
+            match ans.last_mut() {
+                Some(last) if change.is_some() && last.change_id == change_id => {
+                    last.range.end += span_len;
+                }
+                _ => ans.push(TextBlameSpan {
+                    range: pos..pos + span_len,
+                    peer: id.peer,
+                    change_id,
+                    lamport: id.lamport,
+                    timestamp,
+                    message,
+                }),
+            }
+
+            pos += span_len;
+            len -= span_len;
+            id = id.inc(span_len as i32);
+        }
+    }
+
+    ans
+}
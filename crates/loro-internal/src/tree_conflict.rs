@@ -0,0 +1,55 @@
+//! The create and move ops of the trees that are rejected when resolving the
+//! concurrent edits.
+//!
+//! The rejections are recorded in the history cache of the tree when the diff is
+//! calculated, so only the ops after the given version are visited.
+use loro_common::{ContainerID, ContainerType, LoroError, LoroResult};
+
+use crate::{
+    dag::Dag, delta::RejectedTreeMove, diff_calc::tree::TreeDiffCalculator, version::Frontiers,
+    LoroDoc,
+};
+
+impl LoroDoc {
+    /// Get the create and move ops of the tree after `since` that are rejected in the
+    /// current version when resolving the concurrent edits.
+    ///
+    /// An op is rejected if it would create a cycle, or if the new parent is deleted by
+    /// a concurrent op so the target is deleted with it. The ops in the pending
+    /// transaction are not included.
+    pub fn get_rejected_tree_moves(
+        &self,
+        id: &ContainerID,
+        since: &Frontiers,
+    ) -> LoroResult<Vec<RejectedTreeMove>> {
+        if id.container_type() != ContainerType::Tree {
+            return Err(LoroError::ArgErr(
+                format!(
+                    "The rejected moves are only available on tree containers, but got {}",
+                    id
+                )
+                .into_boxed_str(),
+            ));
+        }
+
+        let frontiers = self.state_frontiers();
+        let oplog = self.oplog.try_lock().unwrap();
+        for id in since.iter() {
+            if !oplog.dag().contains(id) {
+                return Err(LoroError::FrontiersNotFound(id));
+            }
+        }
+
+        let Some(mut since_vv) = oplog.dag().frontiers_to_vv(since) else {
+            return Err(LoroError::SwitchToVersionBeforeShallowRoot);
+        };
+        // The ops before the shallow root are all regarded as known
+        since_vv.merge(&oplog.shallow_since_vv().to_vv());
+        let Some(idx) = self.arena.id_to_idx(id) else {
+            return Ok(Vec::new());
+        };
+
+        let to_vv = oplog.dag().frontiers_to_vv(&frontiers).unwrap();
+        Ok(TreeDiffCalculator::new(idx).rejected_moves(&oplog, &since_vv, &to_vv, &frontiers))
+    }
+}
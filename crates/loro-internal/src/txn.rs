@@ -16,7 +16,7 @@ use crate::{
     change::{Change, Lamport, Timestamp},
     container::{
         idx::ContainerIdx,
-        list::list_op::{DeleteSpan, InnerListOp},
+        list::list_op::{DeleteSpan, InnerListOp, ListOp},
         richtext::Style,
         IntoContainerId,
     },
@@ -26,6 +26,7 @@ use crate::{
     handler::{Handler, ValueOrHandler},
     id::{Counter, PeerID, ID},
     op::{Op, RawOp, RawOpContent},
+    schema::{OpValues, SchemaPath},
     span::HasIdSpan,
     version::Frontiers,
     InternalString, LoroError, LoroValue,
@@ -188,6 +189,10 @@ pub(super) enum EventHint {
         start: u32,
         end: u32,
         style: Style,
+        /// The value of a non-exclusive style depends on the other marks of the key.
+        /// So the event lengths of the segments in the range and the values of the
+        /// style in them are read from the state after the mark is applied.
+        values: Option<Vec<(usize, LoroValue)>>,
     },
     InsertText {
         /// pos is a Unicode index. If wasm, it's a UTF-16 index.
@@ -518,8 +523,43 @@ impl Transaction {
             });
         }
 
+        if let Some(schema) = state.config.schema() {
+            // An op that cannot be checked doesn't match the schema, like the imported ones
+            let Some(path) = state.get_path(container) else {
+                return Err(LoroError::SchemaViolation {
+                    path: state.arena.idx_to_id(container).unwrap().to_string().into(),
+                    reason: "the container cannot be located from a root container".into(),
+                });
+            };
+            schema.check_op(
+                &SchemaPath::from_index_path(path),
+                OpValues::from_raw(&raw_op.content),
+            )?;
+        }
+
         let op = self.arena.convert_raw_op(&raw_op);
         state.apply_local_op(&raw_op, &op)?;
+        let event = match (event, &raw_op.content) {
+            (
+                EventHint::Mark {
+                    start, end, style, ..
+                },
+                RawOpContent::List(ListOp::StyleStart { info, .. }),
+            ) if info.is_non_exclusive() => {
+                let values = state.with_state_mut(container, |s| {
+                    s.as_richtext_state_mut()
+                        .unwrap()
+                        .get_style_values_in_event_range(start as usize..end as usize, &style.key)
+                });
+                EventHint::Mark {
+                    start,
+                    end,
+                    style,
+                    values: Some(values),
+                }
+            }
+            (event, _) => event,
+        };
         {
             // update version info
             let mut oplog = self.oplog.try_lock().unwrap();
@@ -716,20 +756,28 @@ fn change_to_diff(
             }
         }
         match hint {
-            EventHint::Mark { start, end, style } => {
-                let mut meta = StyleMeta::default();
-                meta.insert(
-                    style.key.clone(),
-                    StyleMetaItem {
-                        lamport,
-                        peer: change.id.peer,
-                        value: style.data,
-                    },
-                );
-                let diff = DeltaRopeBuilder::new()
-                    .retain(start as usize, Default::default())
-                    .retain((end - start) as usize, meta)
-                    .build();
+            EventHint::Mark {
+                start,
+                end,
+                style,
+                values,
+            } => {
+                let values = values.unwrap_or_else(|| vec![((end - start) as usize, style.data)]);
+                let mut builder =
+                    DeltaRopeBuilder::new().retain(start as usize, Default::default());
+                for (len, value) in values {
+                    let mut meta = StyleMeta::default();
+                    meta.insert(
+                        style.key.clone(),
+                        StyleMetaItem {
+                            lamport,
+                            peer: change.id.peer,
+                            value,
+                        },
+                    );
+                    builder = builder.retain(len, meta);
+                }
+                let diff = builder.build();
                 ans.push(TxnContainerDiff {
                     idx: op.container,
                     diff: Diff::Text(diff),
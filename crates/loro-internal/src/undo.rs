@@ -64,6 +64,39 @@ impl DiffBatch {
     pub fn clear(&mut self) {
         self.0.clear();
     }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn get(&self, id: &ContainerID) -> Option<&Diff> {
+        self.0.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ContainerID, &Diff)> + '_ {
+        self.0.iter()
+    }
+}
+
+impl IntoIterator for DiffBatch {
+    type Item = (ContainerID, Diff);
+    type IntoIter = std::collections::hash_map::IntoIter<ContainerID, Diff>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl FromIterator<(ContainerID, Diff)> for DiffBatch {
+    fn from_iter<T: IntoIterator<Item = (ContainerID, Diff)>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
 }
 
 fn transform_cursor(
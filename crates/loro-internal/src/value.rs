@@ -527,7 +527,10 @@ pub mod wasm {
     use wasm_bindgen::{JsValue, __rt::IntoJsResult};
 
     use crate::{
-        delta::{Delta, DeltaItem, Meta, StyleMeta, TreeDiff, TreeExternalDiff},
+        container::richtext::EMBED_STYLE_KEY,
+        delta::{
+            Delta, DeltaItem, Meta, StyleMeta, TreeDiff, TreeExternalDiff, TreeMoveRejectReason,
+        },
         event::{Index, TextDiff, TextDiffItem},
         utils::string_slice::StringSlice,
     };
@@ -613,6 +616,27 @@ pub mod wasm {
                         js_sys::Reflect::set(&obj, &"oldIndex".into(), &(*old_index).into())
                             .unwrap();
                     }
+                    TreeExternalDiff::MoveRejected { id, parent, reason } => {
+                        js_sys::Reflect::set(&obj, &"action".into(), &"moveRejected".into())
+                            .unwrap();
+                        let op_id = Object::new();
+                        js_sys::Reflect::set(&op_id, &"peer".into(), &id.peer.to_string().into())
+                            .unwrap();
+                        js_sys::Reflect::set(&op_id, &"counter".into(), &id.counter.into())
+                            .unwrap();
+                        js_sys::Reflect::set(&obj, &"id".into(), &op_id).unwrap();
+                        js_sys::Reflect::set(
+                            &obj,
+                            &"parent".into(),
+                            &JsValue::from(parent.tree_id()),
+                        )
+                        .unwrap();
+                        let reason = match reason {
+                            TreeMoveRejectReason::Cycle => "cycle",
+                            TreeMoveRejectReason::ParentDeleted => "parentDeleted",
+                        };
+                        js_sys::Reflect::set(&obj, &"reason".into(), &reason.into()).unwrap();
+                    }
                 }
                 array.push(&obj);
             }
@@ -692,21 +716,14 @@ pub mod wasm {
 
     pub fn text_diff_to_js_value(diff: &TextDiff) -> JsValue {
         let arr = Array::new();
-        let mut i = 0;
         for v in diff.iter() {
-            let (a, b) = text_diff_item_to_js_value(v);
-            arr.set(i as u32, a);
-            i += 1;
-            if let Some(b) = b {
-                arr.set(i as u32, b);
-                i += 1;
-            }
+            push_text_diff_item(&arr, v);
         }
 
         arr.into_js_result().unwrap()
     }
 
-    fn text_diff_item_to_js_value(value: &TextDiffItem) -> (JsValue, Option<JsValue>) {
+    fn push_text_diff_item(arr: &Array, value: &TextDiffItem) {
         match value {
             loro_delta::DeltaItem::Retain { len, attr } => {
                 let obj = Object::new();
@@ -724,32 +741,75 @@ pub mod wasm {
                     )
                     .unwrap();
                 }
-                (obj.into_js_result().unwrap(), None)
+                arr.push(&obj);
             }
             loro_delta::DeltaItem::Replace {
                 value,
                 attr,
                 delete,
             } => {
-                let mut a = None;
-                let mut b = None;
                 if value.rle_len() > 0 {
-                    let obj = Object::new();
-                    js_sys::Reflect::set(
-                        &obj,
-                        &JsValue::from_str("insert"),
-                        &JsValue::from_str(value.as_str()),
-                    )
-                    .unwrap();
-                    if !attr.is_empty() {
-                        js_sys::Reflect::set(
-                            &obj,
-                            &JsValue::from_str("attributes"),
-                            &JsValue::from(attr),
-                        )
-                        .unwrap();
+                    match attr
+                        .get(&EMBED_STYLE_KEY.into())
+                        .filter(|embed| !embed.is_null())
+                    {
+                        Some(embed) => {
+                            // Each embed is an insert of `{ $embed: value }`
+                            let insert = Object::new();
+                            js_sys::Reflect::set(
+                                &insert,
+                                &JsValue::from_str(EMBED_STYLE_KEY),
+                                &JsValue::from(embed.clone()),
+                            )
+                            .unwrap();
+                            let attributes = Object::new();
+                            let mut has_attributes = false;
+                            for (key, style) in attr.iter() {
+                                if key.as_str() != EMBED_STYLE_KEY {
+                                    has_attributes = true;
+                                    js_sys::Reflect::set(
+                                        &attributes,
+                                        &JsValue::from_str(&key),
+                                        &JsValue::from(style.data),
+                                    )
+                                    .unwrap();
+                                }
+                            }
+
+                            for _ in value.as_str().chars() {
+                                let obj = Object::new();
+                                js_sys::Reflect::set(&obj, &JsValue::from_str("insert"), &insert)
+                                    .unwrap();
+                                if has_attributes {
+                                    js_sys::Reflect::set(
+                                        &obj,
+                                        &JsValue::from_str("attributes"),
+                                        &attributes,
+                                    )
+                                    .unwrap();
+                                }
+                                arr.push(&obj);
+                            }
+                        }
+                        None => {
+                            let obj = Object::new();
+                            js_sys::Reflect::set(
+                                &obj,
+                                &JsValue::from_str("insert"),
+                                &JsValue::from_str(value.as_str()),
+                            )
+                            .unwrap();
+                            if !attr.is_empty() {
+                                js_sys::Reflect::set(
+                                    &obj,
+                                    &JsValue::from_str("attributes"),
+                                    &JsValue::from(attr),
+                                )
+                                .unwrap();
+                            }
+                            arr.push(&obj);
+                        }
                     }
-                    a = Some(obj.into_js_result().unwrap());
                 }
 
                 if *delete > 0 {
@@ -760,14 +820,8 @@ pub mod wasm {
                         &JsValue::from_f64(*delete as f64),
                     )
                     .unwrap();
-                    b = Some(obj.into_js_result().unwrap());
+                    arr.push(&obj);
                 }
-
-                if a.is_none() {
-                    a = std::mem::take(&mut b);
-                }
-
-                (a.unwrap(), b)
             }
         }
     }
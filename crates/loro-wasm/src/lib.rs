@@ -8,14 +8,17 @@ use convert::{import_status_to_js_value, js_to_version_vector, resolved_diff_to_
 use js_sys::{Array, Object, Promise, Reflect, Uint8Array};
 use loro_internal::{
     change::Lamport,
-    configure::{StyleConfig, StyleConfigMap},
-    container::{richtext::ExpandType, ContainerID},
+    configure::{StyleConfig, StyleConfigMap, StyleOptions},
+    container::{
+        richtext::{ExpandType, PosType},
+        ContainerID,
+    },
     cursor::{self, Side},
     encoding::ImportBlobMetadata,
     event::Index,
     handler::{
-        Handler, ListHandler, MapHandler, TextDelta, TextHandler, TreeHandler, UpdateOptions,
-        ValueOrHandler,
+        DiffAlgorithm, DiffGranularity, Handler, ListHandler, MapHandler, TextDelta, TextHandler,
+        TreeHandler, UpdateOptions, ValueOrHandler,
     },
     id::{Counter, PeerID, TreeID, ID},
     json::JsonSchema,
@@ -120,7 +123,7 @@ extern "C" {
     pub type JsTreeNodeOrUndefined;
     #[wasm_bindgen(typescript_type = "string | undefined")]
     pub type JsPositionOrUndefined;
-    #[wasm_bindgen(typescript_type = "Delta<string>[]")]
+    #[wasm_bindgen(typescript_type = "Delta<string | TextEmbed>[]")]
     pub type JsStringDelta;
     #[wasm_bindgen(typescript_type = "Map<PeerID, number>")]
     pub type JsVersionVectorMap;
@@ -154,9 +157,11 @@ extern "C" {
     pub type JsLoroTreeOrUndefined;
     #[wasm_bindgen(typescript_type = "[string, Value | Container]")]
     pub type MapEntry;
-    #[wasm_bindgen(typescript_type = "{[key: string]: { expand: 'before'|'after'|'none'|'both' }}")]
+    #[wasm_bindgen(
+        typescript_type = "{[key: string]: { expand: 'before'|'after'|'none'|'both', exclusive?: boolean, allowNesting?: boolean }}"
+    )]
     pub type JsTextStyles;
-    #[wasm_bindgen(typescript_type = "Delta<string>[]")]
+    #[wasm_bindgen(typescript_type = "Delta<string | TextEmbed>[]")]
     pub type JsDelta;
     #[wasm_bindgen(typescript_type = "-1 | 1 | 0 | undefined")]
     pub type JsPartialOrd;
@@ -348,6 +353,18 @@ impl ChangeMeta {
     }
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TextBlameSpan {
+    start: usize,
+    end: usize,
+    peer: String,
+    change_id: StringID,
+    lamport: Lamport,
+    timestamp: f64,
+    message: Option<Arc<str>>,
+}
+
 #[wasm_bindgen]
 impl LoroDoc {
     /// Create a new loro document.
@@ -428,6 +445,11 @@ impl LoroDoc {
     /// - `none`: the mark will not be expanded to include the inserted text at the boundaries
     /// - `both`: when inserting text either right before or right after the given range, the mark will be expanded to include the inserted text
     ///
+    /// The overlapping marks of an `exclusive` style (default) with different values replace each other.
+    /// Set `exclusive: false` to keep them separately, e.g. for comments with distinct ids. The value of such
+    /// a style is the list of its values in the order they are marked. Set `allowNesting: false` to forbid
+    /// such marks from overlapping each other.
+    ///
     /// @example
     /// ```ts
     /// const doc = new LoroDoc();
@@ -464,14 +486,19 @@ impl LoroDoc {
             // read expand value from value
             let expand = Reflect::get(&value, &"expand".into()).expect("`expand` not specified");
             let expand_str = expand.as_string().unwrap();
-            // read allowOverlap value from value
-            style_config.insert(
-                key.into(),
-                StyleConfig {
-                    expand: ExpandType::try_from_str(&expand_str)
-                        .expect("`expand` must be one of `none`, `start`, `end`, `both`"),
-                },
+            let config = StyleConfig::new().expand(
+                ExpandType::try_from_str(&expand_str)
+                    .expect("`expand` must be one of `none`, `start`, `end`, `both`"),
             );
+            // read the optional exclusive and allowNesting values from value
+            let mut options = StyleOptions::new();
+            if let Some(exclusive) = Reflect::get(&value, &"exclusive".into())?.as_bool() {
+                options.exclusive = exclusive;
+            }
+            if let Some(allow) = Reflect::get(&value, &"allowNesting".into())?.as_bool() {
+                options.allow_nesting = allow;
+            }
+            style_config.insert_with_options(key.into(), config, options);
         }
 
         self.0.config_text_style(style_config);
@@ -1924,7 +1951,9 @@ impl LoroText {
     /// Update the current text to the target text.
     ///
     /// It will calculate the minimal difference and apply it to the current text.
-    /// It uses Myers' diff algorithm to compute the optimal difference.
+    /// It uses Myers' diff algorithm to compute the optimal difference by default.
+    /// Set `algorithm` to `"patience"` or `"histogram"` to use the other algorithms,
+    /// and `granularity` to `"word"` to diff word by word.
     ///
     /// This could take a long time for large texts (e.g. > 50_000 characters).
     /// In that case, you should use `updateByLine` instead.
@@ -1942,26 +1971,7 @@ impl LoroText {
     ///
     #[wasm_bindgen(skip_typescript)]
     pub fn update(&self, text: &str, options: JsValue) -> JsResult<()> {
-        let options = if options.is_null() || options.is_undefined() {
-            UpdateOptions {
-                timeout_ms: None,
-                use_refined_diff: true,
-            }
-        } else {
-            let opts = match js_sys::Object::try_from(&options) {
-                Some(o) => o,
-                None => return Err(JsError::new("Invalid options").into()),
-            };
-            UpdateOptions {
-                timeout_ms: js_sys::Reflect::get(opts, &"timeoutMs".into())
-                    .ok()
-                    .and_then(|v| v.as_f64()),
-                use_refined_diff: js_sys::Reflect::get(opts, &"useRefinedDiff".into())
-                    .ok()
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(true),
-            }
-        };
+        let options = parse_update_options(options)?;
         self.handler
             .update(text, options)
             .map_err(|_| JsError::new("Update timeout").into())
@@ -1969,29 +1979,11 @@ impl LoroText {
 
     /// Update the current text to the target text, the difference is calculated line by line.
     ///
-    /// It uses Myers' diff algorithm to compute the optimal difference.
+    /// It uses Myers' diff algorithm to compute the optimal difference by default.
+    /// The `granularity` option is ignored.
     #[wasm_bindgen(js_name = "updateByLine", skip_typescript)]
     pub fn update_by_line(&self, text: &str, options: JsValue) -> JsResult<()> {
-        let options = if options.is_null() || options.is_undefined() {
-            UpdateOptions {
-                timeout_ms: None,
-                use_refined_diff: true,
-            }
-        } else {
-            let opts = match js_sys::Object::try_from(&options) {
-                Some(o) => o,
-                None => return Err(JsError::new("Invalid options").into()),
-            };
-            UpdateOptions {
-                timeout_ms: js_sys::Reflect::get(opts, &"timeoutMs".into())
-                    .ok()
-                    .and_then(|v| v.as_f64()),
-                use_refined_diff: js_sys::Reflect::get(opts, &"useRefinedDiff".into())
-                    .ok()
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(true),
-            }
-        };
+        let options = parse_update_options(options)?;
         self.handler
             .update_by_line(text, options)
             .map_err(|_| JsError::new("Update timeout").into())
@@ -2012,6 +2004,35 @@ impl LoroText {
         Ok(())
     }
 
+    /// Insert an embed, such as an inline image or a mention, at the given index (utf-16 index).
+    ///
+    /// The embed has length 1. It's an insert of `{ $embed: value }` in `toDelta()`.
+    /// The value can't be null or a container. Use `insertContainer` to embed a container.
+    ///
+    /// @example
+    /// ```ts
+    /// import { LoroDoc } from "loro-crdt";
+    ///
+    /// const doc = new LoroDoc();
+    /// const text = doc.getText("text");
+    /// text.insert(0, "Hi !");
+    /// text.insertEmbed(3, { mention: "alice" });
+    /// console.log(text.toDelta());
+    /// // [{ insert: "Hi " }, { insert: { $embed: { mention: "alice" } } }, { insert: "!" }]
+    /// ```
+    #[wasm_bindgen(js_name = "insertEmbed")]
+    pub fn insert_embed(&mut self, index: usize, value: JsValue) -> JsResult<()> {
+        self.handler.insert_embed(index, LoroValue::from(value))?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = "insertContainer", skip_typescript)]
+    pub fn insert_container(&mut self, index: usize, child: JsContainer) -> JsResult<JsContainer> {
+        let child = js_to_container(child)?;
+        let c = self.handler.insert_container(index, child.to_handler())?;
+        Ok(handler_to_js_value(c, self.doc.clone()).into())
+    }
+
     /// Get a string slice (utf-16 index).
     ///
     /// @example
@@ -2169,6 +2190,32 @@ impl LoroText {
         Ok(())
     }
 
+    /// Remove the mark of the given value of a non-exclusive style (utf-16 index).
+    /// The marks of its other values are kept.
+    ///
+    /// It's the same as `unmark` if the style is exclusive.
+    ///
+    /// @example
+    /// ```ts
+    /// import { LoroDoc } from "loro-crdt";
+    ///
+    /// const doc = new LoroDoc();
+    /// doc.configTextStyle({comment: {expand: "none", exclusive: false}});
+    /// const text = doc.getText("text");
+    /// text.insert(0, "Hello World!");
+    /// text.mark({ start: 0, end: 5 }, "comment", "a");
+    /// text.mark({ start: 0, end: 5 }, "comment", "b");
+    /// text.unmarkValue({ start: 0, end: 5 }, "comment", "a");
+    /// ```
+    #[wasm_bindgen(js_name = "unmarkValue")]
+    pub fn unmark_value(&self, range: JsRange, key: &str, value: JsValue) -> Result<(), JsValue> {
+        let range: MarkRange = serde_wasm_bindgen::from_value(range.into())?;
+        let value: LoroValue = LoroValue::from(value);
+        self.handler
+            .unmark_value(range.start, range.end, key, value)?;
+        Ok(())
+    }
+
     /// Convert the text to a string
     #[allow(clippy::inherent_to_string)]
     #[wasm_bindgen(js_name = "toString")]
@@ -2279,6 +2326,53 @@ impl LoroText {
         Ok(())
     }
 
+    /// Update the current text to the rich text of the given delta, which can only
+    /// contain inserts.
+    ///
+    /// The text is diffed like `update`, then the styles are reconciled with the
+    /// minimal marks and unmarks. So the unchanged text keeps its styles and the
+    /// cursors on it.
+    #[wasm_bindgen(js_name = "updateWithMarks", skip_typescript)]
+    pub fn update_with_marks(&self, delta: JsDelta, options: JsValue) -> JsResult<()> {
+        let delta: Vec<TextDelta> = serde_wasm_bindgen::from_value(delta.into())?;
+        let options = parse_update_options(options)?;
+        self.handler.update_with_marks(&delta, options)?;
+        Ok(())
+    }
+
+    /// Get the authors of the text, as the runs of the text inserted by the same change.
+    ///
+    /// The ranges are in UTF-16. The uncommitted text has no change metadata yet.
+    #[wasm_bindgen(js_name = "blame", skip_typescript)]
+    pub fn blame(&self) -> JsResult<JsValue> {
+        let Some(doc) = &self.doc else {
+            return Err(JsValue::from_str("Cannot get the blame of a detached text"));
+        };
+        let to_utf16 = |pos: usize| {
+            self.handler
+                .convert_pos(pos, PosType::Unicode, PosType::Utf16)
+                .unwrap()
+        };
+        let spans: Vec<TextBlameSpan> = doc
+            .text_blame(&self.handler.id())?
+            .into_iter()
+            .map(|span| TextBlameSpan {
+                start: to_utf16(span.range.start),
+                end: to_utf16(span.range.end),
+                peer: span.peer.to_string(),
+                change_id: StringID {
+                    peer: span.change_id.peer.to_string(),
+                    counter: span.change_id.counter,
+                },
+                lamport: span.lamport,
+                timestamp: span.timestamp as f64,
+                message: span.message,
+            })
+            .collect();
+        let s = serde_wasm_bindgen::Serializer::new();
+        Ok(spans.serialize(&s)?)
+    }
+
     /// Get the parent container.
     ///
     /// - The parent of the root is `undefined`.
@@ -3512,6 +3606,50 @@ pub struct LoroTreeNode {
     doc: Option<Arc<LoroDocInner>>,
 }
 
+fn parse_update_options(options: JsValue) -> JsResult<UpdateOptions> {
+    if options.is_null() || options.is_undefined() {
+        return Ok(UpdateOptions::default());
+    }
+
+    let opts = match js_sys::Object::try_from(&options) {
+        Some(o) => o,
+        None => return Err(JsError::new("Invalid options").into()),
+    };
+    let get_str = |key: &str| {
+        js_sys::Reflect::get(opts, &key.into())
+            .ok()
+            .and_then(|v| v.as_string())
+    };
+    let granularity = match get_str("granularity").as_deref() {
+        None | Some("char") => DiffGranularity::Char,
+        Some("word") => DiffGranularity::Word,
+        Some(s) => return Err(JsError::new(&format!("Invalid granularity: {}", s)).into()),
+    };
+    let algorithm = match get_str("algorithm").as_deref() {
+        None | Some("myers") => DiffAlgorithm::Myers,
+        Some("patience") => DiffAlgorithm::Patience,
+        Some("histogram") => DiffAlgorithm::Histogram,
+        Some(s) => return Err(JsError::new(&format!("Invalid algorithm: {}", s)).into()),
+    };
+    let mut options = UpdateOptions::new()
+        .use_refined_diff(
+            js_sys::Reflect::get(opts, &"useRefinedDiff".into())
+                .ok()
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true),
+        )
+        .granularity(granularity)
+        .algorithm(algorithm);
+    if let Some(timeout_ms) = js_sys::Reflect::get(opts, &"timeoutMs".into())
+        .ok()
+        .and_then(|v| v.as_f64())
+    {
+        options = options.timeout_ms(timeout_ms);
+    }
+
+    Ok(options)
+}
+
 fn parse_js_parent(parent: &JsParentTreeID) -> JsResult<Option<TreeID>> {
     let js_value: JsValue = parent.into();
     let parent: Option<TreeID> = if js_value.is_undefined() {
@@ -5024,6 +5162,11 @@ export type Delta<T> =
     insert?: undefined;
   };
 
+/**
+ * An embed inside a text, as the `insert` of its `Delta`.
+ */
+export type TextEmbed = { $embed: Value };
+
 /**
  * The unique id of each operation.
  */
@@ -5032,6 +5175,19 @@ export type OpId = { peer: PeerID, counter: number };
 /**
  * Change is a group of continuous operations
  */
+/**
+ * A run of the text that is inserted by the same change.
+ */
+export interface TextBlameSpan {
+    start: number,
+    end: number,
+    peer: PeerID,
+    changeId: OpId,
+    lamport: number,
+    timestamp: number,
+    message: string | undefined,
+}
+
 export interface Change {
     peer: PeerID,
     counter: number,
@@ -5281,6 +5437,14 @@ export type JsonChange = {
 export interface TextUpdateOptions {
     timeoutMs?: number,
     useRefinedDiff?: boolean,
+    /**
+     * The unit of the diff. Defaults to "char".
+     */
+    granularity?: "char" | "word",
+    /**
+     * The diff algorithm. Defaults to "myers".
+     */
+    algorithm?: "myers" | "patience" | "histogram",
 }
 
 export type ExportMode = {
@@ -5394,7 +5558,12 @@ export type CounterSpan = { start: number, end: number };
 
 export type ImportStatus = {
   success: Map<PeerID, CounterSpan>,
-  pending: Map<PeerID, CounterSpan> | null
+  pending: Map<PeerID, CounterSpan> | null,
+  /**
+   * The changes rejected by the schema or the import filter, and the changes
+   * depending on them
+   */
+  rejected: Map<PeerID, CounterSpan> | null
 }
 
 export type Frontiers = OpId[];
@@ -5456,7 +5625,7 @@ export type ListDiff = {
 
 export type TextDiff = {
     type: "text";
-    diff: Delta<string>[];
+    diff: Delta<string | TextEmbed>[];
 };
 
 export type MapDiff = {
@@ -5486,6 +5655,13 @@ export type TreeDiffItem =
         fractionalIndex: string;
         oldParent: TreeID | undefined;
         oldIndex: number;
+    }
+    | {
+        target: TreeID;
+        action: "moveRejected";
+        id: OpId;
+        parent: TreeID | undefined;
+        reason: "cycle" | "parentDeleted";
     };
 
 export type TreeDiff = {
@@ -5871,6 +6047,23 @@ interface LoroText {
     insert(pos: number, text: string): void;
     delete(pos: number, len: number): void;
     subscribe(listener: Listener): Subscription;
+    /**
+     * Insert a container as an embed at the index (utf-16 index).
+     *
+     * The embed has length 1. It's an insert of `{ $embed: containerId }` in `toDelta()`.
+     *
+     * @example
+     * ```ts
+     * import { LoroDoc, LoroMap } from "loro-crdt";
+     *
+     * const doc = new LoroDoc();
+     * const text = doc.getText("text");
+     * text.insert(0, "Hi !");
+     * const image = text.insertContainer(3, new LoroMap());
+     * image.set("src", "cat.png");
+     * ```
+     */
+    insertContainer<C extends Container>(pos: number, child: C): C;
     /**
      * Update the current text to the target text.
      *
@@ -5897,6 +6090,20 @@ interface LoroText {
      * This update calculation is line-based, which will be more efficient but less precise.
      */
     updateByLine(text: string, options?: TextUpdateOptions): void;
+    /**
+     * Update the current text to the rich text of the given delta, which can only
+     * contain inserts.
+     *
+     * The text is diffed like `update`, then the styles are reconciled with the
+     * minimal marks and unmarks.
+     */
+    updateWithMarks(delta: Delta<string | TextEmbed>[], options?: TextUpdateOptions): void;
+    /**
+     * Get the authors of the text, as the runs of the text inserted by the same change.
+     *
+     * The ranges are in UTF-16. The uncommitted text has no change metadata yet.
+     */
+    blame(): TextBlameSpan[];
 }
 interface LoroTree<T extends Record<string, unknown> = Record<string, unknown>> {
     new(): LoroTree<T>;
@@ -1,17 +1,18 @@
 //! Loro event handling.
 use enum_as_inner::EnumAsInner;
 use loro_internal::container::ContainerID;
-use loro_internal::delta::TreeDiff;
-use loro_internal::event::EventTriggerKind;
+use loro_internal::delta::{ResolvedMapDelta, ResolvedMapValue, TreeDiff};
+use loro_internal::event::{EventTriggerKind, ListDeltaMeta, ListDiff, ListDiffInsertItem};
 use loro_internal::handler::{TextDelta, ValueOrHandler};
-use loro_internal::FxHashMap;
+use loro_internal::undo::DiffBatch as InnerDiffBatch;
 use loro_internal::{
     event::{Diff as DiffInner, Index},
     ContainerDiff as ContainerDiffInner, DiffEvent as DiffEventInner,
 };
-use std::sync::Arc;
+use loro_internal::{FxHashMap, IdLp, InternalString};
+use std::sync::{Arc, OnceLock};
 
-use crate::ValueOrContainer;
+use crate::{ContainerTrait, ValueOrContainer};
 
 /// A subscriber to the event.
 pub type Subscriber = Arc<dyn (for<'a> Fn(DiffEvent<'a>)) + Send + Sync>;
@@ -43,7 +44,7 @@ pub struct ContainerDiff<'a> {
 }
 
 /// A concrete diff.
-#[derive(Debug, EnumAsInner)]
+#[derive(Debug, Clone, EnumAsInner)]
 pub enum Diff<'a> {
     /// A list diff.
     List(Vec<ListDiffItem>),
@@ -73,7 +74,7 @@ pub enum Diff<'a> {
 /// It means that the list has 3 elements that are not changed, 1 element is deleted, and 2 elements are inserted.
 ///
 /// If the original list is [1, 2, 3, 4, 5], the list after the diff is [1, 2, 3, 1, 2, 5].
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ListDiffItem {
     /// Insert a new element into the list.
     Insert {
@@ -97,12 +98,183 @@ pub enum ListDiffItem {
 }
 
 /// A map delta.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MapDelta<'a> {
     /// All the updated keys and their new values.
     pub updated: FxHashMap<&'a str, Option<ValueOrContainer>>,
 }
 
+/// A batch of diffs of multiple containers.
+///
+/// It's returned by [`LoroDoc::diff`](crate::LoroDoc::diff) and can be applied to a
+/// document by [`LoroDoc::apply_diff`](crate::LoroDoc::apply_diff).
+#[derive(Debug, Default, Clone)]
+pub struct DiffBatch {
+    cid_to_events: FxHashMap<ContainerID, BatchEntry>,
+    order: Vec<ContainerID>,
+}
+
+/// The diff of a container in a [DiffBatch]
+#[derive(Debug, Clone)]
+struct BatchEntry {
+    inner: DiffInner,
+    /// The values of the diff converted for [DiffBatch::get] and [DiffBatch::iter].
+    /// They are converted on the first access.
+    converted: OnceLock<ConvertedDiff>,
+}
+
+#[derive(Debug, Clone)]
+enum ConvertedDiff {
+    List(Vec<ListDiffItem>),
+    Text(Vec<TextDelta>),
+    Map(FxHashMap<InternalString, Option<ValueOrContainer>>),
+    /// The diffs that are cheap to convert, e.g. the tree diff is borrowed
+    Other,
+}
+
+impl BatchEntry {
+    fn new(inner: DiffInner) -> Self {
+        Self {
+            inner,
+            converted: OnceLock::new(),
+        }
+    }
+
+    fn diff(&self) -> Diff<'_> {
+        let converted = self.converted.get_or_init(|| match &self.inner {
+            DiffInner::List(_) => ConvertedDiff::List(Diff::from(&self.inner).into_list().unwrap()),
+            DiffInner::Text(_) => ConvertedDiff::Text(Diff::from(&self.inner).into_text().unwrap()),
+            DiffInner::Map(m) => ConvertedDiff::Map(
+                m.updated
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.value.clone().map(|v| v.into())))
+                    .collect(),
+            ),
+            _ => ConvertedDiff::Other,
+        });
+        match converted {
+            ConvertedDiff::List(l) => Diff::List(l.clone()),
+            ConvertedDiff::Text(t) => Diff::Text(t.clone()),
+            ConvertedDiff::Map(m) => Diff::Map(MapDelta {
+                updated: m.iter().map(|(k, v)| (k.as_ref(), v.clone())).collect(),
+            }),
+            ConvertedDiff::Other => Diff::from(&self.inner),
+        }
+    }
+}
+
+impl DiffBatch {
+    /// Push a new event to the batch.
+    ///
+    /// If the cid already exists in the batch, return Err
+    pub fn push(&mut self, cid: ContainerID, diff: Diff<'_>) -> Result<(), Diff<'_>> {
+        if self.cid_to_events.contains_key(&cid) {
+            return Err(diff);
+        }
+
+        self.order.push(cid.clone());
+        self.cid_to_events.insert(cid, BatchEntry::new(diff.into()));
+        Ok(())
+    }
+
+    /// Get the diff of the given container.
+    ///
+    /// The diff is converted from the internal representation once and cached.
+    pub fn get(&self, cid: &ContainerID) -> Option<Diff<'_>> {
+        self.cid_to_events.get(cid).map(BatchEntry::diff)
+    }
+
+    /// Returns an iterator over the diffs in this batch, in the order they were added.
+    ///
+    /// See [DiffBatch::get] for how the diffs are converted.
+    pub fn iter(&self) -> impl Iterator<Item = (&ContainerID, Diff<'_>)> + '_ {
+        self.order
+            .iter()
+            .map(|cid| (cid, self.cid_to_events.get(cid).unwrap().diff()))
+    }
+
+    /// The number of containers in this batch.
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Whether the batch contains no diff.
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+impl From<InnerDiffBatch> for DiffBatch {
+    fn from(value: InnerDiffBatch) -> Self {
+        let mut ans = DiffBatch::default();
+        for (cid, diff) in value.into_iter() {
+            ans.order.push(cid.clone());
+            ans.cid_to_events.insert(cid, BatchEntry::new(diff));
+        }
+
+        ans
+    }
+}
+
+impl From<DiffBatch> for InnerDiffBatch {
+    fn from(value: DiffBatch) -> Self {
+        value
+            .cid_to_events
+            .into_iter()
+            .map(|(cid, entry)| (cid, entry.inner))
+            .collect()
+    }
+}
+
+impl From<Diff<'_>> for DiffInner {
+    fn from(value: Diff<'_>) -> Self {
+        match value {
+            Diff::List(l) => {
+                let mut ans = ListDiff::new();
+                for item in l {
+                    match item {
+                        ListDiffItem::Insert { insert, is_move } => {
+                            let attr = ListDeltaMeta { from_move: is_move };
+                            for chunk in ListDiffInsertItem::from_many(
+                                insert.into_iter().map(ValueOrHandler::from),
+                            ) {
+                                ans.push_insert(chunk, attr);
+                            }
+                        }
+                        ListDiffItem::Delete { delete } => {
+                            ans.push_delete(delete);
+                        }
+                        ListDiffItem::Retain { retain } => {
+                            ans.push_retain(retain, Default::default());
+                        }
+                    }
+                }
+
+                DiffInner::List(ans)
+            }
+            Diff::Text(t) => DiffInner::Text(TextDelta::into_text_diff(t.into_iter())),
+            Diff::Map(m) => {
+                let mut ans = ResolvedMapDelta::new();
+                for (k, v) in m.updated {
+                    ans = ans.with_entry(
+                        InternalString::from(k),
+                        ResolvedMapValue {
+                            value: v.map(ValueOrHandler::from),
+                            idlp: IdLp::NONE_ID,
+                        },
+                    );
+                }
+
+                DiffInner::Map(ans)
+            }
+            Diff::Tree(t) => DiffInner::Tree(t.clone()),
+            #[cfg(feature = "counter")]
+            Diff::Counter(c) => DiffInner::Counter(c),
+            Diff::Unknown => DiffInner::Unknown,
+        }
+    }
+}
+
 impl<'a> From<DiffEventInner<'a>> for DiffEvent<'a> {
     fn from(value: DiffEventInner<'a>) -> Self {
         DiffEvent {
@@ -186,3 +358,12 @@ impl From<ValueOrHandler> for ValueOrContainer {
         }
     }
 }
+
+impl From<ValueOrContainer> for ValueOrHandler {
+    fn from(value: ValueOrContainer) -> Self {
+        match value {
+            ValueOrContainer::Value(v) => ValueOrHandler::Value(v),
+            ValueOrContainer::Container(c) => ValueOrHandler::Handler(c.to_handler()),
+        }
+    }
+}
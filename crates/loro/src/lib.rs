@@ -1,8 +1,8 @@
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
 #![warn(missing_debug_implementations)]
-use event::{DiffEvent, Subscriber};
-use fxhash::FxHashSet;
+use event::{DiffBatch, DiffEvent, Subscriber};
+use fxhash::{FxHashMap, FxHashSet};
 pub use loro_common::InternalString;
 pub use loro_internal::cursor::CannotFindRelativePosition;
 use loro_internal::cursor::Cursor;
@@ -25,13 +25,15 @@ use loro_internal::{
     UnknownHandler as InnerUnknownHandler,
 };
 use std::cmp::Ordering;
+use std::io::{Read, Write};
 use std::ops::ControlFlow;
 use std::ops::Range;
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 use tracing::info;
 
-pub use loro_internal::diff::diff_impl::UpdateOptions;
 pub use loro_internal::diff::diff_impl::UpdateTimeoutError;
+pub use loro_internal::diff::diff_impl::{DiffAlgorithm, DiffGranularity, UpdateOptions};
 pub use loro_internal::subscription::LocalUpdateCallback;
 pub use loro_internal::subscription::PeerIdUpdateCallback;
 pub use loro_internal::ChangeMeta;
@@ -39,29 +41,36 @@ pub mod event;
 pub use loro_internal::awareness;
 pub use loro_internal::change::Timestamp;
 pub use loro_internal::configure::Configure;
-pub use loro_internal::configure::{StyleConfig, StyleConfigMap};
-pub use loro_internal::container::richtext::ExpandType;
+pub use loro_internal::configure::{StyleConfig, StyleConfigMap, StyleOptions};
+pub use loro_internal::container::richtext::{ExpandType, PosType, EMBED_CHAR};
 pub use loro_internal::container::{ContainerID, ContainerType, IntoContainerId};
 pub use loro_internal::cursor;
-pub use loro_internal::delta::{TreeDeltaItem, TreeDiff, TreeDiffItem, TreeExternalDiff};
-pub use loro_internal::encoding::ExportMode;
+pub use loro_internal::delta::{
+    RejectedTreeMove, TreeDeltaItem, TreeDiff, TreeDiffItem, TreeExternalDiff, TreeMoveRejectReason,
+};
 pub use loro_internal::encoding::ImportBlobMetadata;
+pub use loro_internal::encoding::{ExportMode, SnapshotOptions, StateEncoding};
 pub use loro_internal::event::{EventTriggerKind, Index};
-pub use loro_internal::handler::TextDelta;
+pub use loro_internal::handler::{TextDelta, TreePositionKeyStats, TreeValueOptions};
+pub use loro_internal::import_filter::{Decision, ImportFilter, OpKind, OpRef};
 pub use loro_internal::json;
 pub use loro_internal::json::{
     FutureOp as JsonFutureOp, FutureOpWrapper as JsonFutureOpWrapper, JsonChange, JsonOp,
     JsonOpContent, JsonSchema, ListOp as JsonListOp, MapOp as JsonMapOp,
     MovableListOp as JsonMovableListOp, TextOp as JsonTextOp, TreeOp as JsonTreeOp,
 };
-pub use loro_internal::kv_store::{KvStore, MemKvStore};
+pub use loro_internal::kv_store::{
+    CompressionType, FileKvConfig, FileKvStore, KvStore, MemKvStore,
+};
 pub use loro_internal::loro::CommitOptions;
 pub use loro_internal::loro::DocAnalysis;
 pub use loro_internal::oplog::FrontiersNotIncluded;
+pub use loro_internal::schema::{ContainerSchema, MapSchema, Schema, ValueSchema};
 pub use loro_internal::undo;
 pub use loro_internal::version::{Frontiers, VersionRange, VersionVector, VersionVectorDiff};
 pub use loro_internal::ApplyDiff;
 pub use loro_internal::Subscription;
+pub use loro_internal::TextBlameSpan;
 pub use loro_internal::UndoManager as InnerUndoManager;
 pub use loro_internal::{loro_value, to_value};
 pub use loro_internal::{
@@ -78,6 +87,9 @@ pub use loro_internal::jsonpath;
 #[cfg(feature = "jsonpath")]
 pub use loro_internal::jsonpath::JsonPathError;
 
+#[cfg(any(feature = "markdown", feature = "html"))]
+pub mod markup;
+
 #[cfg(feature = "counter")]
 mod counter;
 #[cfg(feature = "counter")]
@@ -117,6 +129,104 @@ impl LoroDoc {
         LoroDoc::_new(doc)
     }
 
+    /// Open the document persisted in the given directory.
+    ///
+    /// A new document is created if the directory doesn't contain one yet.
+    /// The history is read lazily from the disk, so opening a document with a
+    /// long history is cheap. The new changes are only written to the disk
+    /// when [`LoroDoc::flush`] is called.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::open("./my-doc").unwrap();
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// doc.flush().unwrap();
+    ///
+    /// let doc = LoroDoc::open("./my-doc").unwrap();
+    /// assert_eq!(doc.get_text("text").to_string(), "Hello");
+    /// ```
+    pub fn open(path: impl AsRef<Path>) -> LoroResult<Self> {
+        let doc = InnerLoroDoc::open(path)?;
+        doc.start_auto_commit();
+        Ok(LoroDoc::_new(doc))
+    }
+
+    /// Create a document that stores its history in the given [`KvStore`].
+    ///
+    /// If the kv store already contains a document flushed by [`LoroDoc::flush`],
+    /// the document is loaded from it.
+    pub fn new_with_kv_store(kv: Arc<Mutex<dyn KvStore>>) -> LoroResult<Self> {
+        let doc = InnerLoroDoc::new_with_kv_store(kv)?;
+        doc.start_auto_commit();
+        Ok(LoroDoc::_new(doc))
+    }
+
+    /// Commit the pending changes and write the changes that are not persisted
+    /// yet into the kv store of the document, along with the states of the containers
+    /// that have changed since the last flush.
+    ///
+    /// For a document created by [`LoroDoc::open`], they are appended to the files
+    /// on the disk. Nothing is written to the disk for an in-memory kv store.
+    #[inline]
+    pub fn flush(&self) -> LoroResult<()> {
+        self.doc.flush()
+    }
+
+    /// Export the entries of the kv store that holds the history, which have changed
+    /// since the last call.
+    ///
+    /// The entries are `(key, value)` pairs made up of the encoded change blocks and the
+    /// version of the document. It's useful to persist a document incrementally in your own
+    /// storage: write the returned entries into it, overriding the existing entries with the
+    /// same keys. Only the blocks that changed since the last call are returned, and the
+    /// first call returns all of them.
+    ///
+    /// Shallow documents are not supported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::LoroDoc;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let mut storage = BTreeMap::new();
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// storage.extend(doc.export_change_store_delta().unwrap());
+    /// doc.get_text("text").insert(5, " World").unwrap();
+    /// storage.extend(doc.export_change_store_delta().unwrap());
+    ///
+    /// let new_doc = LoroDoc::from_change_store_delta(storage).unwrap();
+    /// assert_eq!(new_doc.get_text("text").to_string(), "Hello World");
+    /// ```
+    pub fn export_change_store_delta(&self) -> LoroResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        Ok(self
+            .doc
+            .export_change_store_delta()?
+            .into_iter()
+            .map(|(k, v)| (k.to_vec(), v.to_vec()))
+            .collect())
+    }
+
+    /// Create a document from the entries exported by [`LoroDoc::export_change_store_delta`].
+    ///
+    /// The entries of all the deltas should be given in the order they were exported,
+    /// so that the later entries override the earlier ones with the same keys.
+    ///
+    /// The entries don't contain the document state, so it's computed from the history.
+    pub fn from_change_store_delta(
+        entries: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> LoroResult<Self> {
+        let doc = InnerLoroDoc::from_change_store_delta(
+            entries.into_iter().map(|(k, v)| (k.into(), v.into())),
+        )?;
+        doc.start_auto_commit();
+        Ok(LoroDoc::_new(doc))
+    }
+
     /// Duplicate the document with a different PeerID
     ///
     /// The time complexity and space complexity of this operation are both O(n),
@@ -232,11 +342,135 @@ impl LoroDoc {
     ///
     /// Expand is used to specify the behavior of expanding when new text is inserted at the
     /// beginning or end of the style.
+    ///
+    /// A style can also declare the type of its values, and whether it's exclusive, with
+    /// [StyleOptions]. The overlapping marks of a non-exclusive style with different values,
+    /// e.g. comments with distinct ids, are kept separately instead of replacing each other.
+    /// The value of such a style is the list of its values in the order they are marked.
+    /// The versions of Loro before the non-exclusive styles don't support them, so all the
+    /// peers of a document that uses them should be upgraded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{
+    ///     ExpandType, LoroDoc, LoroValue, StyleConfig, StyleConfigMap, StyleOptions, ValueSchema,
+    /// };
+    ///
+    /// let doc = LoroDoc::new();
+    /// let mut styles = StyleConfigMap::default_rich_text_config();
+    /// styles.insert_with_options(
+    ///     "comment".into(),
+    ///     StyleConfig::new().expand(ExpandType::None),
+    ///     StyleOptions {
+    ///         exclusive: false,
+    ///         value_type: ValueSchema::String,
+    ///         ..Default::default()
+    ///     },
+    /// );
+    /// doc.config_text_style(styles);
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello world").unwrap();
+    /// text.mark(0..5, "comment", "a").unwrap();
+    /// text.mark(0..11, "comment", "b").unwrap();
+    /// assert!(text.mark(0..5, "comment", 1).is_err());
+    /// let styles = text.get_styles_at(0).unwrap();
+    /// assert_eq!(styles.get("comment"), Some(&LoroValue::from(vec!["a", "b"])));
+    /// ```
     #[inline]
     pub fn config_text_style(&self, text_style: StyleConfigMap) {
         self.doc.config_text_style(text_style)
     }
 
+    /// Set the schema of the document. `None` removes the schema.
+    ///
+    /// When a schema is set, the local edits that don't match it fail with
+    /// [`LoroError::SchemaViolation`] before they are applied. The imported changes
+    /// are checked before they are added to the history. The ones that don't match
+    /// the schema are dropped together with the imported changes depending on them,
+    /// and reported in [`ImportStatus::rejected`]. The rest of the import is applied.
+    ///
+    /// The existing content of the document is not checked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{ContainerSchema, LoroDoc, LoroError, LoroMap, MapSchema, Schema, ValueSchema};
+    ///
+    /// let todo = MapSchema::new()
+    ///     .field("title", ValueSchema::String)
+    ///     .field("done", ValueSchema::Bool);
+    /// let doc = LoroDoc::new();
+    /// doc.set_schema(Some(
+    ///     Schema::new().root("todos", ContainerSchema::movable_list(ContainerSchema::Map(todo))),
+    /// ));
+    ///
+    /// let todos = doc.get_movable_list("todos");
+    /// let item = todos.push_container(LoroMap::new()).unwrap();
+    /// item.insert("title", "Buy milk").unwrap();
+    /// assert!(matches!(
+    ///     item.insert("done", "yes"),
+    ///     Err(LoroError::SchemaViolation { .. })
+    /// ));
+    /// assert!(doc.get_text("notes").insert(0, "hi").is_err());
+    /// ```
+    #[inline]
+    pub fn set_schema(&self, schema: Option<Schema>) {
+        self.doc.set_schema(schema)
+    }
+
+    /// Get the schema of the document
+    #[inline]
+    pub fn schema(&self) -> Option<Arc<Schema>> {
+        self.doc.schema()
+    }
+
+    /// Set the filter of the imported changes. `None` removes the filter.
+    ///
+    /// The filter is called with the meta and the ops of every imported change
+    /// that is new to the document, before anything is applied. A rejected change
+    /// and the imported changes that depend on it, including the later changes of
+    /// the same peer, are left out of the document and reported in
+    /// [`ImportStatus::rejected`]. The rejections are remembered, so the changes
+    /// that depend on a rejected change and arrive in later imports are rejected
+    /// too. Setting the filter again forgets them.
+    ///
+    /// The filter must not access the document, which is locked while it runs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{ContainerID, Decision, ExportMode, LoroDoc, OpKind};
+    ///
+    /// let server = LoroDoc::new();
+    /// // Only the peer 1 can change the settings
+    /// server.set_import_filter(Some(Box::new(|meta, ops| {
+    ///     let changes_settings = ops.iter().any(|op| {
+    ///         matches!(&op.container, ContainerID::Root { name, .. } if name.as_str() == "settings")
+    ///             && matches!(op.kind, OpKind::MapSet { .. } | OpKind::MapDelete { .. })
+    ///     });
+    ///     if changes_settings && meta.id.peer != 1 {
+    ///         Decision::Reject
+    ///     } else {
+    ///         Decision::Accept
+    ///     }
+    /// })));
+    ///
+    /// let client = LoroDoc::new();
+    /// client.set_peer_id(2).unwrap();
+    /// client.get_map("settings").insert("theme", "dark").unwrap();
+    /// let status = server
+    ///     .import(&client.export(ExportMode::all_updates()).unwrap())
+    ///     .unwrap();
+    /// assert!(status.success.is_empty());
+    /// assert_eq!(status.rejected.unwrap().get(&2), Some(&(0, 1)));
+    /// assert!(server.get_map("settings").is_empty());
+    /// ```
+    #[inline]
+    pub fn set_import_filter(&self, filter: Option<ImportFilter>) {
+        self.doc.set_import_filter(filter)
+    }
+
     /// Attach the document state to the latest known version.
     ///
     /// > The document becomes detached during a `checkout` operation.
@@ -261,6 +495,86 @@ impl LoroDoc {
         self.doc.checkout(frontiers)
     }
 
+    /// Calculate the diff between two versions.
+    ///
+    /// Applying the returned [`DiffBatch`] on the state of version `a` will make it
+    /// the same as the state of version `b`.
+    ///
+    /// The state of the doc is not changed by this method and no event will be emitted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use loro::{LoroDoc, event::Diff};
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello").unwrap();
+    /// doc.commit();
+    /// let a = doc.state_frontiers();
+    /// text.insert(5, " World").unwrap();
+    /// doc.commit();
+    /// let b = doc.state_frontiers();
+    /// let diff = doc.diff(&a, &b).unwrap();
+    /// assert_eq!(diff.len(), 1);
+    /// assert!(matches!(diff.get(&text.id()), Some(Diff::Text(_))));
+    /// ```
+    #[inline]
+    pub fn diff(&self, a: &Frontiers, b: &Frontiers) -> LoroResult<DiffBatch> {
+        Ok(self.doc.diff(a, b)?.into())
+    }
+
+    /// Apply a diff to the current document state.
+    ///
+    /// The diff is converted into new local ops. They will be committed with the next commit,
+    /// so collaborators will receive them as ordinary updates.
+    ///
+    /// Containers created inside the diff are recreated with new container ids, because
+    /// a container id can only appear once in the document.
+    #[inline]
+    pub fn apply_diff(&self, diff: &DiffBatch) -> LoroResult<()> {
+        self.doc
+            .apply_diff(diff.clone().into(), &mut Default::default(), true)
+    }
+
+    /// Revert the document to the given version by creating new operations.
+    ///
+    /// Unlike [`LoroDoc::checkout`], the document stays attached and the history is preserved.
+    /// The diff between the current state and the target version is committed as a normal local
+    /// change with the origin `"revert"`, so collaborators receive the revert as ordinary updates.
+    ///
+    /// Use [`LoroDoc::revert_to_with`] to customize the origin or the commit message.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use loro::LoroDoc;
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello").unwrap();
+    /// doc.commit();
+    /// let v = doc.state_frontiers();
+    /// text.insert(5, " World").unwrap();
+    /// doc.commit();
+    /// doc.revert_to(&v).unwrap();
+    /// assert_eq!(text.to_string(), "Hello");
+    /// assert!(!doc.is_detached());
+    /// ```
+    #[inline]
+    pub fn revert_to(&self, version: &Frontiers) -> LoroResult<()> {
+        self.revert_to_with(version, CommitOptions::new().origin("revert"))
+    }
+
+    /// Revert the document to the given version by creating new operations, and commit them
+    /// with the given [`CommitOptions`].
+    ///
+    /// The uncommitted changes are committed with the default options before reverting.
+    /// If an error is returned, no operation is created and the document is left unchanged.
+    pub fn revert_to_with(&self, version: &Frontiers, options: CommitOptions) -> LoroResult<()> {
+        self.doc.revert_to(version)?;
+        self.doc.commit_with(options);
+        Ok(())
+    }
+
     /// Checkout the `DocState` to the latest version.
     ///
     /// > The document becomes detached during a `checkout` operation.
@@ -424,6 +738,32 @@ impl LoroDoc {
         self.doc.import_with(bytes, origin.into())
     }
 
+    /// Import updates/snapshot from the reader.
+    ///
+    /// The data written by [`LoroDoc::export_to_writer`] is read frame by frame up
+    /// to its end frame, and each frame is verified as it arrives. Nothing is
+    /// applied to the document before the end frame is read. The data exported
+    /// by [`LoroDoc::export`] is read until the end of the reader.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{ExportMode, LoroDoc};
+    ///
+    /// let doc = LoroDoc::new();
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// let mut buf = Vec::new();
+    /// doc.export_to_writer(ExportMode::Snapshot, &mut buf).unwrap();
+    ///
+    /// let new_doc = LoroDoc::new();
+    /// new_doc.import_from_reader(&mut buf.as_slice()).unwrap();
+    /// assert_eq!(new_doc.get_text("text").to_string(), "Hello");
+    /// ```
+    #[inline]
+    pub fn import_from_reader(&self, r: &mut impl Read) -> Result<ImportStatus, LoroError> {
+        self.doc.import_from_reader(r)
+    }
+
     /// Import the json schema updates.
     ///
     /// only supports backward compatibility but not forward compatibility.
@@ -728,6 +1068,33 @@ impl LoroDoc {
         self.doc.query_pos(cursor)
     }
 
+    /// Like [`Self::get_cursor_pos`], but the positions in text containers are of the
+    /// given position type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{LoroDoc, PosType};
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "😀😀").unwrap();
+    /// let cursor = text
+    ///     .get_cursor_with_pos_type(4, Default::default(), PosType::Utf8)
+    ///     .unwrap();
+    /// text.insert(0, "a").unwrap();
+    /// let pos = doc.get_cursor_pos_with_pos_type(&cursor, PosType::Utf16).unwrap();
+    /// assert_eq!(pos.current.pos, 3);
+    /// ```
+    #[inline]
+    pub fn get_cursor_pos_with_pos_type(
+        &self,
+        cursor: &Cursor,
+        pos_type: PosType,
+    ) -> Result<PosQueryResult, CannotFindRelativePosition> {
+        self.doc.query_pos_with_pos_type(cursor, pos_type)
+    }
+
     /// Get the inner LoroDoc ref.
     #[inline]
     pub fn inner(&self) -> &InnerLoroDoc {
@@ -768,6 +1135,22 @@ impl LoroDoc {
         self.doc.export(mode)
     }
 
+    /// Export the document in the given mode to the writer.
+    ///
+    /// In the snapshot and updates modes the data is written as checksummed
+    /// frames, one per snapshot section or change block, instead of being
+    /// concatenated in memory first. The other modes write the same bytes as
+    /// [`LoroDoc::export`]. The output can be imported by
+    /// [`LoroDoc::import_from_reader`] or [`LoroDoc::import`].
+    #[inline]
+    pub fn export_to_writer(
+        &self,
+        mode: ExportMode,
+        w: &mut impl Write,
+    ) -> Result<(), LoroEncodeError> {
+        self.doc.export_to_writer(mode, w)
+    }
+
     /// Analyze the container info of the doc
     ///
     /// This is used for development and debugging. It can be slow.
@@ -775,6 +1158,91 @@ impl LoroDoc {
         self.doc.analyze()
     }
 
+    /// Get the authors of the text, as the runs of the text inserted by the same change.
+    ///
+    /// Each span has the peer, the change id, the lamport, the timestamp and the commit
+    /// message of the change. The ranges are in unicode chars. The uncommitted text has
+    /// no change metadata yet.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::{CommitOptions, LoroDoc};
+    ///
+    /// let doc = LoroDoc::new();
+    /// doc.set_peer_id(1).unwrap();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello").unwrap();
+    /// doc.commit_with(CommitOptions::new().commit_msg("greeting"));
+    /// doc.set_peer_id(2).unwrap();
+    /// text.insert(5, " world").unwrap();
+    /// doc.commit();
+    ///
+    /// let blame = doc.text_blame(&text.id()).unwrap();
+    /// assert_eq!(blame.len(), 2);
+    /// assert_eq!((blame[0].range.clone(), blame[0].peer), (0..5, 1));
+    /// assert_eq!(blame[0].message.as_deref(), Some("greeting"));
+    /// assert_eq!((blame[1].range.clone(), blame[1].peer), (5..11, 2));
+    /// ```
+    #[inline]
+    pub fn text_blame(&self, text: &ContainerID) -> LoroResult<Vec<TextBlameSpan>> {
+        self.doc.text_blame(text)
+    }
+
+    /// Get the authors of the text at the given version, without checking out the document.
+    ///
+    /// Only the ops between the current version and the given version are visited.
+    /// The pending transaction is committed first.
+    #[inline]
+    pub fn text_blame_at(
+        &self,
+        text: &ContainerID,
+        frontiers: &Frontiers,
+    ) -> LoroResult<Vec<TextBlameSpan>> {
+        self.doc.text_blame_at(text, frontiers)
+    }
+
+    /// Return the create and move ops of the tree after `since` that are rejected in the
+    /// current version when resolving the concurrent edits.
+    ///
+    /// A move is rejected if it would create a cycle with a concurrent move, or if the new
+    /// parent is deleted by a concurrent edit so the node is deleted with it. The same
+    /// rejections are reported in the events as [TreeExternalDiff::MoveRejected] when
+    /// the concurrent edits are imported.
+    ///
+    /// Only the ops after `since` are visited. The ops in the pending transaction are not
+    /// included.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use loro::{LoroDoc, TreeMoveRejectReason};
+    ///
+    /// let doc_a = LoroDoc::new();
+    /// doc_a.set_peer_id(1).unwrap();
+    /// let tree_a = doc_a.get_tree("tree");
+    /// let a = tree_a.create(None).unwrap();
+    /// let b = tree_a.create(None).unwrap();
+    /// doc_a.commit();
+    /// let doc_b = doc_a.fork();
+    /// doc_b.set_peer_id(2).unwrap();
+    /// let since = doc_a.oplog_frontiers();
+    ///
+    /// tree_a.mov(a, b).unwrap();
+    /// doc_b.get_tree("tree").mov(b, a).unwrap();
+    /// doc_a.import(&doc_b.export(loro::ExportMode::all_updates()).unwrap()).unwrap();
+    /// let rejected = doc_a.get_rejected_tree_moves(&tree_a.id(), &since).unwrap();
+    /// assert_eq!(rejected.len(), 1);
+    /// assert_eq!(rejected[0].reason, TreeMoveRejectReason::Cycle);
+    /// ```
+    #[inline]
+    pub fn get_rejected_tree_moves(
+        &self,
+        tree: &ContainerID,
+        since: &Frontiers,
+    ) -> LoroResult<Vec<RejectedTreeMove>> {
+        self.doc.get_rejected_tree_moves(tree, since)
+    }
+
     /// Get the path from the root to the container
     pub fn get_path_to_container(&self, id: &ContainerID) -> Option<Vec<(ContainerID, Index)>> {
         self.doc.get_path_to_container(id)
@@ -823,6 +1291,71 @@ impl LoroDoc {
         })
     }
 
+    /// Set the value at the given JSONPath, e.g. `$.settings.theme` or `$.items[3]`.
+    ///
+    /// The path can only contain keys and indexes, and it can go through maps, lists
+    /// and movable lists. Missing maps on the path are created on demand. A list item
+    /// at the given index is replaced by the new value.
+    ///
+    /// All the edits are made in the current transaction, so they are committed
+    /// together. An error is returned without editing the doc if the path is invalid,
+    /// cannot be found or hits a Text or Tree container. Other errors are raised by the
+    /// edits themselves and are not rolled back, so the maps created on the path before
+    /// the error stay in the current transaction.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use loro::{LoroDoc, ToJson};
+    /// let doc = LoroDoc::new();
+    /// doc.set_by_path("$.settings.theme", "dark").unwrap();
+    /// doc.commit();
+    /// assert_eq!(
+    ///     doc.get_deep_value().to_json_value(),
+    ///     serde_json::json!({"settings": {"theme": "dark"}})
+    /// );
+    /// ```
+    #[inline]
+    #[cfg(feature = "jsonpath")]
+    pub fn set_by_path(
+        &self,
+        path: &str,
+        value: impl Into<LoroValue>,
+    ) -> Result<(), JsonPathError> {
+        self.doc.set_by_path(path, value.into())
+    }
+
+    /// Insert the value at the given JSONPath, e.g. `$.items[3]`.
+    ///
+    /// For lists, the value is inserted before the item at the given index; the index
+    /// may be equal to the length of the list to append the value. For maps, it behaves
+    /// like [`LoroDoc::set_by_path`].
+    ///
+    /// All the edits are made in the current transaction, so they are committed
+    /// together. An error is returned without editing the doc if the path is invalid,
+    /// cannot be found or hits a Text or Tree container. Other errors are raised by the
+    /// edits themselves and are not rolled back, so the maps created on the path before
+    /// the error stay in the current transaction.
+    #[inline]
+    #[cfg(feature = "jsonpath")]
+    pub fn insert_by_path(
+        &self,
+        path: &str,
+        value: impl Into<LoroValue>,
+    ) -> Result<(), JsonPathError> {
+        self.doc.insert_by_path(path, value.into())
+    }
+
+    /// Delete the value at the given JSONPath, e.g. `$.settings.theme` or `$.items[3]`.
+    ///
+    /// An error is returned without editing the doc if the path is invalid, cannot
+    /// be found or hits a Text or Tree container.
+    #[inline]
+    #[cfg(feature = "jsonpath")]
+    pub fn delete_by_path(&self, path: &str) -> Result<(), JsonPathError> {
+        self.doc.delete_by_path(path)
+    }
+
     /// Get the number of operations in the pending transaction.
     ///
     /// The pending transaction is the one that is not committed yet. It will be committed
@@ -1444,6 +1977,78 @@ impl LoroText {
         self.handler.insert_utf8(pos, s)
     }
 
+    /// Insert an embed, such as an inline image or a mention, at the given unicode position.
+    ///
+    /// The embed has length 1 and it's shown as [EMBED_CHAR] in the plain text. It's an
+    /// insert of the value in [TextDelta], and an insert of `{"$embed": value}` in
+    /// [Self::to_delta]. It can be styled like the other chars.
+    ///
+    /// The value can't be null or a container. Use [Self::insert_container] to embed
+    /// a child container.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{loro_value, LoroDoc, TextDelta, ToJson};
+    /// use serde_json::json;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hi !").unwrap();
+    /// text.insert_embed(3, loro_value!({"mention": "alice"})).unwrap();
+    /// assert_eq!(text.len_unicode(), 5);
+    /// assert_eq!(text.to_string(), "Hi \u{FFFC}!");
+    /// assert_eq!(
+    ///     text.to_delta().to_json_value(),
+    ///     json!([
+    ///         {"insert": "Hi "},
+    ///         {"insert": {"$embed": {"mention": "alice"}}},
+    ///         {"insert": "!"},
+    ///     ])
+    /// );
+    /// assert_eq!(
+    ///     text.slice_delta(3, 4).unwrap(),
+    ///     vec![TextDelta::Embed {
+    ///         insert: loro_value!({"mention": "alice"}),
+    ///         attributes: None,
+    ///     }]
+    /// );
+    /// ```
+    pub fn insert_embed(&self, pos: usize, value: impl Into<LoroValue>) -> LoroResult<()> {
+        self.handler.insert_embed(pos, value.into())
+    }
+
+    /// Insert a child container as an embed at the given unicode position.
+    ///
+    /// The embed has length 1 like the other embeds, and it's an insert of the
+    /// [LoroValue::Container] of the child in [TextDelta]. The child is deleted along
+    /// with the embed. It returns an error if the text is detached.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{LoroDoc, LoroMap, LoroValue, TextDelta};
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hi !").unwrap();
+    /// let image = text.insert_container(3, LoroMap::new()).unwrap();
+    /// image.insert("src", "cat.png").unwrap();
+    /// assert_eq!(
+    ///     text.slice_delta(3, 4).unwrap(),
+    ///     vec![TextDelta::Embed {
+    ///         insert: LoroValue::Container(image.id()),
+    ///         attributes: None,
+    ///     }]
+    /// );
+    /// ```
+    #[inline]
+    pub fn insert_container<C: ContainerTrait>(&self, pos: usize, child: C) -> LoroResult<C> {
+        Ok(C::from_handler(
+            self.handler.insert_container(pos, child.to_handler())?,
+        ))
+    }
+
     /// Delete a range of text at the given unicode position with unicode length.
     pub fn delete(&self, pos: usize, len: usize) -> LoroResult<()> {
         self.handler.delete_unicode(pos, len)
@@ -1469,6 +2074,56 @@ impl LoroText {
         self.handler.splice(pos, len, s)
     }
 
+    /// Get a string slice at the given range of the given position type.
+    pub fn slice_with_pos_type(
+        &self,
+        start_index: usize,
+        end_index: usize,
+        pos_type: PosType,
+    ) -> LoroResult<String> {
+        self.handler
+            .slice_with_pos_type(start_index, end_index, pos_type)
+    }
+
+    /// Get the character at the given position of the given position type.
+    pub fn char_at_with_pos_type(&self, pos: usize, pos_type: PosType) -> LoroResult<char> {
+        self.handler.char_at_with_pos_type(pos, pos_type)
+    }
+
+    /// Delete specified character and insert string at the same position, where `pos`
+    /// and `len` are of the given position type.
+    pub fn splice_with_pos_type(
+        &self,
+        pos: usize,
+        len: usize,
+        s: &str,
+        pos_type: PosType,
+    ) -> LoroResult<String> {
+        self.handler.splice_with_pos_type(pos, len, s, pos_type)
+    }
+
+    /// Convert the position from one type to another.
+    ///
+    /// Return `None` if the position is out of bound or not at a char boundary.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{LoroDoc, PosType};
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "a😀b").unwrap();
+    /// assert_eq!(text.convert_pos(2, PosType::Unicode, PosType::Utf16), Some(3));
+    /// assert_eq!(text.convert_pos(5, PosType::Utf8, PosType::Unicode), Some(2));
+    /// // In the middle of the emoji
+    /// assert_eq!(text.convert_pos(2, PosType::Utf16, PosType::Unicode), None);
+    /// assert_eq!(text.convert_pos(4, PosType::Unicode, PosType::Utf8), None);
+    /// ```
+    pub fn convert_pos(&self, index: usize, from: PosType, to: PosType) -> Option<usize> {
+        self.handler.convert_pos(index, from, to)
+    }
+
     /// Whether the text container is empty.
     pub fn is_empty(&self) -> bool {
         self.handler.is_empty()
@@ -1489,10 +2144,58 @@ impl LoroText {
         self.handler.len_utf16()
     }
 
+    /// Find the non-overlapping occurrences of `pattern`, and return their Unicode ranges.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "😀 abc abc").unwrap();
+    /// assert_eq!(text.find("abc"), vec![2..5, 6..9]);
+    /// ```
+    pub fn find(&self, pattern: &str) -> Vec<Range<usize>> {
+        self.handler.find(pattern)
+    }
+
+    /// Replace all the matches of the regex `pattern` with `replacement`, and return
+    /// the number of the matches.
+    ///
+    /// `replacement` can refer to the capture groups, like `$1` or `${name}`. All the
+    /// replacements are done in one transaction, and only the chars that differ from
+    /// the matched text are deleted and inserted, so the marks on the rest are kept.
+    ///
+    /// The text is matched line by line, so a match never spans a `\n`, and `^` and `$`
+    /// match at the line bounds.
+    ///
+    /// It requires the `regex` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "2024-01-02 and 2024-03-04").unwrap();
+    /// let count = text
+    ///     .replace_all(r"(\d+)-(\d+)-(\d+)", "$3/$2/$1")
+    ///     .unwrap();
+    /// assert_eq!(count, 2);
+    /// assert_eq!(text.to_string(), "02/01/2024 and 04/03/2024");
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn replace_all(&self, pattern: &str, replacement: &str) -> LoroResult<usize> {
+        self.handler.replace_all(pattern, replacement)
+    }
+
     /// Update the current text based on the provided text.
     ///
     /// It will calculate the minimal difference and apply it to the current text.
-    /// It uses Myers' diff algorithm to compute the optimal difference.
+    /// It uses Myers' diff algorithm to compute the optimal difference by default.
+    /// See [UpdateOptions] for diffing word by word or using patience or histogram diff.
     ///
     /// This could take a long time for large texts (e.g. > 50_000 characters).
     /// In that case, you should use `updateByLine` instead.
@@ -1523,6 +2226,33 @@ impl LoroText {
         self.handler.update_by_line(text, options)
     }
 
+    /// Update the current text to the rich text of the given delta, which can only
+    /// contain inserts.
+    ///
+    /// The text is diffed like [`LoroText::update`], then the styles are reconciled
+    /// with the minimal marks and unmarks. So the unchanged text keeps its ids,
+    /// styles and the cursors on it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use loro::{LoroDoc, TextDelta, ToJson};
+    /// use serde_json::json;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello world").unwrap();
+    /// let target = json!([
+    ///     {"insert": "Hello "},
+    ///     {"insert": "Loro", "attributes": {"bold": true}},
+    /// ]);
+    /// let delta: Vec<TextDelta> = serde_json::from_value(target.clone()).unwrap();
+    /// text.update_with_marks(&delta, Default::default()).unwrap();
+    /// assert_eq!(text.to_delta().to_json_value(), target);
+    /// ```
+    pub fn update_with_marks(&self, delta: &[TextDelta], options: UpdateOptions) -> LoroResult<()> {
+        self.handler.update_with_marks(delta, options)
+    }
+
     /// Apply a [delta](https://quilljs.com/docs/delta/) to the text container.
     pub fn apply_delta(&self, delta: &[TextDelta]) -> LoroResult<()> {
         self.handler.apply_delta(delta)
@@ -1541,7 +2271,8 @@ impl LoroText {
     ///
     /// *You should make sure that a key is always associated with the same expand type.*
     ///
-    /// Note: this is not suitable for unmergeable annotations like comments.
+    /// Note: the marks of a key replace each other by default. Configure the key as non-exclusive
+    /// with [LoroDoc::config_text_style] to keep the annotations like comments separately.
     pub fn mark(
         &self,
         range: Range<usize>,
@@ -1551,6 +2282,18 @@ impl LoroText {
         self.handler.mark(range.start, range.end, key, value.into())
     }
 
+    /// Like [`Self::mark`], but the range is of the given position type.
+    pub fn mark_with_pos_type(
+        &self,
+        range: Range<usize>,
+        key: &str,
+        value: impl Into<LoroValue>,
+        pos_type: PosType,
+    ) -> LoroResult<()> {
+        self.handler
+            .mark_with_pos_type(range.start, range.end, key, value.into(), pos_type)
+    }
+
     /// Unmark a range of text with a key and a value.
     ///
     /// You can use it to remove highlights, bolds or links
@@ -1566,11 +2309,37 @@ impl LoroText {
     ///
     /// *You should make sure that a key is always associated with the same expand type.*
     ///
-    /// Note: you cannot delete unmergeable annotations like comments by this method.
+    /// Unmarking a non-exclusive key removes the marks of all its values in the range.
+    /// Use [`Self::unmark_value`] to remove a single one.
     pub fn unmark(&self, range: Range<usize>, key: &str) -> LoroResult<()> {
         self.handler.unmark(range.start, range.end, key)
     }
 
+    /// Remove the mark of the given value of a non-exclusive style in the range.
+    /// The marks of its other values are kept.
+    ///
+    /// It's the same as [`Self::unmark`] if the style is exclusive.
+    pub fn unmark_value(
+        &self,
+        range: Range<usize>,
+        key: &str,
+        value: impl Into<LoroValue>,
+    ) -> LoroResult<()> {
+        self.handler
+            .unmark_value(range.start, range.end, key, value.into())
+    }
+
+    /// Like [`Self::unmark`], but the range is of the given position type.
+    pub fn unmark_with_pos_type(
+        &self,
+        range: Range<usize>,
+        key: &str,
+        pos_type: PosType,
+    ) -> LoroResult<()> {
+        self.handler
+            .unmark_with_pos_type(range.start, range.end, key, pos_type)
+    }
+
     /// Get the text in [Delta](https://quilljs.com/docs/delta/) format.
     ///
     /// # Example
@@ -1602,6 +2371,87 @@ impl LoroText {
         self.handler.get_richtext_value()
     }
 
+    /// Get the styles of the char at the given Unicode position.
+    ///
+    /// It doesn't need to build the delta of the whole text, so it's cheap on large texts.
+    ///
+    /// # Example
+    /// ```
+    /// # use loro::{LoroDoc, LoroValue};
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello world!").unwrap();
+    /// text.mark(0..5, "bold", true).unwrap();
+    /// assert_eq!(text.get_styles_at(1).unwrap().get("bold"), Some(&LoroValue::Bool(true)));
+    /// assert!(text.get_styles_at(5).unwrap().is_empty());
+    /// assert!(text.get_styles_at(12).is_err());
+    /// ```
+    pub fn get_styles_at(&self, pos: usize) -> LoroResult<FxHashMap<String, LoroValue>> {
+        self.handler.get_styles_at(pos)
+    }
+
+    /// Get every Unicode range where the style of `key` is set, with the style values.
+    ///
+    /// The adjacent ranges with the same value are merged. It scans the style runs of
+    /// all the keys, so the cost grows with the number of marks in the text, plus a
+    /// position lookup for each range of `key`.
+    ///
+    /// # Example
+    /// ```
+    /// # use loro::{LoroDoc, LoroValue};
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello world!").unwrap();
+    /// text.mark(0..5, "link", "a.com").unwrap();
+    /// text.mark(6..11, "link", "b.com").unwrap();
+    /// text.mark(2..8, "bold", true).unwrap();
+    /// assert_eq!(
+    ///     text.style_ranges("link"),
+    ///     vec![(0..5, LoroValue::from("a.com")), (6..11, LoroValue::from("b.com"))]
+    /// );
+    /// ```
+    pub fn style_ranges(&self, key: &str) -> Vec<(Range<usize>, LoroValue)> {
+        self.handler.style_ranges(key)
+    }
+
+    /// Get the text in the given Unicode range in [Delta](https://quilljs.com/docs/delta/) format.
+    ///
+    /// # Example
+    /// ```
+    /// # use loro::{LoroDoc, TextDelta};
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello world!").unwrap();
+    /// text.mark(0..5, "bold", true).unwrap();
+    /// assert_eq!(
+    ///     text.slice_delta(3, 8).unwrap(),
+    ///     vec![
+    ///         TextDelta::Insert {
+    ///             insert: "lo".into(),
+    ///             attributes: Some([("bold".to_string(), true.into())].into_iter().collect()),
+    ///         },
+    ///         TextDelta::Insert {
+    ///             insert: " wo".into(),
+    ///             attributes: None,
+    ///         },
+    ///     ]
+    /// );
+    /// ```
+    pub fn slice_delta(&self, start_index: usize, end_index: usize) -> LoroResult<Vec<TextDelta>> {
+        self.handler.slice_delta(start_index, end_index)
+    }
+
+    /// Like [`Self::slice_delta`], but the range is of the given position type.
+    pub fn slice_delta_with_pos_type(
+        &self,
+        start_index: usize,
+        end_index: usize,
+        pos_type: PosType,
+    ) -> LoroResult<Vec<TextDelta>> {
+        self.handler
+            .slice_delta_with_pos_type(start_index, end_index, pos_type)
+    }
+
     /// Get the text content of the text container.
     #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
@@ -1642,6 +2492,19 @@ impl LoroText {
         self.handler.get_cursor(pos, side)
     }
 
+    /// Like [`Self::get_cursor`], but `pos` is of the given position type.
+    ///
+    /// Use [`LoroDoc::get_cursor_pos_with_pos_type`] to get the position of the cursor
+    /// in the same type.
+    pub fn get_cursor_with_pos_type(
+        &self,
+        pos: usize,
+        side: Side,
+        pos_type: PosType,
+    ) -> Option<Cursor> {
+        self.handler.get_cursor_with_pos_type(pos, side, pos_type)
+    }
+
     /// Whether the text container is deleted.
     pub fn is_deleted(&self) -> bool {
         self.handler.is_deleted()
@@ -1927,6 +2790,85 @@ impl LoroTree {
             .map(|h| LoroMap { handler: h })
     }
 
+    /// Duplicate the `target` node and all its descendants to be a child of `parent`
+    /// at the given index, and return the id of the copy of `target`.
+    ///
+    /// The meta maps are deeply copied, including the containers nested in them.
+    /// All the operations are in a single transaction.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use loro::{LoroDoc, LoroText, LoroValue};
+    ///
+    /// let doc = LoroDoc::new();
+    /// let tree = doc.get_tree("tree");
+    /// let folder = tree.create(None).unwrap();
+    /// let file = tree.create(folder).unwrap();
+    /// let content = tree
+    ///     .get_meta(file)
+    ///     .unwrap()
+    ///     .insert_container("content", LoroText::new())
+    ///     .unwrap();
+    /// content.insert(0, "Hello").unwrap();
+    ///
+    /// let copy = tree.duplicate_subtree(folder, None, 1).unwrap();
+    /// let file_copy = tree.children(copy).unwrap()[0];
+    /// assert_ne!(file_copy, file);
+    /// let value = tree.get_meta(file_copy).unwrap().get_deep_value();
+    /// assert_eq!(value.into_map().unwrap()["content"], LoroValue::from("Hello"));
+    /// ```
+    pub fn duplicate_subtree<T: Into<TreeParentId>>(
+        &self,
+        target: TreeID,
+        parent: T,
+        index: usize,
+    ) -> LoroResult<TreeID> {
+        self.handler.duplicate_subtree(target, parent.into(), index)
+    }
+
+    /// Export the `target` node and all its descendants as a [LoroValue].
+    ///
+    /// Each node is a map of its `id`, `meta` and `children`. The containers in the
+    /// meta are exported with their types, so [LoroTree::import_subtree] can rebuild
+    /// them. The value can be imported to another tree or document.
+    ///
+    /// The keys starting with `$` in the map values are escaped as `$$`, so they
+    /// can't be confused with the exported containers.
+    pub fn export_subtree(&self, target: TreeID) -> LoroResult<LoroValue> {
+        self.handler.export_subtree(target)
+    }
+
+    /// Rebuild the nodes exported by [LoroTree::export_subtree] as the last child of
+    /// `parent`, and return the id of the new root node of the subtree.
+    ///
+    /// All the operations are in a single transaction.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let tree = doc.get_tree("tree");
+    /// let root = tree.create(None).unwrap();
+    /// tree.get_meta(root).unwrap().insert("name", "root").unwrap();
+    /// tree.create(root).unwrap();
+    /// let value = tree.export_subtree(root).unwrap();
+    ///
+    /// let other = LoroDoc::new();
+    /// let other_tree = other.get_tree("tree");
+    /// let new_root = other_tree.import_subtree(&value, None).unwrap();
+    /// assert_eq!(other_tree.children_num(new_root), Some(1));
+    /// ```
+    pub fn import_subtree<T: Into<TreeParentId>>(
+        &self,
+        value: &LoroValue,
+        parent: T,
+    ) -> LoroResult<TreeID> {
+        self.handler.import_subtree(value, parent.into())
+    }
+
     /// Return the parent of target node.
     ///
     /// - If the target node does not exist, return `None`.
@@ -1983,6 +2925,90 @@ impl LoroTree {
         self.handler.children_num(&parent)
     }
 
+    /// Iterate the nodes under `start` in depth-first pre-order.
+    ///
+    /// If `start` is a node, it's the first visited node. The deleted nodes are skipped
+    /// unless `with_deleted` is true, in which case walking from the root also visits
+    /// the deleted subtrees after the alive ones.
+    ///
+    /// The iterator is lazy: the children of a node are read when the node is visited.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let tree = doc.get_tree("tree");
+    /// let root = tree.create(None).unwrap();
+    /// let a = tree.create(root).unwrap();
+    /// let b = tree.create(root).unwrap();
+    /// let c = tree.create(a).unwrap();
+    /// assert_eq!(tree.walk_dfs(None, false).collect::<Vec<_>>(), vec![root, a, c, b]);
+    /// assert_eq!(tree.walk_dfs(a, false).collect::<Vec<_>>(), vec![a, c]);
+    /// ```
+    pub fn walk_dfs<T: Into<TreeParentId>>(
+        &self,
+        start: T,
+        with_deleted: bool,
+    ) -> impl Iterator<Item = TreeID> {
+        self.handler.walk_dfs(start.into(), with_deleted)
+    }
+
+    /// Iterate the nodes under `start` in breadth-first order.
+    ///
+    /// See [LoroTree::walk_dfs] for how `start` and `with_deleted` are handled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let tree = doc.get_tree("tree");
+    /// let root = tree.create(None).unwrap();
+    /// let a = tree.create(root).unwrap();
+    /// let b = tree.create(root).unwrap();
+    /// let c = tree.create(a).unwrap();
+    /// assert_eq!(tree.walk_bfs(None, false).collect::<Vec<_>>(), vec![root, a, b, c]);
+    /// ```
+    pub fn walk_bfs<T: Into<TreeParentId>>(
+        &self,
+        start: T,
+        with_deleted: bool,
+    ) -> impl Iterator<Item = TreeID> {
+        self.handler.walk_bfs(start.into(), with_deleted)
+    }
+
+    /// Return the ancestors of the target node, ordered from its parent to the root.
+    ///
+    /// If the target node does not exist, return `None`.
+    pub fn ancestors(&self, target: TreeID) -> Option<Vec<TreeID>> {
+        self.handler.ancestors(&target)
+    }
+
+    /// Return the depth of the target node. The depth of a root node is 0.
+    ///
+    /// If the target node does not exist, return `None`.
+    pub fn depth(&self, target: TreeID) -> Option<usize> {
+        self.handler.depth(&target)
+    }
+
+    /// Return whether `maybe_ancestor` is an ancestor of `node`.
+    ///
+    /// A node is not an ancestor of itself.
+    pub fn is_ancestor_of(&self, maybe_ancestor: TreeID, node: TreeID) -> bool {
+        self.handler.is_ancestor_of(&maybe_ancestor, &node)
+    }
+
+    /// Return the lowest common ancestor of the two nodes, where a node is regarded as
+    /// an ancestor of itself.
+    ///
+    /// If the nodes are not in the same tree or any of them does not exist, return `None`.
+    pub fn lowest_common_ancestor(&self, a: TreeID, b: TreeID) -> Option<TreeID> {
+        self.handler.lowest_common_ancestor(&a, &b)
+    }
+
     /// Return container id of the tree.
     pub fn id(&self) -> ContainerID {
         self.handler.id()
@@ -2008,6 +3034,56 @@ impl LoroTree {
         self.handler.get_deep_value()
     }
 
+    /// Return the hierarchy array of the children of `options.root`, each node is with
+    /// the selected metadata, limited to `options.max_depth` levels and the
+    /// `options.offset`/`options.limit` window of the children of the root.
+    ///
+    /// Besides the keys returned by [LoroTree::get_value_with_meta], each node has
+    /// `children_num`, so the branches that are not loaded can be fetched later by
+    /// passing them as the root.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use loro::{LoroDoc, TreeParentId, TreeValueOptions};
+    ///
+    /// let doc = LoroDoc::new();
+    /// let tree = doc.get_tree("tree");
+    /// let root = tree.create(None).unwrap();
+    /// for i in 0..10 {
+    ///     let child = tree.create(root).unwrap();
+    ///     let meta = tree.get_meta(child).unwrap();
+    ///     meta.insert("title", format!("item {i}")).unwrap();
+    ///     meta.insert("body", "long text").unwrap();
+    ///     tree.create(child).unwrap();
+    /// }
+    ///
+    /// let value = tree
+    ///     .get_value_with_meta_filtered(&TreeValueOptions {
+    ///         root: TreeParentId::Node(root),
+    ///         max_depth: Some(1),
+    ///         meta_keys: Some(vec!["title".to_string()]),
+    ///         offset: 2,
+    ///         limit: Some(3),
+    ///     })
+    ///     .unwrap();
+    /// let nodes = value.into_list().unwrap();
+    /// assert_eq!(nodes.len(), 3);
+    /// let node = nodes[0].as_map().unwrap();
+    /// assert_eq!(node.get("index"), Some(&2.into()));
+    /// assert_eq!(node.get("children_num"), Some(&1.into()));
+    /// assert_eq!(node.get("children").unwrap().as_list().unwrap().len(), 0);
+    /// let meta = node.get("meta").unwrap().as_map().unwrap();
+    /// assert_eq!(meta.get("title"), Some(&"item 2".into()));
+    /// assert!(meta.get("body").is_none());
+    /// ```
+    pub fn get_value_with_meta_filtered(
+        &self,
+        options: &TreeValueOptions,
+    ) -> LoroResult<LoroValue> {
+        self.handler.get_value_with_meta_filtered(options)
+    }
+
     // This method is used for testing only.
     #[doc(hidden)]
     #[allow(non_snake_case)]
@@ -2042,6 +3118,83 @@ impl LoroTree {
         self.handler.disable_fractional_index();
     }
 
+    /// Assign fresh evenly spaced fractional indexes to the children of `parent` without
+    /// changing their order, and return the number of the moved nodes.
+    ///
+    /// The fractional indexes grow when nodes are repeatedly inserted between the same
+    /// siblings. Rebalancing issues a move for each child whose index changes, so that
+    /// the later inserts generate short indexes again.
+    ///
+    /// The moves compete with the concurrent edits of the siblings: a concurrent move of a
+    /// sibling to another parent may be overridden by the rebalancing, and the siblings
+    /// inserted concurrently are positioned by the old indexes. Prefer rebalancing when
+    /// the siblings are not being edited by the other peers.
+    ///
+    /// # Errors
+    ///
+    /// - If the fractional index is disabled, return `LoroTreeError::FractionalIndexNotEnabled`.
+    /// - If the parent is deleted or does not exist, return `LoroTreeError::TreeNodeDeletedOrNotExist`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let tree = doc.get_tree("tree");
+    /// tree.enable_fractional_index(0);
+    /// let first = tree.create(None).unwrap();
+    /// let last = tree.create(None).unwrap();
+    /// for _ in 0..100 {
+    ///     tree.create_at(None, 1).unwrap();
+    /// }
+    /// let before = tree.get_position_key_stats(None).unwrap();
+    /// tree.rebalance_positions(None).unwrap();
+    /// let after = tree.get_position_key_stats(None).unwrap();
+    /// assert!(after.max_len < before.max_len);
+    /// assert_eq!(tree.children(None).unwrap()[0], first);
+    /// assert_eq!(tree.children(None).unwrap()[101], last);
+    /// ```
+    pub fn rebalance_positions<T: Into<TreeParentId>>(&self, parent: T) -> LoroResult<usize> {
+        self.handler.rebalance_positions(parent.into())
+    }
+
+    /// Rebalance the siblings automatically when a local create or move generates a
+    /// fractional index longer than `threshold` bytes. Pass `None` to disable it.
+    ///
+    /// It's disabled by default. See [LoroTree::rebalance_positions], including how the
+    /// rebalancing competes with the concurrent edits.
+    ///
+    /// The threshold is a local setting of the document, like [LoroDoc::config_text_style].
+    /// It's kept after checkout and inherited by [LoroDoc::fork], but it's not included in
+    /// the exported snapshots or updates, so it needs to be set again on the other documents.
+    ///
+    /// It returns [LoroError::MisuseDetachedContainer] if the tree is detached.
+    pub fn set_auto_rebalance_threshold(&self, threshold: Option<usize>) -> LoroResult<()> {
+        self.handler.set_auto_rebalance_threshold(threshold)
+    }
+
+    /// The threshold of the automatic rebalancing.
+    pub fn auto_rebalance_threshold(&self) -> Option<usize> {
+        self.handler.auto_rebalance_threshold()
+    }
+
+    /// Return the distribution of the fractional index lengths of the children of `parent`.
+    ///
+    /// If the parent has no children, return `None`.
+    pub fn get_position_key_stats<T: Into<TreeParentId>>(
+        &self,
+        parent: T,
+    ) -> Option<TreePositionKeyStats> {
+        self.handler.get_position_key_stats(&parent.into())
+    }
+
+    /// Return the distribution of the fractional index lengths for each alive parent that
+    /// has children, including the root.
+    pub fn get_all_position_key_stats(&self) -> Vec<TreePositionKeyStats> {
+        self.handler.get_all_position_key_stats()
+    }
+
     /// Whether the tree is empty.
     ///
     #[inline]
@@ -0,0 +1,480 @@
+//! Convert the rich text to and from Markdown and HTML.
+//!
+//! The inline styles are mapped to the markup by [MarkupConfig]. The block styles
+//! follow the conventions of Quill: they are the attributes of the line break that
+//! ends the line.
+//!
+//! - [HEADER_KEY]: the level of the heading, from 1 to 6
+//! - [LIST_KEY]: `"bullet"` or `"ordered"`
+//! - [BLOCKQUOTE_KEY]: set to `true`
+//! - [CODE_BLOCK_KEY]: set to `true`
+//!
+//! The embeds of `{"image": url}` are images. The other embeds are dropped when
+//! exporting. The styles of the block keys need to be configured by
+//! [crate::LoroDoc::config_text_style] before the parsed delta is applied.
+//!
+//! The urls of the links and images are sanitized both ways: the unsafe ones, such
+//! as `javascript:`, are dropped when parsing, and written as the plain text when
+//! exporting.
+use fxhash::FxHashMap;
+use loro_internal::{handler::TextDelta, LoroValue};
+
+#[cfg(feature = "html")]
+mod html;
+#[cfg(feature = "markdown")]
+mod markdown;
+
+#[cfg(feature = "html")]
+pub use html::{from_html, to_html};
+#[cfg(feature = "markdown")]
+pub use markdown::{from_markdown, to_markdown};
+
+/// The attribute key of the headings
+pub const HEADER_KEY: &str = "header";
+/// The attribute key of the list items
+pub const LIST_KEY: &str = "list";
+/// The attribute key of the quotes
+pub const BLOCKQUOTE_KEY: &str = "blockquote";
+/// The attribute key of the code blocks
+pub const CODE_BLOCK_KEY: &str = "code-block";
+/// The key of the url in the value of an image embed
+pub const IMAGE_KEY: &str = "image";
+
+type Attributes = FxHashMap<String, LoroValue>;
+
+/// The markup of an inline style.
+///
+/// When the styles are nested, the earlier variants are outside.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MarkSyntax {
+    /// `[text](url)` and `<a>`. The value of the mark is the url.
+    Link,
+    /// `**text**` and `<strong>`
+    Bold,
+    /// `*text*` and `<em>`
+    Italic,
+    /// `~~text~~` and `<del>`
+    Strikethrough,
+    /// `` `text` `` and `<code>`
+    Code,
+}
+
+/// The mapping from the mark keys to the markup.
+///
+/// The marks of the other keys are dropped when exporting.
+///
+/// # Example
+/// ```
+/// use loro::markup::{MarkSyntax, MarkupConfig};
+///
+/// let config = MarkupConfig::default().mark("strike", MarkSyntax::Strikethrough);
+/// assert_eq!(config.syntax_of("strike"), Some(MarkSyntax::Strikethrough));
+/// assert_eq!(config.key_of(MarkSyntax::Bold), Some("bold"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkupConfig {
+    marks: Vec<(String, MarkSyntax)>,
+}
+
+impl Default for MarkupConfig {
+    /// Map `bold`, `italic`, `link` and `code` of the default style config
+    fn default() -> Self {
+        Self::new()
+            .mark("bold", MarkSyntax::Bold)
+            .mark("italic", MarkSyntax::Italic)
+            .mark("link", MarkSyntax::Link)
+            .mark("code", MarkSyntax::Code)
+    }
+}
+
+impl MarkupConfig {
+    /// Create a config without any mark
+    pub fn new() -> Self {
+        Self { marks: Vec::new() }
+    }
+
+    /// Map the mark key to the syntax. It replaces the old syntax of the key.
+    ///
+    /// If several keys have the same syntax, the first one is used when parsing.
+    pub fn mark(mut self, key: impl Into<String>, syntax: MarkSyntax) -> Self {
+        let key = key.into();
+        match self.marks.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, s)) => *s = syntax,
+            None => self.marks.push((key, syntax)),
+        }
+        self
+    }
+
+    pub fn syntax_of(&self, key: &str) -> Option<MarkSyntax> {
+        self.marks
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, syntax)| *syntax)
+    }
+
+    pub fn key_of(&self, syntax: MarkSyntax) -> Option<&str> {
+        self.marks
+            .iter()
+            .find(|(_, s)| *s == syntax)
+            .map(|(k, _)| k.as_str())
+    }
+}
+
+/// The inline style of a span. The url is only set for the links.
+type Style = (MarkSyntax, Option<String>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Content {
+    Text(String),
+    /// The url of an image
+    Image(String),
+}
+
+#[derive(Debug, Clone)]
+struct Span {
+    content: Content,
+    /// Sorted by the syntax
+    styles: Vec<Style>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Paragraph,
+    Header(u8),
+    Bullet,
+    Ordered,
+    Quote,
+    Code,
+}
+
+impl Block {
+    fn from_attributes(attributes: Option<&Attributes>) -> Self {
+        let Some(attributes) = attributes else {
+            return Block::Paragraph;
+        };
+        let is_set = |key: &str| attributes.get(key).is_some_and(is_truthy);
+        if is_set(CODE_BLOCK_KEY) {
+            return Block::Code;
+        }
+
+        let level = match attributes.get(HEADER_KEY) {
+            Some(LoroValue::I64(n)) => Some(*n),
+            Some(LoroValue::Double(n)) => Some(*n as i64),
+            _ => None,
+        };
+        if let Some(level @ 1..=6) = level {
+            return Block::Header(level as u8);
+        }
+
+        match attributes.get(LIST_KEY) {
+            Some(LoroValue::String(s)) if s.as_str() == "bullet" => return Block::Bullet,
+            Some(LoroValue::String(s)) if s.as_str() == "ordered" => return Block::Ordered,
+            _ => {}
+        }
+
+        if is_set(BLOCKQUOTE_KEY) {
+            Block::Quote
+        } else {
+            Block::Paragraph
+        }
+    }
+
+    fn to_attributes(self) -> Attributes {
+        let mut ans = Attributes::default();
+        match self {
+            Block::Paragraph => {}
+            Block::Header(level) => {
+                ans.insert(HEADER_KEY.into(), (level as i64).into());
+            }
+            Block::Bullet => {
+                ans.insert(LIST_KEY.into(), "bullet".into());
+            }
+            Block::Ordered => {
+                ans.insert(LIST_KEY.into(), "ordered".into());
+            }
+            Block::Quote => {
+                ans.insert(BLOCKQUOTE_KEY.into(), true.into());
+            }
+            Block::Code => {
+                ans.insert(CODE_BLOCK_KEY.into(), true.into());
+            }
+        }
+        ans
+    }
+
+    /// Whether the consecutive lines of the block are grouped together
+    fn is_grouped(self) -> bool {
+        matches!(
+            self,
+            Block::Bullet | Block::Ordered | Block::Quote | Block::Code
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    spans: Vec<Span>,
+    block: Block,
+}
+
+impl Line {
+    /// The text without the styles and images
+    fn plain_text(&self) -> String {
+        self.spans
+            .iter()
+            .filter_map(|span| match &span.content {
+                Content::Text(text) => Some(text.as_str()),
+                Content::Image(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Whether the url is safe to be used as a link or an image.
+///
+/// The relative urls and the urls of http, https, mailto and tel are allowed.
+fn is_safe_url(url: &str) -> bool {
+    let url = url.trim();
+    let scheme_end = url.find(':');
+    let path_start = url.find(['/', '?', '#']);
+    match (scheme_end, path_start) {
+        (Some(colon), Some(path)) if path < colon => true,
+        (Some(colon), _) => {
+            let scheme = url[..colon].to_ascii_lowercase();
+            matches!(scheme.as_str(), "http" | "https" | "mailto" | "tel")
+        }
+        (None, _) => true,
+    }
+}
+
+fn is_truthy(value: &LoroValue) -> bool {
+    !matches!(value, LoroValue::Null | LoroValue::Bool(false))
+}
+
+fn styles_of(attributes: Option<&Attributes>, config: &MarkupConfig) -> Vec<Style> {
+    let mut ans: Vec<Style> = attributes
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| match (config.syntax_of(key)?, value) {
+            (MarkSyntax::Link, LoroValue::String(url)) if is_safe_url(url) => {
+                Some((MarkSyntax::Link, Some(url.to_string())))
+            }
+            (MarkSyntax::Link, _) => None,
+            (syntax, value) => is_truthy(value).then_some((syntax, None)),
+        })
+        .collect();
+    ans.sort();
+    ans.dedup_by_key(|(syntax, _)| *syntax);
+    ans
+}
+
+fn push_span(spans: &mut Vec<Span>, content: Content, styles: Vec<Style>) {
+    if let (
+        Content::Text(text),
+        Some(Span {
+            content: Content::Text(last),
+            styles: last_styles,
+        }),
+    ) = (&content, spans.last_mut())
+    {
+        if *last_styles == styles {
+            last.push_str(text);
+            return;
+        }
+    }
+
+    spans.push(Span { content, styles });
+}
+
+/// Split the delta into lines. The trailing text without a line break is a paragraph.
+fn lines(delta: &[TextDelta], config: &MarkupConfig) -> Vec<Line> {
+    let mut ans = Vec::new();
+    let mut spans = Vec::new();
+    for item in delta {
+        match item {
+            TextDelta::Insert { insert, attributes } => {
+                let styles = styles_of(attributes.as_ref(), config);
+                for (i, part) in insert.split('\n').enumerate() {
+                    if i > 0 {
+                        ans.push(Line {
+                            spans: std::mem::take(&mut spans),
+                            block: Block::from_attributes(attributes.as_ref()),
+                        });
+                    }
+
+                    if !part.is_empty() {
+                        push_span(&mut spans, Content::Text(part.to_string()), styles.clone());
+                    }
+                }
+            }
+            TextDelta::Embed { insert, attributes } => {
+                if let LoroValue::Map(map) = insert {
+                    if let Some(LoroValue::String(url)) = map.get(IMAGE_KEY) {
+                        // The images of the unsafe urls are dropped
+                        if !is_safe_url(url) {
+                            continue;
+                        }
+
+                        let styles = styles_of(attributes.as_ref(), config);
+                        push_span(&mut spans, Content::Image(url.to_string()), styles);
+                    }
+                }
+            }
+            TextDelta::Retain { .. } | TextDelta::Delete { .. } => {}
+        }
+    }
+
+    if !spans.is_empty() {
+        ans.push(Line {
+            spans,
+            block: Block::Paragraph,
+        });
+    }
+
+    ans
+}
+
+/// Group the consecutive lines of the grouped blocks
+fn groups(lines: &[Line]) -> Vec<&[Line]> {
+    let mut ans = Vec::new();
+    let mut start = 0;
+    for i in 1..=lines.len() {
+        if i == lines.len() || !lines[i].block.is_grouped() || lines[i].block != lines[start].block
+        {
+            ans.push(&lines[start..i]);
+            start = i;
+        }
+    }
+    ans
+}
+
+/// Add the link mark to the attributes, if the url is safe
+fn with_link(attributes: &Attributes, config: &MarkupConfig, url: &str) -> Attributes {
+    if is_safe_url(url) {
+        with_mark(attributes, config, MarkSyntax::Link, Some(url.to_string()))
+    } else {
+        attributes.clone()
+    }
+}
+
+/// Add the mark of the syntax to the attributes, if the syntax is mapped by the config
+fn with_mark(
+    attributes: &Attributes,
+    config: &MarkupConfig,
+    syntax: MarkSyntax,
+    url: Option<String>,
+) -> Attributes {
+    let mut ans = attributes.clone();
+    if let Some(key) = config.key_of(syntax) {
+        ans.insert(key.to_string(), url.map_or(true.into(), Into::into));
+    }
+    ans
+}
+
+/// Build the delta of the parsed lines
+#[derive(Debug, Default)]
+struct DeltaBuilder {
+    delta: Vec<TextDelta>,
+}
+
+impl DeltaBuilder {
+    fn push_text(&mut self, text: &str, attributes: &Attributes) {
+        if text.is_empty() {
+            return;
+        }
+
+        let attributes = (!attributes.is_empty()).then(|| attributes.clone());
+        if let Some(TextDelta::Insert {
+            insert,
+            attributes: last,
+        }) = self.delta.last_mut()
+        {
+            if *last == attributes {
+                insert.push_str(text);
+                return;
+            }
+        }
+
+        self.delta.push(TextDelta::Insert {
+            insert: text.to_string(),
+            attributes,
+        });
+    }
+
+    /// Push the image, if the url is safe
+    fn push_image(&mut self, url: &str, attributes: &Attributes) {
+        if !is_safe_url(url) {
+            return;
+        }
+
+        let mut value = Attributes::default();
+        value.insert(IMAGE_KEY.into(), url.into());
+        self.delta.push(TextDelta::Embed {
+            insert: value.into(),
+            attributes: (!attributes.is_empty()).then(|| attributes.clone()),
+        });
+    }
+
+    fn end_line(&mut self, block: Block) {
+        self.push_text("\n", &block.to_attributes());
+    }
+
+    /// The line break of the last paragraph is removed, so the plain text is kept
+    /// after the round trip
+    fn finish(mut self) -> Vec<TextDelta> {
+        if let Some(TextDelta::Insert {
+            insert,
+            attributes: None,
+        }) = self.delta.last_mut()
+        {
+            if insert.ends_with('\n') {
+                insert.pop();
+                if insert.is_empty() {
+                    self.delta.pop();
+                }
+            }
+        }
+
+        self.delta
+    }
+}
+
+impl crate::LoroText {
+    /// Convert the text to CommonMark. See [mod@crate::markup] for the block styles.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::{markup::MarkupConfig, LoroDoc};
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello world").unwrap();
+    /// text.mark(6..11, "bold", true).unwrap();
+    /// assert_eq!(text.to_markdown(&MarkupConfig::default()), "Hello **world**");
+    /// ```
+    #[cfg(feature = "markdown")]
+    pub fn to_markdown(&self, config: &MarkupConfig) -> String {
+        to_markdown(&self.to_delta_items(), config)
+    }
+
+    /// Convert the text to sanitized HTML. See [mod@crate::markup] for the block styles.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::{markup::MarkupConfig, LoroDoc};
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "a<b").unwrap();
+    /// text.mark(0..1, "italic", true).unwrap();
+    /// assert_eq!(text.to_html(&MarkupConfig::default()), "<p><em>a</em>&lt;b</p>");
+    /// ```
+    #[cfg(feature = "html")]
+    pub fn to_html(&self, config: &MarkupConfig) -> String {
+        to_html(&self.to_delta_items(), config)
+    }
+
+    fn to_delta_items(&self) -> Vec<TextDelta> {
+        self.slice_delta(0, self.len_unicode()).unwrap()
+    }
+}
@@ -0,0 +1,476 @@
+//! Sanitized HTML export, and a parser of the HTML of the rich text editors.
+//!
+//! The exported HTML only contains the tags of the supported styles, and all the
+//! text and attributes are escaped. The links and images of the unsafe urls, such
+//! as `javascript:`, are dropped.
+use std::cell::RefCell;
+
+use html5ever::{
+    tendril::StrTendril,
+    tokenizer::{
+        BufferQueue, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts,
+    },
+};
+use loro_internal::handler::TextDelta;
+
+use super::{
+    groups, lines, with_link, with_mark, Attributes, Block, Content, DeltaBuilder, Line,
+    MarkSyntax, MarkupConfig, Span, Style,
+};
+
+/// Convert the delta to sanitized HTML.
+///
+/// The empty lines are written as `<p><br></p>`.
+///
+/// # Example
+/// ```
+/// use loro::{markup::{to_html, MarkupConfig}, TextDelta};
+/// use serde_json::json;
+///
+/// let delta: Vec<TextDelta> = serde_json::from_value(json!([
+///     {"insert": "Hello "},
+///     {"insert": "Loro", "attributes": {"bold": true, "link": "https://loro.dev"}},
+///     {"insert": "\nItem"},
+///     {"insert": "\n", "attributes": {"list": "bullet"}},
+/// ]))
+/// .unwrap();
+/// assert_eq!(
+///     to_html(&delta, &MarkupConfig::default()),
+///     "<p>Hello <a href=\"https://loro.dev\"><strong>Loro</strong></a></p><ul><li>Item</li></ul>"
+/// );
+/// ```
+pub fn to_html(delta: &[TextDelta], config: &MarkupConfig) -> String {
+    let lines = lines(delta, config);
+    let mut out = String::new();
+    for group in groups(&lines) {
+        match group[0].block {
+            Block::Paragraph => {
+                for line in group {
+                    write_line(&mut out, "p", &line.spans);
+                }
+            }
+            Block::Header(level) => {
+                for line in group {
+                    write_line(&mut out, &format!("h{}", level), &line.spans);
+                }
+            }
+            Block::Bullet | Block::Ordered => {
+                let tag = if group[0].block == Block::Bullet {
+                    "ul"
+                } else {
+                    "ol"
+                };
+                out.push_str(&format!("<{}>", tag));
+                for line in group {
+                    write_line(&mut out, "li", &line.spans);
+                }
+                out.push_str(&format!("</{}>", tag));
+            }
+            Block::Quote => {
+                out.push_str("<blockquote>");
+                for line in group {
+                    write_line(&mut out, "p", &line.spans);
+                }
+                out.push_str("</blockquote>");
+            }
+            Block::Code => {
+                let code = group
+                    .iter()
+                    .map(Line::plain_text)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                out.push_str("<pre><code>");
+                out.push_str(&escape_html(&code));
+                out.push_str("</code></pre>");
+            }
+        }
+    }
+
+    out
+}
+
+fn write_line(out: &mut String, tag: &str, spans: &[Span]) {
+    out.push_str(&format!("<{}>", tag));
+    if spans.is_empty() {
+        out.push_str("<br>");
+    } else {
+        write_inline(out, spans);
+    }
+    out.push_str(&format!("</{}>", tag));
+}
+
+fn write_inline(out: &mut String, spans: &[Span]) {
+    let mut stack: Vec<&Style> = Vec::new();
+    for span in spans {
+        let common = stack
+            .iter()
+            .zip(&span.styles)
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        while stack.len() > common {
+            out.push_str(close_tag(stack.pop().unwrap()));
+        }
+
+        for style in &span.styles[stack.len()..] {
+            out.push_str(&open_tag(style));
+            stack.push(style);
+        }
+
+        match &span.content {
+            Content::Text(text) => out.push_str(&escape_html(text)),
+            Content::Image(url) => out.push_str(&format!("<img src=\"{}\">", escape_html(url))),
+        }
+    }
+
+    while let Some(style) = stack.pop() {
+        out.push_str(close_tag(style));
+    }
+}
+
+fn open_tag(style: &Style) -> String {
+    match style {
+        (MarkSyntax::Link, url) => match url {
+            Some(url) => format!("<a href=\"{}\">", escape_html(url)),
+            None => "<a>".to_string(),
+        },
+        (MarkSyntax::Bold, _) => "<strong>".to_string(),
+        (MarkSyntax::Italic, _) => "<em>".to_string(),
+        (MarkSyntax::Strikethrough, _) => "<del>".to_string(),
+        (MarkSyntax::Code, _) => "<code>".to_string(),
+    }
+}
+
+fn close_tag(style: &Style) -> &'static str {
+    match style.0 {
+        MarkSyntax::Link => "</a>",
+        MarkSyntax::Bold => "</strong>",
+        MarkSyntax::Italic => "</em>",
+        MarkSyntax::Strikethrough => "</del>",
+        MarkSyntax::Code => "</code>",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut ans = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => ans.push_str("&amp;"),
+            '<' => ans.push_str("&lt;"),
+            '>' => ans.push_str("&gt;"),
+            '"' => ans.push_str("&quot;"),
+            '\'' => ans.push_str("&#39;"),
+            c => ans.push(c),
+        }
+    }
+    ans
+}
+
+/// Parse the HTML into a delta that can be applied by [crate::LoroText::apply_delta].
+///
+/// The HTML is sanitized by ammonia first, which drops the unknown tags but keeps
+/// their text, except the content of `<script>` and `<style>`. The whitespaces are
+/// collapsed outside `<pre>`. The nested lists are flattened. The line break of the
+/// last paragraph is omitted.
+///
+/// # Example
+/// ```
+/// use loro::{markup::{from_html, MarkupConfig}, LoroDoc, ToJson};
+/// use serde_json::json;
+///
+/// let delta = from_html(
+///     "<p>Hello <b>Loro</b><script>alert(1)</script></p>",
+///     &MarkupConfig::default(),
+/// );
+/// let doc = LoroDoc::new();
+/// let text = doc.get_text("text");
+/// text.apply_delta(&delta).unwrap();
+/// assert_eq!(
+///     text.to_delta().to_json_value(),
+///     json!([{"insert": "Hello "}, {"insert": "Loro", "attributes": {"bold": true}}])
+/// );
+/// ```
+pub fn from_html(html: &str, config: &MarkupConfig) -> Vec<TextDelta> {
+    let html = sanitize(html);
+    let parser = HtmlParser {
+        builder: DeltaBuilder::default(),
+        config,
+        inline: Vec::new(),
+        heading: None,
+        lists: Vec::new(),
+        in_item: false,
+        quote_depth: 0,
+        in_pre: false,
+        pre_start: false,
+        has_content: false,
+        pending_space: None,
+    };
+    let tokenizer = Tokenizer::new(ParserSink(RefCell::new(parser)), TokenizerOpts::default());
+    let input = BufferQueue::default();
+    input.push_back(StrTendril::from(html));
+    let _ = tokenizer.feed(&input);
+    tokenizer.end();
+
+    let mut parser = tokenizer.sink.0.into_inner();
+    parser.break_line();
+    parser.builder.finish()
+}
+
+/// Keep only the tags and the attributes that are parsed, and the safe urls
+fn sanitize(html: &str) -> String {
+    let mut builder = ammonia::Builder::empty();
+    builder
+        .add_tags(INLINE_TAGS)
+        .add_tags(BLOCK_TAGS)
+        .add_tags(["ul", "ol", "li", "blockquote", "pre", "br", "img"])
+        .add_tags(["h1", "h2", "h3", "h4", "h5", "h6"])
+        .tag_attributes(
+            [("a", ["href"].into()), ("img", ["src"].into())]
+                .into_iter()
+                .collect(),
+        )
+        .generic_attributes(Default::default())
+        .url_schemes(["http", "https", "mailto", "tel"].into())
+        .link_rel(None);
+    builder.clean(html).to_string()
+}
+
+/// The inline tags of the supported styles
+const INLINE_TAGS: [&str; 9] = ["a", "strong", "b", "em", "i", "del", "s", "strike", "code"];
+
+/// The other block tags, which end the current line
+const BLOCK_TAGS: [&str; 16] = [
+    "p", "div", "section", "article", "header", "footer", "nav", "aside", "main", "figure",
+    "table", "tr", "hr", "dl", "dt", "dd",
+];
+
+struct ParserSink<'a>(RefCell<HtmlParser<'a>>);
+
+impl TokenSink for ParserSink<'_> {
+    type Handle = ();
+
+    fn process_token(&self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        let mut parser = self.0.borrow_mut();
+        match token {
+            Token::CharacterTokens(text) => parser.text(&text),
+            Token::TagToken(tag) => {
+                let attributes: Vec<(String, String)> = tag
+                    .attrs
+                    .iter()
+                    .map(|attr| (attr.name.local.to_string(), attr.value.to_string()))
+                    .collect();
+                match tag.kind {
+                    TagKind::StartTag => parser.start(&tag.name, &attributes),
+                    TagKind::EndTag => parser.end(&tag.name),
+                }
+            }
+            _ => {}
+        }
+
+        TokenSinkResult::Continue
+    }
+}
+
+struct HtmlParser<'a> {
+    builder: DeltaBuilder,
+    config: &'a MarkupConfig,
+    /// The open inline elements, and the attributes of the text inside them
+    inline: Vec<(String, Attributes)>,
+    heading: Option<u8>,
+    /// The blocks of the open lists
+    lists: Vec<Block>,
+    in_item: bool,
+    quote_depth: usize,
+    in_pre: bool,
+    /// Whether it's right after `<pre>`, where the first line break is ignored
+    pre_start: bool,
+    /// Whether the current line has any content
+    has_content: bool,
+    /// The attributes of the collapsed whitespaces before the next content
+    pending_space: Option<Attributes>,
+}
+
+impl HtmlParser<'_> {
+    fn attributes(&self) -> Attributes {
+        self.inline
+            .last()
+            .map(|(_, attributes)| attributes.clone())
+            .unwrap_or_default()
+    }
+
+    fn block(&self) -> Block {
+        if self.in_pre {
+            Block::Code
+        } else if let Some(level) = self.heading {
+            Block::Header(level)
+        } else if self.in_item {
+            self.lists.last().copied().unwrap_or(Block::Bullet)
+        } else if self.quote_depth > 0 {
+            Block::Quote
+        } else {
+            Block::Paragraph
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.builder.end_line(self.block());
+        self.has_content = false;
+        self.pending_space = None;
+    }
+
+    /// End the current line if it has any content
+    fn break_line(&mut self) {
+        if self.has_content {
+            self.end_line();
+        }
+
+        self.pending_space = None;
+    }
+
+    /// Push the collapsed whitespaces, which keep the styles of the text before them
+    fn flush_space(&mut self) {
+        if let Some(attributes) = self.pending_space.take() {
+            self.builder.push_text(" ", &attributes);
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.in_pre {
+            let text = match self.pre_start {
+                true => text.strip_prefix('\n').unwrap_or(text),
+                false => text,
+            };
+            self.pre_start = false;
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    self.end_line();
+                }
+
+                // The styles inside the code blocks are dropped
+                self.builder.push_text(line, &Attributes::default());
+                self.has_content |= !line.is_empty();
+            }
+            return;
+        }
+
+        let mut collapsed = String::new();
+        for c in text.chars() {
+            if c.is_ascii_whitespace() {
+                if (self.has_content || !collapsed.is_empty()) && self.pending_space.is_none() {
+                    self.pending_space = Some(self.attributes());
+                }
+            } else {
+                if collapsed.is_empty() {
+                    self.flush_space();
+                } else if self.pending_space.take().is_some() {
+                    collapsed.push(' ');
+                }
+                collapsed.push(c);
+            }
+        }
+
+        if !collapsed.is_empty() {
+            self.builder.push_text(&collapsed, &self.attributes());
+            self.has_content = true;
+        }
+    }
+
+    fn start(&mut self, name: &str, attributes: &[(String, String)]) {
+        let get = |key: &str| {
+            attributes
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        match name {
+            "ul" | "ol" => {
+                self.break_line();
+                self.lists.push(if name == "ul" {
+                    Block::Bullet
+                } else {
+                    Block::Ordered
+                });
+            }
+            "li" => {
+                self.break_line();
+                self.in_item = true;
+            }
+            "blockquote" => {
+                self.break_line();
+                self.quote_depth += 1;
+            }
+            "pre" => {
+                self.break_line();
+                self.in_pre = true;
+                self.pre_start = true;
+            }
+            "br" => self.end_line(),
+            "img" => {
+                if let Some(src) = get("src") {
+                    self.flush_space();
+                    self.builder.push_image(src, &self.attributes());
+                    self.has_content = true;
+                }
+            }
+            _ if heading_level(name).is_some() => {
+                self.break_line();
+                self.heading = heading_level(name);
+            }
+            _ if BLOCK_TAGS.contains(&name) => self.break_line(),
+            _ => {
+                let parent = self.attributes();
+                let attributes = match name {
+                    "a" => match get("href") {
+                        Some(href) => with_link(&parent, self.config, href),
+                        None => parent,
+                    },
+                    "strong" | "b" => with_mark(&parent, self.config, MarkSyntax::Bold, None),
+                    "em" | "i" => with_mark(&parent, self.config, MarkSyntax::Italic, None),
+                    "del" | "s" | "strike" => {
+                        with_mark(&parent, self.config, MarkSyntax::Strikethrough, None)
+                    }
+                    "code" => with_mark(&parent, self.config, MarkSyntax::Code, None),
+                    _ => parent,
+                };
+                self.inline.push((name.to_string(), attributes));
+            }
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        match name {
+            "ul" | "ol" => {
+                self.break_line();
+                self.lists.pop();
+            }
+            "li" => {
+                self.break_line();
+                self.in_item = false;
+            }
+            "blockquote" => {
+                self.break_line();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            "pre" => {
+                self.break_line();
+                self.in_pre = false;
+            }
+            _ if heading_level(name).is_some() => {
+                self.break_line();
+                self.heading = None;
+            }
+            _ if BLOCK_TAGS.contains(&name) => self.break_line(),
+            _ => {
+                if let Some(i) = self.inline.iter().rposition(|(n, _)| n == name) {
+                    self.inline.truncate(i);
+                }
+            }
+        }
+    }
+}
+
+fn heading_level(name: &str) -> Option<u8> {
+    match name.as_bytes() {
+        [b'h', level @ b'1'..=b'6'] => Some(level - b'0'),
+        _ => None,
+    }
+}
@@ -0,0 +1,444 @@
+//! CommonMark export, and the import by pulldown-cmark.
+use loro_internal::handler::TextDelta;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+use super::{
+    groups, lines, with_link, with_mark, Attributes, Block, Content, DeltaBuilder, Line,
+    MarkSyntax, MarkupConfig, Span, Style,
+};
+
+/// Convert the delta to CommonMark.
+///
+/// The paragraphs, headings, lists, quotes and code blocks are separated by blank
+/// lines. The empty paragraphs are dropped, because Markdown can't represent them.
+///
+/// # Example
+/// ```
+/// use loro::{markup::{to_markdown, MarkupConfig}, TextDelta};
+/// use serde_json::json;
+///
+/// let delta: Vec<TextDelta> = serde_json::from_value(json!([
+///     {"insert": "Title"},
+///     {"insert": "\n", "attributes": {"header": 1}},
+///     {"insert": "Hello "},
+///     {"insert": "Loro", "attributes": {"link": "https://loro.dev"}},
+/// ]))
+/// .unwrap();
+/// assert_eq!(
+///     to_markdown(&delta, &MarkupConfig::default()),
+///     "# Title\n\nHello [Loro](https://loro.dev)"
+/// );
+/// ```
+pub fn to_markdown(delta: &[TextDelta], config: &MarkupConfig) -> String {
+    let lines = lines(delta, config);
+    let mut blocks: Vec<String> = Vec::new();
+    for group in groups(&lines) {
+        match group[0].block {
+            Block::Paragraph => blocks.extend(
+                group
+                    .iter()
+                    .filter(|line| !line.spans.is_empty())
+                    .map(|line| write_inline(&line.spans)),
+            ),
+            Block::Header(level) => blocks.extend(group.iter().map(|line| {
+                format!(
+                    "{} {}",
+                    "#".repeat(level as usize),
+                    write_inline(&line.spans)
+                )
+            })),
+            Block::Bullet => blocks.push(
+                group
+                    .iter()
+                    .map(|line| format!("- {}", write_inline(&line.spans)))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Block::Ordered => blocks.push(
+                group
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| format!("{}. {}", i + 1, write_inline(&line.spans)))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            // The lines are separated by empty quote lines, so they are not merged
+            // into one paragraph
+            Block::Quote => blocks.push(
+                group
+                    .iter()
+                    .map(|line| format!("> {}", write_inline(&line.spans)))
+                    .collect::<Vec<_>>()
+                    .join("\n>\n"),
+            ),
+            Block::Code => {
+                let code = group
+                    .iter()
+                    .map(Line::plain_text)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let fence = "`".repeat(longest_run(&code, '`').max(2) + 1);
+                blocks.push(format!("{fence}\n{code}\n{fence}"));
+            }
+        }
+    }
+
+    blocks.join("\n\n")
+}
+
+fn write_inline(spans: &[Span]) -> String {
+    let mut out = String::new();
+    let mut stack: Vec<&Style> = Vec::new();
+    for span in spans {
+        // The code spans are written around the content directly, because the spans
+        // with the same styles are merged
+        let styles: Vec<&Style> = span
+            .styles
+            .iter()
+            .filter(|(syntax, _)| *syntax != MarkSyntax::Code)
+            .collect();
+        let is_code = styles.len() < span.styles.len();
+        let common = stack
+            .iter()
+            .zip(&styles)
+            .take_while(|(a, b)| a == b)
+            .count();
+        close_styles(&mut out, &mut stack, common);
+        match &span.content {
+            Content::Text(text) if text.trim().is_empty() => {
+                // Only whitespaces, which can't be inside the delimiters
+                out.push_str(text);
+            }
+            Content::Text(text) => {
+                // The leading whitespaces are moved outside the delimiters
+                let content = text.trim_start();
+                out.push_str(&text[..text.len() - content.len()]);
+
+                for &style in &styles[stack.len()..] {
+                    out.push_str(open_markup(style));
+                    stack.push(style);
+                }
+
+                if is_code {
+                    out.push_str(&code_span(content));
+                } else {
+                    let at_line_start = out.is_empty();
+                    out.push_str(&escape_text(content, at_line_start));
+                }
+            }
+            Content::Image(url) => {
+                for &style in &styles[stack.len()..] {
+                    out.push_str(open_markup(style));
+                    stack.push(style);
+                }
+
+                out.push_str(&format!("![]({})", escape_url(url)));
+            }
+        }
+    }
+
+    close_styles(&mut out, &mut stack, 0);
+    out
+}
+
+/// Close the styles until there are `len` styles left
+fn close_styles(out: &mut String, stack: &mut Vec<&Style>, len: usize) {
+    if stack.len() <= len {
+        return;
+    }
+
+    // The trailing whitespaces are moved outside the delimiters
+    let trailing = out.len() - out.trim_end().len();
+    let spaces = out.split_off(out.len() - trailing);
+    while stack.len() > len {
+        let style = stack.pop().unwrap();
+        out.push_str(&close_markup(style));
+    }
+
+    out.push_str(&spaces);
+}
+
+fn open_markup(style: &Style) -> &'static str {
+    match style.0 {
+        MarkSyntax::Link => "[",
+        MarkSyntax::Bold => "**",
+        MarkSyntax::Italic => "*",
+        MarkSyntax::Strikethrough => "~~",
+        MarkSyntax::Code => "`",
+    }
+}
+
+fn close_markup(style: &Style) -> String {
+    match style {
+        (MarkSyntax::Link, url) => format!("]({})", escape_url(url.as_deref().unwrap_or(""))),
+        _ => open_markup(style).to_string(),
+    }
+}
+
+fn escape_url(url: &str) -> String {
+    if url.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+        format!("<{}>", url.replace(['<', '>'], ""))
+    } else {
+        url.to_string()
+    }
+}
+
+/// Escape the chars that would be parsed as the markup
+fn escape_text(text: &str, at_line_start: bool) -> String {
+    let mut ans = String::with_capacity(text.len());
+    // The digits that could start an ordered list
+    let digits = if at_line_start {
+        text.bytes().take_while(u8::is_ascii_digit).count()
+    } else {
+        0
+    };
+    for (i, c) in text.char_indices() {
+        let escape = match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '~' => true,
+            '#' | '>' | '-' | '+' => at_line_start && i == 0,
+            '.' | ')' => digits > 0 && i == digits,
+            _ => false,
+        };
+        if escape {
+            ans.push('\\');
+        }
+        ans.push(c);
+    }
+    ans
+}
+
+fn code_span(code: &str) -> String {
+    let fence = "`".repeat(longest_run(code, '`') + 1);
+    let need_padding = code.starts_with('`')
+        || code.ends_with('`')
+        || (code.starts_with(' ') && code.ends_with(' ') && !code.trim().is_empty());
+    let padding = if need_padding { " " } else { "" };
+    format!("{fence}{padding}{code}{padding}{fence}")
+}
+
+fn longest_run(s: &str, c: char) -> usize {
+    let mut ans = 0;
+    let mut len = 0;
+    for x in s.chars() {
+        if x == c {
+            len += 1;
+            ans = ans.max(len);
+        } else {
+            len = 0;
+        }
+    }
+    ans
+}
+
+/// Parse the Markdown into a delta that can be applied by [crate::LoroText::apply_delta].
+///
+/// It's parsed by pulldown-cmark with the strikethrough extension. The lines of a
+/// paragraph are joined by spaces. The nested lists are flattened, and the other
+/// syntax, such as the raw HTML, is kept as text. The line break of the last
+/// paragraph is omitted.
+///
+/// # Example
+/// ```
+/// use loro::{
+///     markup::{from_markdown, MarkupConfig, HEADER_KEY},
+///     ExpandType, LoroDoc, StyleConfig, StyleConfigMap,
+/// };
+///
+/// let doc = LoroDoc::new();
+/// let mut styles = StyleConfigMap::default_rich_text_config();
+/// styles.insert(HEADER_KEY.into(), StyleConfig::new().expand(ExpandType::None));
+/// doc.config_text_style(styles);
+/// let text = doc.get_text("text");
+/// let delta = from_markdown("# Title\n\nHello **Loro**", &MarkupConfig::default());
+/// text.apply_delta(&delta).unwrap();
+/// assert_eq!(text.to_string(), "Title\nHello Loro");
+/// assert_eq!(text.get_styles_at(5).unwrap().len(), 1);
+/// ```
+pub fn from_markdown(markdown: &str, config: &MarkupConfig) -> Vec<TextDelta> {
+    let mut parser = MarkdownParser {
+        builder: DeltaBuilder::default(),
+        config,
+        inline: Vec::new(),
+        heading: None,
+        lists: Vec::new(),
+        item_depth: 0,
+        quote_depth: 0,
+        in_code: false,
+        image_depth: 0,
+        has_content: false,
+    };
+    for event in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH) {
+        parser.event(event);
+    }
+
+    parser.break_line();
+    parser.builder.finish()
+}
+
+struct MarkdownParser<'a> {
+    builder: DeltaBuilder,
+    config: &'a MarkupConfig,
+    /// The attributes of the text inside the open inline elements
+    inline: Vec<Attributes>,
+    heading: Option<u8>,
+    /// The blocks of the open lists
+    lists: Vec<Block>,
+    item_depth: usize,
+    quote_depth: usize,
+    in_code: bool,
+    /// The alt text of the images is dropped
+    image_depth: usize,
+    /// Whether the current line has any content
+    has_content: bool,
+}
+
+impl MarkdownParser<'_> {
+    fn attributes(&self) -> Attributes {
+        self.inline.last().cloned().unwrap_or_default()
+    }
+
+    fn block(&self) -> Block {
+        if self.in_code {
+            Block::Code
+        } else if let Some(level) = self.heading {
+            Block::Header(level)
+        } else if self.item_depth > 0 {
+            self.lists.last().copied().unwrap_or(Block::Bullet)
+        } else if self.quote_depth > 0 {
+            Block::Quote
+        } else {
+            Block::Paragraph
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.builder.end_line(self.block());
+        self.has_content = false;
+    }
+
+    /// End the current line if it has any content
+    fn break_line(&mut self) {
+        if self.has_content {
+            self.end_line();
+        }
+    }
+
+    fn push_text(&mut self, text: &str, attributes: &Attributes) {
+        self.builder.push_text(text, attributes);
+        self.has_content |= !text.is_empty();
+    }
+
+    /// Push the text of the code blocks and the HTML blocks, where each line break
+    /// ends a line of the current block
+    fn push_lines(&mut self, text: &str) {
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.end_line();
+            }
+
+            self.push_text(line, &Attributes::default());
+        }
+    }
+
+    fn push_inline(&mut self, syntax: MarkSyntax) {
+        let attributes = with_mark(&self.attributes(), self.config, syntax, None);
+        self.inline.push(attributes);
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(_) | Event::Code(_) if self.image_depth > 0 => {}
+            Event::Text(text) if self.in_code => self.push_lines(&text),
+            Event::Text(text) | Event::InlineHtml(text) => {
+                self.push_text(&text, &self.attributes())
+            }
+            Event::Code(code) => {
+                let attributes = with_mark(&self.attributes(), self.config, MarkSyntax::Code, None);
+                self.push_text(&code, &attributes);
+            }
+            Event::Html(html) => self.push_lines(&html),
+            Event::SoftBreak => self.push_text(" ", &self.attributes()),
+            Event::HardBreak => self.end_line(),
+            Event::Rule => self.break_line(),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.break_line();
+                self.heading = Some(level as u8);
+            }
+            Tag::BlockQuote(_) => {
+                self.break_line();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.break_line();
+                self.in_code = true;
+            }
+            Tag::List(start) => {
+                self.break_line();
+                self.lists.push(match start {
+                    Some(_) => Block::Ordered,
+                    None => Block::Bullet,
+                });
+            }
+            Tag::Item => {
+                self.break_line();
+                self.item_depth += 1;
+            }
+            Tag::Paragraph | Tag::HtmlBlock => self.break_line(),
+            Tag::Emphasis => self.push_inline(MarkSyntax::Italic),
+            Tag::Strong => self.push_inline(MarkSyntax::Bold),
+            Tag::Strikethrough => self.push_inline(MarkSyntax::Strikethrough),
+            Tag::Link { dest_url, .. } => {
+                let attributes = with_link(&self.attributes(), self.config, &dest_url);
+                self.inline.push(attributes);
+            }
+            Tag::Image { dest_url, .. } => {
+                if self.image_depth == 0 {
+                    self.builder.push_image(&dest_url, &self.attributes());
+                    self.has_content = true;
+                }
+
+                self.image_depth += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) => {
+                self.break_line();
+                self.heading = None;
+            }
+            TagEnd::BlockQuote(_) => {
+                self.break_line();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            TagEnd::CodeBlock => {
+                self.break_line();
+                self.in_code = false;
+            }
+            TagEnd::List(_) => {
+                self.break_line();
+                self.lists.pop();
+            }
+            TagEnd::Item => {
+                self.break_line();
+                self.item_depth = self.item_depth.saturating_sub(1);
+            }
+            TagEnd::Paragraph | TagEnd::HtmlBlock => self.break_line(),
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link => {
+                self.inline.pop();
+            }
+            TagEnd::Image => self.image_depth = self.image_depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use loro::{LoroDoc, LoroMap, ID};
+use std::path::PathBuf;
+
+use loro::{ExportMode, LoroDoc, LoroMap, ID};
 
 #[test]
 fn test_compact_change_store() {
@@ -21,3 +23,141 @@ fn test_compact_change_store() {
     doc.compact_change_store();
     doc.checkout(&ID::new(0, 60).into()).unwrap();
 }
+
+fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("loro-doc-{}", rand::random::<u64>()))
+}
+
+#[test]
+fn test_open_doc_from_disk() {
+    let dir = temp_dir();
+    let doc = LoroDoc::open(&dir).unwrap();
+    doc.set_peer_id(1).unwrap();
+    let text = doc.get_text("text");
+    text.insert(0, "hello").unwrap();
+    let map = doc.get_list("list").push_container(LoroMap::new()).unwrap();
+    map.insert("a", 1).unwrap();
+    doc.flush().unwrap();
+
+    // Flush incrementally
+    text.insert(5, " world").unwrap();
+    doc.flush().unwrap();
+    let value = doc.get_deep_value();
+    let frontiers = doc.oplog_frontiers();
+
+    // Changes that are not flushed are lost
+    text.insert(0, "lost ").unwrap();
+    doc.commit();
+    drop(doc);
+
+    let doc = LoroDoc::open(&dir).unwrap();
+    assert_eq!(doc.get_deep_value(), value);
+    assert_eq!(doc.oplog_frontiers(), frontiers);
+    assert_eq!(doc.len_ops(), 13);
+
+    // The history is available
+    doc.checkout(&ID::new(1, 4).into()).unwrap();
+    assert_eq!(doc.get_text("text").to_string(), "hello");
+    doc.checkout_to_latest();
+
+    // The opened doc can be edited and exported like other docs
+    doc.set_peer_id(2).unwrap();
+    doc.get_text("text").insert(0, "> ").unwrap();
+    doc.commit();
+    let new_doc = LoroDoc::new();
+    new_doc
+        .import(&doc.export(ExportMode::Snapshot).unwrap())
+        .unwrap();
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    doc.flush().unwrap();
+    drop(doc);
+
+    let doc = LoroDoc::open(&dir).unwrap();
+    assert_eq!(doc.get_deep_value(), new_doc.get_deep_value());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_open_doc_flushed_in_detached_mode() {
+    let dir = temp_dir();
+    let doc = LoroDoc::open(&dir).unwrap();
+    let text = doc.get_text("text");
+    text.insert(0, "hello").unwrap();
+    doc.commit();
+    let f = doc.oplog_frontiers();
+    text.insert(0, "hi ").unwrap();
+    doc.checkout(&f).unwrap();
+    doc.flush().unwrap();
+    drop(doc);
+
+    // The state is recomputed from the history
+    let doc = LoroDoc::open(&dir).unwrap();
+    assert!(!doc.is_detached());
+    assert_eq!(doc.get_text("text").to_string(), "hi hello");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_change_store_delta() {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    // Don't merge the changes so that they are stored in multiple blocks
+    doc.set_change_merge_interval(-1);
+    let mut storage = std::collections::BTreeMap::new();
+    let text = doc.get_text("text");
+    for _ in 0..100 {
+        text.insert(0, &"hello".repeat(20)).unwrap();
+        doc.commit();
+    }
+    let delta = doc.export_change_store_delta().unwrap();
+    let full_len = delta.len();
+    storage.extend(delta);
+
+    // Only the updated blocks and the version are exported
+    text.insert(0, "abc").unwrap();
+    doc.commit();
+    let delta = doc.export_change_store_delta().unwrap();
+    assert!(delta.len() < full_len);
+    // Only the block that contains the new change is exported, along with the version
+    assert_eq!(delta.iter().filter(|(k, _)| k.len() == 12).count(), 1);
+    assert!(delta.iter().all(|(k, _)| !k.starts_with(b"doc_state::")));
+    storage.extend(delta);
+
+    // The blocks flushed by other exports are still included in the next delta
+    text.insert(0, "def").unwrap();
+    doc.commit();
+    doc.export(ExportMode::Snapshot).unwrap();
+    let delta = doc.export_change_store_delta().unwrap();
+    assert_eq!(delta.iter().filter(|(k, _)| k.len() == 12).count(), 1);
+    storage.extend(delta);
+
+    let other = LoroDoc::new();
+    other.set_peer_id(2).unwrap();
+    other.get_map("map").insert("a", 1).unwrap();
+    other.commit();
+    doc.import(&other.export(ExportMode::all_updates()).unwrap())
+        .unwrap();
+    storage.extend(doc.export_change_store_delta().unwrap());
+
+    let new_doc = LoroDoc::from_change_store_delta(storage.clone()).unwrap();
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    assert_eq!(new_doc.oplog_vv(), doc.oplog_vv());
+    assert_eq!(new_doc.len_changes(), doc.len_changes());
+    new_doc.checkout(&ID::new(1, 99).into()).unwrap();
+    assert_eq!(new_doc.get_text("text").len_unicode(), 100);
+    new_doc.checkout_to_latest();
+
+    // The reconstructed doc continues to export the deltas
+    new_doc.get_text("text").insert(0, "!").unwrap();
+    storage.extend(new_doc.export_change_store_delta().unwrap());
+    let doc = LoroDoc::from_change_store_delta(storage).unwrap();
+    assert_eq!(doc.get_deep_value(), new_doc.get_deep_value());
+}
+
+#[test]
+fn test_invalid_change_store_delta() {
+    let ans = LoroDoc::from_change_store_delta([(b"invalid key".to_vec(), vec![1, 2, 3])]);
+    assert!(ans.is_err());
+    let ans = LoroDoc::from_change_store_delta([(ID::new(1, 0).to_bytes().to_vec(), vec![1])]);
+    assert!(ans.is_err());
+}
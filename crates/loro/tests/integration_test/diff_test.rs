@@ -0,0 +1,211 @@
+use pretty_assertions::assert_eq;
+
+use super::gen_action;
+use std::sync::{Arc, Mutex};
+
+use loro::{event::Diff, CommitOptions, ExportMode, LoroDoc, LoroError, LoroMap, ToJson};
+use serde_json::json;
+
+#[test]
+fn diff_between_versions() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    let map = doc.get_map("map");
+    text.insert(0, "Hello")?;
+    map.insert("a", 1)?;
+    doc.commit();
+    let a = doc.state_frontiers();
+    text.insert(5, " World")?;
+    map.insert("a", 2)?;
+    map.insert("b", "b")?;
+    doc.commit();
+    let b = doc.state_frontiers();
+
+    let diff = doc.diff(&a, &b)?;
+    assert_eq!(diff.len(), 2);
+    let text_diff = diff.get(&text.id()).unwrap().into_text().unwrap();
+    assert_eq!(text_diff.len(), 2);
+    let map_diff = diff.get(&map.id()).unwrap().into_map().unwrap();
+    assert_eq!(map_diff.updated.len(), 2);
+    let (_, iterated) = diff.iter().find(|(id, _)| **id == text.id()).unwrap();
+    assert_eq!(
+        format!("{:?}", iterated),
+        format!("{:?}", Diff::Text(text_diff))
+    );
+
+    // The state and the editability of the doc are not affected
+    assert_eq!(doc.state_frontiers(), b);
+    assert!(!doc.is_detached());
+    text.insert(0, "!")?;
+    doc.commit();
+    assert_eq!(text.to_string(), "!Hello World");
+    Ok(())
+}
+
+#[test]
+fn diff_does_not_emit_events() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    let a = doc.state_frontiers();
+    text.insert(0, "Hi ")?;
+    doc.commit();
+    let b = doc.state_frontiers();
+
+    let count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let count_clone = count.clone();
+    let _sub = doc.subscribe_root(std::sync::Arc::new(move |_| {
+        count_clone.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }));
+    doc.diff(&b, &a)?;
+    assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 0);
+    text.insert(0, "1")?;
+    doc.commit();
+    assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 1);
+    Ok(())
+}
+
+#[test]
+fn apply_diff_to_go_back() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    gen_action(&doc, 123, 32);
+    doc.commit();
+    let a = doc.state_frontiers();
+    let value_a = doc.get_deep_value();
+    gen_action(&doc, 456, 32);
+    doc.commit();
+    let b = doc.state_frontiers();
+
+    let diff = doc.diff(&b, &a)?;
+    doc.apply_diff(&diff)?;
+    doc.commit();
+    assert_eq!(doc.get_deep_value(), value_a);
+    Ok(())
+}
+
+#[test]
+fn apply_diff_on_another_doc() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let list = doc.get_list("list");
+    list.insert(0, 1)?;
+    doc.commit();
+    let a = doc.state_frontiers();
+    let fork = doc.fork();
+    let map = list.insert_container(1, LoroMap::new())?;
+    map.insert("key", "value")?;
+    doc.get_text("text").insert(0, "abc")?;
+    doc.commit();
+    let b = doc.state_frontiers();
+
+    let diff = doc.diff(&a, &b)?;
+    assert!(diff.iter().any(|(_, d)| matches!(d, Diff::List(_))));
+    fork.apply_diff(&diff)?;
+    fork.commit();
+    assert_eq!(
+        fork.get_deep_value().to_json_value(),
+        json!({
+            "list": [1, {"key": "value"}],
+            "text": "abc"
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn revert_to_creates_new_ops() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    let list = doc.get_list("list");
+    text.insert(0, "Hello")?;
+    list.insert(0, 1)?;
+    doc.commit();
+    let v = doc.state_frontiers();
+    let remote = LoroDoc::new();
+    remote.import(&doc.export(ExportMode::all_updates())?)?;
+
+    text.insert(5, " World")?;
+    let map = list.insert_container(1, LoroMap::new())?;
+    map.insert("k", 1)?;
+    doc.commit();
+    let remote_vv = remote.oplog_vv();
+    remote.import(&doc.export(ExportMode::updates(&remote_vv))?)?;
+    let len = doc.len_ops();
+    let before_revert = doc.state_frontiers();
+    let before_revert_value = doc.get_deep_value();
+
+    doc.revert_to(&v)?;
+    assert!(!doc.is_detached());
+    assert!(doc.len_ops() > len);
+    assert_eq!(
+        doc.get_deep_value().to_json_value(),
+        json!({"text": "Hello", "list": [1]})
+    );
+
+    // The revert is synced as ordinary updates
+    let remote_vv = remote.oplog_vv();
+    remote.import(&doc.export(ExportMode::updates(&remote_vv))?)?;
+    assert_eq!(remote.get_deep_value(), doc.get_deep_value());
+
+    // Reverting to the current version is a no-op
+    let latest = doc.state_frontiers();
+    doc.revert_to(&latest)?;
+    assert_eq!(doc.state_frontiers(), latest);
+
+    // The reverted content can be restored by reverting to the version before the revert
+    doc.revert_to(&before_revert)?;
+    assert_eq!(doc.get_deep_value(), before_revert_value);
+    Ok(())
+}
+
+#[test]
+fn revert_to_with_commit_options() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    let v = doc.state_frontiers();
+    text.insert(0, "Oops ")?;
+    doc.commit();
+
+    let origin = Arc::new(Mutex::new(String::new()));
+    let origin_clone = origin.clone();
+    let _sub = doc.subscribe_root(Arc::new(move |e| {
+        *origin_clone.lock().unwrap() = e.origin.to_string();
+    }));
+    doc.revert_to_with(
+        &v,
+        CommitOptions::new()
+            .origin("my-revert")
+            .commit_msg("revert oops"),
+    )?;
+    assert_eq!(text.to_string(), "Hello");
+    assert_eq!(origin.lock().unwrap().as_str(), "my-revert");
+    let change = doc
+        .get_change(doc.oplog_frontiers().as_single().unwrap())
+        .unwrap();
+    assert_eq!(change.message(), "revert oops");
+    Ok(())
+}
+
+#[test]
+fn revert_to_in_detached_mode_fails() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    let v = doc.state_frontiers();
+    text.insert(0, "Hi ")?;
+    doc.commit();
+    doc.checkout(&v)?;
+    assert!(matches!(
+        doc.revert_to(&v),
+        Err(LoroError::EditWhenDetached)
+    ));
+    Ok(())
+}
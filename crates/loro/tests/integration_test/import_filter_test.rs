@@ -0,0 +1,202 @@
+use std::sync::{Arc, Mutex};
+
+use loro::{
+    ContainerID, ContainerType, Decision, ExportMode, LoroDoc, LoroMap, OpKind, VersionRange, ID,
+};
+use serde_json::json;
+
+fn reject_peer(peer: u64) -> loro::ImportFilter {
+    Box::new(move |meta, _| {
+        if meta.id.peer == peer {
+            Decision::Reject
+        } else {
+            Decision::Accept
+        }
+    })
+}
+
+#[test]
+fn rejected_changes_and_their_dependents_are_dropped() -> anyhow::Result<()> {
+    let alice = LoroDoc::new();
+    alice.set_peer_id(1)?;
+    alice.get_text("text").insert(0, "Hello")?;
+    alice.commit();
+
+    let mallory = LoroDoc::new();
+    mallory.set_peer_id(2)?;
+    mallory.import(&alice.export(ExportMode::all_updates())?)?;
+    mallory.get_text("text").insert(5, " evil")?;
+    mallory.commit();
+
+    // Bob's change depends on Mallory's
+    let bob = LoroDoc::new();
+    bob.set_peer_id(3)?;
+    bob.import(&mallory.export(ExportMode::all_updates())?)?;
+    bob.get_map("meta").insert("author", "Bob")?;
+    bob.commit();
+
+    alice.get_text("text").insert(5, " world")?;
+    alice.commit();
+    bob.import(&alice.export(ExportMode::all_updates())?)?;
+
+    let server = LoroDoc::new();
+    server.set_import_filter(Some(reject_peer(2)));
+    let status = server.import(&bob.export(ExportMode::all_updates())?)?;
+    assert_eq!(status.success.get(&1), Some(&(0, 11)));
+    assert_eq!(status.success.get(&2), None);
+    assert_eq!(status.success.get(&3), None);
+    let rejected = status.rejected.unwrap();
+    assert_eq!(rejected.get(&2), Some(&(0, 5)));
+    assert_eq!(rejected.get(&3), Some(&(0, 1)));
+    assert!(status.pending.is_none());
+    assert_eq!(
+        server.get_deep_value().to_json_value(),
+        json!({"text": "Hello world"})
+    );
+
+    // The later changes of the rejected peer are rejected too
+    mallory.get_text("text").insert(0, "!")?;
+    mallory.commit();
+    let status = server.import(&mallory.export(ExportMode::updates(&server.oplog_vv()))?)?;
+    assert!(status.success.is_empty());
+    assert_eq!(status.rejected.unwrap().get(&2), Some(&(0, 6)));
+
+    // Nothing is rejected after the filter is removed
+    server.set_import_filter(None);
+    let status = server.import(&bob.export(ExportMode::all_updates())?)?;
+    assert!(status.rejected.is_none());
+    assert_eq!(server.get_deep_value(), bob.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn dependents_in_later_imports_are_rejected() -> anyhow::Result<()> {
+    let mallory = LoroDoc::new();
+    mallory.set_peer_id(2)?;
+    mallory.get_text("text").insert(0, "evil")?;
+    mallory.commit();
+    let bob = LoroDoc::new();
+    bob.set_peer_id(3)?;
+    bob.import(&mallory.export(ExportMode::all_updates())?)?;
+    bob.get_text("text").insert(4, "!")?;
+    bob.commit();
+
+    let server = LoroDoc::new();
+    server.set_import_filter(Some(Box::new(|meta, _| {
+        if meta.id == ID::new(2, 0) {
+            Decision::Reject
+        } else {
+            Decision::Accept
+        }
+    })));
+    let status = server.import(&mallory.export(ExportMode::all_updates())?)?;
+    assert_eq!(status.rejected.unwrap().get(&2), Some(&(0, 4)));
+
+    // Bob's change depends on the rejected change, so it's rejected instead of
+    // being kept as pending
+    let status = server.import(&bob.export(ExportMode::updates(&mallory.oplog_vv()))?)?;
+    assert_eq!(status.rejected.unwrap().get(&3), Some(&(0, 1)));
+    assert!(status.pending.is_none());
+    assert!(server.oplog_vv().is_empty());
+    Ok(())
+}
+
+#[test]
+fn snapshots_are_filtered() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.get_list("list").push(1)?;
+    doc.commit();
+    let other = doc.fork();
+    other.set_peer_id(2)?;
+    other.get_list("list").push(2)?;
+    other.commit();
+    doc.import(&other.export(ExportMode::all_updates())?)?;
+    doc.get_list("list").push(3)?;
+    doc.commit();
+
+    let new_doc = LoroDoc::new();
+    new_doc.set_import_filter(Some(reject_peer(2)));
+    let status = new_doc.import(&doc.export(ExportMode::Snapshot)?)?;
+    assert_eq!(
+        status.rejected,
+        Some(VersionRange::from_map(
+            [(1, (1, 2)), (2, (0, 1))].into_iter().collect()
+        ))
+    );
+    assert_eq!(
+        new_doc.get_deep_value().to_json_value(),
+        json!({"list": [1]})
+    );
+    Ok(())
+}
+
+#[test]
+fn filter_sees_the_ops() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let map = doc.get_map("map");
+    map.insert("a", 1)?;
+    map.delete("a")?;
+    let child = map.insert_container("child", LoroMap::new())?;
+    child.insert("b", true)?;
+    doc.get_text("text").insert(0, "abc")?;
+    doc.get_text("text").delete(0, 1)?;
+    doc.get_tree("tree").create(None)?;
+    doc.commit();
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let new_doc = LoroDoc::new();
+    let seen_clone = seen.clone();
+    new_doc.set_import_filter(Some(Box::new(move |meta, ops| {
+        assert_eq!(meta.len, 9);
+        seen_clone.lock().unwrap().extend(
+            ops.iter()
+                .map(|op| (op.id, op.container.clone(), op.kind.clone())),
+        );
+        Decision::Accept
+    })));
+    new_doc.import(&doc.export(ExportMode::all_updates())?)?;
+
+    let root = |name: &str, t| ContainerID::new_root(name, t);
+    let child_id = ContainerID::new_normal(ID::new(1, 2), ContainerType::Map);
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+            (
+                ID::new(1, 0),
+                root("map", ContainerType::Map),
+                OpKind::MapSet { key: "a".into() }
+            ),
+            (
+                ID::new(1, 1),
+                root("map", ContainerType::Map),
+                OpKind::MapDelete { key: "a".into() }
+            ),
+            (
+                ID::new(1, 2),
+                root("map", ContainerType::Map),
+                OpKind::MapSet {
+                    key: "child".into()
+                }
+            ),
+            (ID::new(1, 3), child_id, OpKind::MapSet { key: "b".into() }),
+            (
+                ID::new(1, 4),
+                root("text", ContainerType::Text),
+                OpKind::Insert
+            ),
+            (
+                ID::new(1, 7),
+                root("text", ContainerType::Text),
+                OpKind::Delete
+            ),
+            (
+                ID::new(1, 8),
+                root("tree", ContainerType::Tree),
+                OpKind::TreeCreate
+            ),
+        ]
+    );
+    Ok(())
+}
@@ -1,4 +1,6 @@
-use loro::{LoroDoc, LoroList, LoroMap, LoroValue, ToJson, ValueOrContainer};
+use loro::{
+    JsonPathError, LoroDoc, LoroList, LoroMap, LoroText, LoroValue, ToJson, ValueOrContainer,
+};
 use serde_json::json;
 
 fn to_json(v: Vec<ValueOrContainer>) -> serde_json::Value {
@@ -101,7 +103,6 @@ fn test_all_authors() -> anyhow::Result<()> {
 }
 
 #[test]
-#[ignore = "filter syntax not implemented"]
 fn test_books_with_isbn() -> anyhow::Result<()> {
     let doc = setup_test_doc();
     let ans = doc.jsonpath("$..book[?(@.isbn)]")?;
@@ -216,7 +217,6 @@ fn test_book_number_two_from_tail() -> anyhow::Result<()> {
 }
 
 #[test]
-#[ignore = "filter syntax not implemented"]
 fn test_books_cheaper_than_10() -> anyhow::Result<()> {
     let doc = setup_test_doc();
     let ans = doc.jsonpath("$.store.book[?(@.price < 10)]")?;
@@ -225,10 +225,9 @@ fn test_books_cheaper_than_10() -> anyhow::Result<()> {
 }
 
 #[test]
-#[ignore = "filter syntax not implemented"]
 fn test_books_not_expensive() -> anyhow::Result<()> {
     let doc = setup_test_doc();
-    let ans = doc.jsonpath("$..book[?(@.price <= $.expensive)]")?;
+    let ans = doc.jsonpath("$..book[?(@.price <= $.store.expensive)]")?;
     assert_eq!(ans.len(), 2);
     Ok(())
 }
@@ -308,6 +307,27 @@ fn test_jsonpath_with_array() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_jsonpath_movable_list_negative_index() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let list = doc.get_movable_list("root");
+    list.insert(0, 1)?;
+    list.insert(1, 2)?;
+    list.insert(2, 3)?;
+    assert_eq!(to_json(doc.jsonpath("$.root[-1]")?), json!([3]));
+    assert_eq!(to_json(doc.jsonpath("$.root[-3]")?), json!([1]));
+    assert_eq!(to_json(doc.jsonpath("$.root[-4]")?), json!([]));
+
+    // Consistent with LoroList
+    let list = doc.get_list("list");
+    list.insert(0, 1)?;
+    list.insert(1, 2)?;
+    list.insert(2, 3)?;
+    assert_eq!(to_json(doc.jsonpath("$.list[-3]")?), json!([1]));
+    assert_eq!(to_json(doc.jsonpath("$.list[-4]")?), json!([]));
+    Ok(())
+}
+
 #[test]
 fn test_jsonpath_nested_objects() -> anyhow::Result<()> {
     let doc = LoroDoc::new();
@@ -330,3 +350,174 @@ fn test_jsonpath_wildcard() -> anyhow::Result<()> {
     assert_eq!(to_json(ans), serde_json::json!([1, 2, 3]));
     Ok(())
 }
+
+#[test]
+fn test_filter_logical_operators() -> anyhow::Result<()> {
+    let doc = setup_test_doc();
+    let ans = doc.jsonpath("$.store.book[?(@.price < 10 && @.category == 'fiction')].title")?;
+    assert_eq!(to_json(ans), json!(["Moby Dick"]));
+    let ans = doc.jsonpath("$.store.book[?(@.price > 20 || @.author == \"Nigel Rees\")].title")?;
+    assert_eq!(
+        to_json(ans),
+        json!(["Sayings of the Century", "The Lord of the Rings"])
+    );
+    let ans = doc.jsonpath("$.store.book[?(!(@.category == 'fiction'))].title")?;
+    assert_eq!(to_json(ans), json!(["Sayings of the Century"]));
+    Ok(())
+}
+
+#[test]
+fn test_filter_regex() -> anyhow::Result<()> {
+    let doc = setup_test_doc();
+    let ans = doc.jsonpath("$.store.book[?(@.author =~ /^j\\. r\\. r/i)].title")?;
+    assert_eq!(to_json(ans), json!(["The Lord of the Rings"]));
+    let ans = doc.jsonpath("$.store.book[?(@.isbn =~ /X$/)].title")?;
+    assert_eq!(to_json(ans), json!(["Moby Dick"]));
+    Ok(())
+}
+
+#[test]
+fn test_filter_with_brackets_in_predicate() -> anyhow::Result<()> {
+    let doc = setup_test_doc();
+    let ans = doc.jsonpath(
+        "$.store.book[?(@['title'] == 'Sword of Honour [2nd]' || @['price'] == 12.99)].author",
+    )?;
+    assert_eq!(to_json(ans), json!(["Evelyn Waugh"]));
+    Ok(())
+}
+
+#[test]
+fn test_filter_on_text_and_tree() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let list = doc.get_list("list");
+    let map = list.insert_container(0, LoroMap::new())?;
+    let text = map.insert_container("name", LoroText::new())?;
+    text.insert(0, "Alice")?;
+    let map = list.insert_container(1, LoroMap::new())?;
+    map.insert("name", "Bob")?;
+    let ans = doc.jsonpath("$.list[?(@.name == 'Alice')]")?;
+    assert_eq!(to_json(ans), json!([{"name": "Alice"}]));
+
+    let tree = doc.get_tree("tree");
+    let a = tree.create(None)?;
+    tree.get_meta(a)?.insert("title", "a")?;
+    let b = tree.create(None)?;
+    tree.get_meta(b)?.insert("title", "b")?;
+    let ans = doc.jsonpath("$.tree[?(@.title == 'b')].title")?;
+    assert_eq!(to_json(ans), json!(["b"]));
+    Ok(())
+}
+
+#[test]
+fn test_invalid_filter() {
+    let doc = setup_test_doc();
+    for path in [
+        "$.store.book[?(@.price <)]",
+        "$.store.book[?(@.price == 'a)]",
+        "$.store.book[?(@.price < 10]",
+        "$.store.book[?()]",
+    ] {
+        assert!(
+            matches!(doc.jsonpath(path), Err(JsonPathError::InvalidJsonPath(_))),
+            "{path} should be invalid"
+        );
+    }
+}
+
+#[test]
+fn test_set_by_path_creates_maps() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_by_path("$.settings.theme", "dark")?;
+    doc.set_by_path("$.settings['editor'].font.size", 14)?;
+    doc.set_by_path("$.settings.theme", "light")?;
+    doc.commit();
+    assert_eq!(
+        doc.get_deep_value().to_json_value(),
+        json!({"settings": {"theme": "light", "editor": {"font": {"size": 14}}}})
+    );
+    // Every edit above is in a single change
+    assert_eq!(doc.len_changes(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_edit_lists_by_path() -> anyhow::Result<()> {
+    let doc = setup_test_doc();
+    doc.set_by_path("$.store.book[0].price", 9.5)?;
+    let list = doc
+        .get_map("root")
+        .insert_container("items", LoroList::new())?;
+    list.insert(0, 1)?;
+    list.insert(1, 2)?;
+    let movable = doc
+        .get_map("root")
+        .insert_container("movable", loro::LoroMovableList::new())?;
+    movable.insert(0, "a")?;
+
+    doc.insert_by_path("$.root.items[2]", 3)?;
+    doc.insert_by_path("$.root.items[0]", 0)?;
+    doc.set_by_path("$.root.items[-1]", 4)?;
+    doc.delete_by_path("$.root.items[1]")?;
+    doc.insert_by_path("$.root.movable[1]", "b")?;
+    doc.set_by_path("$.root.movable[0]", "c")?;
+    doc.commit();
+
+    assert_eq!(
+        doc.get_map("root").get_deep_value().to_json_value(),
+        json!({"items": [0, 2, 4], "movable": ["c", "b"]})
+    );
+    assert_eq!(
+        to_json(doc.jsonpath("$.store.book[0].price")?),
+        json!([9.5])
+    );
+
+    doc.delete_by_path("$.store.bicycle")?;
+    assert!(doc.jsonpath("$.store.bicycle")?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_edit_by_path_errors() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.get_map("root")
+        .insert_container("text", LoroText::new())?
+        .insert(0, "hello")?;
+    doc.get_map("root").insert("num", 1)?;
+    doc.get_tree("tree").create(None)?;
+    doc.commit();
+    let len = doc.len_ops();
+
+    assert!(matches!(
+        doc.set_by_path("$.root.text.key", 1),
+        Err(JsonPathError::UnsupportedContainer { .. })
+    ));
+    assert!(matches!(
+        doc.set_by_path("$.tree[0].key", 1),
+        Err(JsonPathError::UnsupportedContainer { .. })
+    ));
+    assert!(matches!(
+        doc.set_by_path("$.root.num.key", 1),
+        Err(JsonPathError::EvaluationError(_))
+    ));
+    assert!(matches!(
+        doc.set_by_path("$.root.a.b[0]", 1),
+        Err(JsonPathError::EvaluationError(_))
+    ));
+    assert!(matches!(
+        doc.delete_by_path("$.root.missing"),
+        Err(JsonPathError::EvaluationError(_))
+    ));
+    assert!(matches!(
+        doc.set_by_path("$.root.*", 1),
+        Err(JsonPathError::InvalidJsonPath(_))
+    ));
+    assert!(matches!(
+        doc.delete_by_path("$.root"),
+        Err(JsonPathError::InvalidJsonPath(_))
+    ));
+
+    // Nothing is edited when the path is invalid
+    doc.commit();
+    assert_eq!(doc.len_ops(), len);
+    Ok(())
+}
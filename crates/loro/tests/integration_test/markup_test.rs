@@ -0,0 +1,172 @@
+use loro::{
+    markup::{MarkupConfig, BLOCKQUOTE_KEY, CODE_BLOCK_KEY, HEADER_KEY, LIST_KEY},
+    ExpandType, LoroDoc, StyleConfig, StyleConfigMap, TextDelta, ToJson,
+};
+use serde_json::json;
+
+fn doc_with_block_styles() -> LoroDoc {
+    let doc = LoroDoc::new();
+    let mut styles = StyleConfigMap::default_rich_text_config();
+    for key in [
+        HEADER_KEY,
+        LIST_KEY,
+        BLOCKQUOTE_KEY,
+        CODE_BLOCK_KEY,
+        "strike",
+    ] {
+        styles.insert(key.into(), StyleConfig::new().expand(ExpandType::None));
+    }
+    doc.config_text_style(styles);
+    doc
+}
+
+fn rich_delta() -> Vec<TextDelta> {
+    serde_json::from_value(json!([
+        {"insert": "Title"},
+        {"insert": "\n", "attributes": {"header": 1}},
+        {"insert": "Hello "},
+        {"insert": "bold", "attributes": {"bold": true}},
+        {"insert": " and "},
+        {"insert": "link", "attributes": {"link": "https://loro.dev"}},
+        {"insert": "\nOne"},
+        {"insert": "\n", "attributes": {"list": "bullet"}},
+        {"insert": "Two"},
+        {"insert": "\n", "attributes": {"list": "bullet"}},
+        {"insert": "Quote"},
+        {"insert": "\n", "attributes": {"blockquote": true}},
+        {"insert": "let a = 1;"},
+        {"insert": "\n", "attributes": {"code-block": true}},
+        {"insert": "End"},
+    ]))
+    .unwrap()
+}
+
+#[cfg(feature = "markdown")]
+#[test]
+fn markdown_round_trip() -> anyhow::Result<()> {
+    let config = MarkupConfig::default();
+    let doc = doc_with_block_styles();
+    let text = doc.get_text("text");
+    text.apply_delta(&rich_delta())?;
+    let markdown = text.to_markdown(&config);
+    assert_eq!(
+        markdown,
+        "# Title\n\nHello **bold** and [link](https://loro.dev)\n\n- One\n- Two\n\n> Quote\n\n```\nlet a = 1;\n```\n\nEnd"
+    );
+
+    let doc_b = doc_with_block_styles();
+    let text_b = doc_b.get_text("text");
+    text_b.apply_delta(&loro::markup::from_markdown(&markdown, &config))?;
+    assert_eq!(
+        text_b.to_delta().to_json_value(),
+        text.to_delta().to_json_value()
+    );
+    Ok(())
+}
+
+#[cfg(feature = "markdown")]
+#[test]
+fn markdown_custom_marks_and_escaping() -> anyhow::Result<()> {
+    let config = MarkupConfig::default().mark("strike", loro::markup::MarkSyntax::Strikethrough);
+    let doc = doc_with_block_styles();
+    let text = doc.get_text("text");
+    text.insert(0, "1. *not* a list")?;
+    text.mark(3..8, "strike", true)?;
+    let markdown = text.to_markdown(&config);
+    assert_eq!(markdown, "1\\. ~~\\*not\\*~~ a list");
+    let delta = loro::markup::from_markdown(&markdown, &config);
+    let doc_b = doc_with_block_styles();
+    let text_b = doc_b.get_text("text");
+    text_b.apply_delta(&delta)?;
+    assert_eq!(text_b.to_string(), "1. *not* a list");
+    assert_eq!(
+        text_b.to_delta().to_json_value(),
+        text.to_delta().to_json_value()
+    );
+    Ok(())
+}
+
+#[cfg(feature = "markdown")]
+#[test]
+fn markdown_urls_are_sanitized() -> anyhow::Result<()> {
+    let config = MarkupConfig::default();
+    let delta: Vec<TextDelta> = serde_json::from_value(json!([
+        {"insert": "click", "attributes": {"link": "javascript:alert(1)"}},
+        {"insert": " "},
+        {"insert": {"$embed": {"image": "javascript:alert(1)"}}},
+    ]))?;
+    assert_eq!(loro::markup::to_markdown(&delta, &config), "click ");
+
+    let delta = loro::markup::from_markdown(
+        "[click](JavaScript:alert(1)) and [ok](https://loro.dev) ![img](javascript:x)",
+        &config,
+    );
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.apply_delta(&delta)?;
+    assert_eq!(
+        text.to_delta().to_json_value(),
+        json!([
+            {"insert": "click and "},
+            {"insert": "ok", "attributes": {"link": "https://loro.dev"}},
+            {"insert": " "},
+        ])
+    );
+    Ok(())
+}
+
+#[cfg(feature = "html")]
+#[test]
+fn html_round_trip() -> anyhow::Result<()> {
+    let config = MarkupConfig::default();
+    let doc = doc_with_block_styles();
+    let text = doc.get_text("text");
+    text.apply_delta(&rich_delta())?;
+    let html = text.to_html(&config);
+    assert_eq!(
+        html,
+        "<h1>Title</h1><p>Hello <strong>bold</strong> and <a href=\"https://loro.dev\">link</a></p>\
+         <ul><li>One</li><li>Two</li></ul><blockquote><p>Quote</p></blockquote>\
+         <pre><code>let a = 1;</code></pre><p>End</p>"
+    );
+
+    let doc_b = doc_with_block_styles();
+    let text_b = doc_b.get_text("text");
+    text_b.apply_delta(&loro::markup::from_html(&html, &config))?;
+    assert_eq!(
+        text_b.to_delta().to_json_value(),
+        text.to_delta().to_json_value()
+    );
+    Ok(())
+}
+
+#[cfg(feature = "html")]
+#[test]
+fn html_is_sanitized() -> anyhow::Result<()> {
+    let config = MarkupConfig::default();
+    let delta: Vec<TextDelta> = serde_json::from_value(json!([
+        {"insert": "click", "attributes": {"link": "javascript:alert(1)"}},
+        {"insert": " <img>"},
+    ]))?;
+    assert_eq!(
+        loro::markup::to_html(&delta, &config),
+        "<p>click &lt;img&gt;</p>"
+    );
+
+    let delta = loro::markup::from_html(
+        "<p><a href=\"JavaScript:alert(1)\">click</a><script>alert(1)</script>\
+         <style>p {}</style> &amp; <i>more</i></p><img src=\"javascript:x\">",
+        &config,
+    );
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.apply_delta(&delta)?;
+    assert_eq!(
+        text.to_delta().to_json_value(),
+        json!([
+            {"insert": "click & "},
+            {"insert": "more", "attributes": {"italic": true}},
+        ])
+    );
+    Ok(())
+}
@@ -1,12 +1,24 @@
 use loro::LoroDoc;
 
 mod detached_editing_test;
+mod diff_test;
+mod import_filter_test;
 #[cfg(feature = "jsonpath")]
 mod jsonpath_test;
+#[cfg(any(feature = "markdown", feature = "html"))]
+mod markup_test;
 mod redact_test;
+mod schema_test;
 mod shallow_snapshot_test;
 mod snapshot_at_test;
+mod text_blame_test;
+mod text_search_test;
 mod text_update_test;
+mod tree_conflict_test;
+mod tree_rebalance_test;
+mod tree_subtree_test;
+mod tree_traversal_test;
+mod tree_value_test;
 mod undo_test;
 
 fn gen_action(doc: &LoroDoc, seed: u64, mut ops_len: usize) {
@@ -0,0 +1,198 @@
+use loro::{
+    ContainerSchema, ExportMode, LoroDoc, LoroError, LoroMap, LoroText, MapSchema, Schema,
+    ValueSchema,
+};
+use serde_json::json;
+
+fn todo_schema() -> Schema {
+    let todo = MapSchema::new()
+        .field("title", ValueSchema::String)
+        .field("done", ValueSchema::Bool);
+    let settings = MapSchema::new().field(
+        "theme",
+        ContainerSchema::Map(MapSchema::new().field("color", ValueSchema::String)),
+    );
+    Schema::new()
+        .root(
+            "todos",
+            ContainerSchema::movable_list(ContainerSchema::Map(todo)),
+        )
+        .root("settings", ContainerSchema::Map(settings))
+}
+
+fn assert_violation<T: std::fmt::Debug>(result: Result<T, LoroError>, expected_path: &str) {
+    match result {
+        Err(LoroError::SchemaViolation { path, .. }) => assert_eq!(&*path, expected_path),
+        other => panic!("expected a schema violation, got {:?}", other),
+    }
+}
+
+#[test]
+fn local_edits_are_checked() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_schema(Some(todo_schema()));
+    let todos = doc.get_movable_list("todos");
+    let todo = todos.push_container(LoroMap::new())?;
+    todo.insert("title", "Buy milk")?;
+    todo.insert("done", false)?;
+    todo.delete("done")?;
+
+    assert_violation(todo.insert("done", "no"), "$.todos[*].done");
+    assert_violation(todo.insert("due", 1), "$.todos[*].due");
+    assert_violation(todos.push(1), "$.todos[*]");
+    assert_violation(todos.set(0, "text"), "$.todos[*]");
+    assert_violation(todos.push_container(LoroText::new()), "$.todos[*]");
+    assert_violation(doc.get_list("settings").push(1), "$.settings");
+    assert_violation(doc.get_map("other").insert("a", 1), "$.other");
+
+    let settings = doc.get_map("settings");
+    let theme = settings.insert_container("theme", LoroMap::new())?;
+    theme.insert("color", "red")?;
+    assert_violation(theme.insert("color", 1), "$.settings.theme.color");
+    doc.commit();
+
+    // The rejected edits are not applied
+    assert_eq!(
+        doc.get_deep_value().to_json_value(),
+        json!({
+            "todos": [{"title": "Buy milk"}],
+            "settings": {"theme": {"color": "red"}}
+        })
+    );
+
+    doc.set_schema(None);
+    doc.get_map("other").insert("a", 1)?;
+    Ok(())
+}
+
+#[test]
+fn edits_on_unreachable_containers_are_rejected() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_schema(Some(todo_schema()));
+    let settings = doc.get_map("settings");
+    let theme = settings.insert_container("theme", LoroMap::new())?;
+    // The old theme cannot be reached from the root after it's overridden,
+    // so its path cannot be checked against the schema
+    settings.insert_container("theme", LoroMap::new())?;
+    assert!(theme.insert("color", 1).is_err());
+    assert!(theme.insert("color", "red").is_err());
+    doc.commit();
+    assert_eq!(
+        doc.get_deep_value().to_json_value(),
+        json!({"settings": {"theme": {}}})
+    );
+    Ok(())
+}
+
+#[test]
+fn schema_options() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_schema(Some(
+        Schema::new()
+            .root(
+                "meta",
+                ContainerSchema::Map(
+                    MapSchema::new()
+                        .field(
+                            "version",
+                            ValueSchema::OneOf(vec![ValueSchema::I64, ValueSchema::Null]),
+                        )
+                        .field("extra", ValueSchema::Any)
+                        .other_fields(ValueSchema::String),
+                ),
+            )
+            .root(
+                "tree",
+                ContainerSchema::Tree(MapSchema::new().field("name", ValueSchema::String)),
+            )
+            .allow_unknown_roots(true),
+    ));
+
+    let meta = doc.get_map("meta");
+    meta.insert("version", 1)?;
+    meta.insert("version", loro::LoroValue::Null)?;
+    assert_violation(meta.insert("version", 1.5), "$.meta.version");
+    meta.insert("author", "Alice")?;
+    assert_violation(meta.insert("author", true), "$.meta.author");
+    let extra = meta.insert_container("extra", LoroMap::new())?;
+    extra.insert("anything", vec![1, 2])?;
+
+    let tree = doc.get_tree("tree");
+    let node = tree.create(None)?;
+    tree.get_meta(node)?.insert("name", "root")?;
+    assert_violation(tree.get_meta(node)?.insert("name", 1), "$.tree[*].name");
+
+    doc.get_text("unknown").insert(0, "hello")?;
+    Ok(())
+}
+
+#[test]
+fn imported_changes_are_checked() -> anyhow::Result<()> {
+    let remote = LoroDoc::new();
+    remote.set_peer_id(1)?;
+    let todo = remote
+        .get_movable_list("todos")
+        .push_container(LoroMap::new())?;
+    todo.insert("title", "Buy milk")?;
+    let theme = remote
+        .get_map("settings")
+        .insert_container("theme", LoroMap::new())?;
+    theme.insert("color", "red")?;
+    remote.commit();
+
+    let base = remote.export(ExportMode::Snapshot)?;
+    let doc = LoroDoc::new();
+    doc.set_schema(Some(todo_schema()));
+    let status = doc.import(&base)?;
+    assert!(status.rejected.is_none());
+    assert_eq!(doc.get_deep_value(), remote.get_deep_value());
+    let vv = doc.oplog_vv();
+
+    // Only the changes that don't match the schema are rejected
+    let another = LoroDoc::new();
+    another.set_peer_id(2)?;
+    another.import(&base)?;
+    another.get_map(todo.id()).insert("done", "yes")?;
+    another.commit();
+    let third = LoroDoc::new();
+    third.set_peer_id(3)?;
+    third.import(&base)?;
+    third.get_map(todo.id()).insert("done", true)?;
+    third.commit();
+    another.import(&third.export(ExportMode::updates(&vv))?)?;
+    let status = doc.import(&another.export(ExportMode::updates(&vv))?)?;
+    assert_eq!(status.rejected.unwrap().get(&2), Some(&(0, 1)));
+    assert_eq!(status.success.get(&3), Some(&(0, 1)));
+    assert_eq!(
+        doc.get_deep_value().to_json_value(),
+        json!({
+            "todos": [{"title": "Buy milk", "done": true}],
+            "settings": {"theme": {"color": "red"}}
+        })
+    );
+    let vv = doc.oplog_vv();
+
+    // The child containers are located by the ops that created them, and the
+    // changes depending on a rejected change are rejected too
+    remote.import(&doc.export(ExportMode::all_updates())?)?;
+    theme.insert("color", 1)?;
+    remote.commit();
+    todo.insert("title", "Buy bread")?;
+    remote.commit();
+    let updates = remote.export(ExportMode::updates(&vv))?;
+    let status = doc.import(&updates)?;
+    assert_eq!(status.rejected.unwrap().get(&1), Some(&(4, 6)));
+    assert_eq!(doc.oplog_vv(), vv);
+
+    // Snapshots are checked too
+    let new_doc = LoroDoc::new();
+    new_doc.set_schema(Some(todo_schema()));
+    let status = new_doc.import(&remote.export(ExportMode::Snapshot)?)?;
+    assert_eq!(status.rejected.unwrap().get(&1), Some(&(4, 6)));
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+    doc.set_schema(None);
+    doc.import(&updates)?;
+    assert_eq!(doc.get_deep_value(), remote.get_deep_value());
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use loro::{CommitOptions, ExportMode, Frontiers, LoroDoc, ID};
+
+#[test]
+fn text_blame_of_concurrent_edits() -> anyhow::Result<()> {
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    let text_a = doc_a.get_text("text");
+    text_a.insert(0, "Hello world")?;
+    doc_a.commit_with(CommitOptions::new().commit_msg("init").timestamp(100));
+
+    let doc_b = doc_a.fork();
+    doc_b.set_peer_id(2)?;
+    let text_b = doc_b.get_text("text");
+    text_b.insert(5, ",")?;
+    doc_b.commit_with(CommitOptions::new().commit_msg("comma").timestamp(200));
+
+    text_a.delete(6, 5)?;
+    text_a.insert(6, "Loro")?;
+    doc_a.commit();
+
+    doc_a.import(&doc_b.export(ExportMode::all_updates())?)?;
+    assert_eq!(text_a.to_string(), "Hello, Loro");
+    let blame = doc_a.text_blame(&text_a.id())?;
+    let summary: Vec<_> = blame
+        .iter()
+        .map(|span| {
+            (
+                span.range.clone(),
+                span.peer,
+                span.change_id,
+                span.message.as_deref().map(|x| x.to_string()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (0..5, 1, ID::new(1, 0), Some("init".to_string())),
+            (5..6, 2, ID::new(2, 0), Some("comma".to_string())),
+            (6..7, 1, ID::new(1, 0), Some("init".to_string())),
+            (7..11, 1, ID::new(1, 11), None),
+        ]
+    );
+    assert_eq!(blame[0].timestamp, 100);
+    assert_eq!(blame[1].timestamp, 200);
+    assert_eq!(blame[1].lamport, 11);
+    assert_eq!(blame[2].lamport, 5);
+    Ok(())
+}
+
+#[test]
+fn text_blame_at_old_version() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "abc")?;
+    doc.commit();
+    let v1 = doc.oplog_frontiers();
+    doc.set_peer_id(2)?;
+    text.delete(1, 1)?;
+    text.insert(0, "x")?;
+    doc.commit();
+
+    let blame = doc.text_blame(&text.id())?;
+    assert_eq!(
+        blame
+            .iter()
+            .map(|s| (s.range.clone(), s.peer))
+            .collect::<Vec<_>>(),
+        vec![(0..1, 2), (1..3, 1)]
+    );
+
+    let old = doc.text_blame_at(&text.id(), &v1)?;
+    assert_eq!(old.len(), 1);
+    assert_eq!((old[0].range.clone(), old[0].peer), (0..3, 1));
+    assert_eq!(old[0].change_id, ID::new(1, 0));
+    // The document is not checked out
+    assert!(!doc.is_detached());
+    assert_eq!(text.to_string(), "xac");
+
+    assert!(doc
+        .text_blame_at(&text.id(), &Frontiers::default())?
+        .is_empty());
+    assert!(doc
+        .text_blame_at(&text.id(), &ID::new(3, 0).into())
+        .is_err());
+    assert!(doc.text_blame(&doc.get_map("map").id()).is_err());
+    Ok(())
+}
+
+#[test]
+fn text_blame_at_from_checked_out_and_shallow_docs() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "abc")?;
+    text.mark(0..2, "bold", true)?;
+    doc.commit();
+    let v1 = doc.oplog_frontiers();
+    doc.set_peer_id(2)?;
+    text.insert(1, "xy")?;
+    text.delete(4, 1)?;
+    doc.commit();
+    let v2 = doc.oplog_frontiers();
+    doc.set_peer_id(3)?;
+    text.insert(0, "z")?;
+    doc.commit();
+
+    let summary = |doc: &LoroDoc, frontiers: &Frontiers| -> anyhow::Result<Vec<_>> {
+        Ok(doc
+            .text_blame_at(&text.id(), frontiers)?
+            .into_iter()
+            .map(|s| (s.range, s.peer))
+            .collect())
+    };
+    assert_eq!(summary(&doc, &v1)?, vec![(0..3, 1)]);
+    assert_eq!(summary(&doc, &v2)?, vec![(0..1, 1), (1..3, 2), (3..4, 1)]);
+
+    // From a checked out version to a newer one
+    doc.checkout(&v1)?;
+    assert_eq!(summary(&doc, &v2)?, vec![(0..1, 1), (1..3, 2), (3..4, 1)]);
+    doc.checkout_to_latest();
+
+    // The text before the shallow root is attributed to the ids of the chars
+    let shallow = LoroDoc::new();
+    shallow.import(&doc.export(ExportMode::shallow_snapshot(&v2))?)?;
+    let blame = shallow.text_blame_at(&text.id(), &v2)?;
+    assert_eq!(
+        blame
+            .iter()
+            .map(|s| (s.range.clone(), s.peer))
+            .collect::<Vec<_>>(),
+        vec![(0..1, 1), (1..3, 2), (3..4, 1)]
+    );
+    assert!(shallow.text_blame_at(&text.id(), &v1).is_err());
+    Ok(())
+}
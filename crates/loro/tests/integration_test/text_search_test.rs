@@ -0,0 +1,67 @@
+use loro::{LoroDoc, LoroText};
+
+#[test]
+fn find_in_text() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "aaaa")?;
+    text.insert(2, "😀")?;
+    assert_eq!(text.find("aa"), vec![0..2, 3..5]);
+    assert_eq!(text.find("a😀a"), vec![1..4]);
+    assert!(text.find("b").is_empty());
+    assert!(text.find("").is_empty());
+    // The marks split the text into chunks, and the matches can span them
+    text.mark(0..3, "bold", true)?;
+    text.mark(4..5, "italic", true)?;
+    assert_eq!(text.find("a😀a"), vec![1..4]);
+    assert_eq!(text.find("aa"), vec![0..2, 3..5]);
+
+    let detached = LoroText::new();
+    detached.insert(0, "abcabc")?;
+    assert_eq!(detached.find("bc"), vec![1..3, 4..6]);
+    Ok(())
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn replace_all_keeps_the_untouched_text() -> anyhow::Result<()> {
+    use loro::{cursor::Side, LoroError, ToJson};
+    use serde_json::json;
+
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world, hello all")?;
+    text.mark(6..11, "bold", true)?;
+    let cursor = text.get_cursor(2, Side::Left).unwrap();
+    assert_eq!(text.replace_all("(?i)hello", "Help")?, 2);
+    assert_eq!(text.to_string(), "Help world, Help all");
+    assert_eq!(
+        text.to_delta().to_json_value(),
+        json!([
+            {"insert": "Help "},
+            {"insert": "world", "attributes": {"bold": true}},
+            {"insert": ", Help all"},
+        ])
+    );
+    // Only "lo" of the first match is replaced, so the cursor on "l" is kept
+    assert_eq!(doc.get_cursor_pos(&cursor)?.current.pos, 2);
+
+    assert_eq!(text.replace_all(r"(\w+) (\w+)", "$2 $1")?, 2);
+    assert_eq!(text.to_string(), "world Help, all Help");
+    assert_eq!(text.replace_all("xyz", "abc")?, 0);
+    assert!(matches!(
+        text.replace_all("(", ""),
+        Err(LoroError::ArgErr(_))
+    ));
+
+    text.insert(text.len_unicode(), "\nnext line")?;
+    assert_eq!(text.replace_all(r"^\w+", "x")?, 2);
+    assert_eq!(text.to_string(), "x Help, all Help\nx line");
+    assert_eq!(text.replace_all(r"Help\nx", "")?, 0);
+
+    let detached = LoroText::new();
+    detached.insert(0, "a-b-c")?;
+    assert_eq!(detached.replace_all("-", "")?, 2);
+    assert_eq!(detached.to_string(), "abc");
+    Ok(())
+}
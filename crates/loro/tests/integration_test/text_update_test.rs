@@ -157,3 +157,157 @@ fn test_failed_case_2() -> anyhow::Result<()> {
     assert_eq!(&text.to_string(), new1);
     Ok(())
 }
+
+#[test]
+fn text_update_by_word() -> anyhow::Result<()> {
+    use loro::{cursor::Side, DiffGranularity, UpdateOptions};
+
+    let options = |granularity| UpdateOptions::new().granularity(granularity);
+    for (granularity, is_deleted) in [
+        (DiffGranularity::Char, false),
+        (DiffGranularity::Word, true),
+    ] {
+        let doc = LoroDoc::new();
+        let text = doc.get_text("text");
+        text.update("the cat sat", options(granularity)).unwrap();
+        // The cursor on "a" of "cat"
+        let cursor = text.get_cursor(5, Side::Left).unwrap();
+        text.update("the cart sat, don't", options(granularity))
+            .unwrap();
+        assert_eq!(&text.to_string(), "the cart sat, don't");
+        let pos = doc.get_cursor_pos(&cursor)?;
+        assert_eq!(pos.update.is_some(), is_deleted);
+    }
+
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    let options = options(DiffGranularity::Word);
+    for s in [
+        "你好世界 hello",
+        "你好, 世界 hello!",
+        "it's  fine\n",
+        "",
+        "a_b c",
+    ] {
+        text.update(s, options.clone()).unwrap();
+        assert_eq!(&text.to_string(), s);
+    }
+    Ok(())
+}
+
+#[test]
+fn text_update_with_patience_and_histogram() -> anyhow::Result<()> {
+    use loro::{DiffAlgorithm, DiffGranularity, UpdateOptions};
+
+    let versions = [
+        "fn a() {\n    1\n}\n\nfn b() {\n    2\n}\n",
+        "fn b() {\n    2\n}\n\nfn a() {\n    1\n}\n",
+        "fn b() {\n    2\n}\n}\n}\nfn c() {\n    1\n}\n",
+        "",
+        "x\ny\nx\ny\n",
+    ];
+    for algorithm in [DiffAlgorithm::Patience, DiffAlgorithm::Histogram] {
+        for granularity in [DiffGranularity::Char, DiffGranularity::Word] {
+            let options = UpdateOptions::new()
+                .algorithm(algorithm)
+                .granularity(granularity);
+            let doc = LoroDoc::new();
+            let text = doc.get_text("text");
+            for s in versions {
+                text.update(s, options.clone()).unwrap();
+                assert_eq!(&text.to_string(), s);
+            }
+            for s in versions.iter().rev() {
+                text.update_by_line(s, options.clone()).unwrap();
+                assert_eq!(&text.to_string(), *s);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn text_update_with_histogram_times_out() {
+    use loro::{DiffAlgorithm, UpdateOptions};
+
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, &"a".repeat(10000)).unwrap();
+    let options = UpdateOptions::new()
+        .timeout_ms(0.1)
+        .algorithm(DiffAlgorithm::Histogram);
+    assert!(text.update(&"b".repeat(10000), options).is_err());
+}
+
+#[test]
+fn text_update_with_marks() -> anyhow::Result<()> {
+    use loro::{cursor::Side, ExportMode, LoroError, LoroText, TextDelta, ToJson};
+    use serde_json::json;
+
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    let text_a = doc_a.get_text("text");
+    text_a.insert(0, "Hello world")?;
+    text_a.mark(6..11, "bold", true)?;
+    doc_a.commit();
+    let doc_b = LoroDoc::new();
+    doc_b.set_peer_id(2)?;
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+    // The cursor on "w"
+    let cursor = text_a.get_cursor(6, Side::Left).unwrap();
+
+    let target = json!([
+        {"insert": "Hi "},
+        {"insert": "world", "attributes": {"italic": true}},
+        {"insert": {"src": "a.png"}, "attributes": {"italic": true}},
+        {"insert": "!"},
+    ]);
+    let delta: Vec<TextDelta> = serde_json::from_value(target.clone())?;
+    text_a.update_with_marks(&delta, Default::default())?;
+    assert_eq!(text_a.to_delta().to_json_value(), target);
+    let pos = doc_a.get_cursor_pos(&cursor)?;
+    assert!(pos.update.is_none());
+    assert_eq!(pos.current.pos, 3);
+
+    // The concurrent styles on the kept text are kept
+    doc_b.get_text("text").mark(8..11, "underline", true)?;
+    doc_a.import(&doc_b.export(ExportMode::all_updates())?)?;
+    assert_eq!(
+        text_a.to_delta().to_json_value(),
+        json!([
+            {"insert": "Hi "},
+            {"insert": "wo", "attributes": {"italic": true}},
+            {"insert": "rld", "attributes": {"italic": true, "underline": true}},
+            {"insert": {"src": "a.png"}, "attributes": {"italic": true}},
+            {"insert": "!"},
+        ])
+    );
+
+    // Updating to the same delta does nothing
+    let version = doc_a.oplog_vv();
+    let delta: Vec<TextDelta> = serde_json::from_value(text_a.to_delta().to_json_value())?;
+    text_a.update_with_marks(&delta, Default::default())?;
+    doc_a.commit();
+    assert_eq!(doc_a.oplog_vv(), version);
+
+    let delta = [TextDelta::Retain {
+        retain: 1,
+        attributes: None,
+    }];
+    assert!(matches!(
+        text_a.update_with_marks(&delta, Default::default()),
+        Err(LoroError::ArgErr(_))
+    ));
+
+    let detached = LoroText::new();
+    detached.insert(0, "abc")?;
+    detached.mark(0..3, "bold", true)?;
+    let target = json!([
+        {"insert": "ab", "attributes": {"bold": true}},
+        {"insert": "cd"},
+    ]);
+    let delta: Vec<TextDelta> = serde_json::from_value(target.clone())?;
+    detached.update_with_marks(&delta, Default::default())?;
+    assert_eq!(detached.to_delta().to_json_value(), target);
+    Ok(())
+}
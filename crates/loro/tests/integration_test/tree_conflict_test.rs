@@ -0,0 +1,191 @@
+use std::sync::{Arc, Mutex};
+
+use loro::{ExportMode, LoroDoc, TreeExternalDiff, TreeID, TreeMoveRejectReason, TreeParentId, ID};
+
+type RejectedMoves = Arc<Mutex<Vec<(TreeID, ID, TreeMoveRejectReason)>>>;
+
+/// Collect the rejected moves in the events of the tree
+fn subscribe_rejected(doc: &LoroDoc) -> (loro::Subscription, RejectedMoves) {
+    let rejected = Arc::new(Mutex::new(vec![]));
+    let rejected_clone = rejected.clone();
+    let sub = doc.subscribe(
+        &doc.get_tree("tree").id(),
+        Arc::new(move |e| {
+            for event in e.events {
+                let Some(tree) = event.diff.as_tree() else {
+                    continue;
+                };
+                for item in tree.iter() {
+                    if let TreeExternalDiff::MoveRejected { id, reason, .. } = &item.action {
+                        rejected_clone
+                            .lock()
+                            .unwrap()
+                            .push((item.target, *id, *reason));
+                    }
+                }
+            }
+        }),
+    );
+    (sub, rejected)
+}
+
+#[test]
+fn cyclic_moves_are_reported() -> anyhow::Result<()> {
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    let tree_a = doc_a.get_tree("tree");
+    let a = tree_a.create(None)?;
+    let b = tree_a.create(None)?;
+    doc_a.commit();
+    let since = doc_a.oplog_frontiers();
+    let doc_b = doc_a.fork();
+    doc_b.set_peer_id(2)?;
+    let tree_b = doc_b.get_tree("tree");
+
+    tree_a.mov(a, b)?;
+    doc_a.commit();
+    tree_b.mov(b, a)?;
+    doc_b.commit();
+    let move_b = ID::new(2, 0);
+
+    // The remote move is rejected
+    let (_sub_a, rejected_a) = subscribe_rejected(&doc_a);
+    doc_a.import(&doc_b.export(ExportMode::all_updates())?)?;
+    assert_eq!(
+        *rejected_a.lock().unwrap(),
+        vec![(b, move_b, TreeMoveRejectReason::Cycle)]
+    );
+
+    // The local move is undone
+    let (_sub_b, rejected_b) = subscribe_rejected(&doc_b);
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+    assert_eq!(
+        *rejected_b.lock().unwrap(),
+        vec![(b, move_b, TreeMoveRejectReason::Cycle)]
+    );
+    assert_eq!(tree_b.parent(b), Some(TreeParentId::Root));
+    assert_eq!(doc_a.get_deep_value(), doc_b.get_deep_value());
+
+    for (doc, tree) in [(&doc_a, &tree_a), (&doc_b, &tree_b)] {
+        let moves = doc.get_rejected_tree_moves(&tree.id(), &since)?;
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].id, move_b);
+        assert_eq!(moves[0].target, b);
+        assert_eq!(moves[0].parent, TreeParentId::Node(a));
+        assert_eq!(moves[0].reason, TreeMoveRejectReason::Cycle);
+    }
+    assert!(doc_a
+        .get_rejected_tree_moves(&tree_a.id(), &doc_a.oplog_frontiers())?
+        .is_empty());
+    assert!(doc_a
+        .get_rejected_tree_moves(&tree_a.id(), &ID::new(3, 0).into())
+        .is_err());
+    assert!(doc_a
+        .get_rejected_tree_moves(&doc_a.get_text("text").id(), &since)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn moves_into_deleted_parent_are_reported() -> anyhow::Result<()> {
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    let tree_a = doc_a.get_tree("tree");
+    let folder = tree_a.create(None)?;
+    let node = tree_a.create(None)?;
+    doc_a.commit();
+    let since = doc_a.oplog_frontiers();
+    let doc_b = doc_a.fork();
+    doc_b.set_peer_id(2)?;
+    let tree_b = doc_b.get_tree("tree");
+
+    tree_a.delete(folder)?;
+    doc_a.commit();
+    tree_b.mov(node, folder)?;
+    doc_b.commit();
+    let move_b = ID::new(2, 0);
+
+    // Exactly one event is emitted for the rejection
+    let (_sub_a, rejected_a) = subscribe_rejected(&doc_a);
+    doc_a.import(&doc_b.export(ExportMode::all_updates())?)?;
+    assert_eq!(
+        *rejected_a.lock().unwrap(),
+        vec![(node, move_b, TreeMoveRejectReason::ParentDeleted)]
+    );
+    assert!(tree_a.is_node_deleted(&node)?);
+
+    let (_sub_b, rejected_b) = subscribe_rejected(&doc_b);
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+    assert_eq!(
+        *rejected_b.lock().unwrap(),
+        vec![(node, move_b, TreeMoveRejectReason::ParentDeleted)]
+    );
+    assert_eq!(doc_a.get_deep_value(), doc_b.get_deep_value());
+
+    let moves = doc_b.get_rejected_tree_moves(&tree_b.id(), &since)?;
+    assert_eq!(moves.len(), 1);
+    assert_eq!(moves[0].target, node);
+    assert_eq!(moves[0].reason, TreeMoveRejectReason::ParentDeleted);
+    Ok(())
+}
+
+#[test]
+fn creations_under_deleted_parent_are_reported_once() -> anyhow::Result<()> {
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    let tree_a = doc_a.get_tree("tree");
+    let folder = tree_a.create(None)?;
+    doc_a.commit();
+    let doc_b = doc_a.fork();
+    doc_b.set_peer_id(2)?;
+    let tree_b = doc_b.get_tree("tree");
+
+    tree_a.delete(folder)?;
+    doc_a.commit();
+    let child = tree_b.create(folder)?;
+    doc_b.commit();
+
+    let (_sub_a, rejected_a) = subscribe_rejected(&doc_a);
+    doc_a.import(&doc_b.export(ExportMode::all_updates())?)?;
+    assert_eq!(
+        *rejected_a.lock().unwrap(),
+        vec![(child, ID::new(2, 0), TreeMoveRejectReason::ParentDeleted)]
+    );
+    assert!(tree_a.is_node_deleted(&child)?);
+    Ok(())
+}
+
+#[test]
+fn rejected_moves_on_shallow_doc() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let tree = doc.get_tree("tree");
+    let a = tree.create(None)?;
+    let b = tree.create(a)?;
+    let c = tree.create(None)?;
+    doc.commit();
+    let since = doc.oplog_frontiers();
+    let shallow = LoroDoc::new();
+    shallow.import(&doc.export(ExportMode::shallow_snapshot(&since))?)?;
+    shallow.set_peer_id(2)?;
+    let shallow_tree = shallow.get_tree("tree");
+
+    // The cycle goes through `b`, whose creation is before the shallow root
+    tree.mov(c, b)?;
+    doc.commit();
+    shallow_tree.mov(a, c)?;
+    shallow.commit();
+    shallow.import(&doc.export(ExportMode::updates(&shallow.oplog_vv()))?)?;
+    doc.import(&shallow.export(ExportMode::updates(&doc.oplog_vv()))?)?;
+    assert_eq!(doc.get_deep_value(), shallow.get_deep_value());
+    assert_eq!(shallow_tree.parent(a), Some(TreeParentId::Root));
+
+    for (doc, tree) in [(&doc, &tree), (&shallow, &shallow_tree)] {
+        let moves = doc.get_rejected_tree_moves(&tree.id(), &since)?;
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].id, ID::new(2, 0));
+        assert_eq!(moves[0].target, a);
+        assert_eq!(moves[0].reason, TreeMoveRejectReason::Cycle);
+    }
+    Ok(())
+}
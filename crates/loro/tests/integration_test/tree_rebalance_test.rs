@@ -0,0 +1,109 @@
+use loro::{ExportMode, LoroDoc, LoroError, LoroTree, LoroTreeError, TreeID, TreeParentId};
+
+/// Insert `n` nodes between the first and the last child of the root
+fn insert_between(tree: &LoroTree, n: usize) -> anyhow::Result<Vec<TreeID>> {
+    tree.create(None)?;
+    tree.create(None)?;
+    for _ in 0..n {
+        tree.create_at(None, 1)?;
+    }
+    Ok(tree.children(None).unwrap())
+}
+
+#[test]
+fn rebalance_positions_keeps_order() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    tree.enable_fractional_index(0);
+    let children = insert_between(&tree, 64)?;
+    doc.commit();
+
+    let before = tree.get_position_key_stats(None).unwrap();
+    assert_eq!(before.parent, TreeParentId::Root);
+    assert_eq!(before.children_num, 66);
+    assert_eq!(before.histogram.iter().sum::<usize>(), 66);
+    assert_eq!(before.histogram.len(), before.max_len + 1);
+
+    let moved = tree.rebalance_positions(None)?;
+    assert!(moved > 0);
+    doc.commit();
+    assert_eq!(tree.children(None).unwrap(), children);
+    let after = tree.get_position_key_stats(None).unwrap();
+    assert!(after.max_len < before.max_len);
+    assert!(after.mean_len() < before.mean_len());
+    // The keys are already evenly spaced
+    assert_eq!(tree.rebalance_positions(None)?, 0);
+
+    let new_doc = LoroDoc::new();
+    new_doc.import(&doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(new_doc.get_tree("tree").children(None).unwrap(), children);
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+    assert_eq!(tree.get_all_position_key_stats(), vec![after]);
+    assert!(tree.get_position_key_stats(children[0]).is_none());
+    tree.delete(children[0])?;
+    assert!(matches!(
+        tree.rebalance_positions(children[0]),
+        Err(LoroError::TreeError(
+            LoroTreeError::TreeNodeDeletedOrNotExist(_)
+        ))
+    ));
+    tree.disable_fractional_index();
+    assert!(matches!(
+        tree.rebalance_positions(None),
+        Err(LoroError::TreeError(
+            LoroTreeError::FractionalIndexNotEnabled
+        ))
+    ));
+    Ok(())
+}
+
+#[test]
+fn auto_rebalance_positions() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    tree.enable_fractional_index(0);
+    assert_eq!(tree.auto_rebalance_threshold(), None);
+    tree.set_auto_rebalance_threshold(Some(4))?;
+    assert_eq!(tree.auto_rebalance_threshold(), Some(4));
+    let parent = tree.create(None)?;
+    let mut expected = vec![tree.create(parent)?, tree.create(parent)?];
+    for _ in 0..64 {
+        let node = tree.create(parent)?;
+        tree.mov_to(node, parent, 1)?;
+        expected.insert(1, node);
+    }
+    doc.commit();
+
+    assert_eq!(tree.children(parent).unwrap(), expected);
+    let stats = tree.get_all_position_key_stats();
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[1].parent, TreeParentId::Node(parent));
+    assert!(stats[1].max_len <= 4);
+
+    // The threshold is a local setting of the document
+    let frontiers = doc.oplog_frontiers();
+    doc.checkout(&frontiers)?;
+    doc.checkout_to_latest();
+    assert_eq!(doc.get_tree("tree").auto_rebalance_threshold(), Some(4));
+    assert_eq!(
+        doc.fork().get_tree("tree").auto_rebalance_threshold(),
+        Some(4)
+    );
+    let new_doc = LoroDoc::new();
+    new_doc.import(&doc.export(ExportMode::Snapshot)?)?;
+    assert_eq!(new_doc.get_tree("tree").children(parent).unwrap(), expected);
+    assert_eq!(new_doc.get_tree("tree").auto_rebalance_threshold(), None);
+
+    tree.set_auto_rebalance_threshold(None)?;
+    assert_eq!(tree.auto_rebalance_threshold(), None);
+
+    // The threshold is kept by the document, so it can't be set on a detached tree
+    let detached = LoroTree::new();
+    assert!(matches!(
+        detached.set_auto_rebalance_threshold(Some(4)),
+        Err(LoroError::MisuseDetachedContainer { .. })
+    ));
+    assert_eq!(detached.auto_rebalance_threshold(), None);
+    Ok(())
+}
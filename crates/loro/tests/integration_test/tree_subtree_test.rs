@@ -0,0 +1,187 @@
+use loro::{
+    LoroDoc, LoroError, LoroList, LoroMap, LoroText, LoroTree, LoroTreeError, LoroValue, TreeID,
+};
+
+/// folder
+/// ├── a (name, content: Text, tags: List[Map])
+/// └── b
+///     └── c
+fn create_folder(tree: &LoroTree) -> anyhow::Result<(TreeID, TreeID)> {
+    let folder = tree.create(None)?;
+    tree.get_meta(folder)?.insert("name", "folder")?;
+    let a = tree.create(folder)?;
+    let meta = tree.get_meta(a)?;
+    meta.insert("name", "a")?;
+    let content = meta.insert_container("content", LoroText::new())?;
+    content.insert(0, "Hello world")?;
+    content.mark(0..5, "bold", true)?;
+    let tags = meta.insert_container("tags", LoroList::new())?;
+    tags.insert(0, "first")?;
+    let tag = tags.insert_container(1, LoroMap::new())?;
+    tag.insert("color", "red")?;
+    let b = tree.create(folder)?;
+    tree.create(b)?;
+    Ok((folder, a))
+}
+
+fn names(tree: &LoroTree, parent: TreeID) -> Vec<LoroValue> {
+    tree.children(parent)
+        .unwrap()
+        .into_iter()
+        .map(|child| {
+            tree.get_meta(child)
+                .unwrap()
+                .get_value()
+                .into_map()
+                .unwrap()
+                .get("name")
+                .cloned()
+                .unwrap_or(LoroValue::Null)
+        })
+        .collect()
+}
+
+#[test]
+fn duplicate_subtree_deep_copies_meta() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let (folder, a) = create_folder(&tree)?;
+    let copy = tree.duplicate_subtree(folder, None, 0)?;
+    doc.commit();
+
+    assert_eq!(tree.roots(), vec![copy, folder]);
+    assert_eq!(names(&tree, copy), names(&tree, folder));
+    let copied_a = tree.children(copy).unwrap()[0];
+    assert_ne!(copied_a, a);
+    assert_eq!(
+        tree.get_meta(copied_a)?.get_deep_value(),
+        tree.get_meta(a)?.get_deep_value()
+    );
+    let copied_b = tree.children(copy).unwrap()[1];
+    assert_eq!(tree.children_num(copied_b), Some(1));
+
+    // The containers are copied rather than shared
+    let text = |node: TreeID| -> LoroText {
+        tree.get_meta(node)
+            .unwrap()
+            .get("content")
+            .unwrap()
+            .into_container()
+            .unwrap()
+            .into_text()
+            .unwrap()
+    };
+    assert_ne!(text(copied_a).id(), text(a).id());
+    assert_eq!(text(copied_a).to_delta(), text(a).to_delta());
+    text(copied_a).insert(0, "! ")?;
+    assert_eq!(text(a).to_string(), "Hello world");
+
+    // Duplicate a subtree into itself
+    let b = tree.children(folder).unwrap()[1];
+    let nested = tree.duplicate_subtree(folder, b, 0)?;
+    assert_eq!(tree.children(b).unwrap()[0], nested);
+    assert_eq!(names(&tree, nested), names(&tree, folder));
+    assert_eq!(
+        tree.children_num(tree.children(nested).unwrap()[1]),
+        Some(1)
+    );
+
+    let new_doc = LoroDoc::new();
+    new_doc.import(&doc.export(loro::ExportMode::Snapshot)?)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn export_and_import_subtree() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let (folder, a) = create_folder(&tree)?;
+    assert!(matches!(
+        tree.duplicate_subtree(a, None, 5),
+        Err(LoroError::TreeError(LoroTreeError::IndexOutOfBound { .. }))
+    ));
+    let value = tree.export_subtree(folder)?;
+    assert_eq!(
+        value.as_map().unwrap().get("id"),
+        Some(&LoroValue::from(folder.to_string()))
+    );
+
+    let other = LoroDoc::new();
+    let other_tree = other.get_tree("tree");
+    let root = other_tree.create(None)?;
+    let imported = other_tree.import_subtree(&value, root)?;
+    assert_eq!(other_tree.children(root), Some(vec![imported]));
+    assert_eq!(names(&other_tree, imported), names(&tree, folder));
+    let imported_a = other_tree.children(imported).unwrap()[0];
+    assert_eq!(
+        other_tree.get_meta(imported_a)?.get_deep_value(),
+        tree.get_meta(a)?.get_deep_value()
+    );
+    let content = other_tree
+        .get_meta(imported_a)?
+        .get("content")
+        .unwrap()
+        .into_container()
+        .unwrap()
+        .into_text()
+        .unwrap();
+    assert_eq!(
+        content.to_delta(),
+        tree.get_meta(a)?
+            .get("content")
+            .unwrap()
+            .into_container()
+            .unwrap()
+            .into_text()
+            .unwrap()
+            .to_delta()
+    );
+    assert_eq!(
+        other_tree.export_subtree(imported)?.as_map().unwrap().len(),
+        3
+    );
+
+    tree.delete(folder)?;
+    assert!(matches!(
+        tree.export_subtree(folder),
+        Err(LoroError::TreeError(
+            LoroTreeError::TreeNodeDeletedOrNotExist(_)
+        ))
+    ));
+    assert!(matches!(
+        tree.import_subtree(&LoroValue::from(1), None),
+        Err(LoroError::ArgErr(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn import_subtree_keeps_map_values_like_exported_containers() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let root = tree.create(None)?;
+    let meta = tree.get_meta(root)?;
+    let lookalike = loro::loro_value!({"$container": "Text", "value": [], "$$x": 1});
+    meta.insert("value", lookalike.clone())?;
+    let list = meta.insert_container("list", LoroList::new())?;
+    list.push(lookalike.clone())?;
+    let value = tree.export_subtree(root)?;
+
+    let other = LoroDoc::new();
+    let other_tree = other.get_tree("tree");
+    let imported = other_tree.import_subtree(&value, None)?;
+    let imported_meta = other_tree.get_meta(imported)?;
+    assert_eq!(imported_meta.get_deep_value(), meta.get_deep_value());
+    assert_eq!(
+        imported_meta.get("value").unwrap().into_value().unwrap(),
+        lookalike
+    );
+
+    let unescaped = loro::loro_value!({"meta": {"x": {"$y": 1}}});
+    assert!(matches!(
+        other_tree.import_subtree(&unescaped, None),
+        Err(LoroError::ArgErr(_))
+    ));
+    Ok(())
+}
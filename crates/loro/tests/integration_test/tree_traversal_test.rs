@@ -0,0 +1,105 @@
+use loro::{LoroDoc, LoroTree, TreeID};
+
+/// a
+/// ├── b
+/// │   ├── d
+/// │   └── e
+/// └── c
+///     └── f
+/// g
+fn create_tree(tree: &LoroTree) -> anyhow::Result<[TreeID; 7]> {
+    let a = tree.create(None)?;
+    let b = tree.create(a)?;
+    let c = tree.create(a)?;
+    let d = tree.create(b)?;
+    let e = tree.create(b)?;
+    let f = tree.create(c)?;
+    let g = tree.create(None)?;
+    Ok([a, b, c, d, e, f, g])
+}
+
+#[test]
+fn walk_tree() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let [a, b, c, d, e, f, g] = create_tree(&tree)?;
+    assert_eq!(
+        tree.walk_dfs(None, false).collect::<Vec<_>>(),
+        vec![a, b, d, e, c, f, g]
+    );
+    assert_eq!(
+        tree.walk_bfs(None, false).collect::<Vec<_>>(),
+        vec![a, g, b, c, d, e, f]
+    );
+    assert_eq!(tree.walk_dfs(b, false).collect::<Vec<_>>(), vec![b, d, e]);
+    assert_eq!(tree.walk_bfs(c, false).collect::<Vec<_>>(), vec![c, f]);
+
+    tree.delete(b)?;
+    assert_eq!(
+        tree.walk_dfs(None, false).collect::<Vec<_>>(),
+        vec![a, c, f, g]
+    );
+    assert_eq!(
+        tree.walk_dfs(None, true).collect::<Vec<_>>(),
+        vec![a, c, f, g, b, d, e]
+    );
+    assert_eq!(
+        tree.walk_bfs(None, true).collect::<Vec<_>>(),
+        vec![a, g, c, f, b, d, e]
+    );
+    assert_eq!(tree.walk_dfs(b, false).count(), 0);
+    assert_eq!(tree.walk_dfs(b, true).collect::<Vec<_>>(), vec![b, d, e]);
+    assert_eq!(tree.walk_bfs(TreeID::new(100, 0), true).count(), 0);
+    Ok(())
+}
+
+#[test]
+fn walk_tree_lazily() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let [a, b, c, d, e, f, g] = create_tree(&tree)?;
+    let mut walk = tree.walk_dfs(None, false);
+    assert_eq!(walk.next(), Some(a));
+    assert_eq!(walk.next(), Some(b));
+    // The children of `d` are read when `d` is visited
+    let h = tree.create(d)?;
+    assert_eq!(walk.collect::<Vec<_>>(), vec![d, h, e, c, f, g]);
+    Ok(())
+}
+
+#[test]
+fn ancestor_queries() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let [a, b, c, d, e, f, g] = create_tree(&tree)?;
+    assert_eq!(tree.ancestors(d), Some(vec![b, a]));
+    assert_eq!(tree.ancestors(a), Some(vec![]));
+    assert_eq!(tree.ancestors(TreeID::new(100, 0)), None);
+    assert_eq!(tree.depth(a), Some(0));
+    assert_eq!(tree.depth(f), Some(2));
+    assert_eq!(tree.depth(TreeID::new(100, 0)), None);
+
+    assert!(tree.is_ancestor_of(a, f));
+    assert!(tree.is_ancestor_of(b, e));
+    assert!(!tree.is_ancestor_of(b, f));
+    assert!(!tree.is_ancestor_of(f, a));
+    assert!(!tree.is_ancestor_of(a, a));
+
+    assert_eq!(tree.lowest_common_ancestor(d, e), Some(b));
+    assert_eq!(tree.lowest_common_ancestor(d, f), Some(a));
+    assert_eq!(tree.lowest_common_ancestor(b, d), Some(b));
+    assert_eq!(tree.lowest_common_ancestor(c, c), Some(c));
+    assert_eq!(tree.lowest_common_ancestor(d, g), None);
+
+    tree.mov(c, d)?;
+    assert_eq!(tree.ancestors(f), Some(vec![c, d, b, a]));
+    assert_eq!(tree.depth(f), Some(4));
+    assert_eq!(tree.lowest_common_ancestor(e, f), Some(b));
+
+    // The deleted subtree is regarded as a separate tree
+    tree.delete(b)?;
+    assert_eq!(tree.ancestors(f), Some(vec![c, d, b]));
+    assert!(!tree.is_ancestor_of(a, f));
+    assert_eq!(tree.lowest_common_ancestor(a, f), None);
+    Ok(())
+}
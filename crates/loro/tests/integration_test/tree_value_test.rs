@@ -0,0 +1,175 @@
+use loro::{
+    LoroDoc, LoroError, LoroText, LoroTree, LoroTreeError, LoroValue, TreeID, TreeParentId,
+    TreeValueOptions,
+};
+
+/// root
+/// ├── n0 (title, body: Text)
+/// │   └── n0-0
+/// │       └── n0-0-0
+/// ├── n1 ...
+/// ...
+fn create_outline(tree: &LoroTree, n: usize) -> anyhow::Result<(TreeID, Vec<TreeID>)> {
+    let root = tree.create(None)?;
+    let mut children = vec![];
+    for i in 0..n {
+        let child = tree.create(root)?;
+        let meta = tree.get_meta(child)?;
+        meta.insert("title", format!("n{i}"))?;
+        let body = meta.insert_container("body", LoroText::new())?;
+        body.insert(0, "body")?;
+        let grandchild = tree.create(child)?;
+        tree.get_meta(grandchild)?
+            .insert("title", format!("n{i}-0"))?;
+        tree.create(grandchild)?;
+        children.push(child);
+    }
+    Ok((root, children))
+}
+
+fn field<'a>(node: &'a LoroValue, key: &str) -> &'a LoroValue {
+    node.as_map().unwrap().get(key).unwrap()
+}
+
+fn children(node: &LoroValue) -> &Vec<LoroValue> {
+    field(node, "children").as_list().unwrap()
+}
+
+fn strip_children_num(value: &mut LoroValue) {
+    for node in value.as_list_mut().unwrap().make_mut().iter_mut() {
+        let map = node.as_map_mut().unwrap().make_mut();
+        map.remove("children_num");
+        strip_children_num(map.get_mut("children").unwrap());
+    }
+}
+
+#[test]
+fn default_options_match_value_with_meta() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    tree.enable_fractional_index(0);
+    create_outline(&tree, 3)?;
+    tree.create(None)?;
+    doc.commit();
+
+    let mut value = tree.get_value_with_meta_filtered(&TreeValueOptions::default())?;
+    assert_eq!(
+        field(&value.as_list().unwrap()[0], "children_num"),
+        &3.into()
+    );
+    strip_children_num(&mut value);
+    assert_eq!(value, tree.get_value_with_meta());
+    Ok(())
+}
+
+#[test]
+fn filter_by_root_depth_and_meta_keys() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let (root, nodes) = create_outline(&tree, 3)?;
+    doc.commit();
+
+    let value = tree.get_value_with_meta_filtered(&TreeValueOptions {
+        root: TreeParentId::Node(root),
+        max_depth: Some(2),
+        meta_keys: Some(vec!["body".to_string(), "missing".to_string()]),
+        ..Default::default()
+    })?;
+    let list = value.as_list().unwrap();
+    assert_eq!(list.len(), 3);
+    let first = &list[0];
+    assert_eq!(field(first, "id"), &nodes[0].to_string().into());
+    assert_eq!(field(first, "parent"), &root.to_string().into());
+    let meta = field(first, "meta").as_map().unwrap();
+    assert_eq!(meta.len(), 1);
+    assert_eq!(meta.get("body"), Some(&"body".into()));
+
+    // The grandchildren are at the depth limit
+    let grandchild = &children(first)[0];
+    assert_eq!(field(grandchild, "children_num"), &1.into());
+    assert!(children(grandchild).is_empty());
+    assert!(field(grandchild, "meta").as_map().unwrap().is_empty());
+
+    let value = tree.get_value_with_meta_filtered(&TreeValueOptions {
+        max_depth: Some(0),
+        ..Default::default()
+    })?;
+    assert!(value.as_list().unwrap().is_empty());
+    Ok(())
+}
+
+#[test]
+fn paginate_children() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let (root, nodes) = create_outline(&tree, 10)?;
+    doc.commit();
+
+    let page = |offset: usize, limit: Option<usize>| -> Vec<LoroValue> {
+        tree.get_value_with_meta_filtered(&TreeValueOptions {
+            root: TreeParentId::Node(root),
+            max_depth: Some(1),
+            meta_keys: Some(vec!["title".to_string()]),
+            offset,
+            limit,
+        })
+        .unwrap()
+        .into_list()
+        .unwrap()
+        .to_vec()
+    };
+    let ids = |page: &[LoroValue]| -> Vec<LoroValue> {
+        page.iter().map(|x| field(x, "id").clone()).collect()
+    };
+    let expected = |range: std::ops::Range<usize>| -> Vec<LoroValue> {
+        nodes[range].iter().map(|x| x.to_string().into()).collect()
+    };
+
+    let second = page(4, Some(4));
+    assert_eq!(ids(&second), expected(4..8));
+    assert_eq!(field(&second[0], "index"), &4.into());
+    assert_eq!(
+        field(&second[0], "meta").as_map().unwrap().get("title"),
+        Some(&"n4".into())
+    );
+    assert_eq!(ids(&page(8, Some(4))), expected(8..10));
+    assert!(page(10, Some(4)).is_empty());
+    assert!(page(20, None).is_empty());
+    assert_eq!(ids(&page(3, None)), expected(3..10));
+
+    // A branch can be loaded by passing it as the root
+    assert!(children(&second[0]).is_empty());
+    let branch = tree.get_value_with_meta_filtered(&TreeValueOptions {
+        root: TreeParentId::Node(nodes[4]),
+        ..Default::default()
+    })?;
+    let grandchild = &branch.as_list().unwrap()[0];
+    assert_eq!(field(grandchild, "parent"), &nodes[4].to_string().into());
+    assert_eq!(children(grandchild).len(), 1);
+    Ok(())
+}
+
+#[test]
+fn invalid_root() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let (root, _) = create_outline(&tree, 1)?;
+    tree.delete(root)?;
+    assert!(matches!(
+        tree.get_value_with_meta_filtered(&TreeValueOptions {
+            root: TreeParentId::Node(root),
+            ..Default::default()
+        }),
+        Err(LoroError::TreeError(
+            LoroTreeError::TreeNodeDeletedOrNotExist(_)
+        ))
+    ));
+    assert!(matches!(
+        tree.get_value_with_meta_filtered(&TreeValueOptions {
+            root: TreeParentId::Deleted,
+            ..Default::default()
+        }),
+        Err(LoroError::TreeError(LoroTreeError::InvalidParent))
+    ));
+    Ok(())
+}
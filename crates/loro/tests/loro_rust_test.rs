@@ -10,9 +10,10 @@ use std::{
 };
 
 use loro::{
-    awareness::Awareness, loro_value, CommitOptions, ContainerID, ContainerTrait, ContainerType,
-    ExportMode, Frontiers, FrontiersNotIncluded, IdSpan, LoroDoc, LoroError, LoroList, LoroMap,
-    LoroText, LoroValue, ToJson,
+    awareness::Awareness, cursor::Side, loro_value, CommitOptions, ContainerID, ContainerTrait,
+    ContainerType, ExportMode, Frontiers, FrontiersNotIncluded, IdSpan, LoroDoc, LoroError,
+    LoroList, LoroMap, LoroText, LoroValue, PosType, StyleConfig, StyleConfigMap, StyleOptions,
+    ToJson, ValueSchema,
 };
 use loro_internal::{
     encoding::EncodedBlobMode, handler::TextDelta, id::ID, version_range, vv, LoroResult,
@@ -443,6 +444,546 @@ fn richtext_test() {
     );
 }
 
+#[test]
+fn richtext_style_queries() -> LoroResult<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello 😀 world!")?;
+    text.mark(0..5, "bold", true)?;
+    text.mark(6..13, "link", "a.com")?;
+    text.unmark(3..5, "bold")?;
+    text.mark(8..13, "link", "b.com")?;
+
+    assert_eq!(
+        text.get_styles_at(0)?,
+        [("bold".to_string(), LoroValue::from(true))]
+            .into_iter()
+            .collect()
+    );
+    assert!(text.get_styles_at(3)?.is_empty());
+    assert_eq!(
+        text.get_styles_at(6)?.get("link"),
+        Some(&LoroValue::from("a.com"))
+    );
+    assert_eq!(
+        text.get_styles_at(9)?.get("link"),
+        Some(&LoroValue::from("b.com"))
+    );
+    assert!(text.get_styles_at(14).is_err());
+
+    assert_eq!(
+        text.style_ranges("bold"),
+        vec![(0..3, LoroValue::from(true))]
+    );
+    assert_eq!(
+        text.style_ranges("link"),
+        vec![
+            (6..8, LoroValue::from("a.com")),
+            (8..13, LoroValue::from("b.com"))
+        ]
+    );
+    assert!(text.style_ranges("italic").is_empty());
+
+    let bold = Some(
+        [("bold".to_string(), LoroValue::from(true))]
+            .into_iter()
+            .collect(),
+    );
+    let link = |url: &str| {
+        Some(
+            [("link".to_string(), LoroValue::from(url))]
+                .into_iter()
+                .collect(),
+        )
+    };
+    assert_eq!(
+        text.slice_delta(1, 9)?,
+        vec![
+            TextDelta::Insert {
+                insert: "el".into(),
+                attributes: bold,
+            },
+            TextDelta::Insert {
+                insert: "lo ".into(),
+                attributes: None,
+            },
+            TextDelta::Insert {
+                insert: "😀 ".into(),
+                attributes: link("a.com"),
+            },
+            TextDelta::Insert {
+                insert: "w".into(),
+                attributes: link("b.com"),
+            },
+        ]
+    );
+    assert!(text.slice_delta(3, 3)?.is_empty());
+    assert!(text.slice_delta(3, 20).is_err());
+    assert!(text.slice_delta(5, 3).is_err());
+
+    // Detached text
+    let detached = LoroText::new();
+    detached.insert(0, "Hello")?;
+    detached.mark(1..3, "bold", true)?;
+    assert_eq!(
+        detached.style_ranges("bold"),
+        vec![(1..3, LoroValue::from(true))]
+    );
+    assert_eq!(
+        detached.get_styles_at(1)?.get("bold"),
+        Some(&LoroValue::from(true))
+    );
+    assert_eq!(
+        detached.slice_delta(0, 2)?,
+        vec![
+            TextDelta::Insert {
+                insert: "H".into(),
+                attributes: None,
+            },
+            TextDelta::Insert {
+                insert: "e".into(),
+                attributes: Some(
+                    [("bold".to_string(), LoroValue::from(true))]
+                        .into_iter()
+                        .collect()
+                ),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn non_exclusive_text_styles() -> LoroResult<()> {
+    let styles = || {
+        let mut styles = StyleConfigMap::default_rich_text_config();
+        styles.insert_with_options(
+            "comment".into(),
+            StyleConfig::new(),
+            StyleOptions {
+                exclusive: false,
+                value_type: ValueSchema::String,
+                ..Default::default()
+            },
+        );
+        styles.insert_with_options(
+            "footnote".into(),
+            StyleConfig::new(),
+            StyleOptions {
+                exclusive: false,
+                allow_nesting: false,
+                ..Default::default()
+            },
+        );
+        styles
+    };
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    doc_a.config_text_style(styles());
+    let text_a = doc_a.get_text("text");
+    // The local events are mirrored to a text with exclusive styles
+    let mirror = LoroDoc::new();
+    let text_mirror = mirror.get_text("text");
+    let sub = doc_a.subscribe(
+        &text_a.id(),
+        Arc::new(move |x| {
+            for event in x.events {
+                if let Some(delta) = event.diff.as_text() {
+                    text_mirror.apply_delta(delta).unwrap();
+                }
+            }
+        }),
+    );
+    text_a.insert(0, "The fox jumped.")?;
+    doc_a.commit();
+    let doc_b = LoroDoc::new();
+    doc_b.set_peer_id(2)?;
+    doc_b.config_text_style(styles());
+    doc_b.import(&doc_a.export(ExportMode::all_updates()).unwrap())?;
+
+    // Concurrent comments with different ids coexist under the same key
+    text_a.mark(0..7, "comment", "alice")?;
+    doc_b.get_text("text").mark(4..14, "comment", "bob")?;
+    doc_a.import(&doc_b.export(ExportMode::all_updates()).unwrap())?;
+    doc_b.import(&doc_a.export(ExportMode::all_updates()).unwrap())?;
+    let expected = json!([
+        {"insert": "The ", "attributes": {"comment": ["alice"]}},
+        {"insert": "fox", "attributes": {"comment": ["alice", "bob"]}},
+        {"insert": " jumped", "attributes": {"comment": ["bob"]}},
+        {"insert": "."}
+    ]);
+    assert_eq!(text_a.to_delta().to_json_value(), expected);
+    assert_eq!(doc_b.get_text("text").to_delta().to_json_value(), expected);
+    assert_eq!(
+        text_a.style_ranges("comment"),
+        vec![
+            (0..4, LoroValue::from(vec!["alice"])),
+            (4..7, LoroValue::from(vec!["alice", "bob"])),
+            (7..14, LoroValue::from(vec!["bob"])),
+        ]
+    );
+
+    assert!(matches!(
+        text_a.mark(0..3, "comment", 1),
+        Err(LoroError::ArgErr(_))
+    ));
+    text_a.mark(2..6, "comment", "carol")?;
+    doc_a.commit();
+    assert_eq!(
+        text_a.get_styles_at(5)?,
+        [(
+            "comment".to_string(),
+            LoroValue::from(vec!["alice", "bob", "carol"])
+        )]
+        .into_iter()
+        .collect()
+    );
+    text_a.unmark_value(0..15, "comment", "alice")?;
+    doc_a.commit();
+    assert_eq!(
+        text_a.to_delta().to_json_value(),
+        json!([
+            {"insert": "Th"},
+            {"insert": "e ", "attributes": {"comment": ["carol"]}},
+            {"insert": "fo", "attributes": {"comment": ["bob", "carol"]}},
+            {"insert": "x jumped", "attributes": {"comment": ["bob"]}},
+            {"insert": "."}
+        ])
+    );
+    assert_eq!(
+        mirror.get_text("text").to_delta().to_json_value(),
+        text_a.to_delta().to_json_value()
+    );
+    text_a.unmark(0..15, "comment")?;
+    doc_a.commit();
+    assert_eq!(
+        text_a.to_delta().to_json_value(),
+        json!([{"insert": "The fox jumped."}])
+    );
+    assert_eq!(
+        mirror.get_text("text").to_delta().to_json_value(),
+        text_a.to_delta().to_json_value()
+    );
+    drop(sub);
+
+    // Footnotes cannot be nested
+    text_a.mark(0..3, "footnote", 1)?;
+    text_a.mark(2..5, "footnote", 1)?;
+    assert!(matches!(
+        text_a.mark(4..6, "footnote", 2),
+        Err(LoroError::ArgErr(_))
+    ));
+    text_a.mark(6..8, "footnote", 2)?;
+    assert_eq!(
+        text_a.get_styles_at(7)?,
+        [("footnote".to_string(), LoroValue::from(vec![2]))]
+            .into_iter()
+            .collect()
+    );
+
+    // The concurrent footnotes that overlap are resolved to the latest one
+    doc_b.import(&doc_a.export(ExportMode::all_updates()).unwrap())?;
+    text_a.mark(10..12, "footnote", 3)?;
+    doc_b.get_text("text").mark(11..13, "footnote", 4)?;
+    doc_a.import(&doc_b.export(ExportMode::all_updates()).unwrap())?;
+    doc_b.import(&doc_a.export(ExportMode::all_updates()).unwrap())?;
+    for text in [&text_a, &doc_b.get_text("text")] {
+        assert_eq!(
+            text.style_ranges("footnote"),
+            vec![
+                (0..5, LoroValue::from(vec![1])),
+                (6..8, LoroValue::from(vec![2])),
+                (10..11, LoroValue::from(vec![3])),
+                (11..13, LoroValue::from(vec![4])),
+            ]
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn text_pos_types() -> LoroResult<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    // "é" is 2 bytes in UTF-8, "😀" is 4 bytes in UTF-8 and 2 units in UTF-16
+    text.insert(0, "héllo 😀 world")?;
+    assert_eq!(text.slice_with_pos_type(0, 6, PosType::Utf8)?, "héllo");
+    assert_eq!(text.slice_with_pos_type(5, 9, PosType::Utf16)?, " 😀 ");
+    assert_eq!(text.char_at_with_pos_type(7, PosType::Utf8)?, '😀');
+    assert_eq!(text.char_at_with_pos_type(6, PosType::Unicode)?, '😀');
+    assert!(matches!(
+        text.slice_with_pos_type(0, 2, PosType::Utf8),
+        Err(LoroError::UTF8InUnicodeCodePoint { pos: 2 })
+    ));
+    assert!(matches!(
+        text.char_at_with_pos_type(7, PosType::Utf16),
+        Err(LoroError::UTF16InUnicodeCodePoint { pos: 7 })
+    ));
+    assert!(matches!(
+        text.slice_with_pos_type(0, 100, PosType::Utf8),
+        Err(LoroError::OutOfBound { .. })
+    ));
+
+    for (from, to) in [
+        (PosType::Unicode, PosType::Utf8),
+        (PosType::Utf8, PosType::Utf16),
+        (PosType::Utf16, PosType::Unicode),
+    ] {
+        for pos in 0..=text.len_utf8() {
+            if let Some(converted) = text.convert_pos(pos, from, to) {
+                assert_eq!(text.convert_pos(converted, to, from), Some(pos));
+            }
+        }
+    }
+    assert_eq!(
+        text.convert_pos(13, PosType::Unicode, PosType::Utf8),
+        Some(17)
+    );
+    assert_eq!(text.convert_pos(14, PosType::Unicode, PosType::Utf8), None);
+
+    assert!(matches!(
+        text.splice_with_pos_type(7, usize::MAX, "", PosType::Utf8),
+        Err(LoroError::OutOfBound { .. })
+    ));
+    assert_eq!(text.splice_with_pos_type(7, 4, "🙂", PosType::Utf8)?, "😀");
+    assert_eq!(text.to_string(), "héllo 🙂 world");
+    text.mark_with_pos_type(0..6, "bold", true, PosType::Utf8)?;
+    text.mark_with_pos_type(6..8, "bold", true, PosType::Utf16)?;
+    text.unmark_with_pos_type(1..3, "bold", PosType::Utf8)?;
+    assert_eq!(
+        text.to_delta().to_json_value(),
+        json!([
+            {"insert": "h", "attributes": {"bold": true}},
+            {"insert": "é"},
+            {"insert": "llo", "attributes": {"bold": true}},
+            {"insert": " "},
+            {"insert": "🙂", "attributes": {"bold": true}},
+            {"insert": " world"},
+        ])
+    );
+    assert_eq!(
+        text.slice_delta_with_pos_type(11, 13, PosType::Utf8)?,
+        vec![TextDelta::Insert {
+            insert: " w".into(),
+            attributes: None,
+        }]
+    );
+
+    let cursor = text
+        .get_cursor_with_pos_type(12, Side::Left, PosType::Utf8)
+        .unwrap();
+    text.insert(0, "😀")?;
+    assert_eq!(
+        doc.get_cursor_pos_with_pos_type(&cursor, PosType::Utf8)
+            .unwrap()
+            .current
+            .pos,
+        16
+    );
+    assert_eq!(
+        doc.get_cursor_pos_with_pos_type(&cursor, PosType::Utf16)
+            .unwrap()
+            .current
+            .pos,
+        11
+    );
+    assert!(text
+        .get_cursor_with_pos_type(1, Side::Left, PosType::Utf16)
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn text_embeds() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world")?;
+    text.insert_embed(5, loro_value!({"image": "cat.png"}))?;
+    doc.commit();
+    assert_eq!(text.len_unicode(), 12);
+    assert_eq!(text.to_string(), "Hello\u{FFFC} world");
+    assert!(matches!(
+        text.mark(0..1, "$embed", 1),
+        Err(LoroError::ArgErr(_))
+    ));
+    assert!(matches!(
+        text.insert_embed(0, LoroValue::Null),
+        Err(LoroError::ArgErr(_))
+    ));
+    assert!(matches!(
+        text.insert_embed(0, LoroValue::Container(text.id())),
+        Err(LoroError::ArgErr(_))
+    ));
+
+    // Concurrent edits around the embed
+    let other = doc.fork();
+    other.set_peer_id(2)?;
+    other.get_text("text").insert(5, "!")?;
+    other.get_text("text").delete(8, 5)?;
+    other.commit();
+    text.mark(0..6, "bold", true)?;
+    text.insert_embed(12, "mention")?;
+    doc.commit();
+
+    let embeds = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let embeds_clone = embeds.clone();
+    let _sub = other.subscribe_root(Arc::new(move |batch| {
+        for e in batch.events {
+            if let loro::event::Diff::Text(delta) = e.diff {
+                embeds_clone.lock().unwrap().extend(
+                    delta
+                        .into_iter()
+                        .filter(|d| matches!(d, TextDelta::Embed { .. })),
+                );
+            }
+        }
+    }));
+    doc.import(&other.export(ExportMode::all_updates())?)?;
+    other.import(&doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(
+        *embeds.lock().unwrap(),
+        vec![TextDelta::Embed {
+            insert: "mention".into(),
+            attributes: None,
+        }]
+    );
+    assert_eq!(doc.get_deep_value(), other.get_deep_value());
+
+    // The text inserted next to an embed is not a part of it
+    text.insert(text.len_unicode(), ".")?;
+    doc.commit();
+    assert_eq!(text.to_string(), "Hello!\u{FFFC} \u{FFFC}.");
+    let delta = json!([
+        {"insert": "Hello!", "attributes": {"bold": true}},
+        {"insert": {"$embed": {"image": "cat.png"}}, "attributes": {"bold": true}},
+        {"insert": " "},
+        {"insert": {"$embed": "mention"}},
+        {"insert": "."},
+    ]);
+    assert_eq!(text.to_delta().to_json_value(), delta);
+    // The string embed can't be confused with the text
+    let items: Vec<TextDelta> = serde_json::from_value(delta.clone())?;
+    assert_eq!(items, text.slice_delta(0, text.len_unicode())?);
+    assert_eq!(
+        text.slice_delta(6, 9)?,
+        vec![
+            TextDelta::Embed {
+                insert: loro_value!({"image": "cat.png"}),
+                attributes: Some([("bold".to_string(), true.into())].into_iter().collect()),
+            },
+            TextDelta::Insert {
+                insert: " ".into(),
+                attributes: None,
+            },
+            TextDelta::Embed {
+                insert: "mention".into(),
+                attributes: None,
+            },
+        ]
+    );
+
+    let snapshot_doc = LoroDoc::new();
+    snapshot_doc.import(&doc.export(ExportMode::Snapshot)?)?;
+    assert_eq!(
+        snapshot_doc.get_text("text").to_delta().to_json_value(),
+        delta
+    );
+
+    let json_updates = doc.export_json_updates(&Default::default(), &doc.oplog_vv());
+    let json_doc = LoroDoc::new();
+    json_doc.import_json_updates(serde_json::to_string(&json_updates).unwrap())?;
+    assert_eq!(json_doc.get_text("text").to_delta().to_json_value(), delta);
+
+    let delta_doc = LoroDoc::new();
+    let delta_text = delta_doc.get_text("text");
+    delta_text.apply_delta(&text.slice_delta(0, text.len_unicode())?)?;
+    assert_eq!(delta_text.to_delta().to_json_value(), delta);
+    Ok(())
+}
+
+#[test]
+fn text_embedded_containers() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let mut undo = loro::UndoManager::new(&doc);
+    let text = doc.get_text("text");
+    text.insert(0, "Hi !")?;
+    let image = text.insert_container(3, LoroMap::new())?;
+    image.insert("src", "cat.png")?;
+    doc.commit();
+    assert_eq!(text.to_string(), "Hi \u{FFFC}!");
+    assert_eq!(
+        text.slice_delta(3, 4)?,
+        vec![TextDelta::Embed {
+            insert: LoroValue::Container(image.id()),
+            attributes: None,
+        }]
+    );
+    assert_eq!(
+        doc.get_path_to_container(&image.id())
+            .unwrap()
+            .last()
+            .unwrap()
+            .1,
+        loro::Index::Seq(3)
+    );
+    assert!(matches!(
+        LoroText::new().insert_container(0, LoroMap::new()),
+        Err(LoroError::MisuseDetachedContainer { .. })
+    ));
+
+    // The embedded container round-trips in the updates, the snapshots and the JSON updates
+    let json_updates = doc.export_json_updates(&Default::default(), &doc.oplog_vv());
+    let json_doc = LoroDoc::new();
+    json_doc.import_json_updates(serde_json::to_string(&json_updates).unwrap())?;
+    for bytes in [
+        doc.export(ExportMode::all_updates())?,
+        doc.export(ExportMode::Snapshot)?,
+    ] {
+        let new_doc = LoroDoc::new();
+        new_doc.import(&bytes)?;
+        for d in [&new_doc, &json_doc] {
+            assert_eq!(
+                d.get_text("text").to_delta().to_json_value(),
+                text.to_delta().to_json_value()
+            );
+            assert_eq!(
+                d.get_map(image.id()).get_deep_value().to_json_value(),
+                json!({"src": "cat.png"})
+            );
+            assert!(!d.get_map(image.id()).is_deleted());
+            assert_eq!(
+                d.get_path_to_container(&image.id()),
+                doc.get_path_to_container(&image.id())
+            );
+        }
+    }
+
+    // The container is deleted along with its embed, and undo brings back a copy of it
+    text.insert(0, "Oh, ")?;
+    undo.record_new_checkpoint(&doc)?;
+    text.delete(7, 1)?;
+    doc.commit();
+    assert!(image.is_deleted());
+    assert_eq!(text.to_string(), "Oh, Hi !");
+    undo.undo(&doc)?;
+    let delta = text.slice_delta(7, 8)?;
+    let TextDelta::Embed {
+        insert: LoroValue::Container(id),
+        ..
+    } = &delta[0]
+    else {
+        panic!("expect an embed, got {:?}", delta);
+    };
+    assert_ne!(id, &image.id());
+    assert_eq!(
+        doc.get_map(id.clone()).get_deep_value().to_json_value(),
+        json!({"src": "cat.png"})
+    );
+    Ok(())
+}
+
 #[test]
 fn sync() {
     use loro::{LoroDoc, ToJson};
@@ -481,6 +1022,228 @@ fn save() {
     assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
 }
 
+#[test]
+fn save_with_compression() {
+    use loro::{CompressionType, SnapshotOptions};
+
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    for i in 0..100 {
+        text.insert(0, &format!("{} hello world ", i)).unwrap();
+        doc.get_map("map").insert(&i.to_string(), i).unwrap();
+    }
+
+    let uncompressed = doc
+        .export(ExportMode::snapshot_with_options(
+            SnapshotOptions::new().compression(CompressionType::None),
+        ))
+        .unwrap();
+    let new_doc = LoroDoc::new();
+    new_doc.import(&uncompressed).unwrap();
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    assert_eq!(new_doc.oplog_vv(), doc.oplog_vv());
+
+    #[cfg(feature = "zstd")]
+    {
+        let zstd = doc
+            .export(ExportMode::snapshot_with_options(
+                SnapshotOptions::new().compression(CompressionType::zstd(19)),
+            ))
+            .unwrap();
+        let new_doc = LoroDoc::new();
+        new_doc.import(&zstd).unwrap();
+        assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    }
+}
+
+#[test]
+fn snapshot_with_compressed_text_state() {
+    use loro::{CompressionType, SnapshotOptions, StateEncoding};
+
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    let text = doc.get_text("text");
+    for i in 0..200 {
+        text.insert(
+            text.len_unicode(),
+            &format!("It was the best of times, it was the worst of times. 章节{i}\n"),
+        )
+        .unwrap();
+        if i % 10 == 0 {
+            let start = text.len_unicode() - 10;
+            text.mark(start..start + 5, "bold", true).unwrap();
+        }
+    }
+    doc.get_map("map").insert("key", "value").unwrap();
+    doc.commit();
+
+    let raw = doc.export(ExportMode::Snapshot).unwrap();
+    let compressed_text = SnapshotOptions::new().state_encoding(StateEncoding::CompressedText);
+    let compressed = doc
+        .export(ExportMode::snapshot_with_options(compressed_text))
+        .unwrap();
+    assert!(
+        compressed.len() < raw.len(),
+        "{} >= {}",
+        compressed.len(),
+        raw.len()
+    );
+
+    let new_doc = LoroDoc::new();
+    new_doc.import(&compressed).unwrap();
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    assert_eq!(new_doc.get_text("text").to_delta(), text.to_delta());
+
+    // The containers that are not loaded yet are exported in the raw encoding
+    let new_doc = LoroDoc::new();
+    new_doc.import(&compressed).unwrap();
+    let exported = new_doc.export(ExportMode::Snapshot).unwrap();
+    assert!(exported.len() > compressed.len());
+    let fork = LoroDoc::new();
+    fork.import(&exported).unwrap();
+    assert_eq!(fork.get_deep_value(), doc.get_deep_value());
+
+    // It works with the block compression
+    let both = doc
+        .export(ExportMode::snapshot_with_options(
+            compressed_text.compression(CompressionType::LZ4),
+        ))
+        .unwrap();
+    let fork = LoroDoc::new();
+    fork.import(&both).unwrap();
+    assert_eq!(fork.get_deep_value(), doc.get_deep_value());
+}
+
+#[test]
+fn export_to_writer_and_import_from_reader() {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    let text = doc.get_text("text");
+    for i in 0..100 {
+        text.insert(0, &format!("{} hello world ", i)).unwrap();
+        doc.get_map("map").insert(&i.to_string(), i).unwrap();
+    }
+    doc.commit();
+    let vv = doc.oplog_vv();
+
+    for mode in [
+        ExportMode::Snapshot,
+        ExportMode::all_updates(),
+        ExportMode::updates_in_range(vec![IdSpan::new(1, 0, 50)]),
+        ExportMode::state_only(None),
+    ] {
+        let mut buf = Vec::new();
+        doc.export_to_writer(mode.clone(), &mut buf).unwrap();
+        let a = LoroDoc::new();
+        a.import_from_reader(&mut buf.as_slice()).unwrap();
+        let b = LoroDoc::new();
+        b.import(&buf).unwrap();
+        let c = LoroDoc::new();
+        c.import(&doc.export(mode).unwrap()).unwrap();
+        assert_eq!(a.get_deep_value(), c.get_deep_value());
+        assert_eq!(b.get_deep_value(), c.get_deep_value());
+        assert_eq!(a.oplog_vv(), c.oplog_vv());
+    }
+
+    // The blobs exported by `export` can be read too
+    let new_doc = LoroDoc::new();
+    new_doc
+        .import_from_reader(&mut doc.export(ExportMode::Snapshot).unwrap().as_slice())
+        .unwrap();
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+    let mut snapshot = Vec::new();
+    doc.export_to_writer(ExportMode::Snapshot, &mut snapshot)
+        .unwrap();
+    let new_doc = LoroDoc::new();
+    new_doc
+        .import_from_reader(&mut snapshot.as_slice())
+        .unwrap();
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    assert_eq!(new_doc.oplog_vv(), vv);
+
+    // Import into a doc that already has the history
+    text.insert(0, "new").unwrap();
+    doc.commit();
+    let mut updates = Vec::new();
+    doc.export_to_writer(ExportMode::updates(&vv), &mut updates)
+        .unwrap();
+    new_doc.import_from_reader(&mut updates.as_slice()).unwrap();
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+    let another = LoroDoc::new();
+    another.get_text("text").insert(0, "abc").unwrap();
+    another
+        .import_from_reader(&mut snapshot.as_slice())
+        .unwrap();
+    another.import_from_reader(&mut updates.as_slice()).unwrap();
+    assert_eq!(another.oplog_vv().get(&1), doc.oplog_vv().get(&1));
+}
+
+#[test]
+fn import_from_reader_checks_the_data() {
+    let doc = LoroDoc::new();
+    doc.get_text("text").insert(0, "Hello world").unwrap();
+    for mode in [ExportMode::Snapshot, ExportMode::all_updates()] {
+        let mut bytes = Vec::new();
+        doc.export_to_writer(mode, &mut bytes).unwrap();
+
+        let mut corrupted = bytes.clone();
+        corrupted[30] ^= 0xff;
+        let new_doc = LoroDoc::new();
+        assert_eq!(
+            new_doc.import_from_reader(&mut corrupted.as_slice()),
+            Err(LoroError::DecodeChecksumMismatchError)
+        );
+        assert!(new_doc
+            .import_from_reader(&mut &bytes[..bytes.len() - 1])
+            .is_err());
+        assert!(new_doc.import_from_reader(&mut &bytes[..10]).is_err());
+        assert_eq!(new_doc.get_deep_value().to_json_value(), json!({}));
+    }
+
+    // The state sections are skipped when the snapshot is imported into a doc with
+    // history, but they are still verified
+    let mut snapshot = Vec::new();
+    doc.export_to_writer(ExportMode::Snapshot, &mut snapshot)
+        .unwrap();
+    let len = snapshot.len();
+    // The checksum of the shallow root state section, which is followed by the end frame
+    snapshot[len - 12] ^= 0xff;
+    let new_doc = LoroDoc::new();
+    new_doc.get_text("text").insert(0, "abc").unwrap();
+    new_doc.commit();
+    assert_eq!(
+        new_doc.import_from_reader(&mut snapshot.as_slice()),
+        Err(LoroError::DecodeChecksumMismatchError)
+    );
+    assert_eq!(new_doc.get_text("text").to_string(), "abc");
+}
+
+#[test]
+fn import_from_reader_stops_at_the_end_frame() {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    doc.get_text("text").insert(0, "Hello").unwrap();
+    doc.commit();
+    let vv = doc.oplog_vv();
+    let mut buf = Vec::new();
+    doc.export_to_writer(ExportMode::Snapshot, &mut buf)
+        .unwrap();
+    doc.get_text("text").insert(5, " world").unwrap();
+    doc.commit();
+    doc.export_to_writer(ExportMode::updates(&vv), &mut buf)
+        .unwrap();
+
+    let mut reader = buf.as_slice();
+    let new_doc = LoroDoc::new();
+    new_doc.import_from_reader(&mut reader).unwrap();
+    assert_eq!(new_doc.get_text("text").to_string(), "Hello");
+    new_doc.import_from_reader(&mut reader).unwrap();
+    assert_eq!(new_doc.get_text("text").to_string(), "Hello world");
+    assert!(reader.is_empty());
+}
+
 #[test]
 fn subscribe() {
     use loro::LoroDoc;